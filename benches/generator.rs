@@ -218,6 +218,41 @@ fn bytes_generate(c: &mut Criterion) {
     }
 }
 
+fn medium_files_generate(c: &mut Criterion) {
+    let mut group = c.benchmark_group("medium_files_generate");
+
+    let num_files = 1_000;
+    for bytes_per_file in [16 * 1024, 64 * 1024, 256 * 1024] {
+        let num_bytes = num_files * bytes_per_file;
+        group
+            .sample_size(20)
+            .throughput(Throughput::Bytes(num_bytes));
+        group.bench_with_input(
+            BenchmarkId::from_parameter(bytes_per_file),
+            &num_bytes,
+            |b, &num_bytes| {
+                b.iter_with_large_drop(|| {
+                    let dir = tempdir().unwrap();
+
+                    Generator::builder()
+                        .root_dir(dir.path().to_path_buf())
+                        .num_files_with_ratio(NumFilesWithRatio::from_num_files(
+                            NonZeroU64::new(num_files).unwrap(),
+                        ))
+                        .max_depth(2)
+                        .num_bytes(num_bytes)
+                        .bytes_exact(true)
+                        .build()
+                        .generate(&mut sink().write_adapter())
+                        .unwrap();
+
+                    dir
+                });
+            },
+        );
+    }
+}
+
 criterion_group! {
     name = benches;
     config = Criterion::default().noise_threshold(0.005).warm_up_time(Duration::from_secs(1));
@@ -229,5 +264,6 @@ criterion_group! {
     simple_generate,
     sparse_generate,
     bytes_generate,
+    medium_files_generate,
 }
 criterion_main!(benches);
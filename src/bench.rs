@@ -0,0 +1,212 @@
+use std::{
+    fmt::Write,
+    fs, io,
+    num::NonZeroUsize,
+    path::{Path, PathBuf},
+    process::ExitCode,
+    thread,
+    time::{Duration, Instant},
+};
+
+use bon::Builder;
+use error_stack::{Report, Result, ResultExt};
+use thousands::Separable;
+
+use crate::generator::{Error, Generator};
+
+/// Runs a standardized set of metadata-heavy phases over a freshly generated
+/// tree, in order:
+///
+/// - `create`: the generator itself populates the tree.
+/// - `readdir`: every directory is listed.
+/// - `stat`: every entry is stat-ed.
+/// - `unlink`: every entry is removed (unless the tree is kept).
+#[derive(Debug, Builder)]
+pub struct Benchmark {
+    generator: Generator,
+    /// Skip the `unlink` phase and leave the generated tree in place.
+    #[builder(default = false)]
+    keep: bool,
+}
+
+struct Tree {
+    dirs: Vec<PathBuf>,
+    files: Vec<PathBuf>,
+}
+
+struct PhaseResult {
+    name: &'static str,
+    ops: usize,
+    unit: &'static str,
+    elapsed: Duration,
+}
+
+impl Benchmark {
+    pub fn run(self, output: &mut impl Write) -> Result<(), Error> {
+        let Self { generator, keep } = self;
+        let root_dir = generator.root_dir.clone();
+        let parallelism = thread::available_parallelism().unwrap_or(NonZeroUsize::new(1).unwrap());
+
+        let start = Instant::now();
+        generator.generate(output)?;
+        let create = start.elapsed();
+
+        let start = Instant::now();
+        let tree = read_tree(&root_dir)?;
+        let readdir = start.elapsed();
+        let num_entries = tree.dirs.len() + tree.files.len();
+
+        let mut results = vec![
+            PhaseResult {
+                name: "create",
+                ops: num_entries,
+                unit: "entries",
+                elapsed: create,
+            },
+            PhaseResult {
+                name: "readdir",
+                ops: tree.dirs.len() + 1,
+                unit: "directories",
+                elapsed: readdir,
+            },
+        ];
+
+        let start = Instant::now();
+        for_each_parallel(&tree.files, parallelism, |p| {
+            fs::symlink_metadata(p).map(drop)
+        })?;
+        for_each_parallel(&tree.dirs, parallelism, |p| {
+            fs::symlink_metadata(p).map(drop)
+        })?;
+        results.push(PhaseResult {
+            name: "stat",
+            ops: num_entries,
+            unit: "entries",
+            elapsed: start.elapsed(),
+        });
+
+        if !keep {
+            let start = Instant::now();
+            for_each_parallel(&tree.files, parallelism, |p| fs::remove_file(p))?;
+            // Directories were collected parents first, so removing them in reverse
+            // guarantees children are gone before their parents.
+            for dir in tree.dirs.iter().rev() {
+                fs::remove_dir(dir)
+                    .attach_printable_lazy(|| format!("Failed to remove directory {dir:?}"))
+                    .change_context(Error::Io)
+                    .attach(ExitCode::from(sysexits::ExitCode::IoErr))?;
+            }
+            results.push(PhaseResult {
+                name: "unlink",
+                ops: num_entries,
+                unit: "entries",
+                elapsed: start.elapsed(),
+            });
+        }
+
+        print_results(&results, output)
+    }
+}
+
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "trace"))]
+fn read_tree(root_dir: &Path) -> Result<Tree, Error> {
+    let mut tree = Tree {
+        dirs: Vec::new(),
+        files: Vec::new(),
+    };
+
+    let mut next = 0;
+    let mut dir = root_dir.to_path_buf();
+    loop {
+        for entry in fs::read_dir(&dir)
+            .attach_printable_lazy(|| format!("Failed to read directory {dir:?}"))
+            .change_context(Error::Io)
+            .attach(ExitCode::from(sysexits::ExitCode::IoErr))?
+        {
+            let entry = entry
+                .attach_printable_lazy(|| format!("Failed to read directory {dir:?}"))
+                .change_context(Error::Io)
+                .attach(ExitCode::from(sysexits::ExitCode::IoErr))?;
+            if entry.file_type().is_ok_and(|t| t.is_dir()) {
+                tree.dirs.push(entry.path());
+            } else {
+                tree.files.push(entry.path());
+            }
+        }
+
+        let Some(d) = tree.dirs.get(next) else {
+            break;
+        };
+        dir.clone_from(d);
+        next += 1;
+    }
+
+    Ok(tree)
+}
+
+fn for_each_parallel(
+    paths: &[PathBuf],
+    parallelism: NonZeroUsize,
+    op: impl Fn(&PathBuf) -> io::Result<()> + Sync,
+) -> Result<(), Error> {
+    if paths.is_empty() {
+        return Ok(());
+    }
+
+    let chunk_size = paths.len().div_ceil(parallelism.get());
+    thread::scope(|scope| {
+        let workers = paths
+            .chunks(chunk_size)
+            .map(|chunk| {
+                let op = &op;
+                scope.spawn(move || {
+                    for path in chunk {
+                        op(path).attach_printable_lazy(|| {
+                            format!("Benchmark operation failed on {path:?}")
+                        })?;
+                    }
+                    Ok::<_, Report<io::Error>>(())
+                })
+            })
+            .collect::<Vec<_>>();
+
+        for worker in workers {
+            worker
+                .join()
+                .map_err(|_| Report::new(Error::TaskJoin))
+                .attach(ExitCode::from(sysexits::ExitCode::Software))?
+                .change_context(Error::Io)
+                .attach(ExitCode::from(sysexits::ExitCode::IoErr))?;
+        }
+        Ok(())
+    })
+}
+
+#[allow(clippy::cast_precision_loss)]
+fn print_results(results: &[PhaseResult], output: &mut impl Write) -> Result<(), Error> {
+    for &PhaseResult {
+        name,
+        ops,
+        unit,
+        elapsed,
+    } in results
+    {
+        let secs = elapsed.as_secs_f64();
+        let throughput = if secs > 0. {
+            (ops as f64 / secs).round() as u64
+        } else {
+            0
+        };
+
+        writeln!(
+            output,
+            "{name:<8}{:>14} {unit} in {elapsed:.2?} ({} ops/s)",
+            ops.separate_with_commas(),
+            throughput.separate_with_commas(),
+        )
+        .attach_printable("Failed to write to output stream")
+        .change_context(Error::Io)
+        .attach(ExitCode::from(sysexits::ExitCode::IoErr))?;
+    }
+    Ok(())
+}
@@ -0,0 +1,152 @@
+//! A minimal C ABI for embedding the generator in non-Rust test harnesses.
+//!
+//! Build the shared library with
+//! `cargo rustc --release --lib --features capi --crate-type cdylib` and
+//! declare the functions as:
+//!
+//! ```c
+//! int ftzz_generate(const char *config_json, char **out_stats_json);
+//! void ftzz_free_string(char *s);
+//! ```
+
+use std::{
+    ffi::{CStr, CString, c_char, c_int},
+    num::NonZeroU64,
+    path::PathBuf,
+};
+
+use serde::Deserialize;
+
+use crate::generator::{Generator, NumFilesWithRatio};
+
+/// The parameters accepted by [`ftzz_generate`], named like their
+/// configuration file counterparts.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+#[serde(deny_unknown_fields)]
+struct CConfig {
+    root_dir: PathBuf,
+    files: NonZeroU64,
+    ftd_ratio: Option<NonZeroU64>,
+    #[serde(default)]
+    files_exact: bool,
+    #[serde(default)]
+    total_bytes: u64,
+    #[serde(default)]
+    bytes_exact: bool,
+    fill_byte: Option<u8>,
+    max_depth: Option<u32>,
+    #[serde(default)]
+    seed: u64,
+    audit_output: Option<PathBuf>,
+    fio_output: Option<PathBuf>,
+    manifest: Option<PathBuf>,
+}
+
+fn generate(config_json: &CStr) -> Result<serde_json::Value, String> {
+    let config_json = config_json
+        .to_str()
+        .map_err(|e| format!("Configuration is not valid UTF-8: {e}"))?;
+    let CConfig {
+        root_dir,
+        files,
+        ftd_ratio,
+        files_exact,
+        total_bytes,
+        bytes_exact,
+        fill_byte,
+        max_depth,
+        seed,
+        audit_output,
+        fio_output,
+        manifest,
+    } = serde_json::from_str(config_json).map_err(|e| format!("Invalid configuration: {e}"))?;
+
+    let num_files_with_ratio = match ftd_ratio {
+        Some(ratio) => NumFilesWithRatio::new(files, ratio).map_err(|e| e.to_string())?,
+        None => NumFilesWithRatio::from_num_files(files),
+    };
+    let (stats, targets) = Generator::builder()
+        .root_dir(root_dir)
+        .num_files_with_ratio(num_files_with_ratio)
+        .files_exact(files_exact)
+        .num_bytes(total_bytes)
+        .bytes_exact(bytes_exact)
+        .maybe_fill_byte(fill_byte)
+        .maybe_max_depth(max_depth)
+        .seed(seed)
+        .maybe_audit_output(audit_output)
+        .maybe_fio_output(fio_output)
+        .maybe_manifest(manifest)
+        .build()
+        .generate_with_stats(&mut String::new())
+        .map_err(|e| format!("{e:?}"))?
+        .ok_or("Generation produced no statistics")?;
+
+    let deltas = targets
+        .deltas(stats)
+        .into_iter()
+        .map(|delta| {
+            (
+                delta.name.to_string(),
+                serde_json::json!({
+                    "requested": delta.requested,
+                    "achieved": delta.achieved,
+                    "absolute": i64::try_from(delta.absolute()).unwrap_or(i64::MAX),
+                    "percentage": delta.percentage(),
+                }),
+            )
+        })
+        .collect::<serde_json::Map<_, _>>();
+    Ok(serde_json::json!({
+        "files": stats.files,
+        "dirs": stats.dirs,
+        "bytes": stats.bytes,
+        "duplicates": stats.duplicates,
+        "deltas": deltas,
+    }))
+}
+
+/// Generates a tree described by the JSON object `config_json`.
+///
+/// On success, returns 0 and stores `{"files": .., "dirs": .., "bytes": ..}`
+/// in `out_stats_json`. On failure, returns 1 and stores `{"error": ".."}`
+/// instead. Either way, the string must be released with
+/// [`ftzz_free_string`]. Returns -1 without touching `out_stats_json` if
+/// either pointer is null.
+///
+/// # Safety
+///
+/// `config_json` must be a valid nul-terminated string and `out_stats_json`
+/// must be valid for writes.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn ftzz_generate(
+    config_json: *const c_char,
+    out_stats_json: *mut *mut c_char,
+) -> c_int {
+    if config_json.is_null() || out_stats_json.is_null() {
+        return -1;
+    }
+    let config_json = unsafe { CStr::from_ptr(config_json) };
+
+    let (status, json) = match generate(config_json) {
+        Ok(stats) => (0, stats),
+        Err(error) => (1, serde_json::json!({ "error": error })),
+    };
+    // JSON escapes control characters, so there can't be any interior nuls
+    let json = CString::new(json.to_string()).unwrap();
+    unsafe { out_stats_json.write(json.into_raw()) };
+    status
+}
+
+/// Releases a string returned by [`ftzz_generate`]. Null is ignored.
+///
+/// # Safety
+///
+/// `s` must have been returned by [`ftzz_generate`] and not already freed.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn ftzz_free_string(s: *mut c_char) {
+    if !s.is_null() {
+        drop(unsafe { CString::from_raw(s) });
+    }
+}
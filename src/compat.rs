@@ -0,0 +1,109 @@
+use std::{
+    fmt::Write,
+    fs,
+    hash::Hasher,
+    io,
+    num::NonZeroU64,
+    path::Path,
+    process::{self, ExitCode},
+};
+
+use error_stack::{Report, Result, ResultExt};
+use twox_hash::XxHash64;
+
+use crate::generator::{Error, Generator, NumFilesWithRatio};
+
+/// The hash of the canonical tree generated by each compatibility level.
+///
+/// A level promises the same parameters and seed produce the same tree, so
+/// these only ever change when a new level is introduced.
+const GOLDEN_TREE_HASHES: &[(&str, u64)] = &[("4", 0x55bf_c4f4_ddb4_5d59)];
+
+/// Generates a small canonical tree in a temporary directory and checks it
+/// against the golden hash of every supported compatibility level.
+///
+/// The tree hash covers relative paths, sizes, and contents, but not
+/// permissions since those depend on the umask.
+pub fn self_check_compat(output: &mut impl Write) -> Result<(), Error> {
+    let root_dir = std::env::temp_dir().join(format!("ftzz-self-check-{}", process::id()));
+    let hash = canonical_tree_hash(&root_dir);
+    drop(fs::remove_dir_all(&root_dir));
+    let hash = hash?;
+
+    let mut mismatched = Vec::new();
+    for &(level, golden) in GOLDEN_TREE_HASHES {
+        let status = if hash == golden {
+            "ok"
+        } else {
+            mismatched.push(level);
+            "CHANGED"
+        };
+        writeln!(output, "compat level {level}: {status}")
+            .attach_printable("Failed to write to output stream")
+            .change_context(Error::Io)
+            .attach(ExitCode::from(sysexits::ExitCode::IoErr))?;
+    }
+
+    if mismatched.is_empty() {
+        Ok(())
+    } else {
+        Err(Report::new(Error::CompatMismatch))
+            .attach_printable(format!("Tree hash {hash:016x} changed for {mismatched:?}"))
+            .attach(ExitCode::from(sysexits::ExitCode::Software))
+    }
+}
+
+fn canonical_tree_hash(root_dir: &Path) -> Result<u64, Error> {
+    Generator::builder()
+        .root_dir(root_dir)
+        .num_files_with_ratio(NumFilesWithRatio::from_num_files(
+            NonZeroU64::new(1000).unwrap(),
+        ))
+        .num_bytes(1_000_000)
+        .seed(42)
+        .build()
+        .generate(&mut String::new())?;
+
+    tree_hash(root_dir)
+        .attach_printable_lazy(|| format!("Failed to hash the canonical tree in {root_dir:?}"))
+        .change_context(Error::Io)
+        .attach(ExitCode::from(sysexits::ExitCode::IoErr))
+}
+
+fn tree_hash(root_dir: &Path) -> io::Result<u64> {
+    // Paths are joined with `/` and numbers hashed as little-endian on every
+    // platform so the hash is portable.
+    let mut entries = Vec::new();
+    let mut pending = vec![String::new()];
+    while let Some(dir) = pending.pop() {
+        for entry in fs::read_dir(root_dir.join(&dir))? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let path = if dir.is_empty() {
+                name
+            } else {
+                format!("{dir}/{name}")
+            };
+            if entry.file_type()?.is_dir() {
+                entries.push((path.clone(), None));
+                pending.push(path);
+            } else {
+                let contents = fs::read(entry.path())?;
+                let size = contents.len() as u64;
+                entries.push((path, Some((size, XxHash64::oneshot(0, &contents)))));
+            }
+        }
+    }
+    entries.sort_unstable();
+
+    let mut hasher = XxHash64::with_seed(0);
+    for (path, file) in entries {
+        hasher.write(path.as_bytes());
+        hasher.write_u8(0);
+        if let Some((size, contents)) = file {
+            hasher.write(&size.to_le_bytes());
+            hasher.write(&contents.to_le_bytes());
+        }
+    }
+    Ok(hasher.finish())
+}
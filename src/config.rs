@@ -1,29 +1,138 @@
 use std::{
+    fmt::Display,
     fs,
-    num::NonZeroU64,
+    num::{NonZeroU8, NonZeroU32, NonZeroU64},
     path::{Path, PathBuf},
+    str::FromStr,
 };
 
 use error_stack::{Result, ResultExt};
-use serde::Deserialize;
+use ftzz::{
+    AuditFormat, ContentMode, DirFanout, ExtensionWeights, FilesPerDirDistribution, FillPattern,
+    Holes, LeftoverStrategy, LevelOverride, LinkFlavor, NameLengthRange, NameStyle, NameTemplate,
+    PermissionMode, PermissionRule, SizeDistribution, TimeRange, TraversalOrder,
+};
+use serde::{Deserialize, Deserializer, de::Error};
+
+use crate::Preset;
 
 #[derive(Debug, Deserialize, Default, Clone)]
 #[serde(rename_all = "kebab-case")]
 #[serde(deny_unknown_fields)]
 pub struct Config {
     pub files: Option<NonZeroU64>,
+    pub entries: Option<NonZeroU64>,
     pub files_exact: Option<bool>,
+    pub dirs: Option<NonZeroU64>,
+    pub dirs_exact: Option<bool>,
     pub total_bytes: Option<u64>,
     pub fill_byte: Option<u8>,
+    #[serde(default, deserialize_with = "from_str")]
+    pub fill_pattern: Option<FillPattern>,
+    pub content_template: Option<PathBuf>,
+    pub content_corpus: Option<PathBuf>,
     pub bytes_exact: Option<bool>,
+    pub direct_io: Option<bool>,
+    pub writeback_bytes: Option<NonZeroU64>,
+    pub fragment: Option<bool>,
+    pub sparse: Option<f64>,
+    #[serde(default, deserialize_with = "from_str")]
+    pub hole_pattern: Option<Holes>,
+    pub preallocate: Option<bool>,
     pub exact: Option<bool>,
     pub max_depth: Option<u32>,
+    pub min_depth: Option<u32>,
+    pub flat: Option<bool>,
+    pub files_at_leaves: Option<bool>,
+    pub skew: Option<f64>,
+    #[serde(default, deserialize_with = "from_str")]
+    pub files_per_dir_distribution: Option<FilesPerDirDistribution>,
     pub ftd_ratio: Option<NonZeroU64>,
+    pub dtd_ratio: Option<NonZeroU64>,
     pub audit_output: Option<PathBuf>,
+    #[serde(default, deserialize_with = "from_str")]
+    pub audit_format: Option<AuditFormat>,
+    pub audit_batch_size: Option<std::num::NonZeroUsize>,
+    pub audit_page_size: Option<u32>,
+    pub audit_cache_size: Option<NonZeroU64>,
     pub seed: Option<u64>,
     pub duplicate_percentage: Option<f64>,
     pub max_duplicates_per_file: Option<std::num::NonZeroUsize>,
+    pub duplicates_as_hardlinks: Option<bool>,
     pub permissions: Option<Vec<String>>,
+    pub dir_permissions: Option<Vec<String>>,
+    pub executable_percentage: Option<f64>,
+    #[serde(default, deserialize_with = "from_str")]
+    pub permission_mode: Option<PermissionMode>,
+    #[serde(default, deserialize_with = "from_strs")]
+    pub permission_rules: Option<Vec<PermissionRule>>,
+    pub fio_output: Option<PathBuf>,
+    pub manifest: Option<PathBuf>,
+    pub compare_with: Option<Vec<PathBuf>>,
+    pub validate: Option<bool>,
+    pub replica_dirs: Option<Vec<PathBuf>>,
+    pub replicas: Option<std::num::NonZeroUsize>,
+    pub shards: Option<Vec<PathBuf>>,
+    pub distinct_seeds: Option<bool>,
+    pub prune_globs: Option<Vec<String>>,
+    #[serde(default, deserialize_with = "from_str")]
+    pub dir_fanout: Option<DirFanout>,
+    #[serde(default, deserialize_with = "from_strs")]
+    pub levels: Option<Vec<LevelOverride>>,
+    pub check_distributions: Option<bool>,
+    #[serde(default, deserialize_with = "from_str")]
+    pub traversal: Option<TraversalOrder>,
+    pub max_in_flight_bytes: Option<NonZeroU64>,
+    pub max_files_per_task: Option<std::num::NonZeroUsize>,
+    pub byte_counts_pool_capacity: Option<std::num::NonZeroUsize>,
+    pub write_block_size: Option<std::num::NonZeroUsize>,
+    pub whiteout_percentage: Option<f64>,
+    pub portable_create: Option<bool>,
+    #[serde(default, deserialize_with = "from_str")]
+    pub name_style: Option<NameStyle>,
+    #[serde(default, deserialize_with = "from_str")]
+    pub file_name_template: Option<NameTemplate>,
+    #[serde(default, deserialize_with = "from_str")]
+    pub dir_name_template: Option<NameTemplate>,
+    #[serde(default, deserialize_with = "from_str")]
+    pub name_length: Option<NameLengthRange>,
+    pub max_name_percentage: Option<f64>,
+    #[serde(default, deserialize_with = "from_str")]
+    pub extensions: Option<ExtensionWeights>,
+    pub case_collision_percentage: Option<f64>,
+    pub broken_symlink_percentage: Option<f64>,
+    pub special_files: Option<f64>,
+    pub acl_percentage: Option<f64>,
+    #[serde(default, deserialize_with = "from_str")]
+    pub mtime_range: Option<TimeRange>,
+    pub timestamp: Option<i64>,
+    pub windows_attribute_percentage: Option<f64>,
+    pub ads_percentage: Option<f64>,
+    #[serde(default, deserialize_with = "from_str")]
+    pub links_per_inode: Option<DirFanout>,
+    pub append_pass: Option<NonZeroU64>,
+    pub append_percentage: Option<f64>,
+    pub modify_percentage: Option<f64>,
+    pub modified_output: Option<PathBuf>,
+    pub symlink_loops: Option<std::num::NonZeroUsize>,
+    #[serde(default, deserialize_with = "from_str")]
+    pub link_flavor: Option<LinkFlavor>,
+    pub read_back: Option<f64>,
+    #[serde(default, deserialize_with = "from_str")]
+    pub leftover_strategy: Option<LeftoverStrategy>,
+    #[serde(default, deserialize_with = "from_str")]
+    pub size_distribution: Option<SizeDistribution>,
+    pub file_headers: Option<bool>,
+    pub magic_bytes: Option<bool>,
+    #[serde(default, deserialize_with = "from_str")]
+    pub content: Option<ContentMode>,
+    pub csv_columns: Option<NonZeroU8>,
+    pub dedup_percentage: Option<f64>,
+    pub dedup_blocks: Option<NonZeroU32>,
+    pub preset: Option<Preset>,
+    /// Trees generated one after the other into their own directories of the
+    /// root directory, each overriding the rest of the configuration.
+    pub layers: Option<Vec<Config>>,
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -37,6 +146,42 @@ pub enum ConfigError {
 impl Config {
     pub fn from_file(path: &Path) -> Result<Self, ConfigError> {
         let content = fs::read_to_string(path).change_context(ConfigError::Read)?;
-        toml::from_str(&content).change_context(ConfigError::Parse)
+        let config = toml::from_str::<Self>(&content).change_context(ConfigError::Parse)?;
+        if config
+            .layers
+            .iter()
+            .flatten()
+            .any(|layer| layer.layers.is_some())
+        {
+            return Err(error_stack::report!(ConfigError::Parse))
+                .attach_printable("Layers cannot have layers of their own.");
+        }
+        Ok(config)
     }
 }
+
+fn from_str<'de, D, T>(deserializer: D) -> std::result::Result<Option<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: Display,
+{
+    Option::<String>::deserialize(deserializer)?
+        .map(|s| s.parse().map_err(D::Error::custom))
+        .transpose()
+}
+
+fn from_strs<'de, D, T>(deserializer: D) -> std::result::Result<Option<Vec<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: FromStr,
+    T::Err: Display,
+{
+    Option::<Vec<String>>::deserialize(deserializer)?
+        .map(|strs| {
+            strs.iter()
+                .map(|s| s.parse().map_err(D::Error::custom))
+                .collect()
+        })
+        .transpose()
+}
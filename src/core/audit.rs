@@ -1,11 +1,21 @@
 use std::{
+    borrow::Cow,
     collections::HashMap,
-    hash::Hasher,
-    io::{self, Write},
+    ffi::OsStr,
+    fs::{self, File},
+    hash::{Hash, Hasher},
+    io::{self, BufWriter, IoSlice, Write},
+    num::{NonZeroU64, NonZeroUsize},
     path::{Path, PathBuf},
-    sync::Mutex,
+    sync::{
+        Arc, Mutex,
+        mpsc::{self, SyncSender},
+    },
+    thread::{self, JoinHandle},
+    time::{SystemTime, UNIX_EPOCH},
 };
 
+use rusqlite::{params_from_iter, types::Value};
 use serde::Serialize;
 use twox_hash::XxHash64;
 
@@ -14,174 +24,1049 @@ use twox_hash::XxHash64;
 pub enum EntryType {
     File,
     Directory,
+    Fifo,
+    Socket,
+    CharDevice,
+    BlockDevice,
+    /// A symbolic link to a directory, or to a file sharing its contents.
+    Symlink,
+    /// An NTFS junction, created in place of a directory symlink on Windows
+    /// when symlinks need privileges the process doesn't hold.
+    Junction,
 }
 
 #[derive(Debug, Serialize, Clone)]
 pub struct AuditEntry {
-    pub path: PathBuf,
+    /// The entry's parent directory in the trail's [`PathArena`].
+    pub parent: DirId,
+    pub name: Box<OsStr>,
     pub entry_type: EntryType,
     pub size: u64,
-    pub hash: Option<String>,
+    pub hash: Option<u64>,
+    /// The mode the entry ended up with, if the platform has such a thing.
     pub permissions: Option<u32>,
     pub owner: Option<String>,
+    /// The access ACL applied to the entry, in short text form.
+    pub acl: Option<String>,
+    /// The modification time given to the entry, in seconds since the Unix
+    /// epoch.
+    pub mtime: Option<i64>,
+    /// The Windows file attributes set on the entry, out of the read-only,
+    /// hidden, and system ones.
+    pub file_attributes: Option<u32>,
+    /// The alternate data streams written to the entry, as `name:size` pairs
+    /// separated by semicolons.
+    pub streams: Option<String>,
     pub is_duplicate: bool,
+    /// The MIME type of the file's contents, if it is known.
+    pub mime: Option<&'static str>,
+}
+
+impl AuditEntry {
+    /// The extension of the file's name, if it is a file and has one.
+    pub fn extension(&self) -> Option<Cow<'_, str>> {
+        if self.entry_type != EntryType::File {
+            return None;
+        }
+        Path::new(&self.name)
+            .extension()
+            .map(OsStr::to_string_lossy)
+    }
+}
+
+/// Optional attributes recorded for entries that were generated with them.
+#[derive(Debug, Default, Clone)]
+pub struct EntryAttributes {
+    pub acl: Option<String>,
+    pub mtime: Option<i64>,
+    pub file_attributes: Option<u32>,
+    pub streams: Option<String>,
+    pub mime: Option<&'static str>,
+}
+
+/// The MIME type of files named with `extension`, for the extensions files
+/// are commonly generated with.
+pub fn mime_type(extension: &str) -> Option<&'static str> {
+    Some(match extension.to_ascii_lowercase().as_str() {
+        "txt" | "log" => "text/plain",
+        "md" => "text/markdown",
+        "csv" => "text/csv",
+        "html" | "htm" => "text/html",
+        "json" => "application/json",
+        "jsonl" | "ndjson" => "application/jsonl",
+        "xml" => "application/xml",
+        "pdf" => "application/pdf",
+        "zip" => "application/zip",
+        "gz" | "tgz" => "application/gzip",
+        "jar" => "application/java-archive",
+        "epub" => "application/epub+zip",
+        "odt" => "application/vnd.oasis.opendocument.text",
+        "docx" => "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+        "xlsx" => "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+        "pptx" => "application/vnd.openxmlformats-officedocument.presentationml.presentation",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "mp3" => "audio/mpeg",
+        "m4a" => "audio/mp4",
+        "mp4" => "video/mp4",
+        "mov" => "video/quicktime",
+        _ => return None,
+    })
+}
+
+/// The Windows file attributes that can be generated.
+pub const FILE_ATTRIBUTE_READONLY: u32 = 0x1;
+pub const FILE_ATTRIBUTE_HIDDEN: u32 = 0x2;
+pub const FILE_ATTRIBUTE_SYSTEM: u32 = 0x4;
+
+pub type DirId = u32;
+
+/// The file format an audit trail is written in.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum AuditFormat {
+    /// Comma-separated values, preceded by the metadata as comments.
+    Csv,
+    /// An SQLite database.
+    Sqlite,
+    /// A single JSON array of records.
+    Json,
+    /// One JSON record per line.
+    Jsonl,
+}
+
+impl AuditFormat {
+    /// Picks the format matching `path`'s extension, falling back to CSV.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|s| s.to_str()) {
+            Some("db" | "sqlite") => Self::Sqlite,
+            Some("json") => Self::Json,
+            Some("jsonl" | "ndjson") => Self::Jsonl,
+            _ => Self::Csv,
+        }
+    }
+}
+
+/// An audit entry as written to JSON trails, with the same representation
+/// of each column as in CSV trails.
+#[derive(Serialize)]
+struct JsonEntry<'a> {
+    path: &'a str,
+    #[serde(rename = "type")]
+    entry_type: EntryType,
+    size: u64,
+    hash: Option<String>,
+    permissions: Option<String>,
+    owner: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    acl: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mtime: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    attributes: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    streams: Option<&'a str>,
+    is_duplicate: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    extension: Option<Cow<'a, str>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mime: Option<&'a str>,
+}
+
+/// Formats `time` as an RFC 3339 UTC timestamp with second precision.
+pub fn timestamp(time: SystemTime) -> String {
+    let secs = time.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    let (days, secs) = (secs / 86400, secs % 86400);
+
+    // Howard Hinnant's days_from_civil inverse, with days counted from
+    // 0000-03-01 so leap days fall at the end of the year.
+    let days = days + 719_468;
+    let era = days / 146_097;
+    let day_of_era = days % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let shifted_month = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+    let month = if shifted_month < 10 {
+        shifted_month + 3
+    } else {
+        shifted_month - 9
+    };
+    let year = era * 400 + year_of_era + u64::from(month <= 2);
+
+    format!(
+        "{year:04}-{month:02}-{day:02}T{:02}:{:02}:{:02}Z",
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60
+    )
+}
+
+/// How an SQLite audit trail is written.
+#[derive(Debug, Clone, Copy)]
+pub struct SqliteSettings {
+    /// The number of rows inserted per statement.
+    pub batch_size: NonZeroUsize,
+    /// The page size of newly created databases.
+    pub page_size: u32,
+    /// The memory, in bytes, SQLite may use to cache pages.
+    pub cache_size: NonZeroU64,
+}
+
+/// The version of the SQLite audit schema, bumped whenever its tables change.
+///
+/// Trails predating the version table are considered version 1.
+const SQLITE_SCHEMA_VERSION: u32 = 7;
+
+fn sqlite_schema_version(conn: &rusqlite::Connection) -> rusqlite::Result<Option<u32>> {
+    let has_versions: bool = conn.query_row(
+        "SELECT EXISTS(SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = 'schema_version')",
+        [],
+        |row| row.get(0),
+    )?;
+    if !has_versions {
+        return Ok(None);
+    }
+    conn.query_row("SELECT MAX(version) FROM schema_version", [], |row| {
+        row.get(0)
+    })
+}
+
+/// Interns directory paths so every entry within a directory shares a single
+/// copy of its prefix instead of holding a full path of its own.
+#[derive(Debug, Default)]
+pub struct PathArena {
+    dirs: Vec<Arc<Path>>,
+    ids: HashMap<Arc<Path>, DirId>,
+}
+
+impl PathArena {
+    fn intern(&mut self, dir: &Path) -> DirId {
+        if let Some(&id) = self.ids.get(dir) {
+            return id;
+        }
+
+        let id = DirId::try_from(self.dirs.len()).expect("Too many directories to audit");
+        let dir = Arc::<Path>::from(dir);
+        self.dirs.push(dir.clone());
+        self.ids.insert(dir, id);
+        id
+    }
+
+    fn dir(&self, id: DirId) -> &Path {
+        &self.dirs[id as usize]
+    }
+
+    fn path(&self, entry: &AuditEntry) -> PathBuf {
+        self.dir(entry.parent).join(&*entry.name)
+    }
+}
+
+#[derive(Debug, Default)]
+struct Entries {
+    list: Vec<AuditEntry>,
+    arena: PathArena,
+    /// The size of the streamed files directly within each directory, which
+    /// aren't kept in the list.
+    streamed_sizes: Vec<u64>,
+}
+
+impl Entries {
+    fn push(&mut self, path: &Path, make_entry: impl FnOnce(DirId, Box<OsStr>) -> AuditEntry) {
+        let (parent, name) = self.split(path);
+        self.list.push(make_entry(parent, name));
+    }
+
+    fn split(&mut self, path: &Path) -> (DirId, Box<OsStr>) {
+        let parent = self.arena.intern(path.parent().unwrap_or(Path::new("")));
+        (parent, path.file_name().unwrap_or_default().into())
+    }
+}
+
+/// The number of records a streaming trail may queue for its writer before
+/// recording blocks.
+const STREAM_CAPACITY: usize = 16 * 1024;
+
+#[derive(Debug)]
+enum Record {
+    Metadata(String, String),
+    Entry(PathBuf, AuditEntry),
+}
+
+/// The channel through which a streaming trail hands records to the thread
+/// writing them out.
+#[derive(Debug)]
+struct Stream {
+    sender: Mutex<Option<SyncSender<Record>>>,
+    writer: Mutex<Option<JoinHandle<io::Result<()>>>>,
+}
+
+impl Stream {
+    fn send(&self, record: Record) {
+        let sender = self.sender.lock().unwrap().clone();
+        // A writer that gave up reports why once the stream is finished
+        if let Some(sender) = sender {
+            let _ = sender.send(record);
+        }
+    }
 }
 
 #[derive(Debug)]
 pub struct AuditTrail {
-    entries: Mutex<Vec<AuditEntry>>,
+    entries: Mutex<Entries>,
+    metadata: Mutex<Vec<(String, String)>>,
+    stream: Option<Stream>,
 }
 
 impl AuditTrail {
-    #[allow(clippy::missing_const_for_fn)]
     pub fn new() -> Self {
         Self {
-            entries: Mutex::new(Vec::new()),
+            entries: Mutex::new(Entries::default()),
+            metadata: Mutex::new(Vec::new()),
+            stream: None,
+        }
+    }
+
+    /// Creates a trail which writes files out to `path` from a background
+    /// thread as they are recorded instead of holding onto them, such that
+    /// memory use doesn't grow with the number of files.
+    ///
+    /// Only directories are kept until [`Self::finish_stream`] since their
+    /// sizes aren't known before then, so such trails can't be used for
+    /// anything that revisits files.
+    pub fn streaming(path: &Path, format: AuditFormat, sqlite: SqliteSettings) -> io::Result<Self> {
+        let mut sink = open_sink(path, format, sqlite)?;
+        let (sender, receiver) = mpsc::sync_channel(STREAM_CAPACITY);
+        let writer = thread::Builder::new()
+            .name("audit-writer".to_string())
+            .spawn(move || {
+                for record in receiver {
+                    match record {
+                        Record::Metadata(key, value) => sink.metadata(&key, &value)?,
+                        Record::Entry(path, entry) => sink.entry(&path, &entry)?,
+                    }
+                }
+                sink.finish()
+            })?;
+
+        Ok(Self {
+            stream: Some(Stream {
+                sender: Mutex::new(Some(sender)),
+                writer: Mutex::new(Some(writer)),
+            }),
+            ..Self::new()
+        })
+    }
+
+    /// Records a key/value pair describing the run as a whole, written ahead
+    /// of the entries unless the trail is streaming and entries have already
+    /// been written.
+    pub fn add_metadata(&self, key: String, value: String) {
+        if let Some(stream) = &self.stream {
+            stream.send(Record::Metadata(key.clone(), value.clone()));
         }
+        self.metadata.lock().unwrap().push((key, value));
     }
 
     pub fn add_file(
         &self,
-        path: PathBuf,
+        path: &Path,
         size: u64,
         hash: Option<u64>,
         is_duplicate: bool,
         permission: Option<u32>,
+        EntryAttributes {
+            acl,
+            mtime,
+            file_attributes,
+            streams,
+            mime,
+        }: EntryAttributes,
     ) {
-        let mut entries = self.entries.lock().unwrap();
-        entries.push(AuditEntry {
-            path,
+        self.add_entry(path, size, |parent, name| AuditEntry {
+            parent,
+            name,
             entry_type: EntryType::File,
             size,
-            hash: hash.map(|h| format!("{h:016x}")),
-            permissions: Some(permission.unwrap_or(0o644)),
+            hash,
+            permissions: permission,
             owner: None,
+            acl,
+            mtime,
+            file_attributes,
+            streams,
             is_duplicate,
+            mime,
+        });
+    }
+
+    /// Records a FIFO, socket, device node, or directory link.
+    pub fn add_special(
+        &self,
+        path: &Path,
+        entry_type: EntryType,
+        permission: Option<u32>,
+        mtime: Option<i64>,
+    ) {
+        self.add_entry(path, 0, |parent, name| AuditEntry {
+            parent,
+            name,
+            entry_type,
+            size: 0,
+            hash: None,
+            permissions: permission,
+            owner: None,
+            acl: None,
+            mtime,
+            file_attributes: None,
+            streams: None,
+            is_duplicate: false,
+            mime: None,
         });
     }
 
-    pub fn add_directory(&self, path: PathBuf, permission: Option<u32>) {
+    fn add_entry(
+        &self,
+        path: &Path,
+        size: u64,
+        make_entry: impl FnOnce(DirId, Box<OsStr>) -> AuditEntry,
+    ) {
         let mut entries = self.entries.lock().unwrap();
-        entries.push(AuditEntry {
-            path,
+        if let Some(stream) = &self.stream {
+            let (parent, name) = entries.split(path);
+            let id = parent as usize;
+            if entries.streamed_sizes.len() <= id {
+                entries.streamed_sizes.resize(id + 1, 0);
+            }
+            entries.streamed_sizes[id] += size;
+            drop(entries);
+
+            stream.send(Record::Entry(path.to_path_buf(), make_entry(parent, name)));
+        } else {
+            entries.push(path, make_entry);
+        }
+    }
+
+    pub fn add_directory(
+        &self,
+        path: &Path,
+        permission: Option<u32>,
+        EntryAttributes {
+            acl,
+            mtime,
+            file_attributes,
+            streams,
+            mime,
+        }: EntryAttributes,
+    ) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.push(path, |parent, name| AuditEntry {
+            parent,
+            name,
             entry_type: EntryType::Directory,
             size: 0, // Will be calculated later
             hash: None,
-            permissions: Some(permission.unwrap_or(0o755)),
+            permissions: permission,
             owner: None,
+            acl,
+            mtime,
+            file_attributes,
+            streams,
             is_duplicate: false,
+            mime,
         });
     }
 
-    pub fn calculate_directory_sizes(&self) {
+    /// The path, size, and hash of every recorded file.
+    pub fn files(&self) -> Vec<(PathBuf, u64, Option<u64>)> {
+        let entries = self.entries.lock().unwrap();
+        entries
+            .list
+            .iter()
+            .filter(|entry| entry.entry_type == EntryType::File)
+            .map(|entry| (entries.arena.path(entry), entry.size, entry.hash))
+            .collect()
+    }
+
+    /// Replaces the size and hash of files whose contents changed after they
+    /// were recorded.
+    pub fn update_files(&self, updates: &HashMap<PathBuf, (u64, Option<u64>)>) {
         let mut entries = self.entries.lock().unwrap();
+        let Entries {
+            ref mut list,
+            ref arena,
+            streamed_sizes: _,
+        } = *entries;
 
-        // Map to store directory sizes
-        let mut dir_sizes: HashMap<PathBuf, u64> = HashMap::new();
+        for entry in list.iter_mut() {
+            if entry.entry_type == EntryType::File
+                && let Some(&(size, hash)) = updates.get(&arena.path(entry))
+            {
+                entry.size = size;
+                entry.hash = hash;
+            }
+        }
+    }
 
-        // First, collect all file sizes and add them to their parent directories
-        for entry in entries.iter() {
+    pub fn calculate_directory_sizes(&self) {
+        let mut entries = self.entries.lock().unwrap();
+        let Entries {
+            ref mut list,
+            ref arena,
+            ref streamed_sizes,
+        } = *entries;
+
+        // First, collect the size of the files directly within each directory
+        let mut own_sizes = streamed_sizes.clone();
+        own_sizes.resize(arena.dirs.len(), 0);
+        for entry in list.iter() {
             if entry.entry_type == EntryType::File {
-                let mut current = entry.path.parent();
-                while let Some(parent) = current {
-                    *dir_sizes.entry(parent.to_path_buf()).or_insert(0) += entry.size;
-                    current = parent.parent();
-                }
+                own_sizes[entry.parent as usize] += entry.size;
+            }
+        }
+
+        // Then add them to every ancestor
+        let mut dir_sizes: HashMap<&Path, u64> = HashMap::new();
+        for (id, size) in own_sizes.into_iter().enumerate() {
+            if size == 0 {
+                continue;
+            }
+            for ancestor in arena.dirs[id].ancestors() {
+                *dir_sizes.entry(ancestor).or_insert(0) += size;
             }
         }
 
         // Update directory entries with calculated sizes
-        for entry in entries.iter_mut() {
-            if let (EntryType::Directory, Some(&size)) =
-                (entry.entry_type, dir_sizes.get(&entry.path))
+        for entry in list.iter_mut() {
+            if entry.entry_type == EntryType::Directory
+                && let Some(&size) = dir_sizes.get(arena.path(entry).as_path())
             {
                 entry.size = size;
             }
         }
     }
 
-    pub fn write_csv(&self, path: &Path) -> io::Result<()> {
+    /// Writes the trail to `path` in the given format, replacing any SQLite
+    /// trail written with an older schema.
+    pub fn write(
+        &self,
+        path: &Path,
+        format: AuditFormat,
+        sqlite: SqliteSettings,
+    ) -> io::Result<()> {
         let entries = self.entries.lock().unwrap();
-        let mut wtr = csv::Writer::from_path(path)?;
-
-        // Write header
-        wtr.write_record([
-            "path",
-            "type",
-            "size",
-            "hash",
-            "permissions",
-            "owner",
-            "is_duplicate",
-        ])?;
+        let mut sink = open_sink(path, format, sqlite)?;
+        for (key, value) in self.metadata.lock().unwrap().iter() {
+            sink.metadata(key, value)?;
+        }
+        for entry in &entries.list {
+            sink.entry(&entries.arena.path(entry), entry)?;
+        }
+        drop(entries);
 
-        for entry in entries.iter() {
-            wtr.write_record([
-                entry.path.to_string_lossy().as_ref(),
-                match entry.entry_type {
-                    EntryType::File => "file",
-                    EntryType::Directory => "directory",
-                },
-                entry.size.to_string().as_str(),
-                entry.hash.as_deref().unwrap_or(""),
-                entry
-                    .permissions
-                    .map(|p| format!("{p:o}"))
-                    .unwrap_or_default()
-                    .as_str(),
-                entry.owner.as_deref().unwrap_or(""),
-                if entry.is_duplicate { "true" } else { "false" },
-            ])?;
+        sink.finish()
+    }
+
+    /// Writes out the directories of a streaming trail and waits for all of
+    /// its records to be written.
+    pub fn finish_stream(&self) -> io::Result<()> {
+        let Some(stream) = &self.stream else {
+            return Ok(());
+        };
+
+        let entries = self.entries.lock().unwrap();
+        for entry in &entries.list {
+            stream.send(Record::Entry(entries.arena.path(entry), entry.clone()));
         }
         drop(entries);
-        wtr.flush()?;
-        Ok(())
+
+        // Hanging up lets the writer drain the channel and finish
+        drop(stream.sender.lock().unwrap().take());
+        let writer = stream.writer.lock().unwrap().take();
+        writer.map_or(Ok(()), |writer| {
+            writer
+                .join()
+                .unwrap_or_else(|_| Err(io::Error::other("Audit writer panicked")))
+        })
     }
 
-    pub fn write_sqlite(&self, path: &Path) -> rusqlite::Result<()> {
+    /// Computes a digest of the recorded entries that is independent of the
+    /// order in which they were generated and of where `root` is located.
+    pub fn digest(&self, root: &Path) -> u64 {
         let entries = self.entries.lock().unwrap();
-        let mut conn = rusqlite::Connection::open(path)?;
+        let mut paths = entries
+            .list
+            .iter()
+            .map(|entry| (entries.arena.path(entry), entry))
+            .collect::<Vec<_>>();
+        paths.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
 
-        conn.execute(
-            "CREATE TABLE IF NOT EXISTS audit_entries (
+        let mut hasher = XxHash64::with_seed(0);
+        for (path, entry) in paths {
+            path.strip_prefix(root).unwrap_or(&path).hash(&mut hasher);
+            (
+                entry.entry_type == EntryType::File,
+                entry.size,
+                entry.hash,
+                entry.permissions,
+                entry.is_duplicate,
+            )
+                .hash(&mut hasher);
+            if !matches!(entry.entry_type, EntryType::File | EntryType::Directory) {
+                type_name(entry.entry_type).hash(&mut hasher);
+            }
+        }
+        hasher.finish()
+    }
+
+    /// Writes the trail's metadata followed by a digest of its entries, closed
+    /// off by a checksum of everything before it.
+    pub fn write_manifest(&self, path: &Path, root: &Path) -> io::Result<()> {
+        let mut manifest = String::new();
+        for (key, value) in self.metadata.lock().unwrap().iter() {
+            manifest.push_str(&format!("{key}: {value}\n"));
+        }
+        manifest.push_str(&format!("audit digest: {:016x}\n", self.digest(root)));
+        let checksum = XxHash64::oneshot(0, manifest.as_bytes());
+        manifest.push_str(&format!("checksum: {checksum:016x}\n"));
+
+        fs::write(path, manifest)
+    }
+
+    /// Writes an fio job file with one sequential read job per non-empty file so
+    /// the generated population can be replayed through fio's IO engines.
+    ///
+    /// Empty files are skipped since fio refuses to run jobs without any IO to
+    /// perform.
+    pub fn write_fio(&self, path: &Path) -> io::Result<()> {
+        let entries = self.entries.lock().unwrap();
+        let mut out = BufWriter::new(File::create(path)?);
+
+        writeln!(out, "; Generated by ftzz {}", env!("CARGO_PKG_VERSION"))?;
+        writeln!(out, "[global]")?;
+        writeln!(out, "rw=read")?;
+        writeln!(out, "bs=128k")?;
+        writeln!(out, "ioengine=psync")?;
+        writeln!(out, "stonewall")?;
+
+        for (i, entry) in entries
+            .list
+            .iter()
+            .filter(|e| e.entry_type == EntryType::File && e.size > 0)
+            .enumerate()
+        {
+            writeln!(out)?;
+            writeln!(out, "[file{i}]")?;
+            // Colons separate multiple files in fio's filename option
+            writeln!(
+                out,
+                "filename={}",
+                entries
+                    .arena
+                    .path(entry)
+                    .to_string_lossy()
+                    .replace(':', "\\:")
+            )?;
+            writeln!(out, "size={}", entry.size)?;
+        }
+        drop(entries);
+
+        out.flush()
+    }
+}
+
+/// A destination audit trails are written to, one record at a time.
+trait AuditSink: Send {
+    fn metadata(&mut self, key: &str, value: &str) -> io::Result<()>;
+
+    fn entry(&mut self, path: &Path, entry: &AuditEntry) -> io::Result<()>;
+
+    fn finish(self: Box<Self>) -> io::Result<()>;
+}
+
+fn open_sink(
+    path: &Path,
+    format: AuditFormat,
+    sqlite: SqliteSettings,
+) -> io::Result<Box<dyn AuditSink>> {
+    Ok(match format {
+        AuditFormat::Csv => Box::new(CsvSink::new(path)?),
+        AuditFormat::Sqlite => Box::new(SqliteSink::new(path, sqlite).map_err(io::Error::other)?),
+        AuditFormat::Json => Box::new(JsonSink::new(path, false)?),
+        AuditFormat::Jsonl => Box::new(JsonSink::new(path, true)?),
+    })
+}
+
+/// Lists Windows file attributes the way `attrib` does: `R` for read-only,
+/// `H` for hidden, and `S` for system.
+fn attribute_letters(attributes: u32) -> String {
+    [
+        (FILE_ATTRIBUTE_READONLY, 'R'),
+        (FILE_ATTRIBUTE_HIDDEN, 'H'),
+        (FILE_ATTRIBUTE_SYSTEM, 'S'),
+    ]
+    .into_iter()
+    .filter(|&(attribute, _)| attributes & attribute != 0)
+    .map(|(_, letter)| letter)
+    .collect()
+}
+
+const fn type_name(entry_type: EntryType) -> &'static str {
+    match entry_type {
+        EntryType::File => "file",
+        EntryType::Directory => "directory",
+        EntryType::Fifo => "fifo",
+        EntryType::Socket => "socket",
+        EntryType::CharDevice => "char_device",
+        EntryType::BlockDevice => "block_device",
+        EntryType::Symlink => "symlink",
+        EntryType::Junction => "junction",
+    }
+}
+
+struct CsvSink {
+    /// Metadata is written as comments straight to the file until the first
+    /// entry arrives, at which point the CSV writer takes it over.
+    out: Option<BufWriter<File>>,
+    records: Option<csv::Writer<BufWriter<File>>>,
+    /// Metadata arriving after the first entry, written as trailing comments.
+    trailing: Vec<(String, String)>,
+}
+
+impl CsvSink {
+    fn new(path: &Path) -> io::Result<Self> {
+        Ok(Self {
+            out: Some(BufWriter::new(File::create(path)?)),
+            records: None,
+            trailing: Vec::new(),
+        })
+    }
+}
+
+impl AuditSink for CsvSink {
+    fn metadata(&mut self, key: &str, value: &str) -> io::Result<()> {
+        match &mut self.out {
+            Some(out) => writeln!(out, "# {key}: {value}"),
+            None => {
+                self.trailing.push((key.to_string(), value.to_string()));
+                Ok(())
+            }
+        }
+    }
+
+    fn entry(&mut self, path: &Path, entry: &AuditEntry) -> io::Result<()> {
+        let records = match (&mut self.records, self.out.take()) {
+            (Some(records), _) => records,
+            (None, out) => {
+                let mut records = csv::Writer::from_writer(out.expect("CSV output went missing"));
+                records.write_record([
+                    "path",
+                    "type",
+                    "size",
+                    "hash",
+                    "permissions",
+                    "owner",
+                    "is_duplicate",
+                    "acl",
+                    "mtime",
+                    "attributes",
+                    "streams",
+                    "extension",
+                    "mime",
+                ])?;
+                self.records.insert(records)
+            }
+        };
+
+        records.write_record([
+            path.to_string_lossy().as_ref(),
+            type_name(entry.entry_type),
+            entry.size.to_string().as_str(),
+            entry
+                .hash
+                .map(|h| format!("{h:016x}"))
+                .unwrap_or_default()
+                .as_str(),
+            entry
+                .permissions
+                .map_or_else(|| "n/a".to_string(), |p| format!("{p:o}"))
+                .as_str(),
+            entry.owner.as_deref().unwrap_or(""),
+            if entry.is_duplicate { "true" } else { "false" },
+            entry.acl.as_deref().unwrap_or(""),
+            entry
+                .mtime
+                .map(|mtime| mtime.to_string())
+                .unwrap_or_default()
+                .as_str(),
+            entry
+                .file_attributes
+                .map(attribute_letters)
+                .unwrap_or_default()
+                .as_str(),
+            entry.streams.as_deref().unwrap_or_default(),
+            entry.extension().unwrap_or_default().as_ref(),
+            entry.mime.unwrap_or_default(),
+        ])?;
+        Ok(())
+    }
+
+    fn finish(self: Box<Self>) -> io::Result<()> {
+        let Self {
+            out,
+            records,
+            trailing,
+        } = *self;
+        let mut out = match (out, records) {
+            (Some(out), _) => out,
+            (None, Some(records)) => records.into_inner().map_err(|e| e.into_error())?,
+            (None, None) => unreachable!("CSV output went missing"),
+        };
+        for (key, value) in trailing {
+            writeln!(out, "# {key}: {value}")?;
+        }
+        out.flush()
+    }
+}
+
+struct JsonSink {
+    out: BufWriter<File>,
+    /// Whether records go on their own lines rather than in an array.
+    lines: bool,
+    records: u64,
+    /// Metadata is collected into a single record written ahead of the first
+    /// entry, or after the last one if it arrives later.
+    metadata: serde_json::Map<String, serde_json::Value>,
+}
+
+impl JsonSink {
+    fn new(path: &Path, lines: bool) -> io::Result<Self> {
+        Ok(Self {
+            out: BufWriter::new(File::create(path)?),
+            lines,
+            records: 0,
+            metadata: serde_json::Map::new(),
+        })
+    }
+
+    fn record(&mut self, record: &impl Serialize) -> io::Result<()> {
+        let separator = match (self.lines, self.records) {
+            (true, _) => "",
+            (false, 0) => "[\n",
+            (false, _) => ",\n",
+        };
+        self.out.write_all(separator.as_bytes())?;
+        serde_json::to_writer(&mut self.out, record)?;
+        if self.lines {
+            self.out.write_all(b"\n")?;
+        }
+        self.records += 1;
+        Ok(())
+    }
+
+    fn flush_metadata(&mut self) -> io::Result<()> {
+        if self.metadata.is_empty() {
+            return Ok(());
+        }
+        let mut metadata = serde_json::Map::new();
+        metadata.insert("type".to_string(), "metadata".into());
+        metadata.append(&mut self.metadata);
+        self.record(&metadata)
+    }
+}
+
+impl AuditSink for JsonSink {
+    fn metadata(&mut self, key: &str, value: &str) -> io::Result<()> {
+        self.metadata.insert(key.to_string(), value.into());
+        Ok(())
+    }
+
+    fn entry(&mut self, path: &Path, entry: &AuditEntry) -> io::Result<()> {
+        if self.records == 0 {
+            self.flush_metadata()?;
+        }
+        self.record(&JsonEntry {
+            path: &path.to_string_lossy(),
+            entry_type: entry.entry_type,
+            size: entry.size,
+            hash: entry.hash.map(|h| format!("{h:016x}")),
+            permissions: entry.permissions.map(|p| format!("{p:o}")),
+            owner: entry.owner.as_deref(),
+            acl: entry.acl.as_deref(),
+            mtime: entry.mtime,
+            attributes: entry.file_attributes.map(attribute_letters),
+            streams: entry.streams.as_deref(),
+            is_duplicate: entry.is_duplicate,
+            extension: entry.extension(),
+            mime: entry.mime,
+        })
+    }
+
+    fn finish(mut self: Box<Self>) -> io::Result<()> {
+        self.flush_metadata()?;
+        if !self.lines {
+            let close = if self.records == 0 { "[]\n" } else { "\n]\n" };
+            self.out.write_all(close.as_bytes())?;
+        }
+        self.out.flush()
+    }
+}
+
+struct SqliteSink {
+    conn: rusqlite::Connection,
+    /// The number of rows inserted per statement.
+    batch_size: usize,
+    /// The values of the rows waiting to be inserted.
+    values: Vec<Value>,
+}
+
+impl SqliteSink {
+    const COLUMNS: usize = 13;
+    // SQLite's default SQLITE_MAX_VARIABLE_NUMBER
+    const MAX_ROWS: usize = 32766 / Self::COLUMNS;
+
+    /// Opens the database, replacing any trail written with an older schema.
+    ///
+    /// Entries are inserted `settings.batch_size` rows per statement, capped at
+    /// the number of rows SQLite accepts in a single statement, all within a
+    /// single transaction.
+    fn new(path: &Path, settings: SqliteSettings) -> rusqlite::Result<Self> {
+        let SqliteSettings {
+            batch_size,
+            page_size,
+            cache_size,
+        } = settings;
+        let conn = rusqlite::Connection::open(path)?;
+        // The page size only applies to new databases and must be set before
+        // switching to WAL. A negative cache size is in KiB.
+        conn.execute_batch(&format!(
+            "PRAGMA page_size = {page_size};
+             PRAGMA journal_mode = WAL;
+             PRAGMA synchronous = NORMAL;
+             PRAGMA cache_size = -{};",
+            cache_size.get().div_ceil(1024)
+        ))?;
+
+        conn.execute_batch("BEGIN")?;
+        if sqlite_schema_version(&conn)? != Some(SQLITE_SCHEMA_VERSION) {
+            conn.execute_batch(
+                "DROP TABLE IF EXISTS audit_entries;
+                 DROP TABLE IF EXISTS metadata;
+                 DROP TABLE IF EXISTS schema_version;",
+            )?;
+        }
+        conn.execute_batch(
+            "CREATE TABLE IF NOT EXISTS schema_version (
+                version INTEGER NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS audit_entries (
                 path TEXT NOT NULL,
                 type TEXT NOT NULL,
                 size INTEGER NOT NULL,
-                hash TEXT,
+                hash BLOB,
                 permissions TEXT,
                 owner TEXT,
-                is_duplicate BOOLEAN NOT NULL DEFAULT 0
-            )",
-            [],
+                is_duplicate BOOLEAN NOT NULL DEFAULT 0,
+                acl TEXT,
+                mtime INTEGER,
+                attributes TEXT,
+                streams TEXT,
+                extension TEXT,
+                mime TEXT
+            );
+            CREATE TABLE IF NOT EXISTS metadata (
+                key TEXT NOT NULL,
+                value TEXT NOT NULL
+            );",
+        )?;
+        conn.execute("DELETE FROM schema_version", [])?;
+        conn.execute(
+            "INSERT INTO schema_version (version) VALUES (?1)",
+            [SQLITE_SCHEMA_VERSION],
         )?;
 
-        let tx = conn.transaction()?;
-        {
-            let mut stmt = tx.prepare(
-                "INSERT INTO audit_entries (path, type, size, hash, permissions, owner, \
-                 is_duplicate)
-                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
-            )?;
+        let batch_size = batch_size.get().min(Self::MAX_ROWS);
+        Ok(Self {
+            conn,
+            batch_size,
+            values: Vec::with_capacity(batch_size * Self::COLUMNS),
+        })
+    }
 
-            for entry in entries.iter() {
-                stmt.execute(rusqlite::params![
-                    entry.path.to_string_lossy(),
-                    match entry.entry_type {
-                        EntryType::File => "file",
-                        EntryType::Directory => "directory",
-                    },
-                    entry.size,
-                    entry.hash,
-                    entry.permissions.map(|p| format!("{p:o}")),
-                    entry.owner,
-                    entry.is_duplicate,
-                ])?;
-            }
+    fn insert_pending(&mut self) -> rusqlite::Result<()> {
+        let rows = self.values.len() / Self::COLUMNS;
+        if rows == 0 {
+            return Ok(());
         }
-        tx.commit()?;
 
+        let row = format!("({})", ["?"; Self::COLUMNS].join(", "));
+        let insert = format!(
+            "INSERT INTO audit_entries (path, type, size, hash, permissions, owner, is_duplicate, \
+             acl, mtime, attributes, streams, extension, mime) VALUES {}",
+            vec![row; rows].join(", ")
+        );
+        self.conn
+            .prepare_cached(&insert)?
+            .execute(params_from_iter(self.values.drain(..)))?;
+        Ok(())
+    }
+
+    fn finish(mut self) -> rusqlite::Result<()> {
+        self.insert_pending()?;
+        self.conn.execute_batch(
+            "CREATE INDEX IF NOT EXISTS audit_entries_path ON audit_entries (path);
+             COMMIT;",
+        )
+    }
+}
+
+impl AuditSink for SqliteSink {
+    fn metadata(&mut self, key: &str, value: &str) -> io::Result<()> {
+        self.conn
+            .prepare_cached("INSERT INTO metadata (key, value) VALUES (?1, ?2)")
+            .and_then(|mut stmt| stmt.execute(rusqlite::params![key, value]))
+            .map_err(io::Error::other)?;
         Ok(())
     }
+
+    fn entry(&mut self, path: &Path, entry: &AuditEntry) -> io::Result<()> {
+        self.values.extend([
+            Value::Text(path.to_string_lossy().into_owned()),
+            Value::Text(type_name(entry.entry_type).to_string()),
+            Value::Integer(i64::try_from(entry.size).unwrap_or(i64::MAX)),
+            entry
+                .hash
+                .map_or(Value::Null, |h| Value::Blob(h.to_be_bytes().to_vec())),
+            Value::Text(
+                entry
+                    .permissions
+                    .map_or_else(|| "n/a".to_string(), |p| format!("{p:o}")),
+            ),
+            entry.owner.clone().map_or(Value::Null, Value::Text),
+            Value::Integer(entry.is_duplicate.into()),
+            entry.acl.clone().map_or(Value::Null, Value::Text),
+            entry.mtime.map_or(Value::Null, Value::Integer),
+            entry.file_attributes.map_or(Value::Null, |attributes| {
+                Value::Text(attribute_letters(attributes))
+            }),
+            entry.streams.clone().map_or(Value::Null, Value::Text),
+            entry
+                .extension()
+                .map_or(Value::Null, |extension| Value::Text(extension.into_owned())),
+            entry
+                .mime
+                .map_or(Value::Null, |mime| Value::Text(mime.to_string())),
+        ]);
+        if self.values.len() == self.batch_size * Self::COLUMNS {
+            self.insert_pending().map_err(io::Error::other)?;
+        }
+        Ok(())
+    }
+
+    fn finish(self: Box<Self>) -> io::Result<()> {
+        SqliteSink::finish(*self).map_err(io::Error::other)
+    }
 }
 
 pub struct HashingWriter<W: Write> {
@@ -210,7 +1095,110 @@ impl<W: Write> Write for HashingWriter<W> {
         Ok(n)
     }
 
+    fn write_vectored(&mut self, bufs: &[IoSlice<'_>]) -> io::Result<usize> {
+        let n = self.inner.write_vectored(bufs)?;
+        let mut remaining = n;
+        for buf in bufs {
+            if remaining == 0 {
+                break;
+            }
+            let written = remaining.min(buf.len());
+            self.hasher.write(&buf[..written]);
+            remaining -= written;
+        }
+        Ok(n)
+    }
+
     fn flush(&mut self) -> io::Result<()> {
         self.inner.flush()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn attributes_are_listed_as_letters() {
+        assert_eq!(attribute_letters(FILE_ATTRIBUTE_READONLY), "R");
+        assert_eq!(
+            attribute_letters(FILE_ATTRIBUTE_HIDDEN | FILE_ATTRIBUTE_SYSTEM),
+            "HS"
+        );
+        assert_eq!(attribute_letters(0x7), "RHS");
+        assert_eq!(attribute_letters(0), "");
+    }
+
+    #[test]
+    fn streaming_writes_the_same_records() {
+        let temp = tempfile::tempdir().unwrap();
+        let settings = SqliteSettings {
+            batch_size: NonZeroUsize::new(2).unwrap(),
+            page_size: 4096,
+            cache_size: NonZeroU64::new(1 << 20).unwrap(),
+        };
+        let record = |trail: &AuditTrail| {
+            trail.add_metadata("root".to_string(), "/r".to_string());
+            let none = EntryAttributes::default;
+            trail.add_directory(Path::new("/r/0.dir"), None, none());
+            trail.add_file(Path::new("/r/0.dir/0"), 3, Some(7), false, None, none());
+            trail.add_file(Path::new("/r/0.dir/1"), 5, None, true, Some(0o644), none());
+            trail.add_file(Path::new("/r/0"), 11, Some(9), false, None, none());
+            trail.add_metadata("finished".to_string(), "now".to_string());
+            trail.calculate_directory_sizes();
+        };
+        let sorted_lines = |path: &Path| {
+            let mut lines = fs::read_to_string(path)
+                .unwrap()
+                .lines()
+                .map(str::to_string)
+                .collect::<Vec<_>>();
+            lines.sort_unstable();
+            lines
+        };
+
+        let buffered = AuditTrail::new();
+        record(&buffered);
+        let buffered_path = temp.path().join("buffered.jsonl");
+        buffered
+            .write(&buffered_path, AuditFormat::Jsonl, settings)
+            .unwrap();
+
+        let streamed_path = temp.path().join("streamed.jsonl");
+        let streamed = AuditTrail::streaming(&streamed_path, AuditFormat::Jsonl, settings).unwrap();
+        record(&streamed);
+        streamed.finish_stream().unwrap();
+
+        let entries = |lines: Vec<String>| {
+            lines
+                .into_iter()
+                .filter(|line| !line.contains(r#""type":"metadata""#))
+                .collect::<Vec<_>>()
+        };
+        let streamed = sorted_lines(&streamed_path);
+        assert_eq!(
+            entries(sorted_lines(&buffered_path)),
+            entries(streamed.clone())
+        );
+        assert!(streamed.contains(&r#"{"path":"/r/0.dir","type":"directory","size":8,"hash":null,"permissions":null,"owner":null,"is_duplicate":false}"#.to_string()));
+        // Metadata recorded after the first entry trails the entries
+        assert_eq!(
+            fs::read_to_string(&streamed_path).unwrap().lines().last(),
+            Some(r#"{"finished":"now","type":"metadata"}"#)
+        );
+    }
+
+    #[test]
+    fn timestamps_are_rfc3339() {
+        for (secs, expected) in [
+            (0, "1970-01-01T00:00:00Z"),
+            (951_782_400, "2000-02-29T00:00:00Z"),
+            (1_709_251_199, "2024-02-29T23:59:59Z"),
+            (4_102_444_800, "2100-01-01T00:00:00Z"),
+        ] {
+            assert_eq!(timestamp(UNIX_EPOCH + Duration::from_secs(secs)), expected);
+        }
+    }
+}
@@ -0,0 +1,335 @@
+#![allow(clippy::cast_precision_loss)]
+
+use std::{collections::BTreeMap, f64::consts::SQRT_2, fs, io, path::Path};
+
+use error_stack::{Result, ResultExt};
+use rand_distr::Normal;
+
+/// The outcome of a goodness-of-fit test.
+#[derive(Debug, Copy, Clone)]
+pub enum FitTest {
+    KolmogorovSmirnov {
+        statistic: f64,
+        samples: usize,
+        p_value: f64,
+    },
+    ChiSquare {
+        statistic: f64,
+        degrees_of_freedom: usize,
+        samples: usize,
+        p_value: f64,
+    },
+    /// Too few samples were available to run the test.
+    InsufficientData { samples: usize },
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct DistributionReport {
+    pub file_sizes: Option<FitTest>,
+    pub dir_fanout: FitTest,
+}
+
+/// Walks the generated tree and tests the achieved file sizes and directory
+/// fan-out against the requested distributions.
+///
+/// File sizes are tested against `file_sizes` (if any) with a
+/// Kolmogorov-Smirnov test. The number of subdirectories of every directory
+/// above `max_depth` is tested against `fanout_pmf` with a chi-square test
+/// where zero and one subdirectories share a bucket since directories
+/// containing files always get at least one subdirectory.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "trace", skip(fanout_pmf))
+)]
+pub fn check_distributions(
+    root_dir: &Path,
+    max_depth: usize,
+    file_sizes: Option<&Normal<f64>>,
+    fanout_pmf: impl Fn(u64) -> f64,
+) -> Result<DistributionReport, io::Error> {
+    let mut sizes = Vec::new();
+    let mut fanouts = BTreeMap::<u64, usize>::new();
+
+    let mut level = vec![root_dir.to_path_buf()];
+    for depth in 0.. {
+        if level.is_empty() {
+            break;
+        }
+
+        let mut next = Vec::new();
+        for dir in level {
+            let mut subdirs = 0;
+            for entry in fs::read_dir(&dir)
+                .attach_printable_lazy(|| format!("Failed to read directory {dir:?}"))?
+            {
+                let entry =
+                    entry.attach_printable_lazy(|| format!("Failed to read directory {dir:?}"))?;
+                let path = entry.path();
+                // Sharded top-level directories are linked into the root
+                let metadata = if depth == 0 {
+                    fs::metadata(&path)
+                } else {
+                    entry.metadata()
+                };
+                let metadata = match metadata {
+                    // A dangling link
+                    Err(e) if depth == 0 && e.kind() == io::ErrorKind::NotFound => continue,
+                    r => r.attach_printable_lazy(|| format!("Failed to stat {path:?}"))?,
+                };
+
+                if metadata.is_dir() {
+                    subdirs += 1;
+                    next.push(path);
+                } else if metadata.is_file() {
+                    sizes.push(metadata.len());
+                }
+            }
+            if depth < max_depth {
+                *fanouts.entry(subdirs).or_default() += 1;
+            }
+        }
+        level = next;
+    }
+
+    Ok(DistributionReport {
+        file_sizes: file_sizes.map(|normal| kolmogorov_smirnov(sizes, normal)),
+        dir_fanout: chi_square(&fanouts, fanout_pmf),
+    })
+}
+
+/// The CDF of a normal distribution truncated to `[0, 2 * mean)` as sampled by
+/// [`sample_truncated`](super::sample_truncated), evaluated at `x`.
+pub fn truncated_normal_cdf(normal: &Normal<f64>, x: f64) -> f64 {
+    let max = normal.mean() * 2.;
+    let cdf = |x: f64| normal_cdf(x.clamp(0., max), normal.mean(), normal.std_dev());
+    let mass = cdf(max) - cdf(0.);
+    if mass <= 0. {
+        return if x >= normal.mean() { 1. } else { 0. };
+    }
+    (cdf(x) - cdf(0.)) / mass
+}
+
+/// Sampled values are truncated to integers, so a size of `k` corresponds to a
+/// sample in `[k, k + 1)`.
+fn kolmogorov_smirnov(mut sizes: Vec<u64>, normal: &Normal<f64>) -> FitTest {
+    let samples = sizes.len();
+    if samples < 2 {
+        return FitTest::InsufficientData { samples };
+    }
+    sizes.sort_unstable();
+
+    let n = samples as f64;
+    let mut statistic = 0f64;
+    let mut below = 0;
+    for run in sizes.chunk_by(|a, b| a == b) {
+        let k = run[0] as f64;
+        let at_or_below = below + run.len();
+        statistic = statistic
+            .max((below as f64 / n - truncated_normal_cdf(normal, k)).abs())
+            .max((at_or_below as f64 / n - truncated_normal_cdf(normal, k + 1.)).abs());
+        below = at_or_below;
+    }
+
+    let sqrt_n = n.sqrt();
+    FitTest::KolmogorovSmirnov {
+        statistic,
+        samples,
+        p_value: kolmogorov_q((sqrt_n + 0.12 + 0.11 / sqrt_n) * statistic),
+    }
+}
+
+fn chi_square(observed: &BTreeMap<u64, usize>, pmf: impl Fn(u64) -> f64) -> FitTest {
+    const MIN_EXPECTED: f64 = 5.;
+
+    let samples = observed.values().sum::<usize>();
+    let n = samples as f64;
+    let max_observed = observed.keys().next_back().copied().unwrap_or(0);
+
+    // Buckets of (observed, expected) counts, each covering consecutive counts
+    let mut buckets = Vec::<(f64, f64)>::new();
+    let mut current = (0., 0.);
+    let mut remaining = 1f64;
+    let mut k = 0;
+    while k <= max_observed || remaining * n >= MIN_EXPECTED {
+        let p = pmf(k);
+        remaining -= p;
+        current.0 += observed.get(&k).copied().unwrap_or(0) as f64;
+        current.1 += p * n;
+        if k >= 1 && current.1 >= MIN_EXPECTED {
+            buckets.push(current);
+            current = (0., 0.);
+        }
+        if k >= max_observed && (remaining <= 1e-12 || p == 0.) {
+            break;
+        }
+        k += 1;
+    }
+    // Whatever is left over goes into the tail
+    current.1 += remaining.max(0.) * n;
+    match buckets.last_mut() {
+        Some(last) if current.1 < MIN_EXPECTED => {
+            last.0 += current.0;
+            last.1 += current.1;
+        }
+        _ => buckets.push(current),
+    }
+
+    if buckets.len() < 2 {
+        return FitTest::InsufficientData { samples };
+    }
+
+    let statistic = buckets
+        .iter()
+        .map(|&(observed, expected)| (observed - expected).powi(2) / expected)
+        .sum::<f64>();
+    let degrees_of_freedom = buckets.len() - 1;
+    FitTest::ChiSquare {
+        statistic,
+        degrees_of_freedom,
+        samples,
+        p_value: gamma_q(degrees_of_freedom as f64 / 2., statistic / 2.),
+    }
+}
+
+fn normal_cdf(x: f64, mean: f64, std_dev: f64) -> f64 {
+    if std_dev <= 0. {
+        return if x >= mean { 1. } else { 0. };
+    }
+    0.5 * erfc(-(x - mean) / (std_dev * SQRT_2))
+}
+
+/// Complementary error function with a fractional error below 1.2e-7.
+fn erfc(x: f64) -> f64 {
+    let z = x.abs();
+    let t = 1. / (1. + 0.5 * z);
+    let r = t
+        * (-z * z - 1.265_512_23
+            + t * (1.000_023_68
+                + t * (0.374_091_96
+                    + t * (0.096_784_18
+                        + t * (-0.186_288_06
+                            + t * (0.278_868_07
+                                + t * (-1.135_203_98
+                                    + t * (1.488_515_87
+                                        + t * (-0.822_152_23 + t * 0.170_872_77)))))))))
+            .exp();
+    if x >= 0. { r } else { 2. - r }
+}
+
+/// The Kolmogorov distribution's survival function.
+fn kolmogorov_q(lambda: f64) -> f64 {
+    if lambda < 0.2 {
+        return 1.;
+    }
+
+    let mut sum = 0.;
+    let mut sign = 2.;
+    for j in 1..=100 {
+        let term = sign * (-2. * f64::from(j * j) * lambda * lambda).exp();
+        sum += term;
+        if term.abs() < 1e-12 {
+            break;
+        }
+        sign = -sign;
+    }
+    sum.clamp(0., 1.)
+}
+
+/// The regularized upper incomplete gamma function Q(a, x).
+fn gamma_q(a: f64, x: f64) -> f64 {
+    const EPSILON: f64 = 1e-14;
+    const TINY: f64 = 1e-300;
+
+    if x <= 0. {
+        return 1.;
+    }
+
+    let ln_prefix = -x + a * x.ln() - ln_gamma(a);
+    if x < a + 1. {
+        // Series representation of P(a, x)
+        let mut ap = a;
+        let mut term = 1. / a;
+        let mut sum = term;
+        for _ in 0..1000 {
+            ap += 1.;
+            term *= x / ap;
+            sum += term;
+            if term.abs() < sum.abs() * EPSILON {
+                break;
+            }
+        }
+        (1. - sum * ln_prefix.exp()).clamp(0., 1.)
+    } else {
+        // Continued fraction representation of Q(a, x)
+        let mut b = x + 1. - a;
+        let mut c = 1. / TINY;
+        let mut d = 1. / b;
+        let mut h = d;
+        for i in 1..1000 {
+            let an = -f64::from(i) * (f64::from(i) - a);
+            b += 2.;
+            d = an * d + b;
+            if d.abs() < TINY {
+                d = TINY;
+            }
+            c = b + an / c;
+            if c.abs() < TINY {
+                c = TINY;
+            }
+            d = 1. / d;
+            let delta = d * c;
+            h *= delta;
+            if (delta - 1.).abs() < EPSILON {
+                break;
+            }
+        }
+        (ln_prefix.exp() * h).clamp(0., 1.)
+    }
+}
+
+fn ln_gamma(x: f64) -> f64 {
+    const COEFFICIENTS: [f64; 6] = [
+        76.180_091_729_471_46,
+        -86.505_320_329_416_77,
+        24.014_098_240_830_91,
+        -1.231_739_572_450_155,
+        0.001_208_650_973_866_179,
+        -0.000_005_395_239_384_953,
+    ];
+
+    let tmp = x + 5.5;
+    let tmp = tmp - (x + 0.5) * tmp.ln();
+    let mut y = x;
+    let mut series = 1.000_000_000_190_015;
+    for c in COEFFICIENTS {
+        y += 1.;
+        series += c / y;
+    }
+    -tmp + (2.506_628_274_631_000_5 * series / x).ln()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chi_square_p_values() {
+        // Critical values at the 5% significance level
+        assert!((gamma_q(0.5, 3.841 / 2.) - 0.05).abs() < 1e-3);
+        assert!((gamma_q(2.5, 11.070 / 2.) - 0.05).abs() < 1e-3);
+        assert!((gamma_q(5., 18.307 / 2.) - 0.05).abs() < 1e-3);
+    }
+
+    #[test]
+    fn kolmogorov_p_values() {
+        assert!((kolmogorov_q(1.358) - 0.05).abs() < 1e-3);
+        assert!((kolmogorov_q(1.628) - 0.01).abs() < 1e-3);
+    }
+
+    #[test]
+    fn normal_cdf_is_symmetric() {
+        assert!((normal_cdf(0., 0., 1.) - 0.5).abs() < 1e-7);
+        assert!((normal_cdf(1.96, 0., 1.) - 0.975).abs() < 1e-4);
+        assert!((normal_cdf(-1.96, 0., 1.) - 0.025).abs() < 1e-4);
+    }
+}
@@ -1,45 +1,79 @@
 #[cfg(unix)]
 use std::os::unix::fs::PermissionsExt;
-use std::{fs, fs::File, io, io::Read};
+use std::{
+    ffi::OsStr,
+    fmt, fs,
+    fs::File,
+    hash::{Hash, Hasher},
+    io,
+    io::{IoSlice, Seek, SeekFrom, Write},
+    mem,
+    num::{NonZeroU8, NonZeroU32, NonZeroU64, NonZeroUsize},
+    path::{Path, PathBuf},
+    sync::{
+        Arc, Mutex, OnceLock, PoisonError,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::{Duration, UNIX_EPOCH},
+};
 
 use cfg_if::cfg_if;
-use rand::{RngCore, SeedableRng, TryRngCore};
-use rand_distr::Normal;
+use rand::{RngCore, SeedableRng};
 use rand_xoshiro::Xoshiro256PlusPlus;
-#[cfg(target_os = "linux")]
-use rustix::fs::{FileType, Mode, mknodat};
-#[cfg(all(unix, not(target_os = "linux")))]
+#[cfg(unix)]
 use rustix::fs::{Mode, OFlags, openat};
+#[cfg(target_os = "linux")]
+use rustix::{
+    fs::{AtFlags, FileType, chmodat, mknodat},
+    io::Errno,
+};
+use twox_hash::XxHash64;
 
 use crate::{
-    core::{FileSpec, sample_truncated},
+    core::{
+        FileSpec, SizeDistr,
+        audit::{self, EntryType},
+    },
     utils::FastPathBuf,
 };
 
 pub trait FileContentsGenerator {
-    type State;
+    type State<'a>;
 
-    fn initialize(&self) -> Self::State;
+    fn initialize<'a>(&self, buffers: &'a WriteBufferPool) -> Self::State<'a>;
 
+    #[allow(clippy::too_many_arguments)]
     fn create_file(
         &mut self,
         file: &mut FastPathBuf,
         file_num: usize,
         retryable: bool,
-        state: &mut Self::State,
+        state: &mut Self::State<'_>,
         hash_seed: Option<u64>,
         spec: &FileSpec,
+        portable_create: &AtomicBool,
     ) -> io::Result<(u64, Option<u64>)>;
 
+    /// Finishes writing the contents of files whose writes were deferred.
+    fn finish(&mut self, state: &mut Self::State<'_>) -> io::Result<()>;
+
     fn byte_counts_pool_return(self) -> Option<Vec<u64>>;
+
+    /// The number of bytes writing `num_files` files is expected to produce.
+    fn expected_bytes(&self, num_files: usize) -> u64;
+
+    /// Splits off the contents of files `at..` into a new generator, leaving
+    /// this one with files `..at`.
+    #[must_use]
+    fn split_off(&mut self, at: usize) -> Self;
 }
 
 pub struct NoGeneratedFileContents;
 
 impl FileContentsGenerator for NoGeneratedFileContents {
-    type State = ();
+    type State<'a> = ();
 
-    fn initialize(&self) -> Self::State {}
+    fn initialize(&self, _: &WriteBufferPool) -> Self::State<'_> {}
 
     #[inline]
     #[cfg_attr(feature = "tracing", tracing::instrument(level = "trace", skip(self)))]
@@ -48,63 +82,107 @@ impl FileContentsGenerator for NoGeneratedFileContents {
         file: &mut FastPathBuf,
         _: usize,
         _: bool,
-        (): &mut Self::State,
+        (): &mut Self::State<'_>,
         _: Option<u64>,
         spec: &FileSpec,
+        portable_create: &AtomicBool,
     ) -> io::Result<(u64, Option<u64>)> {
         let mode = spec.permission.unwrap_or(0o664);
         cfg_if! {
             if #[cfg(any(not(unix), miri))] {
+                let _ = (mode, portable_create);
                 File::create(file).and_then(|f| {
                     if let Some(p) = spec.permission {
                         f.set_permissions(fs::Permissions::from_mode(p))?;
                     }
                     Ok((0, None))
                 })
-            } else if #[cfg(target_os = "linux")] {
-                let cstr = file.to_cstr_mut();
-                mknodat(
-                    rustix::fs::CWD,
-                    &*cstr,
-                    FileType::RegularFile,
-                    Mode::from_bits_retain(mode),
-                    0,
-                )
-                .map_err(io::Error::from)
-                .map(|()| (0, None))
             } else {
                 let cstr = file.to_cstr_mut();
+                #[cfg(target_os = "linux")]
+                if !portable_create.load(Ordering::Relaxed) {
+                    match mknodat(
+                        rustix::fs::CWD,
+                        &*cstr,
+                        FileType::RegularFile,
+                        Mode::from_bits_retain(mode),
+                        0,
+                    ) {
+                        Ok(()) => {
+                            if let Some(mode) = masked_by_umask(spec.permission) {
+                                chmodat(
+                                    rustix::fs::CWD,
+                                    &*cstr,
+                                    Mode::from_bits_retain(mode),
+                                    AtFlags::empty(),
+                                )?;
+                            }
+                            return Ok((0, None));
+                        }
+                        // Some network and FUSE file systems reject mknod for
+                        // regular files
+                        Err(Errno::PERM | Errno::OPNOTSUPP) => {
+                            if !portable_create.swap(true, Ordering::Relaxed) {
+                                log::warn!(
+                                    "The file system rejected mknod, falling back to open."
+                                );
+                            }
+                        }
+                        Err(e) => return Err(e.into()),
+                    }
+                }
+                #[cfg(not(target_os = "linux"))]
+                let _ = portable_create;
+
                 openat(
                     rustix::fs::CWD,
                     &*cstr,
-                    OFlags::CREATE,
+                    OFlags::CREATE | OFlags::CLOEXEC,
                     Mode::from_bits_retain(mode),
                 )
                 .map_err(io::Error::from)
-                .map(|_| (0, None))
+                .and_then(|fd| restore_masked_permissions(&fd.into(), spec.permission))
+                .map(|()| (0, None))
             }
         }
     }
 
+    fn finish(&mut self, (): &mut Self::State<'_>) -> io::Result<()> {
+        Ok(())
+    }
+
     fn byte_counts_pool_return(self) -> Option<Vec<u64>> {
         None
     }
+
+    fn expected_bytes(&self, _: usize) -> u64 {
+        0
+    }
+
+    fn split_off(&mut self, _: usize) -> Self {
+        Self
+    }
 }
 
 #[derive(Debug)]
 pub struct OnTheFlyGeneratedFileContents {
-    pub num_bytes_distr: Normal<f64>,
+    pub num_bytes_distr: SizeDistr,
     pub seed: u64,
-    pub fill_byte: Option<u8>,
+    pub fill_pattern: Option<FillPattern>,
+    pub content: ContentMode,
+    pub direct_io: bool,
+    pub writeback: Option<Writeback>,
+    pub fragments: Option<Fragmenter>,
+    pub holes: Option<Holes>,
+    pub preallocate: bool,
+    pub headers: Option<Arc<FileHeaders>>,
 }
 
 impl FileContentsGenerator for OnTheFlyGeneratedFileContents {
-    type State = Xoshiro256PlusPlus;
-
-    fn initialize(&self) -> Self::State {
-        let Self { seed, .. } = *self;
+    type State<'a> = PooledWriteBuffer<'a>;
 
-        Xoshiro256PlusPlus::seed_from_u64(seed)
+    fn initialize<'a>(&self, buffers: &'a WriteBufferPool) -> Self::State<'a> {
+        buffers.take()
     }
 
     #[inline]
@@ -115,14 +193,22 @@ impl FileContentsGenerator for OnTheFlyGeneratedFileContents {
         file: &mut FastPathBuf,
         file_num: usize,
         retryable: bool,
-        _random: &mut Self::State,
+        buffer: &mut Self::State<'_>,
         hash_seed: Option<u64>,
         spec: &FileSpec,
+        portable_create: &AtomicBool,
     ) -> io::Result<(u64, Option<u64>)> {
         let Self {
             ref num_bytes_distr,
             seed: _,
-            fill_byte,
+            ref fill_pattern,
+            content,
+            direct_io,
+            ref mut writeback,
+            ref mut fragments,
+            holes,
+            preallocate,
+            ref headers,
         } = *self;
 
         // Use the seed from the spec for content generation if applicable.
@@ -143,40 +229,135 @@ impl FileContentsGenerator for OnTheFlyGeneratedFileContents {
         // generator's state. If we want deterministic per file, we should use
         // `spec.seed`.
 
-        let num_bytes = sample_truncated(num_bytes_distr, &mut file_rnd);
+        let num_bytes = num_bytes_distr.sample(&mut file_rnd);
         if num_bytes > 0 || retryable {
-            File::create(&*file).and_then(|f| {
-                let hash = write_bytes(f, num_bytes, (fill_byte, &mut file_rnd), hash_seed)?;
-                #[cfg(unix)]
-                if let Some(p) = spec.permission {
-                    fs::set_permissions(file, fs::Permissions::from_mode(p))?;
+            let header = headers
+                .as_ref()
+                .map(|headers| headers.render(file, spec.seed));
+            let header = header.as_deref().unwrap_or_default();
+            let deferred = fragments.is_some() && !preallocate;
+            create_content_file(file, spec.permission, direct_io).and_then(|f| {
+                let hash = if preallocate {
+                    allocate_zeros(f, num_bytes, buffer, hash_seed)?
+                } else if let Some(fragments) = fragments {
+                    fragments.push(
+                        f,
+                        num_bytes,
+                        fill_pattern.as_ref(),
+                        content,
+                        spec.seed,
+                        header,
+                        file_rnd,
+                        buffer,
+                        hash_seed,
+                        spec.permission,
+                    )?
+                } else {
+                    write_bytes(
+                        f,
+                        num_bytes,
+                        BytesKind::new(
+                            fill_pattern.as_ref(),
+                            content,
+                            num_bytes,
+                            spec.seed,
+                            &mut file_rnd,
+                        ),
+                        header,
+                        buffer,
+                        writeback.as_mut(),
+                        holes,
+                        hash_seed,
+                    )?
+                };
+                if let Some(permissions) = special_permissions(spec.permission)
+                    && !deferred
+                {
+                    fs::set_permissions(&**file, permissions)?;
                 }
                 Ok((num_bytes, hash))
             })
         } else {
-            NoGeneratedFileContents.create_file(file, file_num, retryable, &mut (), hash_seed, spec)
+            NoGeneratedFileContents.create_file(
+                file,
+                file_num,
+                retryable,
+                &mut (),
+                hash_seed,
+                spec,
+                portable_create,
+            )
         }
     }
 
+    fn finish(&mut self, buffer: &mut Self::State<'_>) -> io::Result<()> {
+        self.fragments
+            .as_mut()
+            .map_or(Ok(()), |fragments| fragments.flush(buffer))
+    }
+
     fn byte_counts_pool_return(self) -> Option<Vec<u64>> {
         None
     }
+
+    #[allow(
+        clippy::cast_sign_loss,
+        clippy::cast_possible_truncation,
+        clippy::cast_precision_loss
+    )]
+    fn expected_bytes(&self, num_files: usize) -> u64 {
+        // The truncated distribution is symmetric around its mean
+        (self.num_bytes_distr.mean() * num_files as f64) as u64
+    }
+
+    fn split_off(&mut self, _: usize) -> Self {
+        let Self {
+            num_bytes_distr,
+            seed,
+            ref fill_pattern,
+            content,
+            direct_io,
+            writeback,
+            ref fragments,
+            holes,
+            preallocate,
+            ref headers,
+        } = *self;
+
+        Self {
+            num_bytes_distr,
+            seed,
+            fill_pattern: fill_pattern.clone(),
+            content,
+            direct_io,
+            writeback: writeback.map(Writeback::reset),
+            fragments: fragments.as_ref().map(|_| Fragmenter::default()),
+            holes,
+            preallocate,
+            headers: headers.clone(),
+        }
+    }
 }
 
 #[derive(Debug)]
 pub struct PreDefinedGeneratedFileContents {
     pub byte_counts: Vec<u64>,
     pub seed: u64,
-    pub fill_byte: Option<u8>,
+    pub fill_pattern: Option<FillPattern>,
+    pub content: ContentMode,
+    pub direct_io: bool,
+    pub writeback: Option<Writeback>,
+    pub fragments: Option<Fragmenter>,
+    pub holes: Option<Holes>,
+    pub preallocate: bool,
+    pub headers: Option<Arc<FileHeaders>>,
 }
 
 impl FileContentsGenerator for PreDefinedGeneratedFileContents {
-    type State = Xoshiro256PlusPlus;
+    type State<'a> = PooledWriteBuffer<'a>;
 
-    fn initialize(&self) -> Self::State {
-        let Self { seed, .. } = *self;
-
-        Xoshiro256PlusPlus::seed_from_u64(seed)
+    fn initialize<'a>(&self, buffers: &'a WriteBufferPool) -> Self::State<'a> {
+        buffers.take()
     }
 
     #[inline]
@@ -186,14 +367,22 @@ impl FileContentsGenerator for PreDefinedGeneratedFileContents {
         file: &mut FastPathBuf,
         file_num: usize,
         retryable: bool,
-        _random: &mut Self::State,
+        buffer: &mut Self::State<'_>,
         hash_seed: Option<u64>,
         spec: &FileSpec,
+        portable_create: &AtomicBool,
     ) -> io::Result<(u64, Option<u64>)> {
         let Self {
             ref byte_counts,
             seed: _,
-            fill_byte,
+            ref fill_pattern,
+            content,
+            direct_io,
+            ref mut writeback,
+            ref mut fragments,
+            holes,
+            preallocate,
+            ref headers,
         } = *self;
 
         // For PreDefined, we use the byte counts.
@@ -205,66 +394,1952 @@ impl FileContentsGenerator for PreDefinedGeneratedFileContents {
 
         let num_bytes = byte_counts[file_num];
         if num_bytes > 0 {
-            File::create(&*file)
+            let header = headers
+                .as_ref()
+                .map(|headers| headers.render(file, spec.seed));
+            let header = header.as_deref().unwrap_or_default();
+            let deferred = fragments.is_some() && !preallocate;
+            create_content_file(file, spec.permission, direct_io)
                 .and_then(|f| {
-                    let hash = write_bytes(f, num_bytes, (fill_byte, &mut file_rnd), hash_seed)?;
-                    #[cfg(unix)]
-                    if let Some(p) = spec.permission {
-                        fs::set_permissions(file, fs::Permissions::from_mode(p))?;
+                    let hash = if preallocate {
+                        allocate_zeros(f, num_bytes, buffer, hash_seed)
+                    } else if let Some(fragments) = fragments {
+                        fragments.push(
+                            f,
+                            num_bytes,
+                            fill_pattern.as_ref(),
+                            content,
+                            spec.seed,
+                            header,
+                            file_rnd,
+                            buffer,
+                            hash_seed,
+                            spec.permission,
+                        )
+                    } else {
+                        write_bytes(
+                            f,
+                            num_bytes,
+                            BytesKind::new(
+                                fill_pattern.as_ref(),
+                                content,
+                                num_bytes,
+                                spec.seed,
+                                &mut file_rnd,
+                            ),
+                            header,
+                            buffer,
+                            writeback.as_mut(),
+                            holes,
+                            hash_seed,
+                        )
+                    }?;
+                    if let Some(permissions) = special_permissions(spec.permission)
+                        && !deferred
+                    {
+                        fs::set_permissions(&**file, permissions)?;
                     }
                     Ok(hash)
                 })
                 .map(|hash| (num_bytes, hash))
         } else {
-            NoGeneratedFileContents.create_file(file, file_num, retryable, &mut (), hash_seed, spec)
+            NoGeneratedFileContents.create_file(
+                file,
+                file_num,
+                retryable,
+                &mut (),
+                hash_seed,
+                spec,
+                portable_create,
+            )
         }
     }
 
+    fn finish(&mut self, buffer: &mut Self::State<'_>) -> io::Result<()> {
+        self.fragments
+            .as_mut()
+            .map_or(Ok(()), |fragments| fragments.flush(buffer))
+    }
+
     fn byte_counts_pool_return(self) -> Option<Vec<u64>> {
         Some(self.byte_counts)
     }
+
+    fn expected_bytes(&self, _: usize) -> u64 {
+        self.byte_counts.iter().sum()
+    }
+
+    fn split_off(&mut self, at: usize) -> Self {
+        let Self {
+            ref mut byte_counts,
+            seed,
+            ref fill_pattern,
+            content,
+            direct_io,
+            writeback,
+            ref fragments,
+            holes,
+            preallocate,
+            ref headers,
+        } = *self;
+
+        Self {
+            byte_counts: byte_counts.split_off(at),
+            seed,
+            fill_pattern: fill_pattern.clone(),
+            content,
+            direct_io,
+            writeback: writeback.map(Writeback::reset),
+            fragments: fragments.as_ref().map(|_| Fragmenter::default()),
+            holes,
+            preallocate,
+            headers: headers.clone(),
+        }
+    }
+}
+
+/// A byte sequence repeated as file contents instead of random data, for
+/// example to embed recognizable markers while keeping files compressible.
+///
+/// A corpus of several samples instead gives every file the contents of one
+/// of them, starting at an offset and wrapping around.
+#[derive(Clone, PartialEq, Eq, Hash)]
+pub struct FillPattern(Arc<[Arc<[u8]>]>);
+
+impl FillPattern {
+    /// Returns [`None`] for an empty pattern.
+    #[must_use]
+    pub fn new(pattern: impl Into<Arc<[u8]>>) -> Option<Self> {
+        let pattern = pattern.into();
+        (!pattern.is_empty()).then(|| Self(Arc::from([pattern])))
+    }
+
+    /// Returns [`None`] if all samples are empty.
+    #[must_use]
+    pub fn corpus(samples: impl IntoIterator<Item = Vec<u8>>) -> Option<Self> {
+        let samples = samples
+            .into_iter()
+            .filter(|sample| !sample.is_empty())
+            .map(Arc::from)
+            .collect::<Arc<[_]>>();
+        (!samples.is_empty()).then_some(Self(samples))
+    }
+
+    /// The repeated bytes, or the first sample of a corpus.
+    #[must_use]
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0[0]
+    }
+
+    /// Fills `buf` with the repeated pattern as it appears `offset` bytes into
+    /// a file.
+    pub fn fill(&self, buf: &mut [u8], offset: u64) {
+        fill_cyclic(self.as_bytes(), buf, offset);
+    }
+
+    /// The bytes repeated in the file generated from `seed` and the offset
+    /// into them at which the file starts.
+    fn for_file(&self, seed: u64) -> (&[u8], u64) {
+        if let [pattern] = &*self.0 {
+            return (pattern, 0);
+        }
+        let mixed = seed.wrapping_mul(0xE220_A839_7B1D_CDAF);
+        let sample = &self.0[(mixed >> 32) as usize % self.0.len()];
+        (sample, mixed.rotate_left(17) % sample.len() as u64)
+    }
+}
+
+impl fmt::Debug for FillPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let [pattern] = &*self.0 {
+            f.debug_tuple("FillPattern").field(pattern).finish()
+        } else {
+            // Corpora are too large to print, so tell them apart by hash
+            let mut hasher = XxHash64::with_seed(0);
+            self.0.hash(&mut hasher);
+            f.debug_struct("Corpus")
+                .field("samples", &self.0.len())
+                .field("hash", &format_args!("{:016x}", hasher.finish()))
+                .finish()
+        }
+    }
+}
+
+impl From<u8> for FillPattern {
+    fn from(byte: u8) -> Self {
+        Self(Arc::from([Arc::from([byte])]))
+    }
+}
+
+/// Fills `buf` with `pattern` repeated as it appears `offset` bytes into a
+/// file.
+fn fill_cyclic(pattern: &[u8], buf: &mut [u8], offset: u64) {
+    let start = (offset % pattern.len() as u64) as usize;
+    let head = (pattern.len() - start).min(buf.len());
+    buf[..head].copy_from_slice(&pattern[start..start + head]);
+
+    // Double up whole repetitions of the pattern until the buffer is full
+    let rest = &mut buf[head..];
+    let mut filled = pattern.len().min(rest.len());
+    rest[..filled].copy_from_slice(&pattern[..filled]);
+    while filled < rest.len() {
+        let len = filled.min(rest.len() - filled);
+        rest.copy_within(..len, filled);
+        filled += len;
+    }
+}
+
+/// Fills `buf` with records of a JSON array, the start of which is opened in
+/// the `first` chunk and closed in the `last` one. Records are separated by
+/// whitespace from the chunks around them, so chunks can be generated on
+/// their own.
+fn fill_json(buf: &mut [u8], first: bool, last: bool, random: &mut impl RngCore) {
+    if first && last && buf.len() == 1 {
+        // Too short for an array
+        buf[0] = b'0';
+        return;
+    }
+    let tail: &[u8] = match (last, buf.len() > usize::from(first) + 1) {
+        (true, true) => b"]\n",
+        (true, false) => b"]",
+        (false, _) => b"\n",
+    };
+    let room = buf.len() - tail.len();
+
+    let mut position = 0;
+    if first {
+        buf[0] = b'[';
+        position = 1;
+    }
+    let mut record = Vec::with_capacity(256);
+    loop {
+        record.clear();
+        if position > 1 || !first {
+            record.push(b',');
+        }
+        let bits = random.next_u64();
+        let word = |shift: u32| WORDS[(bits >> shift) as usize % WORDS.len()];
+        write!(
+            record,
+            r#"{{"id":{},"name":"{} {}","active":{},"score":{}.{:02},"tags":["{}","{}"]}}"#,
+            bits >> 44,
+            word(0),
+            word(8),
+            bits & (1 << 16) != 0,
+            (bits >> 17) % 1000,
+            (bits >> 27) % 100,
+            word(34),
+            word(40),
+        )
+        .expect("Writing to a Vec can't fail");
+        if position + record.len() > room {
+            break;
+        }
+        buf[position..][..record.len()].copy_from_slice(&record);
+        position += record.len();
+    }
+    if position == 0 && room >= 2 {
+        // A record too small to be left out keeps separators balanced
+        buf[..2].copy_from_slice(b",0");
+        position = 2;
+    }
+    buf[position..room].fill(b' ');
+    buf[room..].copy_from_slice(tail);
+}
+
+/// Fills `buf` with a copy of one of the first `blocks` dictionary blocks if
+/// a random draw falls below `reuse_threshold`, or with unique random bytes
+/// otherwise.
+fn fill_dedup_block(
+    buf: &mut [u8],
+    reuse_threshold: u64,
+    blocks: NonZeroU32,
+    random: &mut impl RngCore,
+) {
+    let bits = random.next_u64();
+    if bits < reuse_threshold {
+        let index = random.next_u64() % u64::from(blocks.get());
+        let mut block = Xoshiro256PlusPlus::seed_from_u64(index ^ 0x3C79_AC49_2BA7_B653);
+        fill_random_block(buf, &mut block);
+    } else {
+        fill_random_block(buf, random);
+    }
+}
+
+/// The names of CSV columns, repeating with a number once they run out.
+const CSV_COLUMNS: [&str; 8] = [
+    "id", "name", "amount", "date", "active", "city", "count", "note",
+];
+
+/// Fills `buf` with CSV rows of `columns` values, starting with a header row
+/// in the `first` chunk. The last row's final value is padded with spaces to
+/// the end of the chunk, which is left as blank lines if no row fits.
+fn fill_csv(buf: &mut [u8], first: bool, columns: NonZeroU8, random: &mut impl RngCore) {
+    let mut row = Vec::with_capacity(64 * usize::from(columns.get()));
+    let mut position = 0;
+    let mut header = first;
+    loop {
+        row.clear();
+        for column in 0..usize::from(columns.get()) {
+            if column > 0 {
+                row.push(b',');
+            }
+            let name = CSV_COLUMNS[column % CSV_COLUMNS.len()];
+            let bits = random.next_u64();
+            let word = |shift: u32| WORDS[(bits >> shift) as usize % WORDS.len()];
+            if header {
+                write!(row, "{name}")
+            } else {
+                match name {
+                    "id" | "count" => write!(row, "{}", bits >> 40),
+                    "amount" => write!(row, "{}.{:02}", bits % 100_000, (bits >> 20) % 100),
+                    "date" => write!(row, "2024-{:02}-{:02}", 1 + bits % 12, 1 + (bits >> 8) % 28),
+                    "active" => write!(row, "{}", bits & 1 != 0),
+                    "note" => write!(row, "{} {}", word(0), word(8)),
+                    _ => write!(row, "{}", word(0)),
+                }
+            }
+            .expect("Writing to a Vec can't fail");
+            let repeat = column / CSV_COLUMNS.len();
+            if header && repeat > 0 {
+                write!(row, "{}", repeat + 1).expect("Writing to a Vec can't fail");
+            }
+        }
+        row.push(b'\n');
+        header = false;
+        if position + row.len() > buf.len() {
+            break;
+        }
+        buf[position..][..row.len()].copy_from_slice(&row);
+        position += row.len();
+    }
+
+    if position == 0 {
+        buf.fill(b'\n');
+    } else if let Some((last, rest)) = buf.split_last_mut() {
+        // Move the last row's break to the end of the chunk
+        rest[position - 1..].fill(b' ');
+        *last = b'\n';
+    }
+}
+
+/// Log files start somewhere in the year after this Unix time in seconds.
+const LOG_EPOCH: u64 = 1_704_067_200;
+
+/// The span of time in milliseconds covered by each chunk of a log file.
+const LOG_CHUNK_SPAN: u64 = 60_000;
+
+const LOG_HOSTS: [&str; 6] = [
+    "web-01",
+    "web-02",
+    "db-01",
+    "cache-01",
+    "worker-01",
+    "edge-01",
+];
+const LOG_SERVICES: [&str; 6] = ["api", "auth", "nginx", "postgres", "scheduler", "billing"];
+
+/// Fills `buf`, the chunk at `index` of a log file generated from `seed`,
+/// with log lines whose timestamps fall within the chunk's span of time,
+/// padding the last line with spaces where the next one wouldn't fit.
+fn fill_log(buf: &mut [u8], json: bool, seed: u64, index: u64, random: &mut impl RngCore) {
+    let year_ms = 365 * 86_400_000;
+    let start = LOG_EPOCH * 1000 + seed % year_ms + index * LOG_CHUNK_SPAN;
+    let host = LOG_HOSTS[(seed >> 40) as usize % LOG_HOSTS.len()];
+    let service = LOG_SERVICES[(seed >> 48) as usize % LOG_SERVICES.len()];
+    let pid = 1000 + (seed >> 20) % 30_000;
+
+    let mut position = 0;
+    let mut time = start;
+    let mut line = Vec::with_capacity(256);
+    loop {
+        let bits = random.next_u64();
+        time = (time + bits % 1500).min(start + LOG_CHUNK_SPAN - 1);
+        let stamp = audit::timestamp(UNIX_EPOCH + Duration::from_secs(time / 1000));
+        let stamp = format!("{}.{:03}Z", &stamp[..stamp.len() - 1], time % 1000);
+        let (level, severity) = match (bits >> 12) % 20 {
+            0 => ("error", 3),
+            1 | 2 => ("warn", 4),
+            3..=5 => ("debug", 7),
+            _ => ("info", 6),
+        };
+        let word = |shift: u32| WORDS[(bits >> shift) as usize % WORDS.len()];
+        let number = (bits >> 44) % 1000;
+        let message = match (bits >> 20) % 5 {
+            0 => format!("GET /{}/{number} 200 {}ms", word(24), (bits >> 54) % 400),
+            1 => format!(
+                "user {} signed in from 10.0.{}.{}",
+                word(24),
+                number % 256,
+                bits >> 56
+            ),
+            2 => format!("cache miss for {}:{number}", word(24)),
+            3 => format!(
+                "job {}-{number} finished in {}ms",
+                word(24),
+                (bits >> 54) % 900
+            ),
+            _ => format!("{} {} {}", word(24), word(32), word(40)),
+        };
+
+        line.clear();
+        if json {
+            writeln!(
+                line,
+                r#"{{"timestamp":"{stamp}","level":"{level}","host":"{host}","service":"{service}","pid":{pid},"message":"{message}"}}"#
+            )
+        } else {
+            writeln!(
+                line,
+                "<{}>1 {stamp} {host} {service} {pid} - - {message}",
+                16 * 8 + severity
+            )
+        }
+        .expect("Writing to a Vec can't fail");
+        if position + line.len() > buf.len() {
+            break;
+        }
+        buf[position..][..line.len()].copy_from_slice(&line);
+        position += line.len();
+    }
+
+    // Move the last line's break to the end of the chunk
+    if let Some((last, rest)) = buf.split_last_mut() {
+        rest[position.saturating_sub(1)..].fill(b' ');
+        *last = b'\n';
+    }
+}
+
+/// Written over the start of each file's contents: the signature of the
+/// format its extension names, followed by a short line identifying the file
+/// so files that end up elsewhere can be traced back to the run that made them.
+#[derive(Debug)]
+pub struct FileHeaders {
+    pub root_dir: PathBuf,
+    pub run_id: u64,
+    /// Whether to write the identifying line.
+    pub identify: bool,
+    /// Whether to start files with the signature of their format.
+    pub magic_bytes: bool,
+}
+
+impl FileHeaders {
+    /// The header of the file at `file` generated from `seed`.
+    fn render(&self, file: &Path, seed: u64) -> Vec<u8> {
+        let Self {
+            ref root_dir,
+            run_id,
+            identify,
+            magic_bytes,
+        } = *self;
+        let mut header = Vec::new();
+        if magic_bytes && let Some(signature) = file.extension().and_then(signature) {
+            header.extend_from_slice(signature);
+        }
+        if identify {
+            let path = file.strip_prefix(root_dir).unwrap_or(file);
+            header.extend_from_slice(
+                format!(
+                    "ftzz run={run_id:016x} seed={seed:016x} path={}\n",
+                    path.display()
+                )
+                .as_bytes(),
+            );
+        }
+        header
+    }
+}
+
+/// The signatures files of well-known formats start with, by extension.
+const SIGNATURES: [(&[&str], &[u8]); 8] = [
+    (&["png"], b"\x89PNG\r\n\x1a\n"),
+    (&["jpg", "jpeg"], b"\xff\xd8\xff\xe0"),
+    (&["gif"], b"GIF89a"),
+    (&["pdf"], b"%PDF-1.7\n"),
+    (
+        &["zip", "docx", "xlsx", "pptx", "jar", "odt", "epub"],
+        b"PK\x03\x04",
+    ),
+    (&["gz", "tgz"], b"\x1f\x8b\x08"),
+    (&["mp3"], b"ID3\x04\x00"),
+    (&["mp4", "m4a", "mov"], b"\x00\x00\x00\x18ftypmp42"),
+];
+
+/// The signature of the format named by `extension`, ignoring case.
+fn signature(extension: &OsStr) -> Option<&'static [u8]> {
+    let extension = extension.to_str()?;
+    SIGNATURES
+        .iter()
+        .find(|(extensions, _)| {
+            extensions
+                .iter()
+                .any(|known| known.eq_ignore_ascii_case(extension))
+        })
+        .map(|&(_, signature)| signature)
+}
+
+/// Writes the part of `header` that falls within `buf`, which starts
+/// `position` bytes into the file, over the generated contents.
+fn overlay_header(header: &[u8], position: u64, buf: &mut [u8]) {
+    let Some(header) = usize::try_from(position)
+        .ok()
+        .and_then(|position| header.get(position..))
+    else {
+        return;
+    };
+    let len = header.len().min(buf.len());
+    buf[..len].copy_from_slice(&header[..len]);
+}
+
+/// The stretch of a sparse file that ends in a hole.
+const SPARSE_CHUNK: u64 = 64 * 1024;
+
+/// Where sparse files have holes: the tail of every period of a file, by
+/// default [`SPARSE_CHUNK`] bytes, in whole [`DIRECT_IO_ALIGNMENT`] blocks so
+/// file systems can leave them unallocated. Contents are those of a dense file
+/// with the holes zeroed.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Holes {
+    /// The bytes of data at the start of every period.
+    data: u64,
+    /// The bytes of data and hole that repeat throughout the file.
+    period: u64,
+}
+
+impl Holes {
+    /// Holes making up roughly `percentage` of every file, or [`None`] if that
+    /// isn't enough for a single block.
+    #[must_use]
+    #[allow(
+        clippy::cast_sign_loss,
+        clippy::cast_possible_truncation,
+        clippy::cast_precision_loss
+    )]
+    pub fn new(percentage: f64) -> Option<Self> {
+        let hole = (percentage / 100. * SPARSE_CHUNK as f64) as u64 & !(DIRECT_IO_ALIGNMENT - 1);
+        (hole > 0).then_some(Self {
+            data: SPARSE_CHUNK - hole.min(SPARSE_CHUNK),
+            period: SPARSE_CHUNK,
+        })
+    }
+
+    /// Runs of `data` bytes followed by `hole` bytes of hole, or [`None`]
+    /// unless both are whole blocks and there is a hole.
+    #[must_use]
+    pub fn pattern(data: u64, hole: u64) -> Option<Self> {
+        let aligned = |len: u64| len.is_multiple_of(DIRECT_IO_ALIGNMENT);
+        (hole > 0 && aligned(data) && aligned(hole)).then_some(Self {
+            data,
+            period: data.checked_add(hole)?,
+        })
+    }
+
+    /// The bytes of data and of hole every period is made of.
+    #[must_use]
+    pub const fn layout(self) -> (u64, u64) {
+        (self.data, self.period - self.data)
+    }
+
+    /// Whether `offset` falls in a hole, and how many of the next `len` bytes
+    /// do likewise.
+    fn run(self, offset: u64, len: u64) -> (bool, u64) {
+        let within = offset % self.period;
+        if within < self.data {
+            (false, (self.data - within).min(len))
+        } else {
+            (true, (self.period - within).min(len))
+        }
+    }
+
+    /// Zeroes the parts of `buf`, which starts `offset` bytes into the file,
+    /// that fall in a hole.
+    fn punch(self, mut buf: &mut [u8], mut offset: u64) {
+        while !buf.is_empty() {
+            let (hole, len) = self.run(offset, buf.len() as u64);
+            let (head, tail) = buf.split_at_mut(len as usize);
+            if hole {
+                head.fill(0);
+            }
+            offset += len;
+            buf = tail;
+        }
+    }
+}
+
+/// What generated files are filled with when no fill pattern is given.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum ContentMode {
+    /// Deterministically random bytes.
+    #[default]
+    Random,
+    /// Deterministically random words strung into English-looking sentences.
+    Text,
+    /// A JSON array of deterministically random records.
+    Json,
+    /// CSV rows of deterministically random values under a header row.
+    Csv { columns: NonZeroU8 },
+    /// Blocks drawn from a dictionary of `blocks` random blocks whenever a
+    /// block's random draw falls below `reuse_threshold`, and unique random
+    /// blocks otherwise. Dictionary blocks only depend on their index, so
+    /// they are shared by every file of every tree.
+    Dedup {
+        reuse_threshold: u64,
+        blocks: NonZeroU32,
+    },
+    /// Syslog lines in the RFC 5424 format with timestamps counting up from a
+    /// start time derived from the file's seed.
+    Syslog,
+    /// Log records as JSON Lines with timestamps counting up like [`Syslog`]'s.
+    ///
+    /// [`Syslog`]: Self::Syslog
+    JsonLines,
+}
+
+impl ContentMode {
+    /// The MIME type of the contents, unless they are opaque bytes.
+    pub const fn mime(self) -> Option<&'static str> {
+        match self {
+            Self::Random | Self::Dedup { .. } => None,
+            Self::Text | Self::Syslog => Some("text/plain"),
+            Self::Json => Some("application/json"),
+            Self::Csv { .. } => Some("text/csv"),
+            Self::JsonLines => Some("application/jsonl"),
+        }
+    }
 }
 
 enum BytesKind<'a, R> {
     Random(&'a mut R),
-    Fixed(u8),
+    Text(&'a mut R),
+    Json {
+        random: &'a mut R,
+        offset: u64,
+        len: u64,
+    },
+    Csv {
+        random: &'a mut R,
+        offset: u64,
+        columns: NonZeroU8,
+    },
+    Dedup {
+        random: &'a mut R,
+        reuse_threshold: u64,
+        blocks: NonZeroU32,
+    },
+    Log {
+        random: &'a mut R,
+        offset: u64,
+        json: bool,
+        seed: u64,
+    },
+    Pattern {
+        pattern: &'a [u8],
+        /// Where in the pattern the file starts.
+        start: u64,
+        offset: u64,
+    },
+}
+
+impl<'a, R: RngCore> BytesKind<'a, R> {
+    /// The contents of a file `len` bytes long generated from `seed`.
+    fn new(
+        fill_pattern: Option<&'a FillPattern>,
+        content: ContentMode,
+        len: u64,
+        seed: u64,
+        random: &'a mut R,
+    ) -> Self {
+        fill_pattern.map_or(
+            match content {
+                ContentMode::Random => BytesKind::Random(random),
+                ContentMode::Text => BytesKind::Text(random),
+                ContentMode::Json => BytesKind::Json {
+                    random,
+                    offset: 0,
+                    len,
+                },
+                ContentMode::Csv { columns } => BytesKind::Csv {
+                    random,
+                    offset: 0,
+                    columns,
+                },
+                ContentMode::Dedup {
+                    reuse_threshold,
+                    blocks,
+                } => BytesKind::Dedup {
+                    random,
+                    reuse_threshold,
+                    blocks,
+                },
+                ContentMode::Syslog | ContentMode::JsonLines => BytesKind::Log {
+                    random,
+                    offset: 0,
+                    json: content == ContentMode::JsonLines,
+                    seed,
+                },
+            },
+            |pattern| {
+                let (pattern, start) = pattern.for_file(seed);
+                BytesKind::Pattern {
+                    pattern,
+                    start,
+                    offset: 0,
+                }
+            },
+        )
+    }
+
+    /// Continues the contents `offset` bytes into the file.
+    const fn at(mut self, at: u64) -> Self {
+        if let Self::Pattern { ref mut offset, .. }
+        | Self::Json { ref mut offset, .. }
+        | Self::Csv { ref mut offset, .. }
+        | Self::Log { ref mut offset, .. } = self
+        {
+            *offset = at;
+        }
+        self
+    }
+
+    fn fill(&mut self, buf: &mut [u8]) {
+        // Buffers always start on a chunk boundary, so generated documents are
+        // the same however the file's writes are split up
+        match self {
+            Self::Random(random) => fill_random_block(buf, *random),
+            Self::Text(random) => {
+                for chunk in buf.chunks_mut(CONTENT_CHUNK) {
+                    fill_text(chunk, *random);
+                }
+            }
+            Self::Json {
+                random,
+                offset,
+                len,
+            } => {
+                for chunk in buf.chunks_mut(CONTENT_CHUNK) {
+                    let end = *offset + chunk.len() as u64;
+                    fill_json(chunk, *offset == 0, end == *len, *random);
+                    *offset = end;
+                }
+            }
+            Self::Csv {
+                random,
+                offset,
+                columns,
+            } => {
+                for chunk in buf.chunks_mut(CONTENT_CHUNK) {
+                    fill_csv(chunk, *offset == 0, *columns, *random);
+                    *offset += chunk.len() as u64;
+                }
+            }
+            Self::Dedup {
+                random,
+                reuse_threshold,
+                blocks,
+            } => {
+                for chunk in buf.chunks_mut(CONTENT_CHUNK) {
+                    fill_dedup_block(chunk, *reuse_threshold, *blocks, *random);
+                }
+            }
+            Self::Log {
+                random,
+                offset,
+                json,
+                seed,
+            } => {
+                for chunk in buf.chunks_mut(CONTENT_CHUNK) {
+                    let index = *offset / CONTENT_CHUNK as u64;
+                    fill_log(chunk, *json, *seed, index, *random);
+                    *offset += chunk.len() as u64;
+                }
+            }
+            Self::Pattern {
+                pattern,
+                start,
+                offset,
+            } => {
+                fill_cyclic(pattern, buf, *start + *offset);
+                *offset += buf.len() as u64;
+            }
+        }
+    }
+}
+
+/// The stretch of generated contents written in one go, ending in a line
+/// break.
+const CONTENT_CHUNK: usize = DIRECT_IO_ALIGNMENT as usize;
+
+/// Common English words, most frequent first.
+const WORDS: [&str; 96] = [
+    "the", "of", "and", "to", "a", "in", "is", "it", "that", "was", "for", "on", "are", "with",
+    "as", "they", "be", "at", "one", "have", "this", "from", "by", "not", "but", "what", "all",
+    "were", "when", "we", "there", "can", "an", "your", "which", "their", "said", "if", "will",
+    "each", "about", "how", "up", "out", "them", "then", "she", "many", "some", "so", "these",
+    "would", "other", "into", "has", "more", "her", "two", "like", "him", "see", "time", "could",
+    "make", "than", "first", "been", "its", "who", "now", "people", "made", "over", "did", "down",
+    "only", "way", "find", "use", "may", "water", "long", "little", "very", "after", "words",
+    "called", "just", "where", "most", "know", "get", "through", "back", "much", "before",
+];
+
+/// Fills `buf` with sentences of [`WORDS`], padding the end with spaces and a
+/// line break where the next word wouldn't fit.
+fn fill_text(buf: &mut [u8], random: &mut impl RngCore) {
+    let mut position = 0;
+    let mut sentence_words = 0;
+    loop {
+        let bits = random.next_u64();
+        let capitalize = sentence_words == 0;
+        if capitalize {
+            sentence_words = 4 + bits % 12;
+        }
+        sentence_words -= 1;
+
+        // Multiplying two picks favors the common words at the start
+        let len = WORDS.len() as u64;
+        let word = WORDS[((bits >> 8) % len * ((bits >> 24) % len) / len) as usize];
+        let separator = match (sentence_words, (bits >> 40) % 8) {
+            (0, 0) => ".\n\n",
+            (0, _) => ". ",
+            (_, 0) => ", ",
+            _ => " ",
+        };
+        let end = position + word.len() + separator.len();
+        if end >= buf.len() {
+            break;
+        }
+
+        buf[position..][..word.len()].copy_from_slice(word.as_bytes());
+        if capitalize {
+            buf[position].make_ascii_uppercase();
+        }
+        buf[position + word.len()..end].copy_from_slice(separator.as_bytes());
+        position = end;
+    }
+    if let Some((last, rest)) = buf[position..].split_last_mut() {
+        rest.fill(b' ');
+        *last = b'\n';
+    }
+}
+
+/// A freshly created file about to receive its contents.
+#[derive(Debug)]
+struct ContentFile {
+    file: File,
+    /// Whether the file was opened with `O_DIRECT`.
+    direct: bool,
+}
+
+/// The alignment `O_DIRECT` requires of buffer addresses, lengths, and file
+/// offsets on all but the most exotic devices.
+const DIRECT_IO_ALIGNMENT: u64 = 4096;
+
+/// Creates `path` for writing with its final `mode`, bypassing the page cache
+/// if `direct_io` is set and the file system supports it.
+fn create_content_file(path: &Path, mode: Option<u32>, direct_io: bool) -> io::Result<ContentFile> {
+    #[cfg(target_os = "linux")]
+    if direct_io {
+        use rustix::{
+            fs::{OFlags, open},
+            io::Errno,
+        };
+
+        match open(
+            path,
+            OFlags::WRONLY | OFlags::CREATE | OFlags::TRUNC | OFlags::CLOEXEC | OFlags::DIRECT,
+            Mode::from_bits_retain(mode.unwrap_or(0o666)),
+        ) {
+            Ok(fd) => {
+                let file = fd.into();
+                restore_masked_permissions(&file, mode)?;
+                return Ok(ContentFile { file, direct: true });
+            }
+            // The file system doesn't support direct I/O
+            Err(Errno::INVAL) => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    #[cfg(not(target_os = "linux"))]
+    let _ = direct_io;
+
+    let mut options = File::options();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    if let Some(mode) = mode {
+        use std::os::unix::fs::OpenOptionsExt;
+
+        options.mode(mode);
+    }
+    let file = options.open(path)?;
+    restore_masked_permissions(&file, mode)?;
+    Ok(ContentFile {
+        file,
+        direct: false,
+    })
+}
+
+/// Sets the modification and access times of `path`, in seconds since the
+/// Unix epoch, without following symlinks.
+pub fn set_times(path: &Path, mtime: i64, atime: i64) -> io::Result<()> {
+    cfg_if! {
+        if #[cfg(unix)] {
+            use rustix::fs::{AtFlags, CWD, Timespec, Timestamps, utimensat};
+
+            let at = |secs| Timespec {
+                tv_sec: secs,
+                tv_nsec: 0,
+            };
+            utimensat(
+                CWD,
+                path,
+                &Timestamps {
+                    last_access: at(atime),
+                    last_modification: at(mtime),
+                },
+                AtFlags::SYMLINK_NOFOLLOW,
+            )?;
+            Ok(())
+        } else {
+            use std::time::{Duration, UNIX_EPOCH};
+
+            let at = |secs: i64| {
+                let offset = Duration::from_secs(secs.unsigned_abs());
+                if secs < 0 { UNIX_EPOCH - offset } else { UNIX_EPOCH + offset }
+            };
+            let mut options = std::fs::File::options();
+            options.write(true);
+            #[cfg(windows)]
+            {
+                use std::os::windows::fs::OpenOptionsExt;
+
+                // Only ask to write attributes so read-only files can be
+                // stamped too, back up semantics so directories can be opened
+                // at all, and the reparse point itself so links aren't
+                // followed
+                const FILE_WRITE_ATTRIBUTES: u32 = 0x100;
+                options
+                    .access_mode(FILE_WRITE_ATTRIBUTES)
+                    .custom_flags(FILE_FLAG_BACKUP_SEMANTICS | FILE_FLAG_OPEN_REPARSE_POINT);
+            }
+            options.open(path)?.set_times(
+                std::fs::FileTimes::new()
+                    .set_accessed(at(atime))
+                    .set_modified(at(mtime)),
+            )
+        }
+    }
+}
+
+/// Writes one to three alternate data streams of up to 4 KiB of random
+/// contents, derived from `hash`, to the file at `path`. Returns the names and
+/// sizes of the streams as `name:size` pairs separated by semicolons.
+pub fn write_streams(path: &Path, hash: u64) -> io::Result<String> {
+    let mut random = Xoshiro256PlusPlus::seed_from_u64(hash);
+    let mut streams = Vec::new();
+    for i in 0..=hash % 3 {
+        let name = format!("ftzz{i}");
+        let size = random.next_u64() % 4097;
+        let mut contents = vec![0; usize::try_from(size).unwrap_or_default()];
+        random.fill_bytes(&mut contents);
+        write_stream(path, &name, &contents)?;
+        streams.push(format!("{name}:{size}"));
+    }
+    Ok(streams.join(";"))
+}
+
+fn write_stream(path: &Path, name: &str, contents: &[u8]) -> io::Result<()> {
+    cfg_if! {
+        if #[cfg(windows)] {
+            let mut stream = path.as_os_str().to_owned();
+            stream.push(":");
+            stream.push(name);
+            std::fs::write(stream, contents)
+        } else {
+            let _ = (path, name, contents);
+            Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "alternate data streams can only be written on Windows",
+            ))
+        }
+    }
+}
+
+/// The error Windows reports when creating a symlink without the privilege to.
+#[cfg(windows)]
+pub const ERROR_PRIVILEGE_NOT_HELD: i32 = 1314;
+#[cfg(windows)]
+const FILE_FLAG_BACKUP_SEMANTICS: u32 = 0x0200_0000;
+#[cfg(windows)]
+const FILE_FLAG_OPEN_REPARSE_POINT: u32 = 0x0020_0000;
+
+/// Creates an NTFS junction at `link` pointing at the directory `target`,
+/// which must be absolute. Unlike directory symlinks, junctions need no
+/// privileges.
+#[cfg(windows)]
+pub fn create_junction(target: &Path, link: &Path) -> io::Result<()> {
+    use std::{
+        ffi::c_void,
+        os::windows::{ffi::OsStrExt, fs::OpenOptionsExt, io::AsRawHandle},
+    };
+
+    #[link(name = "kernel32")]
+    unsafe extern "system" {
+        fn DeviceIoControl(
+            device: *mut c_void,
+            control_code: u32,
+            in_buffer: *const c_void,
+            in_buffer_size: u32,
+            out_buffer: *mut c_void,
+            out_buffer_size: u32,
+            bytes_returned: *mut u32,
+            overlapped: *mut c_void,
+        ) -> i32;
+    }
+    const GENERIC_WRITE: u32 = 0x4000_0000;
+    const FSCTL_SET_REPARSE_POINT: u32 = 0x0009_00A4;
+    const IO_REPARSE_TAG_MOUNT_POINT: u32 = 0xA000_0003;
+
+    const BACKSLASH: u16 = b'\\' as u16;
+    const QUESTION_MARK: u16 = b'?' as u16;
+
+    // Junctions name their target by its NT path
+    let target = target.as_os_str().encode_wide().collect::<Vec<_>>();
+    let print_name = match target.as_slice() {
+        [BACKSLASH, BACKSLASH, QUESTION_MARK, BACKSLASH, rest @ ..] => rest.to_vec(),
+        _ => target.clone(),
+    };
+    let substitute_name = [BACKSLASH, QUESTION_MARK, QUESTION_MARK, BACKSLASH]
+        .into_iter()
+        .chain(print_name.iter().copied())
+        .collect::<Vec<_>>();
+    let name_bytes = |name: &[u16]| u16::try_from(name.len() * 2).map_err(io::Error::other);
+    let substitute_len = name_bytes(&substitute_name)?;
+    let print_len = name_bytes(&print_name)?;
+
+    let mut buffer = Vec::new();
+    buffer.extend(IO_REPARSE_TAG_MOUNT_POINT.to_le_bytes());
+    // The mount point header plus both names and their terminators
+    buffer.extend((8 + substitute_len + 2 + print_len + 2).to_le_bytes());
+    buffer.extend(0u16.to_le_bytes());
+    for field in [0, substitute_len, substitute_len + 2, print_len] {
+        buffer.extend(field.to_le_bytes());
+    }
+    for c in substitute_name
+        .into_iter()
+        .chain([0])
+        .chain(print_name)
+        .chain([0])
+    {
+        buffer.extend(c.to_le_bytes());
+    }
+
+    std::fs::create_dir(link)?;
+    let result = std::fs::File::options()
+        .access_mode(GENERIC_WRITE)
+        .custom_flags(FILE_FLAG_BACKUP_SEMANTICS | FILE_FLAG_OPEN_REPARSE_POINT)
+        .open(link)
+        .and_then(|dir| {
+            let mut returned = 0;
+            // SAFETY: the handle is open for the duration of the call and the
+            // buffer lengths match the buffers.
+            let ok = unsafe {
+                DeviceIoControl(
+                    dir.as_raw_handle(),
+                    FSCTL_SET_REPARSE_POINT,
+                    buffer.as_ptr().cast(),
+                    u32::try_from(buffer.len()).map_err(io::Error::other)?,
+                    std::ptr::null_mut(),
+                    0,
+                    &mut returned,
+                    std::ptr::null_mut(),
+                )
+            };
+            if ok == 0 {
+                Err(io::Error::last_os_error())
+            } else {
+                Ok(())
+            }
+        });
+    if result.is_err() {
+        let _ = std::fs::remove_dir(link);
+    }
+    result
+}
+
+/// Adds the Windows file `attributes` to those `path` already has.
+pub fn set_file_attributes(path: &Path, attributes: u32) -> io::Result<()> {
+    cfg_if! {
+        if #[cfg(windows)] {
+            use std::os::windows::{ffi::OsStrExt, fs::MetadataExt};
+
+            #[link(name = "kernel32")]
+            unsafe extern "system" {
+                fn SetFileAttributesW(file_name: *const u16, attributes: u32) -> i32;
+            }
+
+            // FILE_ATTRIBUTE_NORMAL is only valid on its own
+            const FILE_ATTRIBUTE_NORMAL: u32 = 0x80;
+            let existing = std::fs::symlink_metadata(path)?.file_attributes() & !FILE_ATTRIBUTE_NORMAL;
+            let path = path
+                .as_os_str()
+                .encode_wide()
+                .chain([0])
+                .collect::<Vec<_>>();
+            // SAFETY: the path is NUL-terminated and outlives the call.
+            if unsafe { SetFileAttributesW(path.as_ptr(), existing | attributes) } == 0 {
+                return Err(io::Error::last_os_error());
+            }
+            Ok(())
+        } else {
+            let _ = (path, attributes);
+            Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "file attributes can only be set on Windows",
+            ))
+        }
+    }
+}
+
+/// Creates a FIFO, socket, or device node at `path` with `permission`. Device
+/// nodes get the numbers of `/dev/null` and `/dev/loop0` respectively.
+pub fn create_special(
+    path: &Path,
+    entry_type: EntryType,
+    permission: Option<u32>,
+) -> io::Result<()> {
+    cfg_if! {
+        if #[cfg(all(target_os = "linux", not(miri)))] {
+            use rustix::fs::makedev;
+
+            let (file_type, dev) = match entry_type {
+                EntryType::Fifo => (FileType::Fifo, 0),
+                EntryType::Socket => (FileType::Socket, 0),
+                EntryType::CharDevice => (FileType::CharacterDevice, makedev(1, 3)),
+                EntryType::BlockDevice => (FileType::BlockDevice, makedev(7, 0)),
+                EntryType::File
+                | EntryType::Directory
+                | EntryType::Symlink
+                | EntryType::Junction => {
+                    return Err(io::ErrorKind::InvalidInput.into());
+                }
+            };
+            mknodat(
+                rustix::fs::CWD,
+                path,
+                file_type,
+                Mode::from_bits_retain(permission.unwrap_or(0o664)),
+                dev,
+            )?;
+            if let Some(mode) = masked_by_umask(permission) {
+                chmodat(
+                    rustix::fs::CWD,
+                    path,
+                    Mode::from_bits_retain(mode),
+                    AtFlags::empty(),
+                )?;
+            }
+            Ok(())
+        } else {
+            let _ = (path, entry_type, permission);
+            Err(io::ErrorKind::Unsupported.into())
+        }
+    }
 }
 
-impl<'a, R> From<(Option<u8>, &'a mut R)> for BytesKind<'a, R> {
-    fn from((fill_byte, random): (Option<u8>, &'a mut R)) -> Self {
-        fill_byte.map_or(BytesKind::Random(random), |byte| BytesKind::Fixed(byte))
+/// The process umask, read once since doing so requires briefly changing it.
+#[cfg(unix)]
+pub fn umask() -> u32 {
+    static UMASK: OnceLock<u32> = OnceLock::new();
+
+    *UMASK.get_or_init(|| {
+        let umask = rustix::process::umask(Mode::empty());
+        rustix::process::umask(umask);
+        umask.bits()
+    })
+}
+
+/// Files are created with their final mode in a single syscall unless the
+/// umask strips some of its bits, in which case the mode is returned so it can
+/// be set explicitly.
+fn masked_by_umask(mode: Option<u32>) -> Option<u32> {
+    cfg_if! {
+        if #[cfg(unix)] {
+            mode.filter(|mode| mode & umask() != 0)
+        } else {
+            mode
+        }
+    }
+}
+
+/// Writing to a file clears its setuid and setgid bits unless the process is
+/// privileged, so files with either get their mode set again once their
+/// contents have been written.
+fn special_permissions(mode: Option<u32>) -> Option<fs::Permissions> {
+    cfg_if! {
+        if #[cfg(unix)] {
+            mode.filter(|mode| mode & 0o6000 != 0)
+                .map(fs::Permissions::from_mode)
+        } else {
+            let _ = mode;
+            None
+        }
+    }
+}
+
+fn restore_masked_permissions(file: &File, mode: Option<u32>) -> io::Result<()> {
+    #[cfg(unix)]
+    if let Some(mode) = masked_by_umask(mode) {
+        file.set_permissions(fs::Permissions::from_mode(mode))?;
     }
+    #[cfg(not(unix))]
+    let _ = (file, mode);
+    Ok(())
+}
+
+/// Turns `O_DIRECT` back off so an unaligned tail can be written.
+fn disable_direct_io(file: &File) -> io::Result<()> {
+    #[cfg(target_os = "linux")]
+    {
+        use rustix::fs::{OFlags, fcntl_getfl, fcntl_setfl};
+
+        fcntl_setfl(file, fcntl_getfl(file)? - OFlags::DIRECT)?;
+    }
+    #[cfg(not(target_os = "linux"))]
+    let _ = file;
+    Ok(())
 }
 
 #[cfg_attr(
     feature = "tracing",
-    tracing::instrument(level = "trace", skip(file, kind))
+    tracing::instrument(level = "trace", skip(file, kind, buffer))
 )]
-fn write_bytes<'a, R: RngCore + 'static>(
-    file: File,
+#[allow(clippy::too_many_arguments)]
+fn write_bytes<R: RngCore + 'static>(
+    ContentFile { file, direct }: ContentFile,
     num: u64,
-    kind: impl Into<BytesKind<'a, R>>,
+    mut kind: BytesKind<'_, R>,
+    header: &[u8],
+    buffer: &mut WriteBuffer,
+    writeback: Option<&mut Writeback>,
+    holes: Option<Holes>,
     hash_seed: Option<u64>,
 ) -> io::Result<Option<u64>> {
     use crate::core::audit::HashingWriter;
 
-    if let Some(seed) = hash_seed {
-        let mut writer = HashingWriter::new(file, seed);
-        let copied = match kind.into() {
-            BytesKind::Random(random) => {
-                io::copy(&mut random.read_adapter().take(num), &mut writer)
-            }
-            BytesKind::Fixed(byte) => io::copy(&mut io::repeat(byte).take(num), &mut writer),
-        }?;
-        debug_assert_eq!(num, copied);
-        Ok(Some(writer.finalize()))
+    // The header replaces the start of the contents rather than shifting
+    // them, so files keep their size and the same contents otherwise
+    let mut position = 0;
+    let mut fill = |buf: &mut [u8]| {
+        kind.fill(buf);
+        overlay_header(header, position, buf);
+        if let Some(holes) = holes {
+            holes.punch(buf, position);
+        }
+        position += buf.len() as u64;
+    };
+    // Direct I/O can only write whole aligned blocks
+    let aligned = if direct {
+        num & !(DIRECT_IO_ALIGNMENT - 1)
     } else {
-        let mut file = file;
-        let copied = match kind.into() {
-            BytesKind::Random(random) => io::copy(&mut random.read_adapter().take(num), &mut file),
-            BytesKind::Fixed(byte) => io::copy(&mut io::repeat(byte).take(num), &mut file),
-        }?;
-        debug_assert_eq!(num, copied);
-        Ok(None)
+        num
+    };
+    let mut write = |out: &mut dyn Write| {
+        write_blocks(out, aligned, buffer, &mut fill)?;
+        if aligned < num {
+            disable_direct_io(&file)?;
+            write_blocks(out, num - aligned, buffer, &mut fill)?;
+        }
+        io::Result::Ok(())
+    };
+
+    let mut writer = ContentWriter {
+        file: &file,
+        writeback,
+        holes,
+        position: 0,
+    };
+    let hash = if let Some(seed) = hash_seed {
+        let mut writer = HashingWriter::new(writer, seed);
+        write(&mut writer)?;
+        Some(writer.finalize())
+    } else {
+        write(&mut writer)?;
+        None
+    };
+    if holes.is_some() {
+        // Seeking past the end doesn't grow the file if it ends in a hole
+        file.set_len(num)?;
+    }
+    Ok(hash)
+}
+
+/// Allocates `num` bytes for `file` without writing them, so they read back as
+/// zeros, returning the hash they will have.
+fn allocate_zeros(
+    ContentFile { file, .. }: ContentFile,
+    num: u64,
+    buffer: &mut WriteBuffer,
+    hash_seed: Option<u64>,
+) -> io::Result<Option<u64>> {
+    use crate::core::audit::HashingWriter;
+
+    if num > 0 {
+        cfg_if! {
+            if #[cfg(all(target_os = "linux", not(miri)))] {
+                rustix::fs::fallocate(&file, rustix::fs::FallocateFlags::empty(), 0, num)?;
+            } else {
+                file.set_len(num)?;
+            }
+        }
+    }
+    hash_seed
+        .map(|seed| {
+            let mut writer = HashingWriter::new(io::sink(), seed);
+            write_blocks(&mut writer, num, buffer, &mut |buf| buf.fill(0))?;
+            Ok(writer.finalize())
+        })
+        .transpose()
+}
+
+/// The number of files whose appends are interleaved at once.
+const FRAGMENTED_FILES: usize = 64;
+
+/// Defers writing file contents so up to [`FRAGMENTED_FILES`] files can be
+/// appended to a block at a time, taking turns. Each round is flushed to disk
+/// before the next so delayed allocation can't undo the interleaving, leaving
+/// the files heavily fragmented on extent-based file systems.
+#[derive(Debug, Default)]
+pub struct Fragmenter {
+    pending: Vec<PendingContents>,
+}
+
+#[derive(Debug)]
+struct PendingContents {
+    file: ContentFile,
+    written: u64,
+    remaining: u64,
+    fill_pattern: Option<FillPattern>,
+    content: ContentMode,
+    /// The file's seed.
+    seed: u64,
+    header: Box<[u8]>,
+    random: Xoshiro256PlusPlus,
+    /// Restored once the contents have been written.
+    special_permissions: Option<fs::Permissions>,
+}
+
+impl Fragmenter {
+    /// Queues writing `num` bytes to `file`, returning the hash the contents
+    /// will have.
+    #[allow(clippy::too_many_arguments)]
+    fn push(
+        &mut self,
+        file: ContentFile,
+        num: u64,
+        fill_pattern: Option<&FillPattern>,
+        content: ContentMode,
+        seed: u64,
+        header: &[u8],
+        random: Xoshiro256PlusPlus,
+        buffer: &mut WriteBuffer,
+        hash_seed: Option<u64>,
+        mode: Option<u32>,
+    ) -> io::Result<Option<u64>> {
+        use crate::core::audit::HashingWriter;
+
+        // The contents aren't written until later, so hash a replay of them
+        let hash = hash_seed
+            .map(|seed| {
+                let mut writer = HashingWriter::new(io::sink(), seed);
+                let mut replay = random.clone();
+                let mut kind = BytesKind::new(fill_pattern, content, num, seed, &mut replay);
+                let mut position = 0;
+                write_blocks(&mut writer, num, buffer, &mut |buf| {
+                    kind.fill(buf);
+                    overlay_header(header, position, buf);
+                    position += buf.len() as u64;
+                })?;
+                io::Result::Ok(writer.finalize())
+            })
+            .transpose()?;
+
+        self.pending.push(PendingContents {
+            file,
+            written: 0,
+            remaining: num,
+            fill_pattern: fill_pattern.cloned(),
+            content,
+            seed,
+            header: header.into(),
+            random,
+            special_permissions: special_permissions(mode),
+        });
+        if self.pending.len() >= FRAGMENTED_FILES {
+            self.flush(buffer)?;
+        }
+        Ok(hash)
+    }
+
+    /// Writes out the contents of every queued file.
+    fn flush(&mut self, buffer: &mut WriteBuffer) -> io::Result<()> {
+        while !self.pending.is_empty() {
+            for PendingContents {
+                file,
+                written,
+                remaining,
+                fill_pattern,
+                content,
+                seed,
+                header,
+                random,
+                special_permissions: _,
+            } in &mut self.pending
+            {
+                // Whole lanes keep the random stream identical to writing the
+                // file in one go
+                let len = (*remaining).min(DIRECT_IO_ALIGNMENT);
+                if file.direct && len < DIRECT_IO_ALIGNMENT {
+                    disable_direct_io(&file.file)?;
+                    file.direct = false;
+                }
+                let mut kind = BytesKind::new(
+                    fill_pattern.as_ref(),
+                    *content,
+                    *written + *remaining,
+                    *seed,
+                    random,
+                )
+                .at(*written);
+                let mut position = *written;
+                write_blocks(&mut &file.file, len, buffer, &mut |buf| {
+                    kind.fill(buf);
+                    overlay_header(header, position, buf);
+                    position += buf.len() as u64;
+                })?;
+                *written += len;
+                *remaining -= len;
+            }
+
+            cfg_if! {
+                if #[cfg(all(target_os = "linux", not(miri)))] {
+                    rustix::fs::syncfs(&self.pending[0].file.file)?;
+                } else {
+                    for PendingContents { file, .. } in &self.pending {
+                        file.file.sync_data()?;
+                    }
+                }
+            }
+            for PendingContents {
+                file,
+                remaining,
+                special_permissions,
+                ..
+            } in &mut self.pending
+            {
+                if *remaining == 0
+                    && let Some(permissions) = special_permissions.take()
+                {
+                    file.file.set_permissions(permissions)?;
+                }
+            }
+            self.pending.retain(|contents| contents.remaining > 0);
+        }
+        Ok(())
+    }
+}
+
+/// Bounds the volume of dirty pages a task leaves behind by flushing them to
+/// disk every time `limit` bytes have been written.
+#[derive(Debug, Copy, Clone)]
+pub struct Writeback {
+    limit: NonZeroU64,
+    dirty: u64,
+}
+
+impl Writeback {
+    #[must_use]
+    pub const fn new(limit: NonZeroU64) -> Self {
+        Self { limit, dirty: 0 }
+    }
+
+    const fn reset(self) -> Self {
+        Self::new(self.limit)
+    }
+
+    fn record(&mut self, file: &File, written: usize) -> io::Result<()> {
+        self.dirty += written as u64;
+        if self.dirty < self.limit.get() {
+            return Ok(());
+        }
+        self.dirty = 0;
+
+        cfg_if! {
+            if #[cfg(all(target_os = "linux", not(miri)))] {
+                // Files closed earlier in the task are dirty too, so flush
+                // the whole file system rather than just this file.
+                rustix::fs::syncfs(file)?;
+            } else if #[cfg(all(unix, not(miri)))] {
+                rustix::fs::fdatasync(file)?;
+            } else {
+                file.sync_data()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Writes to a content file, seeking over the holes of sparse files instead of
+/// writing their zeros.
+struct ContentWriter<'a> {
+    file: &'a File,
+    writeback: Option<&'a mut Writeback>,
+    holes: Option<Holes>,
+    position: u64,
+}
+
+impl Write for ContentWriter<'_> {
+    fn write(&mut self, mut buf: &[u8]) -> io::Result<usize> {
+        if let Some(holes) = self.holes {
+            let (hole, len) = holes.run(self.position, buf.len() as u64);
+            if hole {
+                (&*self.file).seek(SeekFrom::Current(len.cast_signed()))?;
+                self.position += len;
+                return Ok(len as usize);
+            }
+            buf = &buf[..len as usize];
+        }
+        let n = (&mut &*self.file).write(buf)?;
+        self.position += n as u64;
+        if let Some(writeback) = &mut self.writeback {
+            writeback.record(self.file, n)?;
+        }
+        Ok(n)
+    }
+
+    fn write_vectored(&mut self, bufs: &[IoSlice<'_>]) -> io::Result<usize> {
+        if self.holes.is_some() {
+            let buf = bufs
+                .iter()
+                .find(|buf| !buf.is_empty())
+                .map_or(&[][..], |buf| buf);
+            return self.write(buf);
+        }
+        let n = (&mut &*self.file).write_vectored(bufs)?;
+        self.position += n as u64;
+        if let Some(writeback) = &mut self.writeback {
+            writeback.record(self.file, n)?;
+        }
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// The number of blocks handed to the kernel in a single vectored write.
+const BLOCKS_PER_WRITE: usize = 8;
+
+/// A reusable buffer of [`BLOCKS_PER_WRITE`] blocks for staging file contents.
+///
+/// Blocks are aligned and padded to [`DIRECT_IO_ALIGNMENT`] so they can be
+/// handed straight to `O_DIRECT` files.
+#[derive(Default)]
+pub struct WriteBuffer {
+    bytes: Vec<u8>,
+    block_size: usize,
+}
+
+impl WriteBuffer {
+    fn new(block_size: usize) -> Self {
+        let alignment = DIRECT_IO_ALIGNMENT as usize;
+        let block_size = block_size.next_multiple_of(alignment);
+        Self {
+            // Over-allocate so the blocks can start on an aligned address
+            bytes: vec![0; block_size * BLOCKS_PER_WRITE + alignment - 1],
+            block_size,
+        }
+    }
+
+    fn blocks(&mut self) -> &mut [u8] {
+        let start = self
+            .bytes
+            .as_ptr()
+            .align_offset(DIRECT_IO_ALIGNMENT as usize);
+        &mut self.bytes[start..][..self.block_size * BLOCKS_PER_WRITE]
+    }
+}
+
+/// Hands out [`WriteBuffer`]s to tasks, keeping the ones they are done with
+/// around for later tasks.
+///
+/// At most one buffer is live per running task, so the pool never grows
+/// beyond the number of blocking threads.
+pub struct WriteBufferPool {
+    block_size: usize,
+    buffers: Mutex<Vec<WriteBuffer>>,
+}
+
+impl WriteBufferPool {
+    /// Blocks are rounded up to a multiple of [`DIRECT_IO_ALIGNMENT`].
+    #[must_use]
+    pub const fn new(block_size: NonZeroUsize) -> Self {
+        Self {
+            block_size: block_size.get(),
+            buffers: Mutex::new(Vec::new()),
+        }
+    }
+
+    fn take(&self) -> PooledWriteBuffer<'_> {
+        let buffer = self
+            .buffers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .pop()
+            .unwrap_or_else(|| WriteBuffer::new(self.block_size));
+        PooledWriteBuffer { buffer, pool: self }
+    }
+}
+
+impl std::fmt::Debug for WriteBufferPool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("WriteBufferPool")
+            .field("block_size", &self.block_size)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A [`WriteBuffer`] that returns to its pool when dropped.
+pub struct PooledWriteBuffer<'a> {
+    buffer: WriteBuffer,
+    pool: &'a WriteBufferPool,
+}
+
+impl std::ops::Deref for PooledWriteBuffer<'_> {
+    type Target = WriteBuffer;
+
+    fn deref(&self) -> &Self::Target {
+        &self.buffer
+    }
+}
+
+impl std::ops::DerefMut for PooledWriteBuffer<'_> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.buffer
+    }
+}
+
+impl Drop for PooledWriteBuffer<'_> {
+    fn drop(&mut self) {
+        self.pool
+            .buffers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(mem::take(&mut self.buffer));
+    }
+}
+
+/// Writes `num` bytes, filling up to [`BLOCKS_PER_WRITE`] blocks of `buffer`
+/// before handing them off in a single vectored write to cut down on syscalls
+/// for medium-sized files.
+///
+/// Random streams are identical to reading from [`RngCore::fill_bytes`] since
+/// blocks are always a multiple of the lane size and only the very last
+/// partial lane is handed off to it.
+fn write_blocks(
+    out: &mut (impl Write + ?Sized),
+    num: u64,
+    buffer: &mut WriteBuffer,
+    fill: &mut impl FnMut(&mut [u8]),
+) -> io::Result<()> {
+    let block_size = buffer.block_size;
+    let blocks = buffer.blocks();
+    let mut remaining = num;
+    while remaining > 0 {
+        let mut lens = [0; BLOCKS_PER_WRITE];
+        for (block, len) in blocks.chunks_exact_mut(block_size).zip(&mut lens) {
+            if remaining == 0 {
+                break;
+            }
+            *len = usize::try_from(remaining).map_or(block_size, |r| r.min(block_size));
+            fill(&mut block[..*len]);
+            remaining -= *len as u64;
+        }
+
+        let mut slices = [IoSlice::new(&[]); BLOCKS_PER_WRITE];
+        for ((slice, block), &len) in slices
+            .iter_mut()
+            .zip(blocks.chunks_exact(block_size))
+            .zip(&lens)
+        {
+            *slice = IoSlice::new(&block[..len]);
+        }
+        let filled = lens.iter().take_while(|&&len| len > 0).count();
+        write_all_vectored(out, &mut slices[..filled])?;
+    }
+    Ok(())
+}
+
+/// Fills `buf` with whole 64-bit lanes four at a time so the loop unrolls into
+/// wide stores.
+pub fn fill_random_block(buf: &mut [u8], random: &mut impl RngCore) {
+    let mut quads = buf.chunks_exact_mut(4 * size_of::<u64>());
+    for quad in &mut quads {
+        let lanes = [
+            random.next_u64(),
+            random.next_u64(),
+            random.next_u64(),
+            random.next_u64(),
+        ];
+        for (dst, lane) in quad.chunks_exact_mut(size_of::<u64>()).zip(lanes) {
+            dst.copy_from_slice(&lane.to_le_bytes());
+        }
+    }
+    let mut lanes = quads.into_remainder().chunks_exact_mut(size_of::<u64>());
+    for lane in &mut lanes {
+        lane.copy_from_slice(&random.next_u64().to_le_bytes());
+    }
+    random.fill_bytes(lanes.into_remainder());
+}
+
+fn write_all_vectored(
+    out: &mut (impl Write + ?Sized),
+    mut bufs: &mut [IoSlice<'_>],
+) -> io::Result<()> {
+    while !bufs.is_empty() {
+        match out.write_vectored(bufs) {
+            Ok(0) => return Err(io::ErrorKind::WriteZero.into()),
+            Ok(n) => IoSlice::advance_slices(&mut bufs, n),
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+
+    #[test]
+    fn fill_pattern_repeats_from_offset() {
+        let pattern = FillPattern::new(*b"abc").unwrap();
+        for (offset, len) in [(0, 0), (0, 2), (1, 7), (5, 16), (3, 3)] {
+            let mut actual = vec![0; len];
+            pattern.fill(&mut actual, offset);
+
+            let expected = b"abc"
+                .iter()
+                .cycle()
+                .skip((offset % 3) as usize)
+                .take(len)
+                .copied()
+                .collect::<Vec<_>>();
+            assert_eq!(actual, expected, "{offset} {len}");
+        }
+        assert!(FillPattern::new([]).is_none());
+    }
+
+    #[test]
+    fn corpus_files_wrap_around_a_sample() {
+        let samples = [b"abcdefg".to_vec(), Vec::new(), b"0123".to_vec()];
+        let corpus = FillPattern::corpus(samples).unwrap();
+        assert!(FillPattern::corpus([Vec::new()]).is_none());
+
+        let mut picked = HashSet::new();
+        for seed in 0..32 {
+            let mut buf = vec![0; 20];
+            let mut random = Xoshiro256PlusPlus::seed_from_u64(seed);
+            BytesKind::new(Some(&corpus), ContentMode::Random, 20, seed, &mut random)
+                .fill(&mut buf);
+
+            let (sample, start) = corpus.for_file(seed);
+            let expected = sample
+                .iter()
+                .cycle()
+                .skip(start as usize)
+                .take(20)
+                .copied()
+                .collect::<Vec<_>>();
+            assert_eq!(buf, expected, "{seed}");
+            picked.insert(sample.to_vec());
+        }
+        assert_eq!(picked.len(), 2);
+    }
+
+    #[test]
+    fn random_blocks_match_fill_bytes() {
+        for block_size in [4096, 8192] {
+            let mut buffer = WriteBuffer::new(block_size);
+            for num in [0, 1, 4, 5, 8, 31, 33, block_size + 3, 3 * block_size] {
+                let mut expected = vec![0; num];
+                Xoshiro256PlusPlus::seed_from_u64(42).fill_bytes(&mut expected);
+
+                let mut actual = Vec::new();
+                let mut random = Xoshiro256PlusPlus::seed_from_u64(42);
+                write_blocks(&mut actual, num as u64, &mut buffer, &mut |buf| {
+                    fill_random_block(buf, &mut random);
+                })
+                .unwrap();
+
+                assert_eq!(expected, actual, "{block_size} {num}");
+            }
+        }
+    }
+
+    #[test]
+    fn text_fills_chunks_with_words() {
+        for len in [0, 1, 5, 100, CONTENT_CHUNK] {
+            let mut buf = vec![0; len];
+            fill_text(&mut buf, &mut Xoshiro256PlusPlus::seed_from_u64(42));
+
+            assert!(buf.last().is_none_or(|&b| b == b'\n'), "{len}");
+            let text = str::from_utf8(&buf).unwrap();
+            for word in text.split([' ', ',', '.', '\n']).filter(|w| !w.is_empty()) {
+                assert!(
+                    WORDS.contains(&word.to_ascii_lowercase().as_str()),
+                    "{len} {word}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn json_files_are_valid_at_any_length() {
+        for len in [
+            1,
+            2,
+            3,
+            50,
+            1000,
+            CONTENT_CHUNK,
+            CONTENT_CHUNK + 1,
+            3 * CONTENT_CHUNK - 2,
+        ] {
+            let mut buf = vec![0; len];
+            let mut random = Xoshiro256PlusPlus::seed_from_u64(42);
+            BytesKind::new(None, ContentMode::Json, len as u64, 0, &mut random).fill(&mut buf);
+
+            let value = serde_json::from_slice::<serde_json::Value>(&buf).unwrap();
+            if len >= 1000 {
+                assert!(value.as_array().is_some_and(|records| records.len() > 5));
+            }
+        }
+    }
+
+    #[test]
+    fn csv_rows_have_every_column() {
+        let columns = NonZeroU8::new(11).unwrap();
+        for len in [1, 20, 1000, 3 * CONTENT_CHUNK + 5] {
+            let mut buf = vec![0; len];
+            let mut random = Xoshiro256PlusPlus::seed_from_u64(42);
+            BytesKind::new(
+                None,
+                ContentMode::Csv { columns },
+                len as u64,
+                0,
+                &mut random,
+            )
+            .fill(&mut buf);
+
+            let mut reader = csv::Reader::from_reader(&*buf);
+            if len >= 1000 {
+                let headers = reader.headers().unwrap();
+                assert_eq!(headers.len(), 11);
+                assert_eq!(&headers[8], "id2");
+            }
+            let rows = reader.records().map(Result::unwrap).count();
+            assert_eq!(rows > 0, len >= 1000, "{len}");
+        }
+    }
+
+    #[test]
+    fn dedup_blocks_repeat_at_the_reuse_rate() {
+        let blocks = NonZeroU32::new(4).unwrap();
+        for (reuse_threshold, distinct) in [(u64::MAX, 1..=4), (0, 64..=64)] {
+            let mut buf = vec![0; 64 * CONTENT_CHUNK];
+            let mut random = Xoshiro256PlusPlus::seed_from_u64(42);
+            let content = ContentMode::Dedup {
+                reuse_threshold,
+                blocks,
+            };
+            BytesKind::new(None, content, buf.len() as u64, 0, &mut random).fill(&mut buf);
+
+            let unique = buf.chunks(CONTENT_CHUNK).collect::<HashSet<_>>();
+            assert!(distinct.contains(&unique.len()), "{}", unique.len());
+        }
+    }
+
+    #[test]
+    fn log_chunks_follow_each_other() {
+        let mut random = Xoshiro256PlusPlus::seed_from_u64(42);
+        let mut previous = String::new();
+        for index in 0..3 {
+            let mut buf = vec![0; CONTENT_CHUNK];
+            fill_log(&mut buf, true, 7, index, &mut random);
+
+            assert_eq!(buf.last(), Some(&b'\n'));
+            for line in str::from_utf8(&buf).unwrap().lines() {
+                let record = serde_json::from_str::<serde_json::Value>(line).unwrap();
+                let time = record["timestamp"].as_str().unwrap().to_string();
+                assert!(time >= previous, "{previous} {time}");
+                previous = time;
+            }
+        }
+    }
+
+    #[test]
+    fn headers_start_with_signatures() {
+        let headers = FileHeaders {
+            root_dir: PathBuf::from("/root"),
+            run_id: 0xabc,
+            identify: true,
+            magic_bytes: true,
+        };
+        let header = headers.render(Path::new("/root/a/Photo 1.JPG"), 1);
+        assert_eq!(
+            header,
+            b"\xff\xd8\xff\xe0ftzz run=0000000000000abc seed=0000000000000001 path=a/Photo 1.JPG\n"
+        );
+
+        let headers = FileHeaders {
+            identify: false,
+            ..headers
+        };
+        assert_eq!(headers.render(Path::new("/root/b.docx"), 1), b"PK\x03\x04");
+        assert!(headers.render(Path::new("/root/c.txt"), 1).is_empty());
+        assert!(headers.render(Path::new("/root/123"), 1).is_empty());
+    }
+
+    #[test]
+    fn holes_take_up_chunk_tails() {
+        assert!(Holes::new(1.).is_none());
+
+        let holes = Holes::new(50.).unwrap();
+        let mut buf = vec![1; 3 * SPARSE_CHUNK as usize / 2];
+        holes.punch(&mut buf, SPARSE_CHUNK / 4);
+
+        let half = SPARSE_CHUNK as usize / 2;
+        let quarter = half / 2;
+        assert!(buf[..quarter].iter().all(|&b| b == 1));
+        assert!(buf[quarter..][..half].iter().all(|&b| b == 0));
+        assert!(buf[quarter + half..][..half].iter().all(|&b| b == 1));
+        assert!(buf[quarter + 2 * half..].iter().all(|&b| b == 0));
+        assert_eq!(holes.run(SPARSE_CHUNK - 1, 10), (true, 1));
+    }
+
+    #[test]
+    fn hole_patterns_alternate_data_and_holes() {
+        assert!(Holes::pattern(4096, 0).is_none());
+        assert!(Holes::pattern(4096, 1000).is_none());
+        assert!(Holes::pattern(u64::MAX - 4095, 4096).is_none());
+
+        let holes = Holes::pattern(4096, 3 * 4096).unwrap();
+        assert_eq!(holes.layout(), (4096, 3 * 4096));
+        assert_eq!(holes.run(0, 20_000), (false, 4096));
+        assert_eq!(holes.run(4096, 20_000), (true, 3 * 4096));
+        assert_eq!(holes.run(4 * 4096 + 100, 20_000), (false, 3996));
+        assert!(Holes::pattern(0, 4096).unwrap().run(0, 10).0);
     }
 }
@@ -1,27 +1,743 @@
-use std::{fs::create_dir_all, io, io::ErrorKind::NotFound, sync::Arc};
+use std::{
+    borrow::Cow,
+    ffi::OsStr,
+    fs::{create_dir_all, hard_link, metadata, symlink_metadata},
+    io,
+    io::ErrorKind::{AlreadyExists, NotFound},
+    path::{Component, Path, PathBuf},
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicU64, Ordering},
+    },
+};
 
+use cfg_if::cfg_if;
 use error_stack::{Report, Result, ResultExt};
+use glob::{MatchOptions, Pattern};
+use twox_hash::XxHash64;
 
+#[cfg(windows)]
+use crate::core::file_contents::ERROR_PRIVILEGE_NOT_HELD;
 use crate::{
-    core::{FileSpec, audit::AuditTrail, file_contents::FileContentsGenerator},
-    utils::{FastPathBuf, with_dir_name, with_file_name},
+    core::{
+        Acl, DuplicateLinks, ExtensionTable, FileSpec, HardlinkPool, Inode, LinkFlavor,
+        PermissionTable,
+        audit::{AuditTrail, EntryAttributes, EntryType, mime_type},
+        file_contents::{
+            FileContentsGenerator, WriteBufferPool, create_special, set_file_attributes, set_times,
+            write_streams,
+        },
+    },
+    generator::{NAME_MAX, NameLengthRange, TimeRange},
+    utils::{
+        FastPathBuf, NameTemplate, PopGuard, pad_name, with_dir_name, with_file_name,
+        with_realistic_file_name, with_templated_dir_name, with_templated_file_name,
+        with_unicode_dir_name, with_unicode_file_name,
+    },
+};
+
+const GLOB_OPTIONS: MatchOptions = MatchOptions {
+    case_sensitive: true,
+    require_literal_separator: true,
+    require_literal_leading_dot: false,
 };
 
 pub struct GeneratorTaskParams<G: FileContentsGenerator> {
     pub target_dir: FastPathBuf,
     pub file_objs: Vec<FileSpec>,
     pub num_dirs: usize,
+    /// The index of the first directory to create, past any the target
+    /// directory already holds.
+    pub dir_offset: usize,
     pub file_offset: u64,
     pub file_contents: G,
     pub audit_trail: Option<Arc<AuditTrail>>,
+    pub settings: Arc<TaskSettings>,
     #[allow(dead_code)]
     pub task_index: u64,
 }
 
+/// How generated files are named.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub enum NameStyle {
+    /// Name files by their index in their directory.
+    #[default]
+    Numeric,
+    /// Name files like a person might, with words and common extensions.
+    Realistic,
+    /// Mix CJK, Cyrillic, Arabic, and emoji codepoints into the names of
+    /// files and directories.
+    Unicode,
+}
+
+/// How generated directories are named.
+#[derive(Debug, Default, Clone)]
+pub struct DirNames {
+    pub style: NameStyle,
+    /// Directories are named after this template instead if set.
+    pub template: Option<NameTemplate>,
+}
+
+impl DirNames {
+    /// Calls `f` with the name of directory `i` at `depth`, which only
+    /// depends on `i` and `depth` so the scheduler can find the directory
+    /// again.
+    pub fn with_dir_name<T>(&self, i: usize, depth: usize, f: impl FnOnce(&str) -> T) -> T {
+        if let Some(template) = &self.template {
+            return with_templated_dir_name(template, i, depth, f);
+        }
+        match self.style {
+            NameStyle::Numeric | NameStyle::Realistic => with_dir_name(i, f),
+            NameStyle::Unicode => with_unicode_dir_name(i, f),
+        }
+    }
+}
+
+/// Options shared by every task of a generation run.
+#[derive(Debug)]
+pub struct TaskSettings {
+    pub root_dir: PathBuf,
+    /// Directories across which the top-level directories of the tree are
+    /// spread round-robin along with the root directory.
+    ///
+    /// Top-level directories assigned to a shard are created there and linked
+    /// into the root so the tree can still be addressed (and audited) as a
+    /// whole.
+    pub shards: Vec<PathBuf>,
+    /// Patterns matched against directory paths relative to the root
+    /// directory. Nothing is generated beneath a matching directory.
+    pub prune_globs: Vec<Pattern>,
+    /// Patterns matched like `prune_globs` whose permissions replace the
+    /// run-wide ones beneath matching directories.
+    pub permission_rules: Vec<(Pattern, PermissionTable)>,
+    /// Directories with more files than this are split into several tasks,
+    /// each creating a contiguous range of the files.
+    pub max_files_per_task: usize,
+    /// Buffers reused across tasks for staging file contents.
+    pub write_buffers: WriteBufferPool,
+    /// Files whose mixed seed falls below this are named like whiteouts.
+    pub whiteout_threshold: u64,
+    /// Create empty files with plain `open(O_CREAT)` instead of `mknod`. Set
+    /// automatically once the file system rejects `mknod`.
+    pub portable_create: AtomicBool,
+    pub name_style: NameStyle,
+    /// Files are named after this template instead of `name_style` if set.
+    pub file_name_template: Option<NameTemplate>,
+    pub dir_names: DirNames,
+    /// File names are padded to a length picked from this range if set.
+    pub name_length: Option<NameLengthRange>,
+    /// File names whose mixed seed falls below this are padded to exactly
+    /// [`NAME_MAX`] bytes instead.
+    pub max_name_threshold: u64,
+    /// Files are given one of these extensions if set.
+    pub extensions: Option<ExtensionTable>,
+    /// The MIME type of the contents files are filled with, if the content
+    /// mode determines one.
+    pub content_mime: Option<&'static str>,
+    /// Files are named `file<n>` if set, and odd files whose mixed seed falls
+    /// below it `File<n - 1>` instead.
+    pub case_collision_threshold: Option<u64>,
+    /// The number of files skipped because their name collided with a
+    /// sibling's on a case-insensitive file system.
+    pub case_collisions: AtomicU64,
+    /// Files whose mixed seed falls below this get a dangling symlink next to
+    /// them.
+    pub broken_symlink_threshold: u64,
+    /// The number of dangling symlinks created.
+    pub broken_symlinks: AtomicU64,
+    /// Files whose mixed seed falls below this are created as one of
+    /// `special_types` instead, unless they have duplicates.
+    pub special_threshold: u64,
+    pub special_types: Vec<EntryType>,
+    /// Files and directories whose mixed seed falls below this get an access
+    /// ACL.
+    pub acl_threshold: u64,
+    /// Directories are seeded from the hash of their path relative to the
+    /// root directory with this seed.
+    pub dir_seed: u64,
+    /// The permissions directories are given, picked by their seed.
+    pub dir_permissions: PermissionTable,
+    /// Files are given modification and access times picked from this range
+    /// if set.
+    pub mtime_range: Option<TimeRange>,
+    /// Every entry is stamped with this time once the tree is complete if
+    /// set. Only recorded in the audit trail while generating.
+    pub timestamp: Option<i64>,
+    /// Files and directories whose mixed seed falls below this get some
+    /// combination of the read-only, hidden, and system attributes.
+    pub attribute_threshold: u64,
+    /// Files whose mixed seed falls below this get alternate data streams.
+    pub stream_threshold: u64,
+    /// Every file is a hardlink to one of these inodes if set.
+    pub hardlinks: Option<HardlinkPool>,
+    /// Duplicates and the files they duplicate are hardlinked to whichever of
+    /// them was created first if set.
+    pub duplicate_links: Option<DuplicateLinks>,
+    /// How files sharing an inode are linked to the first of them: by
+    /// hardlink, or by symlink.
+    pub file_links: LinkFlavor,
+}
+
+impl TaskSettings {
+    /// Creates `file` with `create`, unless the tree is a hardlink farm and
+    /// the inode `spec` picks already exists (or duplicates are hardlinked and
+    /// a copy of `spec`'s contents already exists), in which case `file` is
+    /// linked to it. Returns the file's size and hash, and how it was linked,
+    /// if it was.
+    fn create_or_link(
+        &self,
+        file: &mut FastPathBuf,
+        spec: &FileSpec,
+        create: impl FnOnce(&mut FastPathBuf, &FileSpec) -> io::Result<(u64, Option<u64>)>,
+    ) -> io::Result<(u64, Option<u64>, Option<LinkFlavor>)> {
+        if let Some(entry_type) = self.special_type(spec) {
+            return create_special(file, entry_type, spec.permission).map(|()| (0, None, None));
+        }
+        if let Some(links) = &self.duplicate_links
+            && (spec.is_duplicate || spec.has_duplicates)
+        {
+            return self.link_or_create(&links.inode(spec.seed), file, spec, create);
+        }
+        let Some(pool) = &self.hardlinks else {
+            return create(file, spec).map(|(bytes, hash)| (bytes, hash, None));
+        };
+
+        let (inode, seed) = pool.pick(spec.seed);
+        // The inode must look the same regardless of which link created it
+        self.link_or_create(
+            inode,
+            file,
+            &FileSpec {
+                seed,
+                is_duplicate: false,
+                has_duplicates: false,
+                permission: None,
+            },
+            create,
+        )
+    }
+
+    /// Links `file` to `inode` if it has been created, or creates it from
+    /// `spec` otherwise.
+    fn link_or_create(
+        &self,
+        inode: &Inode,
+        file: &mut FastPathBuf,
+        spec: &FileSpec,
+        create: impl FnOnce(&mut FastPathBuf, &FileSpec) -> io::Result<(u64, Option<u64>)>,
+    ) -> io::Result<(u64, Option<u64>, Option<LinkFlavor>)> {
+        // Hold the lock while creating the inode so no other file races to
+        // create it too
+        let mut inode = inode.lock().unwrap();
+        if let Some((target, bytes, hash)) = &*inode {
+            let flavor = self.link_file(target, file)?;
+            return Ok((*bytes, *hash, Some(flavor)));
+        }
+
+        let (bytes, hash) = create(file, spec)?;
+        *inode = Some((file.to_path_buf(), bytes, hash));
+        Ok((bytes, hash, None))
+    }
+
+    /// Links `file` to `target` with the flavor files are linked with,
+    /// returning the flavor it ended up with.
+    fn link_file(&self, target: &Path, file: &Path) -> io::Result<LinkFlavor> {
+        if self.file_links != LinkFlavor::Symlink {
+            return hard_link(target, file).map(|()| LinkFlavor::Hardlink);
+        }
+        // Relative so the link survives moving the tree, unless `..` would
+        // resolve through a shard rather than the root
+        let link_target = if self.shards.is_empty() {
+            relative_target(target, file)
+        } else {
+            std::path::absolute(target)?
+        };
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(&link_target, file)?;
+        #[cfg(windows)]
+        match std::os::windows::fs::symlink_file(&link_target, file) {
+            Err(e) if e.raw_os_error() == Some(ERROR_PRIVILEGE_NOT_HELD) => {
+                return hard_link(target, file).map(|()| LinkFlavor::Hardlink);
+            }
+            result => result?,
+        }
+        Ok(LinkFlavor::Symlink)
+    }
+
+    /// What kind of special file `spec` is created as, if any.
+    fn special_type(&self, spec: &FileSpec) -> Option<EntryType> {
+        if spec.is_duplicate
+            || spec.has_duplicates
+            || spec.seed.wrapping_mul(0x8EBC_6AF0_9C88_C6E3) >= self.special_threshold
+        {
+            return None;
+        }
+        self.special_types
+            .get((spec.seed >> 32) as usize % self.special_types.len().max(1))
+            .copied()
+    }
+
+    /// Records `file`, generated from `spec` and created with `bytes` and
+    /// `hash` (or linked), in `audit`.
+    fn audit_file(
+        &self,
+        audit: &AuditTrail,
+        file: &Path,
+        spec: &FileSpec,
+        (bytes, hash, linked): (u64, Option<u64>, Option<LinkFlavor>),
+        attributes: EntryAttributes,
+    ) {
+        if let Some(extensions) = &self.extensions {
+            extensions.record(spec.seed);
+        }
+        if let Some(entry_type) = self.special_type(spec) {
+            audit.add_special(file, entry_type, applied_mode(file), attributes.mtime);
+        } else if linked == Some(LinkFlavor::Symlink) {
+            audit.add_special(file, EntryType::Symlink, None, attributes.mtime);
+        } else {
+            audit.add_file(
+                file,
+                bytes,
+                hash,
+                spec.is_duplicate || linked.is_some(),
+                applied_mode(file),
+                attributes,
+            );
+        }
+    }
+
+    /// Applies the ACL, alternate data streams, times, and file attributes
+    /// `spec` calls for to `file`, returning them for the audit trail. Files linked to an existing inode are left as
+    /// they are.
+    fn apply_file_attributes(
+        &self,
+        file: &Path,
+        spec: &FileSpec,
+        linked: Option<LinkFlavor>,
+    ) -> io::Result<EntryAttributes> {
+        if linked.is_some() {
+            return Ok(EntryAttributes {
+                acl: None,
+                mtime: self.timestamp,
+                file_attributes: None,
+                streams: None,
+                mime: self.mime(file),
+            });
+        }
+        let acl = self.apply_file_acl(file, spec)?;
+        // Before stamping since writing a stream updates the file's times
+        let streams = self.write_file_streams(file, spec)?;
+        let mtime = self.stamp_file(file, spec)?.or(self.timestamp);
+        // Last since the read-only attribute stops anything else from
+        // changing the file
+        let file_attributes = if self.special_type(spec).is_some() {
+            None
+        } else {
+            apply_windows_attributes(spec.seed, file, self.attribute_threshold)?
+        };
+        Ok(EntryAttributes {
+            acl: acl.map(|acl| acl.to_string()),
+            mtime,
+            file_attributes,
+            streams,
+            mime: self.mime(file),
+        })
+    }
+
+    /// The MIME type of the contents written to `file`: the content mode's if
+    /// it has one, or the one its extension suggests otherwise.
+    fn mime(&self, file: &Path) -> Option<&'static str> {
+        self.content_mime
+            .or_else(|| file.extension().and_then(OsStr::to_str).and_then(mime_type))
+    }
+
+    /// Sets the modification time of `file` to the one `spec` picks from the
+    /// time range, and its access time to one between that and the end of
+    /// the range.
+    fn stamp_file(&self, file: &Path, spec: &FileSpec) -> io::Result<Option<i64>> {
+        let Some(range) = self.mtime_range else {
+            return Ok(None);
+        };
+        let hash = spec.seed.wrapping_mul(0x2127_599B_F432_5C37);
+        let mtime = range.pick(hash);
+        let atime = TimeRange {
+            start: mtime,
+            end: range.end,
+        }
+        .pick(hash.rotate_left(32));
+        set_times(file, mtime, atime)?;
+        Ok(Some(mtime))
+    }
+
+    /// Applies an access ACL to `file`, generated from `spec`, if it calls
+    /// for one. Inodes shared by several files and special files are left
+    /// alone.
+    fn apply_file_acl(&self, file: &Path, spec: &FileSpec) -> io::Result<Option<Acl>> {
+        if self.is_shared_or_special(spec) {
+            return Ok(None);
+        }
+        apply_acl(spec.seed, file, self.acl_threshold)
+    }
+
+    /// Writes alternate data streams to `file`, generated from `spec`, if it
+    /// calls for them, returning their names and sizes. Inodes shared by
+    /// several files and special files are left alone.
+    fn write_file_streams(&self, file: &Path, spec: &FileSpec) -> io::Result<Option<String>> {
+        let hash = spec.seed.wrapping_mul(0x1656_67B1_9E37_79F9);
+        if hash >= self.stream_threshold || self.is_shared_or_special(spec) {
+            return Ok(None);
+        }
+        write_streams(file, hash).map(Some)
+    }
+
+    /// Whether the file generated from `spec` shares its inode with other
+    /// files or is a special file.
+    fn is_shared_or_special(&self, spec: &FileSpec) -> bool {
+        self.hardlinks.is_some()
+            || (self.duplicate_links.is_some() && (spec.is_duplicate || spec.has_duplicates))
+            || self.special_type(spec).is_some()
+    }
+
+    /// Applies the permissions and ACL the path of `dir` calls for to it,
+    /// returning them for the audit trail.
+    fn apply_dir_attributes(&self, dir: &Path) -> io::Result<EntryAttributes> {
+        let mut attributes = EntryAttributes {
+            acl: None,
+            mtime: self.timestamp,
+            file_attributes: None,
+            streams: None,
+            mime: None,
+        };
+        if self.acl_threshold == 0
+            && self.dir_permissions.is_empty()
+            && self.attribute_threshold == 0
+        {
+            return Ok(attributes);
+        }
+
+        let relative = dir.strip_prefix(&self.root_dir).unwrap_or(dir);
+        let hash = XxHash64::oneshot(self.dir_seed, relative.as_os_str().as_encoded_bytes());
+        // Mix the hash so the mode is independent of the ACL
+        #[cfg(unix)]
+        if let Some(mode) = self
+            .dir_permissions
+            .pick(hash.wrapping_mul(0xF135_7AEA_2E62_A9C5))
+        {
+            use std::{fs::Permissions, os::unix::fs::PermissionsExt};
+
+            std::fs::set_permissions(dir, Permissions::from_mode(mode))?;
+        }
+        attributes.acl = apply_acl(hash, dir, self.acl_threshold)?.map(|acl| acl.to_string());
+        attributes.file_attributes = apply_windows_attributes(hash, dir, self.attribute_threshold)?;
+        Ok(attributes)
+    }
+
+    /// Pushes the name of file `i`, generated from `spec`, onto `file`.
+    fn push_file_name<'a>(
+        &self,
+        file: &'a mut FastPathBuf,
+        i: u64,
+        spec: &FileSpec,
+    ) -> PopGuard<'a> {
+        // Mix the seed differently from permissions so being a whiteout is
+        // independent of the mode
+        let whiteout = if spec.seed.wrapping_mul(0xD6E8_FEB8_6659_FD93) < self.whiteout_threshold {
+            ".wh."
+        } else {
+            ""
+        };
+        // Mix the seed so the name is independent of the mode
+        let seed = spec.seed.wrapping_mul(0xFF51_AFD7_ED55_8CCD);
+        let extension = self.extensions.as_ref().map(|table| table.pick(spec.seed));
+        let mut name = NameParts {
+            whiteout,
+            extension,
+            padding: self
+                .name_length(spec)
+                .map(|len| (len.saturating_sub(whiteout.len()), seed)),
+        };
+        if let Some(template) = &self.file_name_template {
+            if template.has_extension() {
+                name.extension = None;
+            }
+            let depth = file
+                .strip_prefix(&self.root_dir)
+                .map_or(0, |dir| dir.components().count())
+                + 1;
+            return with_templated_file_name(template, i, depth, seed, extension, |s| {
+                name.push(file, s)
+            });
+        }
+        match self.name_style {
+            NameStyle::Numeric => {}
+            NameStyle::Realistic => {
+                return with_realistic_file_name(i, seed, |s| name.push(file, s));
+            }
+            NameStyle::Unicode => {
+                return with_unicode_file_name(i, seed, |s| name.push(file, s));
+            }
+        }
+        match self.case_collision_threshold {
+            None => with_file_name(i, |s| name.push(file, s)),
+            Some(_) if self.is_case_twin(i, spec) => {
+                with_file_name(i - 1, |s| file.push(&format!("{whiteout}File{s}")))
+            }
+            Some(_) => with_file_name(i, |s| file.push(&format!("{whiteout}file{s}"))),
+        }
+    }
+
+    /// The length the name of the file generated from `spec` is padded to, if
+    /// any.
+    fn name_length(&self, spec: &FileSpec) -> Option<usize> {
+        let hash = spec.seed.wrapping_mul(0x27D4_EB2F_1656_67C5);
+        if hash < self.max_name_threshold {
+            return Some(NAME_MAX);
+        }
+        self.name_length
+            .map(|range| range.pick(hash.rotate_left(32)))
+    }
+
+    /// Whether file `i` is named to collide with its predecessor on
+    /// case-insensitive file systems.
+    fn is_case_twin(&self, i: u64, spec: &FileSpec) -> bool {
+        self.case_collision_threshold.is_some_and(|threshold| {
+            i % 2 == 1 && spec.seed.wrapping_mul(0x94D0_49BB_1331_11EB) < threshold
+        })
+    }
+
+    /// Whether file `i` at `file` should be skipped because the file system
+    /// already considers its name taken by the sibling it was meant to collide
+    /// with.
+    fn is_case_collision(&self, file: &Path, i: u64, spec: &FileSpec) -> bool {
+        let collision = self.is_case_twin(i, spec) && symlink_metadata(file).is_ok();
+        if collision {
+            self.case_collisions.fetch_add(1, Ordering::Relaxed);
+        }
+        collision
+    }
+
+    /// Creates a dangling symlink `<file>.link` pointing at `<file>.missing`
+    /// if `spec` calls for one.
+    fn link_broken(&self, file: &Path, spec: &FileSpec) -> io::Result<()> {
+        if spec.seed.wrapping_mul(0xA076_1D64_78BD_642F) >= self.broken_symlink_threshold {
+            return Ok(());
+        }
+        let Some(name) = file.file_name() else {
+            return Ok(());
+        };
+        if name.len() + ".link".len() > NAME_MAX {
+            return Ok(());
+        }
+
+        let mut link = file.as_os_str().to_owned();
+        link.push(".link");
+        // Relative so the link keeps dangling wherever the tree is moved
+        let mut target = name.to_owned();
+        target.push(".missing");
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(&target, &link)?;
+        #[cfg(windows)]
+        match std::os::windows::fs::symlink_file(&target, &link) {
+            // There's nothing to fall back to for files
+            Err(e) if e.raw_os_error() == Some(ERROR_PRIVILEGE_NOT_HELD) => return Ok(()),
+            result => result?,
+        }
+        self.broken_symlinks.fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Makes sure the top-level directory containing `dir` has been linked in
+    /// from its shard if it lives on one.
+    fn link_sharded_dir(&self, dir: &Path) -> io::Result<()> {
+        let Some(Component::Normal(name)) = dir
+            .strip_prefix(&self.root_dir)
+            .ok()
+            .and_then(|relative| relative.components().next())
+        else {
+            return Ok(());
+        };
+        let Some(index) = name
+            .to_str()
+            .and_then(|name| name.strip_suffix(".dir"))
+            // Unicode names lead with their codepoints
+            .map(|name| name.trim_start_matches(|c: char| !c.is_ascii_digit()))
+            .and_then(|index| index.parse::<usize>().ok())
+        else {
+            return Ok(());
+        };
+        let Some(shard) = (index % (self.shards.len() + 1))
+            .checked_sub(1)
+            .map(|shard| &self.shards[shard])
+        else {
+            return Ok(());
+        };
+
+        let link = self.root_dir.join(name);
+        if symlink_metadata(&link).is_ok() {
+            return Ok(());
+        }
+
+        let target = shard.join(name);
+        create_dir_all(&target)?;
+        #[cfg(unix)]
+        let result = std::os::unix::fs::symlink(&target, &link);
+        #[cfg(windows)]
+        let result = std::os::windows::fs::symlink_dir(&target, &link);
+        match result {
+            // Another task got there first
+            Err(e) if e.kind() == AlreadyExists => Ok(()),
+            r => r,
+        }
+    }
+
+    /// The ancestors of `dir` relative to the root directory, deepest first.
+    fn relative_ancestors<'a>(&self, dir: &'a Path) -> impl Iterator<Item = &'a Path> {
+        dir.strip_prefix(&self.root_dir)
+            .into_iter()
+            .flat_map(Path::ancestors)
+            .filter(|ancestor| !ancestor.as_os_str().is_empty())
+    }
+
+    fn is_pruned(&self, dir: &Path) -> bool {
+        if self.prune_globs.is_empty() {
+            return false;
+        }
+        self.relative_ancestors(dir).any(|ancestor| {
+            self.prune_globs
+                .iter()
+                .any(|glob| glob.matches_path_with(ancestor, GLOB_OPTIONS))
+        })
+    }
+
+    /// The permissions of the files in `dir`: those of the rule matching its
+    /// deepest ancestor if any, `default` otherwise.
+    pub fn permissions_for<'a>(
+        &'a self,
+        dir: &Path,
+        default: &'a PermissionTable,
+    ) -> &'a PermissionTable {
+        if self.permission_rules.is_empty() {
+            return default;
+        }
+        self.relative_ancestors(dir)
+            .find_map(|ancestor| {
+                self.permission_rules
+                    .iter()
+                    .find(|(glob, _)| glob.matches_path_with(ancestor, GLOB_OPTIONS))
+            })
+            .map_or(default, |(_, permissions)| permissions)
+    }
+}
+
+/// The path leading from the directory containing `link` to `target`, both
+/// relative to the same directory.
+fn relative_target(target: &Path, link: &Path) -> PathBuf {
+    let dir = link.parent().unwrap_or(link);
+    let common = target
+        .components()
+        .zip(dir.components())
+        .take_while(|(a, b)| a == b)
+        .count();
+    dir.components()
+        .skip(common)
+        .map(|_| Component::ParentDir)
+        .chain(target.components().skip(common))
+        .collect()
+}
+
+fn create_dir(dir: &Path, settings: &TaskSettings) -> io::Result<()> {
+    if !settings.shards.is_empty() {
+        settings.link_sharded_dir(dir)?;
+    }
+    create_dir_all(dir)
+}
+
+/// Applies an ACL derived from `hash` to `path` if its mixed hash falls below
+/// `threshold`.
+fn apply_acl(hash: u64, path: &Path, threshold: u64) -> io::Result<Option<Acl>> {
+    if hash.wrapping_mul(0x5851_F42D_4C95_7F2D) >= threshold {
+        return Ok(None);
+    }
+    // The ACL's owner, group, and other entries replace the mode bits
+    let acl = Acl::new(hash, applied_mode(path).unwrap_or(0o644));
+    acl.apply(path)?;
+    Ok(Some(acl))
+}
+
+/// What goes around a file's base name.
+struct NameParts<'a> {
+    /// Prefixed to the name.
+    whiteout: &'a str,
+    /// Replaces the name's extension, or is appended if it has none.
+    extension: Option<&'a str>,
+    /// The length the name is padded to and the seed of the letters it's
+    /// padded with.
+    padding: Option<(usize, u64)>,
+}
+
+impl NameParts<'_> {
+    /// Pushes `name` with its parts onto `file`.
+    fn push<'a>(&self, file: &'a mut FastPathBuf, name: &str) -> PopGuard<'a> {
+        let &Self {
+            whiteout,
+            extension,
+            padding,
+        } = self;
+        if whiteout.is_empty() && extension.is_none() && padding.is_none() {
+            return file.push(name);
+        }
+
+        let mut name = Cow::Borrowed(name);
+        if let Some(extension) = extension {
+            let stem = name
+                .rfind('.')
+                .filter(|&at| at > 0)
+                .map_or(&*name, |at| &name[..at]);
+            name = Cow::Owned(format!("{stem}.{extension}"));
+        }
+        if let Some((len, seed)) = padding {
+            name = Cow::Owned(pad_name(&name, len, seed));
+        }
+        file.push(&format!("{whiteout}{name}"))
+    }
+}
+
+/// Sets the read-only, hidden, or system attributes on `path` if its mixed
+/// `hash` falls below `threshold`, returning the ones set.
+fn apply_windows_attributes(hash: u64, path: &Path, threshold: u64) -> io::Result<Option<u32>> {
+    let hash = hash.wrapping_mul(0xC2B2_AE3D_27D4_EB4F);
+    if hash >= threshold {
+        return Ok(None);
+    }
+    // Any nonempty combination of the three attributes
+    let attributes = (hash >> 61) as u32 % 7 + 1;
+    set_file_attributes(path, attributes)?;
+    Ok(Some(attributes))
+}
+
+/// The mode `path` actually ended up with, as opposed to the one that was
+/// asked for, so audits can be trusted.
+fn applied_mode(path: &Path) -> Option<u32> {
+    cfg_if! {
+        if #[cfg(unix)] {
+            use std::os::unix::fs::PermissionsExt;
+
+            metadata(path)
+                .ok()
+                .map(|metadata| metadata.permissions().mode() & 0o7777)
+        } else {
+            // Nothing but the read-only flag is applied
+            let _ = path;
+            None
+        }
+    }
+}
+
 pub struct GeneratorTaskOutcome {
     pub files_generated: u64,
     pub dirs_generated: usize,
     pub bytes_generated: u64,
+    pub duplicates_generated: u64,
 
     pub pool_return_file: FastPathBuf,
     pub pool_return_byte_counts: Option<Vec<u64>>,
@@ -36,49 +752,82 @@ pub fn create_files_and_dirs(
         mut target_dir,
         file_objs,
         num_dirs,
+        dir_offset,
         file_offset,
         mut file_contents,
         audit_trail,
+        settings,
         task_index: _,
     }: GeneratorTaskParams<impl FileContentsGenerator>,
 ) -> Result<GeneratorTaskOutcome, io::Error> {
+    if settings.is_pruned(&target_dir) {
+        return Ok(GeneratorTaskOutcome {
+            files_generated: 0,
+            dirs_generated: 0,
+            bytes_generated: 0,
+            duplicates_generated: 0,
+
+            pool_return_file: target_dir,
+            pool_return_byte_counts: file_contents.byte_counts_pool_return(),
+        });
+    }
+
     let num_files = file_objs.len() as u64;
-    create_dirs(num_dirs, &mut target_dir, audit_trail.as_deref())?;
+    create_dirs(
+        num_dirs,
+        dir_offset,
+        &mut target_dir,
+        audit_trail.as_deref(),
+        &settings,
+    )?;
     create_files(
         &file_objs,
         file_offset,
         &mut target_dir,
         &mut file_contents,
         audit_trail.as_deref(),
+        &settings,
     )
-    .map(|bytes_written| GeneratorTaskOutcome {
-        files_generated: num_files,
+    .map(
+        |(bytes_written, skipped, duplicates)| GeneratorTaskOutcome {
+            files_generated: num_files - skipped,
 
-        dirs_generated: num_dirs,
-        bytes_generated: bytes_written,
+            dirs_generated: num_dirs,
+            bytes_generated: bytes_written,
+            duplicates_generated: duplicates,
 
-        pool_return_file: target_dir,
-        pool_return_byte_counts: file_contents.byte_counts_pool_return(),
-    })
+            pool_return_file: target_dir,
+            pool_return_byte_counts: file_contents.byte_counts_pool_return(),
+        },
+    )
 }
 
 #[cfg_attr(
     feature = "tracing",
-    tracing::instrument(level = "trace", skip(audit_trail))
+    tracing::instrument(level = "trace", skip(audit_trail, settings))
 )]
 fn create_dirs(
     num_dirs: usize,
+    offset: usize,
     dir: &mut FastPathBuf,
     audit_trail: Option<&AuditTrail>,
+    settings: &TaskSettings,
 ) -> Result<(), io::Error> {
-    for i in 0..num_dirs {
-        let dir = with_dir_name(i, |s| dir.push(s));
+    let depth = dir
+        .strip_prefix(&settings.root_dir)
+        .map_or(0, |dir| dir.components().count())
+        + 1;
+    for i in offset..offset + num_dirs {
+        let dir = settings.dir_names.with_dir_name(i, depth, |s| dir.push(s));
 
-        create_dir_all(&dir)
+        create_dir(&dir, settings)
             .attach_printable_lazy(|| format!("Failed to create directory {dir:?}"))?;
+        let attributes = settings
+            .apply_dir_attributes(&dir)
+            .attach_printable_lazy(|| format!("Failed to apply attributes to {dir:?}"))?;
 
         if let Some(audit) = audit_trail {
-            audit.add_directory(dir.to_path_buf(), None);
+            audit.add_directory(&dir, applied_mode(&dir), attributes);
         }
 
         dir.pop();
@@ -88,7 +837,7 @@ fn create_dirs(
 
 #[cfg_attr(
     feature = "tracing",
-    tracing::instrument(level = "trace", skip(contents, audit_trail))
+    tracing::instrument(level = "trace", skip(contents, audit_trail, settings))
 )]
 fn create_files(
     file_objs: &[FileSpec],
@@ -96,9 +845,14 @@ fn create_files(
     file: &mut FastPathBuf,
     contents: &mut impl FileContentsGenerator,
     audit_trail: Option<&AuditTrail>,
-) -> Result<u64, io::Error> {
-    let mut state = contents.initialize();
+    settings: &TaskSettings,
+) -> Result<(u64, u64, u64), io::Error> {
+    let mut state = contents.initialize(&settings.write_buffers);
     let mut bytes_written = 0;
+    let mut collisions = 0;
+    // Like symlink loops, symlinks to other files aren't counted as files
+    let mut symlinks = 0;
+    let mut duplicates = 0;
 
     let hash_seed = audit_trail.is_some().then_some(0); // Using 0 as default seed for xxhash
 
@@ -107,34 +861,64 @@ fn create_files(
     // We only try to create the parent dir for the first file if there are any
     // files.
     if let Some(first_spec) = file_objs.first() {
-        let mut guard = with_file_name(offset, |s| file.push(s));
-
-        match contents.create_file(&mut guard, 0, true, &mut state, hash_seed, first_spec) {
-            Ok((bytes, hash)) => {
-                bytes_written += bytes;
-                if let Some(audit) = audit_trail {
-                    audit.add_file(
-                        guard.to_path_buf(),
-                        bytes,
-                        hash,
-                        first_spec.is_duplicate,
-                        first_spec.permission,
-                    );
-                }
-                start_file += 1;
-                guard.pop();
-            }
-            Err(e) => {
-                if e.kind() == NotFound {
-                    #[cfg(feature = "tracing")]
-                    tracing::event!(tracing::Level::TRACE, file = ?guard, "Parent directory not created in time");
+        let mut guard = settings.push_file_name(file, offset, first_spec);
 
+        if settings.is_case_collision(&guard, offset, first_spec) {
+            collisions += 1;
+            start_file += 1;
+            guard.pop();
+        } else {
+            match settings.create_or_link(&mut guard, first_spec, |file, spec| {
+                contents.create_file(
+                    file,
+                    0,
+                    true,
+                    &mut state,
+                    hash_seed,
+                    spec,
+                    &settings.portable_create,
+                )
+            }) {
+                Ok((bytes, hash, linked)) => {
+                    if linked == Some(LinkFlavor::Symlink) {
+                        symlinks += 1;
+                    } else {
+                        bytes_written += bytes;
+                    }
+                    duplicates += u64::from(first_spec.is_duplicate);
+                    let attributes = settings
+                        .apply_file_attributes(&guard, first_spec, linked)
+                        .attach_printable_lazy(|| {
+                            format!("Failed to apply attributes to {guard:?}")
+                        })?;
+                    if let Some(audit) = audit_trail {
+                        settings.audit_file(
+                            audit,
+                            &guard,
+                            first_spec,
+                            (bytes, hash, linked),
+                            attributes,
+                        );
+                    }
+                    settings
+                        .link_broken(&guard, first_spec)
+                        .attach_printable_lazy(|| format!("Failed to link next to {guard:?}"))?;
+                    start_file += 1;
                     guard.pop();
-                    create_dir_all(&*file)
-                        .attach_printable_lazy(|| format!("Failed to create directory {file:?}"))?;
-                } else {
-                    return Err(Report::new(e))
-                        .attach_printable_lazy(|| format!("Failed to create file {file:?}"));
+                }
+                Err(e) => {
+                    if e.kind() == NotFound {
+                        #[cfg(feature = "tracing")]
+                        tracing::event!(tracing::Level::TRACE, file = ?guard, "Parent directory not created in time");
+
+                        guard.pop();
+                        create_dir(file, settings).attach_printable_lazy(|| {
+                            format!("Failed to create directory {file:?}")
+                        })?;
+                    } else {
+                        return Err(Report::new(e))
+                            .attach_printable_lazy(|| format!("Failed to create file {file:?}"));
+                    }
                 }
             }
         }
@@ -144,25 +928,48 @@ fn create_files(
         // The spec implies we just want to create 'a file' with specific content.
         // The original code used `i + offset` for naming.
         // We will continue to use monotonic naming for valid filenames.
-        let mut file = with_file_name((i as u64) + offset, |s| file.push(s));
+        let mut file = settings.push_file_name(file, (i as u64) + offset, spec);
+        if settings.is_case_collision(&file, (i as u64) + offset, spec) {
+            collisions += 1;
+            file.pop();
+            continue;
+        }
 
-        let (bytes, hash) = contents
-            .create_file(&mut file, i, false, &mut state, hash_seed, spec)
+        let (bytes, hash, linked) = settings
+            .create_or_link(&mut file, spec, |file, spec| {
+                contents.create_file(
+                    file,
+                    i,
+                    false,
+                    &mut state,
+                    hash_seed,
+                    spec,
+                    &settings.portable_create,
+                )
+            })
             .attach_printable_lazy(|| format!("Failed to create file {file:?}"))?;
 
-        bytes_written += bytes;
+        if linked == Some(LinkFlavor::Symlink) {
+            symlinks += 1;
+        } else {
+            bytes_written += bytes;
+        }
+        duplicates += u64::from(spec.is_duplicate);
+        let attributes = settings
+            .apply_file_attributes(&file, spec, linked)
+            .attach_printable_lazy(|| format!("Failed to apply attributes to {file:?}"))?;
         if let Some(audit) = audit_trail {
-            audit.add_file(
-                file.to_path_buf(),
-                bytes,
-                hash,
-                spec.is_duplicate,
-                spec.permission,
-            );
+            settings.audit_file(audit, &file, spec, (bytes, hash, linked), attributes);
         }
+        settings
+            .link_broken(&file, spec)
+            .attach_printable_lazy(|| format!("Failed to link next to {file:?}"))?;
 
         file.pop();
     }
+    contents
+        .finish(&mut state)
+        .attach_printable_lazy(|| format!("Failed to write files in {file:?}"))?;
 
-    Ok(bytes_written)
+    Ok((bytes_written, collisions + symlinks, duplicates))
 }
@@ -1,24 +1,289 @@
-use rand::Rng;
-use rand_distr::{Distribution, Normal};
-pub use scheduler::{GeneratorStats, run};
-pub use tasks::{DynamicGenerator, GeneratorBytes, StaticGenerator};
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+
+pub use acl::Acl;
+pub use distributions::{DistributionReport, FitTest, check_distributions, truncated_normal_cdf};
+#[cfg(unix)]
+pub use file_contents::umask;
+pub use file_contents::{
+    ContentMode, FileHeaders, FillPattern, Holes, WriteBufferPool, create_special,
+};
+pub use files::{DirNames, NameStyle, TaskSettings};
+pub use passes::{append_to_files, link_loops, modify_files, read_back_files, rehash, stamp_tree};
+use rand::{Rng, SeedableRng, distr::Uniform};
+use rand_distr::{Distribution, LogNormal, Normal, Pareto, Poisson, Zipf};
+use rand_xoshiro::Xoshiro256PlusPlus;
+pub use recount::recount;
+pub use scheduler::{
+    GeneratorStats, LevelSettings, SchedulerSettings, SchedulerStats, TraversalOrder, run,
+};
+pub use tasks::{DynamicGenerator, GeneratorBytes, LeftoverStrategy, StaticGenerator};
 
 #[derive(Debug, Clone, Copy)]
 pub struct FileSpec {
     pub seed: u64,
     pub is_duplicate: bool,
+    /// Whether duplicates of this file are generated elsewhere in the tree.
+    pub has_duplicates: bool,
     pub permission: Option<u32>,
 }
 
+/// The file permissions to pick from, each weighted by how often it should be
+/// used.
+#[derive(Debug, Clone, Default)]
+pub struct PermissionTable {
+    modes: Vec<u32>,
+    /// The running total of the weights up to and including each mode.
+    cumulative_weights: Vec<u64>,
+    /// Files whose mixed seed falls below this get their executable bits set.
+    executable_threshold: u64,
+    /// Bits stripped from every picked mode.
+    umask: u32,
+}
+
+/// The mode files are reported to have if none was configured.
+pub const DEFAULT_FILE_MODE: u32 = 0o644;
+
+impl PermissionTable {
+    /// Every mode is equally likely if `weights` is empty. Returns `None` if
+    /// there isn't a weight for every mode or all weights are zero.
+    #[must_use]
+    pub fn new(modes: Vec<u32>, weights: &[u32]) -> Option<Self> {
+        if modes.is_empty() {
+            return weights.is_empty().then(Self::default);
+        }
+
+        let cumulative_weights: Vec<u64> = if weights.is_empty() {
+            (1..=modes.len() as u64).collect()
+        } else if weights.len() == modes.len() {
+            weights
+                .iter()
+                .scan(0, |total, &weight| {
+                    *total += u64::from(weight);
+                    Some(*total)
+                })
+                .collect()
+        } else {
+            return None;
+        };
+        (cumulative_weights.last() != Some(&0)).then_some(Self {
+            modes,
+            cumulative_weights,
+            executable_threshold: 0,
+            umask: 0,
+        })
+    }
+
+    /// Sets the executable bits on roughly `percentage` percent of files,
+    /// starting from [`DEFAULT_FILE_MODE`] for files without a configured
+    /// mode.
+    #[must_use]
+    #[allow(
+        clippy::cast_sign_loss,
+        clippy::cast_possible_truncation,
+        clippy::cast_precision_loss
+    )]
+    pub fn with_executable_percentage(self, percentage: f64) -> Self {
+        Self {
+            executable_threshold: (percentage / 100. * u64::MAX as f64) as u64,
+            ..self
+        }
+    }
+
+    /// Strips `umask` from every picked mode the way creating a file
+    /// normally would.
+    #[must_use]
+    pub fn with_umask(self, umask: u32) -> Self {
+        Self { umask, ..self }
+    }
+
+    #[must_use]
+    pub fn is_empty(&self) -> bool {
+        self.modes.is_empty() && self.executable_threshold == 0
+    }
+
+    /// Deterministically picks the permission of the file generated from
+    /// `seed`.
+    #[must_use]
+    pub fn pick(&self, seed: u64) -> Option<u32> {
+        let mode = self.cumulative_weights.last().map(|&total| {
+            let target = seed % total;
+            let index = self
+                .cumulative_weights
+                .partition_point(|&weight| weight <= target);
+            self.modes[index]
+        });
+
+        // Mix the seed so being executable is independent of the mode
+        let mode = if seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) < self.executable_threshold {
+            Some(mode.unwrap_or(DEFAULT_FILE_MODE) | 0o111)
+        } else {
+            mode
+        };
+        mode.map(|mode| mode & !self.umask)
+    }
+}
+
+/// The file extensions to pick from, each weighted by how often it should be
+/// used, along with how many files were given each.
+#[derive(Debug)]
+pub struct ExtensionTable {
+    extensions: Vec<String>,
+    /// The running total of the weights up to and including each extension.
+    cumulative_weights: Vec<u64>,
+    counts: Vec<AtomicU64>,
+}
+
+impl ExtensionTable {
+    /// Returns `None` if there are no extensions or all weights are zero.
+    #[must_use]
+    pub fn new(extensions: Vec<(String, u32)>) -> Option<Self> {
+        let (extensions, cumulative_weights): (Vec<_>, Vec<_>) = extensions
+            .into_iter()
+            .scan(0, |total, (extension, weight)| {
+                *total += u64::from(weight);
+                Some((extension, *total))
+            })
+            .unzip();
+        cumulative_weights
+            .last()
+            .is_some_and(|&total| total > 0)
+            .then(|| Self {
+                counts: extensions.iter().map(|_| AtomicU64::new(0)).collect(),
+                extensions,
+                cumulative_weights,
+            })
+    }
+
+    /// The index of the extension the file generated from `seed` is given.
+    fn index(&self, seed: u64) -> usize {
+        // Mix the seed so the extension is independent of the mode
+        let target =
+            seed.wrapping_mul(0x4F1B_BCDC_BFA5_3E0B) % self.cumulative_weights.last().unwrap();
+        self.cumulative_weights
+            .partition_point(|&weight| weight <= target)
+    }
+
+    /// Deterministically picks the extension of the file generated from
+    /// `seed`.
+    #[must_use]
+    pub fn pick(&self, seed: u64) -> &str {
+        &self.extensions[self.index(seed)]
+    }
+
+    /// Counts the file generated from `seed` towards its extension.
+    pub fn record(&self, seed: u64) {
+        self.counts[self.index(seed)].fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// How many files were given each extension, as `EXT=COUNT` pairs
+    /// separated by commas.
+    #[must_use]
+    pub fn summary(&self) -> String {
+        self.extensions
+            .iter()
+            .zip(&self.counts)
+            .map(|(extension, count)| format!("{extension}={}", count.load(Ordering::Relaxed)))
+            .collect::<Vec<_>>()
+            .join(",")
+    }
+}
+
+/// How entries are linked to a file or directory created before them.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum LinkFlavor {
+    /// Hardlinks to the same inode, which only files can have.
+    #[default]
+    Hardlink,
+    /// Symbolic links. On Windows, files are hardlinked and directories
+    /// joined by junctions instead if symlinks require a privilege the
+    /// process doesn't hold.
+    Symlink,
+    /// NTFS junctions, which only link directories. Elsewhere than on
+    /// Windows, symlinks are created in their place.
+    Junction,
+}
+
+/// The path, size, and hash of an inode once its first link has been
+/// created.
+type Inode = Mutex<Option<(PathBuf, u64, Option<u64>)>>;
+
+/// A small pool of inodes that every file is hardlinked to, each weighted by
+/// how many links it should get.
+#[derive(Debug)]
+pub struct HardlinkPool {
+    /// The running total of the link weights up to and including each inode.
+    cumulative_links: Vec<u64>,
+    /// The seed each inode's contents are generated from.
+    seeds: Vec<u64>,
+    inodes: Vec<Inode>,
+}
+
+impl HardlinkPool {
+    /// Draws the relative link count of each of `num_inodes` inodes from
+    /// `links_per_inode`. Returns `None` if every inode drew zero links.
+    #[must_use]
+    pub fn new(links_per_inode: &FanoutDistr, num_inodes: usize, seed: u64) -> Option<Self> {
+        let mut rng = Xoshiro256PlusPlus::seed_from_u64(seed);
+        let (cumulative_links, seeds) = (0..num_inodes)
+            .scan(0, |total, _| {
+                *total += links_per_inode.sample(&mut rng);
+                Some((*total, rng.random::<u64>()))
+            })
+            .unzip::<_, _, Vec<_>, Vec<_>>();
+        (cumulative_links.last().is_some_and(|&total| total > 0)).then(|| Self {
+            cumulative_links,
+            seeds,
+            inodes: (0..num_inodes).map(|_| Mutex::default()).collect(),
+        })
+    }
+
+    /// Deterministically picks the inode the file generated from `seed` links
+    /// to, along with the seed that inode's contents are generated from.
+    fn pick(&self, seed: u64) -> (&Inode, u64) {
+        // Mix the seed so the inode is independent of the file's permission
+        let target =
+            seed.wrapping_mul(0xBF58_476D_1CE4_E5B9) % self.cumulative_links.last().unwrap();
+        let index = self
+            .cumulative_links
+            .partition_point(|&links| links <= target);
+        (&self.inodes[index], self.seeds[index])
+    }
+}
+
+/// The inodes of files with duplicates, keyed by the seed their contents are
+/// generated from, such that duplicates can be hardlinked to whichever copy
+/// was created first instead of being written again.
+#[derive(Debug, Default)]
+pub struct DuplicateLinks {
+    inodes: Mutex<HashMap<u64, Arc<Inode>>>,
+}
+
+impl DuplicateLinks {
+    fn inode(&self, seed: u64) -> Arc<Inode> {
+        self.inodes.lock().unwrap().entry(seed).or_default().clone()
+    }
+}
+
 #[derive(Debug, Clone, Copy)]
 pub struct PendingDuplicate {
     pub spec: FileSpec,
     pub size: Option<u64>,
 }
 
+mod acl;
 pub mod audit;
+mod distributions;
 mod file_contents;
 mod files;
+mod passes;
+mod recount;
 mod scheduler;
 mod tasks;
 
@@ -28,6 +293,133 @@ pub fn truncatable_normal(mean: f64) -> Normal<f64> {
     Normal::new(mean, mean / 3.).unwrap()
 }
 
+/// The distribution the number of subdirectories in each directory is drawn
+/// from.
+#[derive(Debug, Clone)]
+pub enum FanoutDistr {
+    Normal(Normal<f64>),
+    Uniform(Uniform<u64>),
+    Poisson(Poisson<f64>),
+}
+
+impl FanoutDistr {
+    #[allow(clippy::cast_sign_loss, clippy::cast_possible_truncation)]
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> u64 {
+        match self {
+            Self::Normal(normal) => sample_truncated(normal, rng),
+            Self::Uniform(uniform) => uniform.sample(rng),
+            Self::Poisson(poisson) => poisson.sample(rng) as u64,
+        }
+    }
+}
+
+/// The distribution the number of files in each directory is drawn from.
+///
+/// The scheduler rescales it to the number of files each level of the tree
+/// should hold per directory.
+#[derive(Debug, Clone, Copy)]
+pub enum FilesPerDirDistr {
+    Normal(Normal<f64>),
+    /// Counts are multiples of `unit` with the multiple drawn from `distr`.
+    Zipf {
+        distr: Zipf<f64>,
+        unit: f64,
+        mean: f64,
+    },
+}
+
+impl FilesPerDirDistr {
+    /// The same distribution rescaled to average `mean` files.
+    fn with_mean(&self, mean: f64) -> Self {
+        match *self {
+            Self::Normal(_) => Self::Normal(truncatable_normal(mean)),
+            Self::Zipf {
+                distr,
+                unit,
+                mean: old_mean,
+            } => Self::Zipf {
+                distr,
+                unit: unit * mean / old_mean,
+                mean,
+            },
+        }
+    }
+
+    #[allow(clippy::cast_sign_loss, clippy::cast_possible_truncation)]
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> u64 {
+        match *self {
+            Self::Normal(ref normal) => sample_truncated(normal, rng),
+            Self::Zipf {
+                ref distr, unit, ..
+            } => weighted(distr.sample(rng) as u64, unit, rng),
+        }
+    }
+
+    #[must_use]
+    pub fn mean(&self) -> f64 {
+        match *self {
+            Self::Normal(ref normal) => normal.mean(),
+            Self::Zipf { mean, .. } => mean,
+        }
+    }
+}
+
+/// Heavy-tailed file sizes are capped at this multiple of their mean.
+const MAX_SIZE_FACTOR: f64 = 1000.;
+
+/// The distribution file sizes are drawn from.
+#[derive(Debug, Clone, Copy)]
+pub enum SizeDistr {
+    Normal(Normal<f64>),
+    LogNormal {
+        distr: LogNormal<f64>,
+        mean: f64,
+    },
+    Pareto {
+        distr: Pareto<f64>,
+        mean: f64,
+    },
+    /// Sizes are multiples of `unit` with the multiple drawn from `distr`.
+    Zipf {
+        distr: Zipf<f64>,
+        unit: f64,
+        mean: f64,
+    },
+    Uniform {
+        distr: Uniform<u64>,
+        mean: f64,
+    },
+}
+
+impl SizeDistr {
+    #[allow(clippy::cast_sign_loss, clippy::cast_possible_truncation)]
+    fn sample<R: Rng + ?Sized>(&self, rng: &mut R) -> u64 {
+        let (size, mean) = match *self {
+            Self::Normal(ref normal) => return sample_truncated(normal, rng),
+            Self::Uniform { ref distr, .. } => return distr.sample(rng),
+            Self::LogNormal { ref distr, mean } => (distr.sample(rng), mean),
+            Self::Pareto { ref distr, mean } => (distr.sample(rng), mean),
+            Self::Zipf {
+                ref distr,
+                unit,
+                mean,
+            } => (distr.sample(rng) * unit, mean),
+        };
+        size.min(mean * MAX_SIZE_FACTOR) as u64
+    }
+
+    #[must_use]
+    pub fn mean(&self) -> f64 {
+        match *self {
+            Self::Normal(ref normal) => normal.mean(),
+            Self::LogNormal { mean, .. }
+            | Self::Pareto { mean, .. }
+            | Self::Zipf { mean, .. }
+            | Self::Uniform { mean, .. } => mean,
+        }
+    }
+}
+
 // TODO https://github.com/rust-random/rand/issues/1189
 #[allow(clippy::cast_sign_loss, clippy::cast_possible_truncation)]
 #[cfg_attr(feature = "tracing", tracing::instrument(level = "trace", skip(rng)))]
@@ -41,3 +433,16 @@ fn sample_truncated<R: Rng + ?Sized>(normal: &Normal<f64>, rng: &mut R) -> u64 {
     }
     normal.mean() as u64
 }
+
+/// Scales a sampled count by a directory's weight among its siblings, rounding
+/// randomly such that many lightly weighted siblings don't all round to zero.
+#[allow(
+    clippy::cast_sign_loss,
+    clippy::cast_possible_truncation,
+    clippy::cast_precision_loss
+)]
+fn weighted<R: Rng + ?Sized>(count: u64, weight: f64, rng: &mut R) -> u64 {
+    let scaled = count as f64 * weight;
+    let fraction = scaled.fract();
+    scaled as u64 + u64::from(fraction > 0. && rng.random::<f64>() < fraction)
+}
@@ -0,0 +1,414 @@
+//! Passes over an already generated tree that change some of its files,
+//! simulating what happens to a tree after it was first written, or check
+//! that they still hold what was written.
+
+use std::{
+    collections::HashMap,
+    fs::{self, File, OpenOptions},
+    io::{self, Write},
+    num::NonZeroUsize,
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, SystemTime},
+};
+
+use error_stack::{Report, Result, ResultExt};
+use rand::SeedableRng;
+use rand_xoshiro::Xoshiro256PlusPlus;
+use twox_hash::XxHash64;
+
+#[cfg(windows)]
+use crate::core::file_contents::{ERROR_PRIVILEGE_NOT_HELD, create_junction};
+use crate::core::{
+    audit::{AuditTrail, EntryType, HashingWriter},
+    file_contents::{FillPattern, fill_random_block, set_times},
+};
+
+/// Reopens the files beneath `root_dir` whose mixed path hash falls below
+/// `threshold` and appends `num_bytes` bytes to each, simulating files that
+/// grow after they were first written.
+///
+/// Which files grow and what they grow by is derived from `seed` and each
+/// file's path relative to `root_dir`, so reruns append the same data.
+/// Symbolic links directly inside the root are followed when
+/// `follow_root_links` is set. Returns the number of bytes appended.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "trace", skip(audit_trail))
+)]
+pub fn append_to_files(
+    root_dir: &Path,
+    num_bytes: u64,
+    threshold: u64,
+    seed: u64,
+    fill_pattern: Option<&FillPattern>,
+    follow_root_links: bool,
+    audit_trail: Option<&AuditTrail>,
+) -> Result<u64, io::Error> {
+    let mut appended = 0;
+    let mut updates = HashMap::new();
+
+    // Mix the hash so the files that grow don't cluster in hash order
+    for (path, path_hash) in select_files(root_dir, seed, follow_root_links, |hash| {
+        hash.wrapping_mul(0xFF51_AFD7_ED55_8CCD) < threshold
+    })? {
+        let mut random = Xoshiro256PlusPlus::seed_from_u64(path_hash);
+        OpenOptions::new()
+            .append(true)
+            .open(&path)
+            .and_then(|mut file| {
+                // Patterns carry on where the existing contents left off
+                let offset = file.metadata()?.len();
+                write_contents(&mut file, num_bytes, offset, fill_pattern, &mut random)
+            })
+            .attach_printable_lazy(|| format!("Failed to append to {path:?}"))?;
+        appended += num_bytes;
+        if audit_trail.is_some() {
+            let update =
+                rehash(&path).attach_printable_lazy(|| format!("Failed to hash {path:?}"))?;
+            updates.insert(path, update);
+        }
+    }
+
+    if let Some(trail) = audit_trail {
+        trail.update_files(&updates);
+    }
+    Ok(appended)
+}
+
+/// Rewrites the files beneath `root_dir` whose mixed path hash falls below
+/// `threshold` with new random contents of the same size and pushes their
+/// modification time forward, producing a known set of changes.
+///
+/// Files are picked like [`append_to_files`] picks them, but independently
+/// of it. Returns the modified files in sorted order.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "trace", skip(audit_trail))
+)]
+pub fn modify_files(
+    root_dir: &Path,
+    threshold: u64,
+    seed: u64,
+    follow_root_links: bool,
+    audit_trail: Option<&AuditTrail>,
+) -> Result<Vec<PathBuf>, io::Error> {
+    let mut modified = Vec::new();
+    let mut updates = HashMap::new();
+
+    for (path, path_hash) in select_files(root_dir, seed, follow_root_links, |hash| {
+        hash.wrapping_mul(0xC4CE_B9FE_1A85_EC53) < threshold
+    })? {
+        // The new contents must differ from those generated from the same
+        // path hash by the append pass
+        let mut random = Xoshiro256PlusPlus::seed_from_u64(!path_hash);
+        rewrite(&path, &mut random)
+            .attach_printable_lazy(|| format!("Failed to modify {path:?}"))?;
+        if audit_trail.is_some() {
+            let update =
+                rehash(&path).attach_printable_lazy(|| format!("Failed to hash {path:?}"))?;
+            updates.insert(path.clone(), update);
+        }
+        modified.push(path);
+    }
+
+    if let Some(trail) = audit_trail {
+        trail.update_files(&updates);
+    }
+    modified.sort_unstable();
+    Ok(modified)
+}
+
+/// Links `count` of the directories beneath `root_dir` back to themselves or
+/// one of their ancestors with a `loop` symlink, creating cycles.
+///
+/// The directories with the lowest mixed hash of their path relative to the
+/// root, seeded with `seed`, are linked. Links stay within the top-level
+/// directory they're in so they loop even if it lives on a shard. Symbolic
+/// links directly inside the root are followed when `follow_root_links` is
+/// set. On Windows, junctions are created instead if `junctions` is set, or
+/// stand in for the symlinks if creating symlinks requires a privilege the
+/// process doesn't hold.
+///
+/// Returns the links created along with what they were created as, which are
+/// fewer than `count` if the tree has fewer directories.
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "trace"))]
+pub fn link_loops(
+    root_dir: &Path,
+    count: usize,
+    seed: u64,
+    follow_root_links: bool,
+    #[cfg_attr(not(windows), allow(unused_variables))] junctions: bool,
+) -> Result<Vec<(PathBuf, EntryType)>, io::Error> {
+    let mut dirs = select_dirs(root_dir, seed, follow_root_links)?;
+    dirs.sort_unstable_by_key(|&(_, path_hash)| path_hash.wrapping_mul(0xE703_7ED1_A0B4_28DB));
+    dirs.truncate(count);
+
+    let mut links = Vec::with_capacity(dirs.len());
+    for (dir, path_hash) in &dirs {
+        let depth = dir
+            .strip_prefix(root_dir)
+            .map_or(1, |relative| relative.components().count());
+        let target = match path_hash % depth as u64 {
+            0 => PathBuf::from("."),
+            up => (0..up).map(|_| "..").collect(),
+        };
+        let link = dir.join("loop");
+        #[cfg(unix)]
+        let result = std::os::unix::fs::symlink(&target, &link).map(|()| EntryType::Symlink);
+        #[cfg(windows)]
+        let result = {
+            // Junctions only take absolute targets
+            let junction = || {
+                dir.join(&target)
+                    .canonicalize()
+                    .and_then(|target| create_junction(&target, &link))
+                    .map(|()| EntryType::Junction)
+            };
+            match (!junctions).then(|| std::os::windows::fs::symlink_dir(&target, &link)) {
+                None => junction(),
+                Some(Err(e)) if e.raw_os_error() == Some(ERROR_PRIVILEGE_NOT_HELD) => junction(),
+                Some(result) => result.map(|()| EntryType::Symlink),
+            }
+        };
+        let entry_type =
+            result.attach_printable_lazy(|| format!("Failed to link {link:?} to {target:?}"))?;
+        links.push((link, entry_type));
+    }
+    Ok(links)
+}
+
+/// Sets the modification and access times of `root_dir` and everything
+/// beneath it, symlinks included, to `timestamp` seconds since the Unix
+/// epoch.
+///
+/// Symbolic links directly inside the root are followed, on top of being
+/// stamped themselves, when `follow_root_links` is set. Returns the number of
+/// entries stamped.
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "trace"))]
+pub fn stamp_tree(
+    root_dir: &Path,
+    timestamp: i64,
+    follow_root_links: bool,
+) -> Result<u64, io::Error> {
+    let stamp = |path: &Path| {
+        set_times(path, timestamp, timestamp)
+            .attach_printable_lazy(|| format!("Failed to stamp {path:?}"))
+    };
+
+    let mut stamped = 0;
+    let mut queue = vec![(root_dir.to_path_buf(), follow_root_links)];
+    while let Some((dir, follow_links)) = queue.pop() {
+        for entry in fs::read_dir(&dir)
+            .attach_printable_lazy(|| format!("Failed to read directory {dir:?}"))?
+        {
+            let entry =
+                entry.attach_printable_lazy(|| format!("Failed to read directory {dir:?}"))?;
+            let path = entry.path();
+            let file_type = entry
+                .file_type()
+                .attach_printable_lazy(|| format!("Failed to stat {path:?}"))?;
+            // Stamping an entry doesn't touch its parent, so order is irrelevant
+            stamp(&path)?;
+            stamped += 1;
+
+            if file_type.is_dir() {
+                queue.push((path, false));
+            } else if follow_links && file_type.is_symlink() && path.is_dir() {
+                stamp(
+                    &fs::canonicalize(&path)
+                        .attach_printable_lazy(|| format!("Failed to resolve {path:?}"))?,
+                )?;
+                queue.push((path, false));
+            }
+        }
+    }
+    stamp(root_dir)?;
+    Ok(stamped + 1)
+}
+
+/// Reads back the files recorded in `audit_trail` whose mixed path hash falls
+/// below `threshold`, spreading them across `parallelism` threads, and
+/// compares their sizes and hashes against those recorded while writing them.
+///
+/// Returns the number of files read back and the ones that no longer match.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "trace", skip(audit_trail))
+)]
+pub fn read_back_files(
+    audit_trail: &AuditTrail,
+    threshold: u64,
+    seed: u64,
+    parallelism: NonZeroUsize,
+) -> Result<(u64, Vec<PathBuf>), io::Error> {
+    let files = audit_trail
+        .files()
+        .into_iter()
+        .filter(|(path, ..)| {
+            let path_hash = XxHash64::oneshot(seed, path.as_os_str().as_encoded_bytes());
+            path_hash.wrapping_mul(0x9FB2_1C65_1E98_DF25) < threshold
+        })
+        .collect::<Vec<_>>();
+    if files.is_empty() {
+        return Ok((0, Vec::new()));
+    }
+
+    let chunk_size = files.len().div_ceil(parallelism.get());
+    let mut mismatches = thread::scope(|scope| {
+        files
+            .chunks(chunk_size)
+            .map(|chunk| {
+                scope.spawn(move || {
+                    let mut mismatches = Vec::new();
+                    for (path, size, hash) in chunk {
+                        let (actual_size, actual_hash) = rehash(path)
+                            .attach_printable_lazy(|| format!("Failed to read back {path:?}"))?;
+                        // Empty files aren't hashed while writing them
+                        if actual_size != *size
+                            || hash.is_some_and(|hash| Some(hash) != actual_hash)
+                        {
+                            mismatches.push(path.clone());
+                        }
+                    }
+                    Ok(mismatches)
+                })
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
+            .map(|worker| {
+                worker
+                    .join()
+                    .unwrap_or_else(|_| Err(Report::new(io::Error::other("Worker panicked"))))
+            })
+            .collect::<Result<Vec<Vec<_>>, io::Error>>()
+    })?
+    .concat();
+
+    mismatches.sort_unstable();
+    Ok((files.len() as u64, mismatches))
+}
+
+/// Walks the tree rooted at `root_dir`, returning each file whose hash of its
+/// path relative to the root, seeded with `seed`, passes `selected`.
+fn select_files(
+    root_dir: &Path,
+    seed: u64,
+    follow_root_links: bool,
+    selected: impl Fn(u64) -> bool,
+) -> Result<Vec<(PathBuf, u64)>, io::Error> {
+    let mut files = Vec::new();
+    let mut queue = vec![(root_dir.to_path_buf(), follow_root_links)];
+    while let Some((dir, follow_links)) = queue.pop() {
+        for entry in fs::read_dir(&dir)
+            .attach_printable_lazy(|| format!("Failed to read directory {dir:?}"))?
+        {
+            let path = entry
+                .attach_printable_lazy(|| format!("Failed to read directory {dir:?}"))?
+                .path();
+            let metadata = if follow_links {
+                fs::metadata(&path)
+            } else {
+                fs::symlink_metadata(&path)
+            };
+            let metadata = match metadata {
+                // A dangling link
+                Err(e) if follow_links && e.kind() == io::ErrorKind::NotFound => continue,
+                r => r.attach_printable_lazy(|| format!("Failed to stat {path:?}"))?,
+            };
+            if metadata.is_dir() {
+                queue.push((path, false));
+                continue;
+            }
+            // Symlinks, FIFOs, sockets, and devices have no contents to change
+            if !metadata.is_file() {
+                continue;
+            }
+
+            let relative = path.strip_prefix(root_dir).unwrap_or(&path);
+            let path_hash = XxHash64::oneshot(seed, relative.as_os_str().as_encoded_bytes());
+            if selected(path_hash) {
+                files.push((path, path_hash));
+            }
+        }
+    }
+    Ok(files)
+}
+
+/// Walks the tree rooted at `root_dir` like [`select_files`], returning every
+/// directory along with the hash of its path relative to the root.
+fn select_dirs(
+    root_dir: &Path,
+    seed: u64,
+    follow_root_links: bool,
+) -> Result<Vec<(PathBuf, u64)>, io::Error> {
+    let mut dirs = Vec::new();
+    let mut queue = vec![(root_dir.to_path_buf(), follow_root_links)];
+    while let Some((dir, follow_links)) = queue.pop() {
+        for entry in fs::read_dir(&dir)
+            .attach_printable_lazy(|| format!("Failed to read directory {dir:?}"))?
+        {
+            let path = entry
+                .attach_printable_lazy(|| format!("Failed to read directory {dir:?}"))?
+                .path();
+            let metadata = if follow_links {
+                fs::metadata(&path)
+            } else {
+                fs::symlink_metadata(&path)
+            };
+            let metadata = match metadata {
+                // A dangling link
+                Err(e) if follow_links && e.kind() == io::ErrorKind::NotFound => continue,
+                r => r.attach_printable_lazy(|| format!("Failed to stat {path:?}"))?,
+            };
+            if !metadata.is_dir() {
+                continue;
+            }
+
+            let relative = path.strip_prefix(root_dir).unwrap_or(&path);
+            let path_hash = XxHash64::oneshot(seed, relative.as_os_str().as_encoded_bytes());
+            dirs.push((path.clone(), path_hash));
+            queue.push((path, false));
+        }
+    }
+    Ok(dirs)
+}
+
+fn write_contents(
+    file: &mut File,
+    num_bytes: u64,
+    offset: u64,
+    fill_pattern: Option<&FillPattern>,
+    random: &mut Xoshiro256PlusPlus,
+) -> io::Result<()> {
+    let mut block = [0; 4096];
+    let mut remaining = num_bytes;
+    while remaining > 0 {
+        let len = usize::try_from(remaining).map_or(block.len(), |r| r.min(block.len()));
+        match fill_pattern {
+            Some(pattern) => pattern.fill(&mut block[..len], offset + num_bytes - remaining),
+            None => fill_random_block(&mut block[..len], random),
+        }
+        file.write_all(&block[..len])?;
+        remaining -= len as u64;
+    }
+    Ok(())
+}
+
+fn rewrite(path: &Path, random: &mut Xoshiro256PlusPlus) -> io::Result<()> {
+    let metadata = fs::metadata(path)?;
+    let mut file = OpenOptions::new().write(true).truncate(true).open(path)?;
+    write_contents(&mut file, metadata.len(), 0, None, random)?;
+
+    // Change detection tools often only look at modification times with a
+    // coarse granularity, so make sure the change can't go unnoticed
+    let modified = metadata.modified()? + Duration::from_secs(2);
+    file.set_modified(modified.max(SystemTime::now()))
+}
+
+/// The size and audit hash of the file's full contents.
+pub fn rehash(path: &Path) -> io::Result<(u64, Option<u64>)> {
+    let mut writer = HashingWriter::new(io::sink(), 0);
+    let size = io::copy(&mut File::open(path)?, &mut writer)?;
+    Ok((size, Some(writer.finalize())))
+}
@@ -0,0 +1,152 @@
+use std::{fs, io, num::NonZeroUsize, path::PathBuf, thread};
+
+use error_stack::{Report, Result, ResultExt};
+
+use crate::core::GeneratorStats;
+
+struct DirSummary {
+    files: u64,
+    bytes: u64,
+    subdirs: Vec<PathBuf>,
+}
+
+/// Walks the tree rooted at `root_dir` one level at a time, spreading the
+/// directories of each level across `parallelism` threads, and tallies up
+/// what actually exists on disk.
+///
+/// The root directory itself is not counted, matching what the scheduler
+/// reports. Duplicates can't be told apart from other files and are left at
+/// zero. Symbolic links directly inside the root are followed when
+/// `follow_root_links` is set so sharded top-level directories are included,
+/// other symbolic links aren't counted.
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "trace"))]
+pub fn recount(
+    root_dir: PathBuf,
+    parallelism: NonZeroUsize,
+    follow_root_links: bool,
+) -> Result<GeneratorStats, io::Error> {
+    let mut stats = GeneratorStats {
+        files: 0,
+        dirs: 0,
+        bytes: 0,
+        duplicates: 0,
+    };
+
+    let mut level = vec![root_dir];
+    let mut follow_links = follow_root_links;
+    while !level.is_empty() {
+        let chunk_size = level.len().div_ceil(parallelism.get());
+        let summaries = thread::scope(|scope| {
+            level
+                .chunks(chunk_size)
+                .map(|chunk| {
+                    scope.spawn(move || {
+                        chunk
+                            .iter()
+                            .map(|dir| summarize_dir(dir, follow_links))
+                            .collect()
+                    })
+                })
+                .collect::<Vec<_>>()
+                .into_iter()
+                .map(|worker| {
+                    worker
+                        .join()
+                        .unwrap_or_else(|_| Err(Report::new(io::Error::other("Worker panicked"))))
+                })
+                .collect::<Result<Vec<Vec<_>>, io::Error>>()
+        })?;
+
+        level.clear();
+        follow_links = false;
+        for DirSummary {
+            files,
+            bytes,
+            subdirs,
+        } in summaries.into_iter().flatten()
+        {
+            stats.files += files;
+            stats.bytes += bytes;
+            stats.dirs += subdirs.len();
+            level.extend(subdirs);
+        }
+    }
+
+    Ok(stats)
+}
+
+enum Kind {
+    Dir,
+    File(u64),
+    Symlink,
+}
+
+fn summarize_dir(dir: &PathBuf, follow_links: bool) -> Result<DirSummary, io::Error> {
+    let mut summary = DirSummary {
+        files: 0,
+        bytes: 0,
+        subdirs: Vec::new(),
+    };
+
+    for entry in
+        fs::read_dir(dir).attach_printable_lazy(|| format!("Failed to read directory {dir:?}"))?
+    {
+        let path = entry
+            .attach_printable_lazy(|| format!("Failed to read directory {dir:?}"))?
+            .path();
+        let kind = match stat(&path, follow_links) {
+            // A dangling link
+            Err(e) if follow_links && e.kind() == io::ErrorKind::NotFound => continue,
+            r => r.attach_printable_lazy(|| format!("Failed to stat {path:?}"))?,
+        };
+        match kind {
+            Kind::Dir => summary.subdirs.push(path),
+            Kind::File(size) => {
+                summary.files += 1;
+                summary.bytes += size;
+            }
+            Kind::Symlink => {}
+        }
+    }
+
+    Ok(summary)
+}
+
+#[cfg(all(target_os = "linux", not(miri)))]
+fn stat(path: &PathBuf, follow_links: bool) -> io::Result<Kind> {
+    use rustix::fs::{AtFlags, CWD, FileType, StatxFlags, statx};
+
+    let stx = statx(
+        CWD,
+        path,
+        if follow_links {
+            AtFlags::empty()
+        } else {
+            AtFlags::SYMLINK_NOFOLLOW
+        },
+        StatxFlags::TYPE | StatxFlags::SIZE,
+    )?;
+    Ok(match FileType::from_raw_mode(stx.stx_mode.into()) {
+        FileType::Directory => Kind::Dir,
+        FileType::Symlink => Kind::Symlink,
+        _ => Kind::File(stx.stx_size),
+    })
+}
+
+#[cfg(not(all(target_os = "linux", not(miri))))]
+fn stat(path: &PathBuf, follow_links: bool) -> io::Result<Kind> {
+    if follow_links {
+        fs::metadata(path)
+    } else {
+        fs::symlink_metadata(path)
+    }
+    .map(|m| {
+        if m.is_dir() {
+            Kind::Dir
+        } else if m.is_symlink() {
+            Kind::Symlink
+        } else {
+            Kind::File(m.len())
+        }
+    })
+}
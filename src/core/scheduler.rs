@@ -10,24 +10,40 @@ use std::{
 };
 
 use error_stack::{Result, ResultExt};
-use rand_distr::Normal;
-use tokio::task::{JoinError, JoinHandle};
+use tokio::task::JoinError;
 
 use crate::{
     core::{
-        files::GeneratorTaskOutcome,
-        tasks::{QueueErrors, QueueOutcome, TaskGenerator},
-        truncatable_normal,
+        FanoutDistr, FilesPerDirDistr,
+        files::{DirNames, GeneratorTaskOutcome},
+        tasks::{ByteCountsPool, QueueErrors, QueueOutcome, Task, TaskGenerator},
     },
     generator::Error,
-    utils::{FastPathBuf, with_dir_name, with_file_name},
+    utils::{FastPathBuf, with_file_name},
 };
 
-#[derive(Debug, Copy, Clone)]
+/// The order in which directories are visited and their contents scheduled.
+///
+/// Tasks are numbered in scheduling order, so the same seed produces a
+/// different tree under each order.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub enum TraversalOrder {
+    /// Finish each subtree before moving on to its next sibling such that
+    /// completed subtrees appear as early as possible.
+    #[default]
+    DepthFirst,
+    /// Finish each level of the tree before moving on to the next one such
+    /// that the tree's full width appears as early as possible.
+    BreadthFirst,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
 pub struct GeneratorStats {
     pub files: u64,
     pub dirs: usize,
     pub bytes: u64,
+    /// How many of the files are duplicates of another file.
+    pub duplicates: u64,
 }
 
 impl AddAssign<&GeneratorTaskOutcome> for GeneratorStats {
@@ -37,24 +53,82 @@ impl AddAssign<&GeneratorTaskOutcome> for GeneratorStats {
             files_generated,
             dirs_generated,
             bytes_generated,
+            duplicates_generated,
             ..
         }: &GeneratorTaskOutcome,
     ) {
         self.files += files_generated;
         self.dirs += dirs_generated;
         self.bytes += bytes_generated;
+        self.duplicates += duplicates_generated;
     }
 }
 
+/// Overrides of the tree's shape for the directories at one depth.
+#[derive(Debug, Clone, Default)]
+pub struct LevelSettings {
+    /// The mean number of files in each directory.
+    pub files_per_dir: Option<f64>,
+    /// The distribution the number of subdirectories in each directory is
+    /// drawn from along with its mean.
+    pub dirs_per_dir: Option<(FanoutDistr, f64)>,
+}
+
+/// Settings controlling how generation work is scheduled.
+#[derive(Debug, Clone)]
+pub struct SchedulerSettings {
+    pub traversal: TraversalOrder,
+    /// The maximum number of bytes queued for writing but not yet written.
+    ///
+    /// The limit is checked before each directory's children are queued, so
+    /// it can be exceeded by up to one directory's worth of files.
+    pub max_in_flight_bytes: Option<NonZeroU64>,
+    /// The maximum number of byte count buffers kept around for reuse in exact
+    /// bytes mode. Defaults to half the task queue's capacity.
+    pub byte_counts_pool_capacity: Option<NonZeroUsize>,
+    /// How directories are named, which the scheduler needs to know to find
+    /// them again.
+    pub dir_names: DirNames,
+    /// The depth the first directory of each level is guaranteed to keep
+    /// descending to.
+    pub min_depth: usize,
+    /// Only place files in directories at the maximum depth, every other
+    /// directory holding at least one subdirectory.
+    pub files_at_leaves: bool,
+    /// How unevenly files and subdirectories are spread among siblings, from
+    /// `0` for evenly to just under `1` for the first sibling getting nearly
+    /// everything.
+    pub skew: f64,
+    /// The distribution the number of files in each directory is drawn from,
+    /// rescaled to each level's expected number of files per directory.
+    pub files_per_dir: FilesPerDirDistr,
+    /// Overrides for the directories at each depth, indexed by depth with the
+    /// root directory at depth 0.
+    pub levels: Vec<LevelSettings>,
+}
+
+/// Bookkeeping about the scheduler itself rather than the generated tree.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct SchedulerStats {
+    /// The most memory held by recycled byte count buffers at any one time.
+    pub peak_byte_counts_pool_bytes: usize,
+}
+
 struct Scheduler<'a> {
-    #[cfg(not(feature = "dry_run"))]
-    tasks: &'a mut VecDeque<JoinHandle<Result<GeneratorTaskOutcome, io::Error>>>,
-    #[cfg(feature = "dry_run")]
-    tasks: &'a mut VecDeque<GeneratorTaskOutcome>,
+    /// Queued tasks along with the number of bytes they are expected to write.
+    tasks: &'a mut VecDeque<(Task, u64)>,
     stats: &'a mut GeneratorStats,
+    in_flight_bytes: u64,
+    max_in_flight_bytes: u64,
 
     stack: Vec<Directory>,
     target_dir: FastPathBuf,
+    dir_names: DirNames,
+    min_depth: usize,
+    files_at_leaves: bool,
+    skew: f64,
+    files_per_dir: FilesPerDirDistr,
+    levels: Vec<LevelSettings>,
 
     cache: ObjectPool,
 }
@@ -80,7 +154,7 @@ struct DirChild {
 struct ObjectPool {
     directories: Vec<Vec<DirChild>>,
     paths: Vec<FastPathBuf>,
-    byte_counts: Vec<Vec<u64>>,
+    byte_counts: ByteCountsPool,
 }
 
 #[cfg_attr(
@@ -93,28 +167,48 @@ pub async fn run(
     dirs_per_dir: f64,
     max_depth: usize,
     parallelism: NonZeroUsize,
+    SchedulerSettings {
+        traversal,
+        max_in_flight_bytes,
+        byte_counts_pool_capacity,
+        dir_names,
+        min_depth,
+        files_at_leaves,
+        skew,
+        files_per_dir,
+        levels,
+    }: SchedulerSettings,
     mut generator: impl TaskGenerator + Send,
-) -> Result<GeneratorStats, Error> {
+) -> Result<(GeneratorStats, SchedulerStats), Error> {
     // Minus 1 because VecDeque adds 1 and then rounds to a power of 2
     let mut tasks = VecDeque::with_capacity(parallelism.get().pow(2) - 1);
     let mut stats = GeneratorStats {
         files: 0,
         dirs: 0,
         bytes: 0,
+        duplicates: 0,
     };
 
     let mut scheduler = Scheduler {
         stack: Vec::with_capacity(max_depth),
         target_dir: FastPathBuf::from(root_dir),
+        dir_names,
+        min_depth,
+        files_at_leaves,
+        skew,
+        files_per_dir,
+        levels,
 
         cache: {
             let paths = Vec::with_capacity(tasks.capacity() / 2);
             ObjectPool {
                 directories: Vec::with_capacity(max_depth),
                 byte_counts: if generator.uses_byte_counts_pool() {
-                    Vec::with_capacity(paths.capacity())
+                    ByteCountsPool::with_capacity(
+                        byte_counts_pool_capacity.map_or(paths.capacity(), NonZeroUsize::get),
+                    )
                 } else {
-                    Vec::default()
+                    ByteCountsPool::default()
                 },
                 paths,
             }
@@ -122,6 +216,8 @@ pub async fn run(
 
         tasks: &mut tasks,
         stats: &mut stats,
+        in_flight_bytes: 0,
+        max_in_flight_bytes: max_in_flight_bytes.map_or(u64::MAX, NonZeroU64::get),
     };
 
     #[cfg(feature = "tracing")]
@@ -142,6 +238,10 @@ pub async fn run(
         &mut scheduler,
     );
 
+    if traversal == TraversalOrder::BreadthFirst {
+        run_breadth_first(&mut generator, dirs_per_dir, max_depth, &mut scheduler).await?;
+    }
+
     #[cfg(feature = "tracing")]
     let gen_span = tracing::span!(tracing::Level::TRACE, "dir_gen");
     while let Some(&mut Directory {
@@ -164,6 +264,9 @@ pub async fn run(
         if scheduler.tasks.len() + num_dirs_to_generate >= scheduler.tasks.capacity() {
             flush_tasks(&mut scheduler).await?;
         }
+        if scheduler.in_flight_bytes > scheduler.max_in_flight_bytes {
+            drain_in_flight_bytes(&mut scheduler).await?;
+        }
 
         let Ok(directory) = schedule_task(
             target_file_count,
@@ -180,36 +283,182 @@ pub async fn run(
 
         if let Some(directory) = directory {
             scheduler.stack.push(directory);
-            with_dir_name(0, |s| scheduler.target_dir.push(s));
+            let depth = scheduler.stack.len() - 1;
+            scheduler
+                .dir_names
+                .with_dir_name(0, depth, |s| scheduler.target_dir.push(s));
         } else if !is_completing {
-            with_dir_name(next_stack_dir, |s| unsafe {
-                scheduler.target_dir.set_file_name(s);
-            });
+            let depth = scheduler.stack.len() - 1;
+            scheduler
+                .dir_names
+                .with_dir_name(next_stack_dir, depth, |s| unsafe {
+                    scheduler.target_dir.set_file_name(s);
+                });
         }
     }
     #[cfg(feature = "tracing")]
     drop(gen_span);
 
+    let scheduler_stats = SchedulerStats {
+        peak_byte_counts_pool_bytes: scheduler.cache.byte_counts.peak_retained_bytes(),
+    };
     schedule_last_task(generator, scheduler);
 
-    for task in tasks {
+    for (task, _) in tasks {
         #[cfg(not(feature = "dry_run"))]
         handle_task_result(task.await, &mut stats)?;
         #[cfg(feature = "dry_run")]
         handle_task_result(task, &mut stats)?;
     }
 
-    Ok(stats)
+    Ok((stats, scheduler_stats))
 }
 
+/// Drains the scheduler's stack one level at a time, leaving it empty and the
+/// target directory at the root.
 #[cfg_attr(
     feature = "tracing",
-    tracing::instrument(level = "trace", skip(tasks, path_pool, byte_counts_pool))
+    tracing::instrument(level = "trace", skip(generator, scheduler))
 )]
-async fn flush_tasks(
+async fn run_breadth_first(
+    generator: &mut impl TaskGenerator,
+    dirs_per_dir: f64,
+    max_depth: usize,
+    scheduler: &mut Scheduler<'_>,
+) -> Result<(), Error> {
+    let mut queue = VecDeque::new();
+    if let Some(Directory {
+        total_dirs: _,
+        child_dir_counts,
+    }) = scheduler.stack.pop()
+    {
+        for child in child_dir_counts {
+            queue.push_back((scheduler.target_dir.clone(), 1, child, true));
+        }
+    }
+
+    while let Some((
+        parent,
+        depth,
+        DirChild {
+            files: target_file_count,
+            dirs: num_dirs_to_generate,
+        },
+        first_chain,
+    )) = queue.pop_front()
+    {
+        if scheduler.tasks.len() + num_dirs_to_generate >= scheduler.tasks.capacity() {
+            flush_tasks(scheduler).await?;
+        }
+        if scheduler.in_flight_bytes > scheduler.max_in_flight_bytes {
+            drain_in_flight_bytes(scheduler).await?;
+        }
+
+        let gen_next_dirs = depth < max_depth;
+        let num_files_distr = num_files_distr(
+            &scheduler.files_per_dir,
+            &scheduler.levels,
+            target_file_count,
+            dirs_per_dir,
+            depth,
+            max_depth,
+            scheduler.min_depth.saturating_sub(depth),
+            scheduler.files_at_leaves,
+        );
+        for i in 0..num_dirs_to_generate {
+            let mut path = scheduler.cache.paths.pop().unwrap_or_default();
+            path.clone_from(&parent);
+            scheduler
+                .dir_names
+                .with_dir_name(i, depth, |s| path.push(s));
+            let child_path = gen_next_dirs.then(|| path.clone());
+            let first_chain = first_chain && i == 0;
+            let weight = sibling_weight(scheduler.skew, i, num_dirs_to_generate);
+
+            match generator.queue_gen(
+                &num_files_distr,
+                num_dirs_distr(&scheduler.levels, depth),
+                path,
+                gen_next_dirs,
+                usize::from(
+                    scheduler.files_at_leaves || first_chain && depth < scheduler.min_depth,
+                ),
+                weight,
+                &mut scheduler.cache.byte_counts,
+            ) {
+                Ok(QueueOutcome {
+                    task,
+                    chunks,
+                    num_files,
+                    num_dirs,
+                    bytes,
+                    done,
+                }) => {
+                    scheduler.tasks.push_back((task, bytes));
+                    scheduler.in_flight_bytes += bytes + chunk_bytes(&chunks);
+                    scheduler.tasks.extend(chunks);
+                    if done {
+                        return Ok(());
+                    }
+                    if let Some(child_path) = child_path
+                        && num_dirs > 0
+                    {
+                        queue.push_back((
+                            child_path,
+                            depth + 1,
+                            DirChild {
+                                files: next_target_file_count(
+                                    weighted_target(target_file_count, weight),
+                                    num_dirs,
+                                    num_files,
+                                ),
+                                dirs: num_dirs,
+                            },
+                            first_chain,
+                        ));
+                    }
+                }
+                Err(QueueErrors::NothingToDo(path)) => scheduler.cache.paths.push(path),
+            }
+        }
+        scheduler.cache.paths.push(parent);
+    }
+    Ok(())
+}
+
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "trace", skip_all))]
+async fn flush_tasks(scheduler: &mut Scheduler<'_>) -> Result<(), Error> {
+    #[cfg(feature = "tracing")]
+    tracing::event!(tracing::Level::TRACE, "Flushing pending task queue");
+
+    for _ in 0..scheduler.tasks.len() / 2 {
+        complete_oldest_task(scheduler).await?;
+    }
+    Ok(())
+}
+
+/// Waits for queued tasks to finish until the bytes they have yet to write
+/// fit within the in-flight limit again.
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "trace", skip_all))]
+async fn drain_in_flight_bytes(scheduler: &mut Scheduler<'_>) -> Result<(), Error> {
+    #[cfg(feature = "tracing")]
+    tracing::event!(
+        tracing::Level::TRACE,
+        in_flight_bytes = scheduler.in_flight_bytes,
+        "Waiting for in-flight bytes to drain"
+    );
+
+    while scheduler.in_flight_bytes > scheduler.max_in_flight_bytes && !scheduler.tasks.is_empty() {
+        complete_oldest_task(scheduler).await?;
+    }
+    Ok(())
+}
+
+async fn complete_oldest_task(
     &mut Scheduler {
         ref mut tasks,
         ref mut stats,
+        ref mut in_flight_bytes,
         cache:
             ObjectPool {
                 directories: _,
@@ -219,25 +468,19 @@ async fn flush_tasks(
         ..
     }: &mut Scheduler<'_>,
 ) -> Result<(), Error> {
-    #[cfg(feature = "tracing")]
-    tracing::event!(tracing::Level::TRACE, "Flushing pending task queue");
-
-    let mut drain = tasks.len() / 2;
-    while drain > 0 {
-        let task = tasks.pop_front().unwrap();
-
-        #[cfg(not(feature = "dry_run"))]
-        let outcome = handle_task_result(task.await, stats)?;
-        #[cfg(feature = "dry_run")]
-        let outcome = handle_task_result(task, stats)?;
+    let Some((task, bytes)) = tasks.pop_front() else {
+        return Ok(());
+    };
+    *in_flight_bytes -= bytes;
 
-        path_pool.push(outcome.pool_return_file);
-        if let Some(mut vec) = outcome.pool_return_byte_counts {
-            vec.clear();
-            byte_counts_pool.push(vec);
-        }
+    #[cfg(not(feature = "dry_run"))]
+    let outcome = handle_task_result(task.await, stats)?;
+    #[cfg(feature = "dry_run")]
+    let outcome = handle_task_result(task, stats)?;
 
-        drain -= 1;
+    path_pool.push(outcome.pool_return_file);
+    if let Some(vec) = outcome.pool_return_byte_counts {
+        byte_counts_pool.recycle(vec);
     }
     Ok(())
 }
@@ -275,8 +518,16 @@ fn schedule_root_dir(
     &mut Scheduler {
         ref mut tasks,
         stats: _,
+        ref mut in_flight_bytes,
+        max_in_flight_bytes: _,
         ref mut stack,
         ref target_dir,
+        dir_names: _,
+        min_depth,
+        files_at_leaves,
+        skew: _,
+        ref files_per_dir,
+        ref levels,
         cache:
             ObjectPool {
                 directories: _,
@@ -286,18 +537,34 @@ fn schedule_root_dir(
     }: &mut Scheduler<'_>,
 ) {
     match generator.queue_gen(
-        &num_files_distr(target_file_count.get(), dirs_per_dir, max_depth),
+        &num_files_distr(
+            files_per_dir,
+            levels,
+            target_file_count.get(),
+            dirs_per_dir,
+            0,
+            max_depth,
+            min_depth,
+            files_at_leaves,
+        ),
+        num_dirs_distr(levels, 0),
         target_dir.clone(),
         max_depth > 0,
+        usize::from(files_at_leaves || min_depth > 0),
+        1.,
         byte_counts_pool,
     ) {
         Ok(QueueOutcome {
             task,
+            chunks,
             num_files,
             num_dirs,
+            bytes,
             done: _,
         }) => {
-            tasks.push_back(task);
+            tasks.push_back((task, bytes));
+            *in_flight_bytes += bytes + chunk_bytes(&chunks);
+            tasks.extend(chunks);
             if num_dirs > 0 {
                 stack.push(Directory {
                     total_dirs: 1,
@@ -328,8 +595,16 @@ fn schedule_task(
     &mut Scheduler {
         ref mut tasks,
         stats: _,
+        ref mut in_flight_bytes,
+        max_in_flight_bytes: _,
         ref stack,
         ref target_dir,
+        ref dir_names,
+        min_depth,
+        files_at_leaves,
+        skew,
+        ref files_per_dir,
+        ref levels,
         cache:
             ObjectPool {
                 directories: ref mut dir_pool,
@@ -341,6 +616,11 @@ fn schedule_task(
 ) -> result::Result<Option<Directory>, ()> {
     let depth = stack.len();
     let gen_next_dirs = depth < max_depth;
+    // The first directory of every level descended into so far
+    let first_chain = depth < min_depth
+        && stack
+            .iter()
+            .all(|dir| dir.total_dirs - dir.child_dir_counts.len() == 1);
 
     let mut next_dirs = dir_pool.pop().unwrap_or_default();
     debug_assert!(next_dirs.is_empty());
@@ -355,17 +635,27 @@ fn schedule_task(
     // contention).
     let raw_next_dirs = next_dirs.spare_capacity_mut();
 
-    let num_files_distr = num_files_distr(target_file_count, dirs_per_dir, max_depth - depth);
+    let num_files_distr = num_files_distr(
+        files_per_dir,
+        levels,
+        target_file_count,
+        dirs_per_dir,
+        depth,
+        max_depth,
+        min_depth.saturating_sub(depth),
+        files_at_leaves,
+    );
+    let num_dirs_distr = num_dirs_distr(levels, depth);
     #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
     let expected_file_name_length = max(
-        with_dir_name(dirs_per_dir.round() as usize, str::len),
+        dir_names.with_dir_name(dirs_per_dir.round() as usize, depth, str::len),
         with_file_name(num_files_distr.mean().round() as u64, str::len),
     );
 
     #[cfg(feature = "tracing")]
     let span_guard = gen_span.enter();
     for i in 0..num_dirs_to_generate {
-        let path = with_dir_name(i, |s| {
+        let path = dir_names.with_dir_name(i, depth, |s| {
             let mut buf = path_pool.pop().unwrap_or_else(FastPathBuf::new);
 
             // Space for the parent dir, the path separator, the target dir, child separator
@@ -381,28 +671,45 @@ fn schedule_task(
             buf
         });
 
-        let child =
-            match generator.queue_gen(&num_files_distr, path, gen_next_dirs, byte_counts_pool) {
-                Ok(QueueOutcome {
-                    task,
-                    num_files,
-                    num_dirs,
-                    done,
-                }) => {
-                    tasks.push_back(task);
-                    if done {
-                        return Err(());
-                    }
-                    DirChild {
-                        files: next_target_file_count(target_file_count, num_dirs, num_files),
-                        dirs: num_dirs,
-                    }
+        let min_dirs = usize::from(files_at_leaves || first_chain && i == 0);
+        let weight = sibling_weight(skew, i, num_dirs_to_generate);
+        let child = match generator.queue_gen(
+            &num_files_distr,
+            num_dirs_distr,
+            path,
+            gen_next_dirs,
+            min_dirs,
+            weight,
+            byte_counts_pool,
+        ) {
+            Ok(QueueOutcome {
+                task,
+                chunks,
+                num_files,
+                num_dirs,
+                bytes,
+                done,
+            }) => {
+                tasks.push_back((task, bytes));
+                *in_flight_bytes += bytes + chunk_bytes(&chunks);
+                tasks.extend(chunks);
+                if done {
+                    return Err(());
                 }
-                Err(QueueErrors::NothingToDo(path)) => {
-                    path_pool.push(path);
-                    DirChild { files: 0, dirs: 0 }
+                DirChild {
+                    files: next_target_file_count(
+                        weighted_target(target_file_count, weight),
+                        num_dirs,
+                        num_files,
+                    ),
+                    dirs: num_dirs,
                 }
-            };
+            }
+            Err(QueueErrors::NothingToDo(path)) => {
+                path_pool.push(path);
+                DirChild { files: 0, dirs: 0 }
+            }
+        };
 
         if gen_next_dirs {
             raw_next_dirs[num_dirs_to_generate - i - 1].write(child);
@@ -430,8 +737,16 @@ fn schedule_last_task(mut generator: impl TaskGenerator, mut scheduler: Schedule
     let Scheduler {
         ref mut tasks,
         stats: _,
+        in_flight_bytes: _,
+        max_in_flight_bytes: _,
         stack: _,
         target_dir,
+        dir_names: _,
+        min_depth: _,
+        files_at_leaves: _,
+        skew: _,
+        files_per_dir: _,
+        levels: _,
         cache:
             ObjectPool {
                 byte_counts: ref mut byte_counts_pool,
@@ -441,12 +756,15 @@ fn schedule_last_task(mut generator: impl TaskGenerator, mut scheduler: Schedule
 
     if let Ok(QueueOutcome {
         task,
+        chunks,
         num_files: _,
         num_dirs: _,
+        bytes,
         done: _,
     }) = generator.maybe_queue_final_gen(target_dir, byte_counts_pool)
     {
-        tasks.push_back(task);
+        tasks.push_back((task, bytes));
+        tasks.extend(chunks);
     }
 }
 
@@ -458,8 +776,16 @@ fn handle_directory_completion(
     &mut Scheduler {
         tasks: _,
         stats: _,
+        in_flight_bytes: _,
+        max_in_flight_bytes: _,
         ref mut stack,
         ref mut target_dir,
+        ref dir_names,
+        min_depth: _,
+        files_at_leaves: _,
+        skew: _,
+        files_per_dir: _,
+        levels: _,
         cache:
             ObjectPool {
                 directories: ref mut directory_pool,
@@ -485,13 +811,53 @@ fn handle_directory_completion(
         }
 
         if !child_dir_counts.is_empty() {
-            with_dir_name(total_dirs - child_dir_counts.len(), |s| unsafe {
-                target_dir.set_file_name(s);
-            });
+            dir_names.with_dir_name(
+                total_dirs - child_dir_counts.len(),
+                stack.len() - 1,
+                |s| unsafe {
+                    target_dir.set_file_name(s);
+                },
+            );
         }
     }
 }
 
+fn chunk_bytes(chunks: &[(Task, u64)]) -> u64 {
+    chunks.iter().map(|&(_, bytes)| bytes).sum()
+}
+
+/// The share of files and subdirectories the `i`th of `num_siblings`
+/// directories gets relative to an average sibling. Shares shrink
+/// geometrically with `skew` such that they still average out to one.
+#[allow(clippy::cast_precision_loss)]
+fn sibling_weight(skew: f64, i: usize, num_siblings: usize) -> f64 {
+    if skew <= 0. {
+        return 1.;
+    }
+
+    let ratio = 1. - skew;
+    num_siblings as f64 * ratio.powf(i as f64) * skew / (1. - ratio.powf(num_siblings as f64))
+}
+
+#[allow(
+    clippy::cast_possible_truncation,
+    clippy::cast_sign_loss,
+    clippy::cast_precision_loss
+)]
+fn weighted_target(target_file_count: u64, weight: f64) -> u64 {
+    (target_file_count as f64 * weight).round() as u64
+}
+
+/// The distribution the number of subdirectories of the directories at
+/// `depth` is drawn from if it differs from the run-wide one.
+fn num_dirs_distr(levels: &[LevelSettings], depth: usize) -> Option<&FanoutDistr> {
+    levels
+        .get(depth)?
+        .dirs_per_dir
+        .as_ref()
+        .map(|(distr, _)| distr)
+}
+
 fn next_target_file_count(target_file_count: u64, dirs_created: usize, files_created: u64) -> u64 {
     let files = target_file_count.saturating_sub(files_created);
     files
@@ -499,20 +865,43 @@ fn next_target_file_count(target_file_count: u64, dirs_created: usize, files_cre
         .unwrap_or(files_created)
 }
 
-#[allow(clippy::cast_precision_loss)]
+#[allow(clippy::cast_precision_loss, clippy::too_many_arguments)]
 #[cfg_attr(feature = "tracing", tracing::instrument(level = "trace"))]
 fn num_files_distr(
+    files_per_dir: &FilesPerDirDistr,
+    levels: &[LevelSettings],
     target_file_count: u64,
     dirs_per_dir: f64,
-    remaining_depth: usize,
-) -> Normal<f64> {
-    fn files_per_dir(total_files: u64, dirs_per_dir: f64, remaining_depth: usize) -> f64 {
+    depth: usize,
+    max_depth: usize,
+    remaining_min_depth: usize,
+    files_at_leaves: bool,
+) -> FilesPerDirDistr {
+    fn mean_files_per_dir(total_files: u64, dirs_per_dir: f64, remaining_depth: usize) -> f64 {
         (total_files as f64) * dirs_per_dir.powf(-(remaining_depth as f64))
     }
 
-    truncatable_normal(files_per_dir(
-        target_file_count,
-        dirs_per_dir,
-        remaining_depth,
-    ))
+    let remaining_depth = max_depth - depth;
+    let mut files = if let Some(files) = levels.get(depth).and_then(|level| level.files_per_dir) {
+        files
+    } else {
+        // Levels with their own fan-out multiply the directories below by it instead
+        let (overridden_levels, overridden_dirs) = (depth..max_depth)
+            .filter_map(|depth| levels.get(depth)?.dirs_per_dir.as_ref())
+            .fold((0, 1.), |(levels, dirs), &(_, mean)| {
+                (levels + 1, dirs * mean)
+            });
+        mean_files_per_dir(
+            target_file_count,
+            dirs_per_dir,
+            remaining_depth - overridden_levels,
+        ) / overridden_dirs
+    };
+    if files_at_leaves && remaining_depth > 0 {
+        files = 0.;
+    } else if remaining_min_depth > 0 {
+        // Narrow trees would otherwise leave most files above the minimum depth
+        files = files.min(target_file_count as f64 / (remaining_min_depth + 1) as f64);
+    }
+    files_per_dir.with_mean(files)
 }
@@ -4,37 +4,48 @@
     clippy::cast_precision_loss
 )]
 
-use std::{cmp::min, io, num::NonZeroU64, sync::Arc};
+use std::{cmp::min, io, mem, num::NonZeroU64, sync::Arc};
 
-use rand::{RngCore, SeedableRng};
-use rand_distr::Normal;
+use rand::{Rng, RngCore, SeedableRng};
 use rand_xoshiro::Xoshiro256PlusPlus;
 use tokio::{task, task::JoinHandle};
 
 use crate::{
     core::{
-        FileSpec, PendingDuplicate,
+        FanoutDistr, FileSpec, FilesPerDirDistr, PendingDuplicate, PermissionTable, SizeDistr,
         audit::AuditTrail,
         file_contents::{
-            FileContentsGenerator, NoGeneratedFileContents, OnTheFlyGeneratedFileContents,
-            PreDefinedGeneratedFileContents,
+            ContentMode, FileContentsGenerator, FileHeaders, FillPattern, Fragmenter, Holes,
+            NoGeneratedFileContents, OnTheFlyGeneratedFileContents,
+            PreDefinedGeneratedFileContents, Writeback,
         },
-        files::{GeneratorTaskOutcome, GeneratorTaskParams, create_files_and_dirs},
-        sample_truncated,
+        files::{GeneratorTaskOutcome, GeneratorTaskParams, TaskSettings, create_files_and_dirs},
+        weighted,
     },
     utils::FastPathBuf,
 };
 
 pub type QueueResult = Result<QueueOutcome, QueueErrors>;
 
+/// The most directories the remainder of an exact run is spread across.
+const MAX_REMAINDER_DIRS: usize = 256;
+
+#[cfg(not(feature = "dry_run"))]
+pub type Task = JoinHandle<error_stack::Result<GeneratorTaskOutcome, io::Error>>;
+#[cfg(feature = "dry_run")]
+pub type Task = GeneratorTaskOutcome;
+
 pub struct QueueOutcome {
-    #[cfg(not(feature = "dry_run"))]
-    pub task: JoinHandle<error_stack::Result<GeneratorTaskOutcome, io::Error>>,
-    #[cfg(feature = "dry_run")]
-    pub task: GeneratorTaskOutcome,
+    pub task: Task,
+    /// Additional tasks creating the remaining files of a directory too large
+    /// for a single task, or the remainder of an exact run in other
+    /// directories, along with the number of bytes each is expected to write.
+    pub chunks: Vec<(Task, u64)>,
 
     pub num_files: u64,
     pub num_dirs: usize,
+    /// The number of bytes the task is expected to write.
+    pub bytes: u64,
     pub done: bool,
 }
 
@@ -43,16 +54,86 @@ pub enum QueueErrors {
     NothingToDo(FastPathBuf),
 }
 
+/// Recycles the per-task byte count buffers used in exact bytes mode.
+///
+/// At most `capacity` buffers are retained and buffers much larger than what
+/// they last held are shrunk before being retained, so a few huge directories
+/// don't pin memory for the rest of the run.
+#[derive(Debug, Default)]
+pub struct ByteCountsPool {
+    buffers: Vec<Vec<u64>>,
+    capacity: usize,
+    retained_bytes: usize,
+    peak_retained_bytes: usize,
+}
+
+impl ByteCountsPool {
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            buffers: Vec::with_capacity(capacity),
+            capacity,
+            retained_bytes: 0,
+            peak_retained_bytes: 0,
+        }
+    }
+
+    pub fn take(&mut self) -> Vec<u64> {
+        let Some(buffer) = self.buffers.pop() else {
+            return Vec::new();
+        };
+        self.retained_bytes -= buffer.capacity() * size_of::<u64>();
+        buffer
+    }
+
+    pub fn recycle(&mut self, mut buffer: Vec<u64>) {
+        if self.buffers.len() >= self.capacity {
+            return;
+        }
+
+        let used = buffer.len();
+        buffer.clear();
+        if buffer.capacity() > 2 * used {
+            buffer.shrink_to(used);
+        }
+        if buffer.capacity() == 0 {
+            return;
+        }
+
+        self.retained_bytes += buffer.capacity() * size_of::<u64>();
+        self.peak_retained_bytes = self.peak_retained_bytes.max(self.retained_bytes);
+        self.buffers.push(buffer);
+    }
+
+    #[cfg(feature = "tracing")]
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// The most memory held by buffers sitting in the pool at any one time.
+    pub fn peak_retained_bytes(&self) -> usize {
+        self.peak_retained_bytes
+    }
+}
+
 pub trait TaskGenerator {
+    /// Queues the generation of `file`'s contents. When `gen_dirs` is set, at
+    /// least `min_dirs` subdirectories are generated, their number being drawn
+    /// from `num_dirs_distr` if given instead of the generator's own
+    /// distribution. The sampled file and subdirectory counts are scaled by
+    /// `weight`, the directory's share relative to an average sibling.
+    #[allow(clippy::too_many_arguments)]
     fn queue_gen(
         &mut self,
-        num_files_distr: &Normal<f64>,
+        num_files_distr: &FilesPerDirDistr,
+        num_dirs_distr: Option<&FanoutDistr>,
         file: FastPathBuf,
         gen_dirs: bool,
-        byte_counts_pool: &mut Vec<Vec<u64>>,
+        min_dirs: usize,
+        weight: f64,
+        byte_counts_pool: &mut ByteCountsPool,
     ) -> QueueResult;
 
-    fn maybe_queue_final_gen(&mut self, file: FastPathBuf, _: &mut Vec<Vec<u64>>) -> QueueResult {
+    fn maybe_queue_final_gen(&mut self, file: FastPathBuf, _: &mut ByteCountsPool) -> QueueResult {
         Err(QueueErrors::NothingToDo(file))
     }
 
@@ -66,61 +147,162 @@ pub trait TaskGenerator {
     tracing::instrument(level = "trace", skip(params))
 )]
 fn queue(
-    params: GeneratorTaskParams<impl FileContentsGenerator + Send + 'static>,
+    mut params: GeneratorTaskParams<impl FileContentsGenerator + Send + 'static>,
     done: bool,
     _task_index: u64,
 ) -> QueueResult {
     if !params.file_objs.is_empty() || params.num_dirs > 0 {
+        let num_files = params.file_objs.len();
+        let num_dirs = params.num_dirs;
+        let chunks = split_into_chunks(&mut params);
+        let (task, bytes) = spawn(params);
         Ok(QueueOutcome {
-            num_files: params.file_objs.len() as u64,
-            num_dirs: params.num_dirs,
-            done,
+            task,
+            chunks,
 
-            #[cfg(not(feature = "dry_run"))]
-            task: task::spawn_blocking(move || create_files_and_dirs(params)),
-            #[cfg(feature = "dry_run")]
-            task: {
-                std::hint::black_box(&params);
-                GeneratorTaskOutcome {
-                    files_generated: params.file_objs.len() as u64,
-                    dirs_generated: params.num_dirs,
-                    bytes_generated: 0,
-
-                    pool_return_file: params.target_dir,
-                    pool_return_byte_counts: None,
-                }
-            },
+            num_files: num_files as u64,
+            num_dirs,
+            bytes,
+            done,
         })
     } else {
         Err(QueueErrors::NothingToDo(params.target_dir))
     }
 }
 
+/// Splits off every file past the first `max_files_per_task` into tasks of
+/// their own so huge directories are spread across worker threads. The
+/// subdirectories are left to the original task.
+fn split_into_chunks(
+    params: &mut GeneratorTaskParams<impl FileContentsGenerator + Send + 'static>,
+) -> Vec<(Task, u64)> {
+    let max_files = params.settings.max_files_per_task.max(1);
+    let num_files = params.file_objs.len();
+    if num_files <= max_files {
+        return Vec::new();
+    }
+
+    let mut chunks = Vec::with_capacity((num_files - 1) / max_files);
+    let mut at = (num_files - 1) / max_files * max_files;
+    while at > 0 {
+        chunks.push(spawn(GeneratorTaskParams {
+            target_dir: params.target_dir.clone(),
+            file_objs: params.file_objs.split_off(at),
+            num_dirs: 0,
+            dir_offset: 0,
+            file_offset: params.file_offset + at as u64,
+            file_contents: params.file_contents.split_off(at),
+            audit_trail: params.audit_trail.clone(),
+            settings: params.settings.clone(),
+            task_index: params.task_index,
+        }));
+        at -= max_files;
+    }
+    chunks.reverse();
+    chunks
+}
+
+fn spawn(params: GeneratorTaskParams<impl FileContentsGenerator + Send + 'static>) -> (Task, u64) {
+    let bytes = params.file_contents.expected_bytes(params.file_objs.len());
+
+    #[cfg(not(feature = "dry_run"))]
+    let task = task::spawn_blocking(move || create_files_and_dirs(params));
+    #[cfg(feature = "dry_run")]
+    let task = {
+        std::hint::black_box(&params);
+        GeneratorTaskOutcome {
+            files_generated: params.file_objs.len() as u64,
+            dirs_generated: params.num_dirs,
+            bytes_generated: 0,
+            duplicates_generated: params
+                .file_objs
+                .iter()
+                .filter(|spec| spec.is_duplicate)
+                .count() as u64,
+
+            pool_return_file: params.target_dir,
+            pool_return_byte_counts: None,
+        }
+    };
+
+    (task, bytes)
+}
+
+/// Hands the `leftovers` of an exact bytes run to the final files' sizes.
+fn add_leftovers(byte_counts: &mut [u64], leftovers: u64, strategy: LeftoverStrategy) {
+    let target = match strategy {
+        LeftoverStrategy::Spread => {
+            let base = leftovers / byte_counts.len() as u64;
+            let mut remainder = leftovers % byte_counts.len() as u64;
+            for count in byte_counts {
+                if remainder > 0 {
+                    *count += base + 1;
+                    remainder -= 1;
+                } else {
+                    *count += base;
+                }
+            }
+            return;
+        }
+        LeftoverStrategy::LastFile => byte_counts.last_mut(),
+        // Ties go to the first file so the choice stays deterministic
+        LeftoverStrategy::LargestFile => byte_counts.iter_mut().rev().max_by_key(|count| **count),
+    };
+    if let Some(count) = target {
+        *count += leftovers;
+    }
+}
+
 fn dirs_to_gen<R: RngCore + ?Sized>(
     files_created: u64,
     gen_dirs: bool,
-    num_dirs_distr: &Normal<f64>,
+    min_dirs: usize,
+    weight: f64,
+    num_dirs_distr: &FanoutDistr,
     random: &mut R,
 ) -> usize {
     if gen_dirs {
-        let dirs = usize::try_from(sample_truncated(num_dirs_distr, random)).unwrap_or(usize::MAX);
+        let dirs = num_dirs_distr.sample(random);
+        let dirs = weighted(dirs, weight, random);
+        let dirs = usize::try_from(dirs).unwrap_or(usize::MAX);
         if files_created > 0 && dirs == 0 {
-            1
+            min_dirs.max(1)
         } else {
-            dirs
+            dirs.max(min_dirs)
         }
     } else {
         0
     }
 }
 
+/// Where the bytes left over once every file of an exact bytes run has been
+/// given its size end up.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub enum LeftoverStrategy {
+    /// Spread the leftovers evenly over the last files, distorting many sizes
+    /// slightly.
+    #[default]
+    Spread,
+    /// Add all leftovers to the very last file.
+    LastFile,
+    /// Add all leftovers to the largest of the last files.
+    LargestFile,
+}
+
 pub struct GeneratorBytes {
-    pub num_bytes_distr: Normal<f64>,
-    pub fill_byte: Option<u8>,
+    pub num_bytes_distr: SizeDistr,
+    pub fill_pattern: Option<FillPattern>,
+    pub content: ContentMode,
+    pub direct_io: bool,
+    pub writeback_bytes: Option<NonZeroU64>,
+    pub fragment: bool,
+    pub holes: Option<Holes>,
+    pub preallocate: bool,
+    pub headers: Option<Arc<FileHeaders>>,
 }
 
 pub struct DynamicGenerator {
-    pub num_dirs_distr: Normal<f64>,
+    pub num_dirs_distr: FanoutDistr,
     pub seed: u64,
 
     pub bytes: Option<GeneratorBytes>,
@@ -128,14 +310,15 @@ pub struct DynamicGenerator {
     pub max_duplicates_per_file: std::num::NonZeroUsize,
     pub pending_duplicates: Vec<PendingDuplicate>,
     pub audit_trail: Option<Arc<AuditTrail>>,
-    pub permissions: Vec<u32>,
+    pub settings: Arc<TaskSettings>,
+    pub permissions: PermissionTable,
     pub next_task_index: u64,
 }
 
 fn generate_primary_specs(
     num_files: u64,
     rng: &mut impl RngCore,
-    permissions: &[u32],
+    permissions: &PermissionTable,
 ) -> Vec<FileSpec> {
     let mut specs = Vec::with_capacity(num_files as usize);
     for _ in 0..num_files {
@@ -143,11 +326,8 @@ fn generate_primary_specs(
         specs.push(FileSpec {
             seed,
             is_duplicate: false,
-            permission: if permissions.is_empty() {
-                None
-            } else {
-                Some(permissions[(seed % permissions.len() as u64) as usize])
-            },
+            has_duplicates: false,
+            permission: permissions.pick(seed),
         });
     }
     specs
@@ -160,7 +340,7 @@ fn add_duplicates_to_specs_and_buffer(
     duplicate_percentage: f64,
     max_duplicates_per_file: std::num::NonZeroUsize,
     rng: &mut impl RngCore,
-    permissions: &[u32],
+    permissions: &PermissionTable,
 ) {
     let num_files = specs.len() as u64;
     if num_files == 0 || duplicate_percentage <= 0.0 {
@@ -199,6 +379,7 @@ fn add_duplicates_to_specs_and_buffer(
 
         let threshold = (prob * (u64::MAX as f64)) as u64;
         if rng.next_u64() < threshold {
+            specs[i].has_duplicates = true;
             // Generate duplicates
             let max_extra = max_dups - 1;
             let copies = if max_extra > 1 {
@@ -211,11 +392,8 @@ fn add_duplicates_to_specs_and_buffer(
                 let spec = FileSpec {
                     seed: original_seed,
                     is_duplicate: true,
-                    permission: if permissions.is_empty() {
-                        None
-                    } else {
-                        Some(permissions[(original_seed % permissions.len() as u64) as usize])
-                    },
+                    has_duplicates: false,
+                    permission: permissions.pick(original_seed),
                 };
 
                 // Hybrid approach: 50% chance to scatter, 50% chance to keep local
@@ -252,10 +430,13 @@ impl TaskGenerator for DynamicGenerator {
     #[cfg_attr(feature = "tracing", tracing::instrument(level = "trace", skip(self)))]
     fn queue_gen(
         &mut self,
-        num_files_distr: &Normal<f64>,
+        num_files_distr: &FilesPerDirDistr,
+        level_dirs_distr: Option<&FanoutDistr>,
         file: FastPathBuf,
         gen_dirs: bool,
-        _: &mut Vec<Vec<u64>>,
+        min_dirs: usize,
+        weight: f64,
+        _: &mut ByteCountsPool,
     ) -> QueueResult {
         let Self {
             ref num_dirs_distr,
@@ -264,6 +445,7 @@ impl TaskGenerator for DynamicGenerator {
             duplicate_percentage,
             max_duplicates_per_file,
             ref audit_trail,
+            ref settings,
             ref mut pending_duplicates,
             ref permissions,
             ref mut next_task_index,
@@ -277,9 +459,21 @@ impl TaskGenerator for DynamicGenerator {
         let mut rng_for_content =
             Xoshiro256PlusPlus::seed_from_u64(*seed ^ task_index ^ 0xABCD1234);
 
-        let num_files = sample_truncated(num_files_distr, &mut rng_for_counts);
-        let num_dirs = dirs_to_gen(num_files, gen_dirs, num_dirs_distr, &mut rng_for_counts);
-
+        let num_files = weighted(
+            num_files_distr.sample(&mut rng_for_counts),
+            weight,
+            &mut rng_for_counts,
+        );
+        let num_dirs = dirs_to_gen(
+            num_files,
+            gen_dirs,
+            min_dirs,
+            weight,
+            level_dirs_distr.unwrap_or(num_dirs_distr),
+            &mut rng_for_counts,
+        );
+
+        let permissions = settings.permissions_for(&file, permissions);
         let mut file_specs = generate_primary_specs(num_files, &mut deterministic_rng, permissions);
 
         // Use a separate deterministic RNG for duplicates
@@ -313,9 +507,11 @@ impl TaskGenerator for DynamicGenerator {
                     target_dir: file,
                     file_objs: $file_specs,
                     num_dirs,
+                    dir_offset: 0,
                     file_offset: 0,
                     file_contents: $file_contents,
                     audit_trail: $audit_trail.clone(),
+                    settings: settings.clone(),
                     task_index,
                 }
             }};
@@ -323,7 +519,14 @@ impl TaskGenerator for DynamicGenerator {
 
         if let Some(GeneratorBytes {
             num_bytes_distr,
-            fill_byte,
+            ref fill_pattern,
+            content,
+            direct_io,
+            writeback_bytes,
+            fragment,
+            holes,
+            preallocate,
+            ref headers,
         }) = *bytes
         {
             queue(
@@ -332,7 +535,14 @@ impl TaskGenerator for DynamicGenerator {
                     OnTheFlyGeneratedFileContents {
                         num_bytes_distr,
                         seed: rng_for_content.next_u64(),
-                        fill_byte,
+                        fill_pattern: fill_pattern.clone(),
+                        content,
+                        direct_io,
+                        writeback: writeback_bytes.map(Writeback::new),
+                        fragments: fragment.then(Fragmenter::default),
+                        holes,
+                        preallocate,
+                        headers: headers.clone(),
                     },
                     audit_trail
                 ),
@@ -348,7 +558,7 @@ impl TaskGenerator for DynamicGenerator {
         }
     }
 
-    fn maybe_queue_final_gen(&mut self, file: FastPathBuf, _: &mut Vec<Vec<u64>>) -> QueueResult {
+    fn maybe_queue_final_gen(&mut self, file: FastPathBuf, _: &mut ByteCountsPool) -> QueueResult {
         let Self {
             ref pending_duplicates,
             audit_trail: _,
@@ -370,6 +580,7 @@ impl TaskGenerator for DynamicGenerator {
             ref mut pending_duplicates,
             ref bytes,
             ref audit_trail,
+            ref settings,
             ref mut next_task_index,
             ref seed,
             ..
@@ -394,9 +605,11 @@ impl TaskGenerator for DynamicGenerator {
                     target_dir: file,
                     file_objs: $file_specs,
                     num_dirs: 0,
+                    dir_offset: 0,
                     file_offset: 0,
                     file_contents: $file_contents,
                     audit_trail: $audit_trail.clone(),
+                    settings: settings.clone(),
                     task_index,
                 }
             }};
@@ -404,7 +617,14 @@ impl TaskGenerator for DynamicGenerator {
 
         if let Some(GeneratorBytes {
             num_bytes_distr,
-            fill_byte,
+            ref fill_pattern,
+            content,
+            direct_io,
+            writeback_bytes,
+            fragment,
+            holes,
+            preallocate,
+            ref headers,
         }) = *bytes
         {
             queue(
@@ -413,7 +633,14 @@ impl TaskGenerator for DynamicGenerator {
                     OnTheFlyGeneratedFileContents {
                         num_bytes_distr,
                         seed: rng_for_content.next_u64(),
-                        fill_byte,
+                        fill_pattern: fill_pattern.clone(),
+                        content,
+                        direct_io,
+                        writeback: writeback_bytes.map(Writeback::new),
+                        fragments: fragment.then(Fragmenter::default),
+                        holes,
+                        preallocate,
+                        headers: headers.clone(),
                     },
                     audit_trail
                 ),
@@ -434,18 +661,34 @@ pub struct StaticGenerator {
     pub seed: u64,
     pub files_exact: Option<u64>,
     pub bytes_exact: Option<u64>,
+    pub dirs_exact: Option<u64>,
+    pub leftover_strategy: LeftoverStrategy,
+    /// Whether files may only be placed in the directories at the maximum
+    /// depth, which the remainder of an exact run must respect as well.
+    pub files_at_leaves: bool,
     pub duplicate_percentage: f64,
     pub max_duplicates_per_file: std::num::NonZeroUsize,
     pub audit_trail: Option<Arc<AuditTrail>>,
+    pub settings: Arc<TaskSettings>,
     pub done: bool,
-    pub root_num_files_hack: Option<u64>,
+    /// A uniform sample of the directories generated so far along with the
+    /// number of files each already holds. Whatever is left of the exact
+    /// counts once the tree is exhausted is spread across them.
+    pub remainder_dirs: Vec<(FastPathBuf, u64)>,
+    pub dirs_seen: u64,
+    /// A uniform sample of the directories generated so far that may hold
+    /// subdirectories along with the number each already holds. Whatever is
+    /// left of an exact directory count once the tree is exhausted is spread
+    /// across them.
+    pub branch_dirs: Vec<(FastPathBuf, usize)>,
+    pub branch_dirs_seen: u64,
 
     // We keep DynamicGenerator's distributions for directory generation and probabilistic file
     // sizes
-    pub num_dirs_distr: Normal<f64>,
+    pub num_dirs_distr: FanoutDistr,
     pub bytes: Option<GeneratorBytes>,
     pub pending_duplicates: Vec<PendingDuplicate>,
-    pub permissions: Vec<u32>,
+    pub permissions: PermissionTable,
     pub next_task_index: u64,
 }
 
@@ -454,6 +697,9 @@ impl StaticGenerator {
         dynamic: DynamicGenerator,
         files_exact: Option<NonZeroU64>,
         bytes_exact: Option<NonZeroU64>,
+        dirs_exact: Option<NonZeroU64>,
+        leftover_strategy: LeftoverStrategy,
+        files_at_leaves: bool,
     ) -> Self {
         let DynamicGenerator {
             num_dirs_distr,
@@ -462,20 +708,28 @@ impl StaticGenerator {
             duplicate_percentage,
             max_duplicates_per_file,
             audit_trail,
+            settings,
             pending_duplicates,
             permissions,
             next_task_index,
         } = dynamic;
-        debug_assert!(files_exact.is_some() || bytes_exact.is_some());
+        debug_assert!(files_exact.is_some() || bytes_exact.is_some() || dirs_exact.is_some());
         Self {
             seed,
             files_exact: files_exact.map(NonZeroU64::get),
             bytes_exact: bytes_exact.map(NonZeroU64::get),
+            dirs_exact: dirs_exact.map(NonZeroU64::get),
+            leftover_strategy,
+            files_at_leaves,
             duplicate_percentage,
             max_duplicates_per_file,
             audit_trail,
+            settings,
             done: false,
-            root_num_files_hack: None,
+            remainder_dirs: Vec::new(),
+            dirs_seen: 0,
+            branch_dirs: Vec::new(),
+            branch_dirs_seen: 0,
             num_dirs_distr,
             bytes,
             pending_duplicates,
@@ -494,16 +748,24 @@ impl StaticGenerator {
         num_files: u64,
         num_dirs: usize,
         offset: u64,
-        byte_counts_pool: &mut Vec<Vec<u64>>,
+        dir_offset: usize,
+        byte_counts_pool: &mut ByteCountsPool,
     ) -> QueueResult {
         let Self {
-            files_exact: _,
+            files_exact,
             ref mut bytes_exact,
+            dirs_exact: _,
+            leftover_strategy,
+            files_at_leaves: _,
             duplicate_percentage,
             max_duplicates_per_file,
             ref audit_trail,
+            ref settings,
             done,
-            root_num_files_hack: _,
+            remainder_dirs: _,
+            dirs_seen: _,
+            branch_dirs: _,
+            branch_dirs_seen: _,
             num_dirs_distr: _,
             bytes: ref bytes_opt,
             ref mut pending_duplicates,
@@ -514,23 +776,33 @@ impl StaticGenerator {
 
         let task_index = *next_task_index;
         *next_task_index += 1;
+        // The files of an exact run may run out before its directories do
+        let last_files = done || files_exact == Some(0);
 
         let mut deterministic_rng = Xoshiro256PlusPlus::seed_from_u64(seed ^ task_index);
         let mut rng_for_content = Xoshiro256PlusPlus::seed_from_u64(seed ^ task_index ^ 0xABCD1234);
         let mut rng_for_counts = Xoshiro256PlusPlus::seed_from_u64(seed ^ task_index ^ 0x55555555);
+        let permissions = settings.permissions_for(&file, permissions);
         let mut file_specs = generate_primary_specs(num_files, &mut deterministic_rng, permissions);
         let mut dup_rng = deterministic_rng;
 
         if let Some(GeneratorBytes {
             num_bytes_distr,
-            fill_byte,
+            ref fill_pattern,
+            content,
+            direct_io,
+            writeback_bytes,
+            fragment,
+            holes,
+            preallocate,
+            ref headers,
         }) = *bytes_opt
         {
             // We have bytes config. We might have duplicates.
 
             if let Some(bytes) = bytes_exact {
                 // Exact bytes mode: we need to track byte counts for every file (primary + dup)
-                let mut byte_counts: Vec<u64> = byte_counts_pool.pop().unwrap_or_default();
+                let mut byte_counts: Vec<u64> = byte_counts_pool.take();
                 debug_assert!(byte_counts.is_empty());
 
                 // 1. Handle Primary Files
@@ -543,10 +815,7 @@ impl StaticGenerator {
                         .0;
 
                     for count in raw_byte_counts {
-                        let num_bytes = min(
-                            *bytes,
-                            sample_truncated(&num_bytes_distr, &mut rng_for_counts),
-                        );
+                        let num_bytes = min(*bytes, num_bytes_distr.sample(&mut rng_for_counts));
                         *bytes -= num_bytes;
                         count.write(num_bytes);
                     }
@@ -555,18 +824,8 @@ impl StaticGenerator {
                         byte_counts.set_len(num_files_usize);
                     }
 
-                    if done {
-                        // Spread leftovers if done
-                        let base = *bytes / num_files;
-                        let mut leftovers = *bytes % num_files;
-                        for count in &mut byte_counts {
-                            if leftovers > 0 {
-                                *count += base + 1;
-                                leftovers -= 1;
-                            } else {
-                                *count += base;
-                            }
-                        }
+                    if last_files {
+                        add_leftovers(&mut byte_counts, *bytes, leftover_strategy);
                     }
                 }
 
@@ -584,7 +843,7 @@ impl StaticGenerator {
                 }
 
                 // 3. Inject PENDING duplicates
-                let limit = if done {
+                let limit = if last_files {
                     pending_duplicates.len()
                 } else {
                     (num_files / 2).max(1) as usize
@@ -623,9 +882,11 @@ impl StaticGenerator {
                             target_dir: file,
                             file_objs: $file_specs,
                             num_dirs,
+                            dir_offset,
                             file_offset: offset,
                             file_contents: $file_contents,
                             audit_trail: $audit_trail.clone(),
+                            settings: settings.clone(),
                             task_index,
                         }
                     }};
@@ -639,7 +900,14 @@ impl StaticGenerator {
                             PreDefinedGeneratedFileContents {
                                 byte_counts,
                                 seed: rng_for_content.next_u64(),
-                                fill_byte,
+                                fill_pattern: fill_pattern.clone(),
+                                content,
+                                direct_io,
+                                writeback: writeback_bytes.map(Writeback::new),
+                                fragments: fragment.then(Fragmenter::default),
+                                holes,
+                                preallocate,
+                                headers: headers.clone(),
                             },
                             audit_trail
                         ),
@@ -648,8 +916,7 @@ impl StaticGenerator {
                     )
                 } else {
                     // Recycled byte_counts since unused
-                    byte_counts.clear();
-                    byte_counts_pool.push(byte_counts);
+                    byte_counts_pool.recycle(byte_counts);
                     queue(
                         build_params!(file_specs, NoGeneratedFileContents, audit_trail),
                         done,
@@ -665,9 +932,11 @@ impl StaticGenerator {
                             target_dir: file,
                             file_objs: $file_specs,
                             num_dirs,
+                            dir_offset,
                             file_offset: offset,
                             file_contents: $file_contents,
                             audit_trail: $audit_trail.clone(),
+                            settings: settings.clone(),
                             task_index,
                         }
                     }};
@@ -687,7 +956,7 @@ impl StaticGenerator {
                 }
 
                 // 3. Inject PENDING duplicates
-                let limit = if done {
+                let limit = if last_files {
                     pending_duplicates.len()
                 } else {
                     (num_files / 2).max(1) as usize
@@ -705,7 +974,14 @@ impl StaticGenerator {
                         OnTheFlyGeneratedFileContents {
                             num_bytes_distr,
                             seed: rng_for_content.next_u64(),
-                            fill_byte,
+                            fill_pattern: fill_pattern.clone(),
+                            content,
+                            direct_io,
+                            writeback: writeback_bytes.map(Writeback::new),
+                            fragments: fragment.then(Fragmenter::default),
+                            holes,
+                            preallocate,
+                            headers: headers.clone(),
                         },
                         audit_trail
                     ),
@@ -721,9 +997,11 @@ impl StaticGenerator {
                         target_dir: file,
                         file_objs: $file_specs,
                         num_dirs,
+                        dir_offset,
                         file_offset: offset,
                         file_contents: $file_contents,
                         audit_trail: $audit_trail.clone(),
+                        settings: settings.clone(),
                         task_index,
                     }
                 }};
@@ -738,26 +1016,107 @@ impl StaticGenerator {
     }
 }
 
+impl StaticGenerator {
+    /// Reservoir samples `dir` into the directories the remainder of an exact
+    /// run may be spread across.
+    fn sample_remainder_dir(&mut self, dir: FastPathBuf, num_files: u64) {
+        self.dirs_seen += 1;
+        if self.remainder_dirs.len() < MAX_REMAINDER_DIRS {
+            self.remainder_dirs.push((dir, num_files));
+            return;
+        }
+
+        let mut random = Xoshiro256PlusPlus::seed_from_u64(
+            self.seed ^ self.dirs_seen.wrapping_mul(0x2545_F491_4F6C_DD1D),
+        );
+        if let Ok(i) = usize::try_from(random.random_range(0..self.dirs_seen))
+            && i < MAX_REMAINDER_DIRS
+        {
+            self.remainder_dirs[i] = (dir, num_files);
+        }
+    }
+
+    /// Reservoir samples `dir` into the directories the remainder of an exact
+    /// directory count may be spread across.
+    fn sample_branch_dir(&mut self, dir: FastPathBuf, num_dirs: usize) {
+        self.branch_dirs_seen += 1;
+        if self.branch_dirs.len() < MAX_REMAINDER_DIRS {
+            self.branch_dirs.push((dir, num_dirs));
+            return;
+        }
+
+        let mut random = Xoshiro256PlusPlus::seed_from_u64(
+            self.seed ^ self.branch_dirs_seen.wrapping_mul(0x9E6C_63D0_676A_9A99),
+        );
+        if let Ok(i) = usize::try_from(random.random_range(0..self.branch_dirs_seen))
+            && i < MAX_REMAINDER_DIRS
+        {
+            self.branch_dirs[i] = (dir, num_dirs);
+        }
+    }
+}
+
+/// Folds the tasks of `next` into `outcome` so the remainder of an exact run
+/// is reported as a single outcome.
+fn merge_outcomes(outcome: &mut Option<QueueOutcome>, next: QueueOutcome) {
+    let QueueOutcome {
+        task,
+        mut chunks,
+        num_files,
+        num_dirs,
+        bytes,
+        done: _,
+    } = next;
+    match outcome {
+        Some(outcome) => {
+            outcome.chunks.push((task, bytes));
+            outcome.chunks.append(&mut chunks);
+            outcome.num_files += num_files;
+            outcome.num_dirs += num_dirs;
+        }
+        None => {
+            *outcome = Some(QueueOutcome {
+                task,
+                chunks,
+                num_files,
+                num_dirs,
+                bytes,
+                done: true,
+            });
+        }
+    }
+}
+
 impl TaskGenerator for StaticGenerator {
     #[cfg_attr(
         feature = "tracing",
         tracing::instrument(level = "trace", skip(self, byte_counts_pool))
     )]
-        fn queue_gen(
+    fn queue_gen(
         &mut self,
-        num_files_distr: &Normal<f64>,
+        num_files_distr: &FilesPerDirDistr,
+        level_dirs_distr: Option<&FanoutDistr>,
         file: FastPathBuf,
         gen_dirs: bool,
-        byte_counts_pool: &mut Vec<Vec<u64>>,
+        min_dirs: usize,
+        weight: f64,
+        byte_counts_pool: &mut ByteCountsPool,
     ) -> QueueResult {
         let Self {
             ref mut files_exact,
             bytes_exact: _,
+            ref mut dirs_exact,
+            leftover_strategy: _,
+            files_at_leaves: _,
             duplicate_percentage: _,
             max_duplicates_per_file: _,
             audit_trail: _,
+            settings: _,
             ref mut done,
-            ref mut root_num_files_hack,
+            remainder_dirs: _,
+            dirs_seen: _,
+            branch_dirs: _,
+            branch_dirs_seen: _,
             ref num_dirs_distr,
             bytes: _,
             pending_duplicates: _,
@@ -772,63 +1131,122 @@ impl TaskGenerator for StaticGenerator {
         debug_assert!(!*done);
 
         let mut rng_for_counts = Xoshiro256PlusPlus::seed_from_u64(self.seed ^ task_index);
-        let mut num_files = sample_truncated(num_files_distr, &mut rng_for_counts);
+        let mut num_files = weighted(
+            num_files_distr.sample(&mut rng_for_counts),
+            weight,
+            &mut rng_for_counts,
+        );
+        let mut files_done = false;
         if let Some(files) = files_exact {
             if num_files >= *files {
-                *done = true;
+                files_done = true;
                 num_files = *files;
+                *files = 0;
             } else {
                 *files -= num_files;
             }
         }
 
-        if root_num_files_hack.is_none() {
-            *root_num_files_hack = Some(num_files);
-        }
-
-        let num_dirs = if *done {
+        let mut num_dirs = if files_done && dirs_exact.is_none() {
             0
         } else {
-            dirs_to_gen(num_files, gen_dirs, num_dirs_distr, &mut rng_for_counts)
+            dirs_to_gen(
+                num_files,
+                gen_dirs,
+                min_dirs,
+                weight,
+                level_dirs_distr.unwrap_or(num_dirs_distr),
+                &mut rng_for_counts,
+            )
         };
-        self.queue_gen_internal(file, num_files, num_dirs, 0, byte_counts_pool)
+        if let Some(dirs) = dirs_exact {
+            num_dirs = num_dirs.min(usize::try_from(*dirs).unwrap_or(usize::MAX));
+            *dirs -= num_dirs as u64;
+        }
+        *done = files_done && dirs_exact.is_none_or(|dirs| dirs == 0);
+
+        let dir = file.clone();
+        let branch = (gen_dirs && self.dirs_exact.is_some()).then(|| dir.clone());
+        let result = self.queue_gen_internal(file, num_files, num_dirs, 0, 0, byte_counts_pool);
+        if !(gen_dirs && self.files_at_leaves) {
+            self.sample_remainder_dir(dir, result.as_ref().map_or(0, |outcome| outcome.num_files));
+        }
+        if let Some(branch) = branch {
+            self.sample_branch_dir(branch, num_dirs);
+        }
+        result
     }
 
     fn maybe_queue_final_gen(
         &mut self,
         file: FastPathBuf,
-        byte_counts_pool: &mut Vec<Vec<u64>>,
+        byte_counts_pool: &mut ByteCountsPool,
     ) -> QueueResult {
         if self.done {
             return Err(QueueErrors::NothingToDo(file));
         }
         self.done = true;
 
-        // We set done = true, so calling queue_gen_internal below will trigger the
-        // flush logic because we passed `done` (which is now true) to it?
-        // Wait, `queue_gen_internal` reads `done` from `self.done`.
-        // We modified `self.done` above.
-        // So yes, it will see true.
-
-        if let Some(files) = self.files_exact {
-            self.queue_gen_internal(
-                file,
-                files,
-                0,
-                self.root_num_files_hack.unwrap_or(0),
-                byte_counts_pool,
-            )
-        } else if matches!(self.bytes_exact, Some(b) if b > 0) {
-            self.queue_gen_internal(
-                file,
-                1,
-                0,
-                self.root_num_files_hack.unwrap_or(0),
-                byte_counts_pool,
-            )
+        let mut dirs = mem::take(&mut self.remainder_dirs);
+        if dirs.is_empty() {
+            dirs.push((file.clone(), 0));
+        }
+        let num_files = if let Some(files) = self.files_exact {
+            files
+        } else if let Some(bytes) = self.bytes_exact
+            && bytes > 0
+        {
+            // Add about as many files as the leftover bytes would have filled
+            let mean = self
+                .bytes
+                .as_ref()
+                .map_or(1., |bytes| bytes.num_bytes_distr.mean().max(1.));
+            ((bytes as f64 / mean).ceil() as u64).clamp(1, dirs.len() as u64)
         } else {
-            Err(QueueErrors::NothingToDo(file))
+            0
+        };
+
+        // Hand out the remainder round-robin so no directory gets more than one
+        // file over any other
+        let num_dirs = dirs.len() as u64;
+        let mut bytes_left = self.bytes_exact;
+        let mut outcome: Option<QueueOutcome> = None;
+        for (i, (dir, offset)) in (0..num_dirs).zip(dirs) {
+            let files = num_files / num_dirs + u64::from(i < num_files % num_dirs);
+            if files == 0 {
+                break;
+            }
+            let files_left = num_files - i * (num_files / num_dirs) - i.min(num_files % num_dirs);
+            if let Some(bytes) = &mut bytes_left {
+                let share =
+                    u64::try_from(u128::from(*bytes) * u128::from(files) / u128::from(files_left))
+                        .unwrap_or(*bytes);
+                *bytes -= share;
+                self.bytes_exact = Some(share);
+            }
+
+            if let Ok(next) = self.queue_gen_internal(dir, files, 0, offset, 0, byte_counts_pool) {
+                merge_outcomes(&mut outcome, next);
+            }
+        }
+        self.bytes_exact = bytes_left;
+
+        // Directories the tree ran out of room for are handed out the same way
+        let num_dirs_left = self.dirs_exact.take().unwrap_or(0);
+        let branches = mem::take(&mut self.branch_dirs);
+        let num_branches = branches.len() as u64;
+        for (i, (dir, offset)) in (0..num_branches).zip(branches) {
+            let dirs = num_dirs_left / num_branches + u64::from(i < num_dirs_left % num_branches);
+            if dirs == 0 {
+                break;
+            }
+            let dirs = usize::try_from(dirs).unwrap_or(usize::MAX);
+            if let Ok(next) = self.queue_gen_internal(dir, 0, dirs, 0, offset, byte_counts_pool) {
+                merge_outcomes(&mut outcome, next);
+            }
         }
+
+        outcome.ok_or(QueueErrors::NothingToDo(file))
     }
 
     fn uses_byte_counts_pool(&self) -> bool {
@@ -837,3 +1255,42 @@ impl TaskGenerator for StaticGenerator {
         matches!(bytes_exact, Some(b) if b > 0)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn byte_counts_pool_is_bounded() {
+        let mut pool = ByteCountsPool::with_capacity(1);
+        pool.recycle(vec![1; 3]);
+        pool.recycle(vec![1; 3]);
+
+        assert_eq!(pool.peak_retained_bytes(), 3 * size_of::<u64>());
+        assert!(pool.take().is_empty());
+        assert_eq!(pool.take().capacity(), 0);
+    }
+
+    #[test]
+    fn byte_counts_pool_shrinks_oversized_buffers() {
+        let mut pool = ByteCountsPool::with_capacity(1);
+        let mut buffer = Vec::with_capacity(1024);
+        buffer.push(1);
+        pool.recycle(buffer);
+
+        assert!(pool.take().capacity() < 1024);
+    }
+
+    #[test]
+    fn leftovers_follow_strategy() {
+        let leftovers = |strategy| {
+            let mut byte_counts = [3, 7, 1, 7];
+            add_leftovers(&mut byte_counts, 6, strategy);
+            byte_counts
+        };
+
+        assert_eq!(leftovers(LeftoverStrategy::Spread), [5, 9, 2, 8]);
+        assert_eq!(leftovers(LeftoverStrategy::LastFile), [3, 7, 1, 13]);
+        assert_eq!(leftovers(LeftoverStrategy::LargestFile), [3, 13, 1, 7]);
+    }
+}
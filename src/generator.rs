@@ -7,26 +7,47 @@
 use std::{
     cmp::max,
     fmt::Write,
-    fs::create_dir_all,
+    fs::{self, create_dir_all},
     hash::{DefaultHasher, Hash, Hasher},
-    io,
-    num::{NonZeroU64, NonZeroUsize},
-    path::PathBuf,
+    io, mem,
+    num::{NonZeroU8, NonZeroU32, NonZeroU64, NonZeroUsize},
+    path::{Path, PathBuf},
     process::ExitCode,
-    sync::Arc,
+    str::FromStr,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicU64, Ordering},
+    },
     thread,
+    time::{Instant, SystemTime},
 };
 
 use bon::Builder;
 use bytesize::ByteSize;
 use error_stack::{Report, Result, ResultExt};
+use glob::Pattern;
 use log::{Level, log};
+use rand::distr::Uniform;
+use rand_distr::{LogNormal, Normal, Pareto, Poisson, Zipf};
 use thiserror::Error;
 use thousands::Separable;
+use twox_hash::XxHash64;
 
 use crate::core::{
-    DynamicGenerator, GeneratorBytes, GeneratorStats, StaticGenerator, audit::AuditTrail, run,
-    truncatable_normal,
+    DirNames, DistributionReport, DuplicateLinks, DynamicGenerator, ExtensionTable, FanoutDistr,
+    FileHeaders, FilesPerDirDistr, FitTest, GeneratorBytes, GeneratorStats, HardlinkPool,
+    LevelSettings, PermissionTable, SchedulerSettings, SchedulerStats, SizeDistr, StaticGenerator,
+    TaskSettings, WriteBufferPool, append_to_files,
+    audit::{AuditTrail, EntryType, SqliteSettings, timestamp},
+    check_distributions, link_loops, modify_files, read_back_files, recount, run, stamp_tree,
+    truncatable_normal, truncated_normal_cdf,
+};
+pub use crate::{
+    core::{
+        ContentMode, FillPattern, Holes, LeftoverStrategy, LinkFlavor, NameStyle, TraversalOrder,
+        audit::AuditFormat,
+    },
+    utils::{NameTemplate, NameTemplateError},
 };
 
 #[derive(Error, Debug)]
@@ -39,6 +60,30 @@ pub enum Error {
     InvalidEnvironment,
     #[error("Failed to create the async runtime.")]
     RuntimeCreation,
+    #[error("The generated tree does not match what was reported as generated.")]
+    ValidationFailed,
+    #[error("Files read back differ from what was written.")]
+    ReadBackFailed,
+    #[error("Invalid glob pattern.")]
+    InvalidPattern,
+    #[error("Invalid distribution.")]
+    InvalidDistribution,
+    #[error("Invalid permission weights.")]
+    InvalidPermissionWeights,
+    #[error("Failed to check the generated distributions.")]
+    DistributionCheck,
+    #[error("Invalid audit trail.")]
+    InvalidAudit,
+    #[error("The tree does not match its audit trail.")]
+    VerificationFailed,
+    #[error("Invalid file listing.")]
+    InvalidListing,
+    #[error("Generated trees no longer match a supported compatibility level.")]
+    CompatMismatch,
+    #[error("No seed satisfying the constraints was found.")]
+    SeedNotFound,
+    #[error("Invalid arguments.")]
+    InvalidArgs,
 }
 
 #[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
@@ -88,135 +133,2830 @@ impl NumFilesWithRatio {
             file_to_dir_ratio: NonZeroU64::new(max(num_files.get() / 1000, 1)).unwrap(),
         }
     }
+
+    /// Splits a target number of total entries (files and directories) into
+    /// files and directories according to the file to directory ratio, or the
+    /// default ratio if none is specified.
+    ///
+    /// # Errors
+    ///
+    /// The file to directory ratio cannot be larger than the resulting number
+    /// of files.
+    pub fn from_num_entries(
+        num_entries: NonZeroU64,
+        file_to_dir_ratio: Option<NonZeroU64>,
+    ) -> std::result::Result<Self, NumFilesWithRatioError> {
+        let files_for_ratio = |ratio: u64| {
+            let files =
+                (u128::from(num_entries.get()) * u128::from(ratio)).div_ceil(u128::from(ratio) + 1);
+            NonZeroU64::new(u64::try_from(files).unwrap_or(u64::MAX).max(1)).unwrap()
+        };
+
+        if let Some(ratio) = file_to_dir_ratio {
+            return Self::new(files_for_ratio(ratio.get()), ratio);
+        }
+
+        // The default ratio depends on the number of files, so iterate until the
+        // split settles.
+        let mut num_files = num_entries;
+        for _ in 0..16 {
+            let next = files_for_ratio(Self::from_num_files(num_files).file_to_dir_ratio.get());
+            if next == num_files {
+                break;
+            }
+            num_files = next;
+        }
+        Ok(Self::from_num_files(num_files))
+    }
+
+    #[must_use]
+    pub const fn num_files(&self) -> NonZeroU64 {
+        self.num_files
+    }
 }
 
-#[derive(Debug, Builder)]
-pub struct Generator {
-    #[builder(into)]
-    root_dir: PathBuf,
-    num_files_with_ratio: NumFilesWithRatio,
-    #[builder(default = false)]
-    files_exact: bool,
-    #[builder(default = 0)]
-    num_bytes: u64,
-    fill_byte: Option<u8>,
-    #[builder(default = false)]
-    bytes_exact: bool,
-    #[builder(default = 5)]
-    max_depth: u32,
-    #[builder(default = 0)]
-    seed: u64,
-    pub duplicate_percentage: Option<f64>,
-    pub max_duplicates_per_file: Option<NonZeroUsize>,
-    pub audit_output: Option<PathBuf>,
-    #[builder(default)]
+/// The distribution the number of subdirectories in each directory is drawn
+/// from.
+///
+/// Parsed from `normal:MEAN[,STD_DEV]`, `uniform:MIN,MAX`, or `poisson:MEAN`.
+/// The standard deviation of the normal distribution defaults to a third of
+/// its mean.
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum DirFanout {
+    /// A normal distribution truncated to `[0, 2 * mean]`.
+    Normal { mean: f64, std_dev: f64 },
+    /// Every count from `min` to `max` (inclusive) is equally likely.
+    Uniform { min: u64, max: u64 },
+    /// A Poisson distribution.
+    Poisson { mean: f64 },
+}
+
+#[derive(Error, Debug)]
+#[error(
+    "Invalid directory fan-out {0:?}: expected normal:MEAN[,STD_DEV], uniform:MIN,MAX, or \
+     poisson:MEAN with a positive mean."
+)]
+pub struct DirFanoutError(String);
+
+impl DirFanout {
+    /// The default fan-out shape centered on `dirs_per_dir`.
+    #[must_use]
+    pub fn from_ratio(dirs_per_dir: NonZeroU64) -> Self {
+        let mean = dirs_per_dir.get() as f64;
+        Self::Normal {
+            mean,
+            std_dev: mean / 3.,
+        }
+    }
+
+    #[must_use]
+    pub fn mean(&self) -> f64 {
+        match *self {
+            Self::Normal { mean, .. } | Self::Poisson { mean } => mean,
+            Self::Uniform { min, max } => (min + max) as f64 / 2.,
+        }
+    }
+
+    /// The probability of a directory getting exactly `k` subdirectories.
+    fn probability(&self, k: u64) -> f64 {
+        match *self {
+            Self::Normal { mean, std_dev } => {
+                let Ok(normal) = Normal::new(mean + 0.5, std_dev) else {
+                    return 0.;
+                };
+                truncated_normal_cdf(&normal, k as f64 + 1.)
+                    - truncated_normal_cdf(&normal, k as f64)
+            }
+            Self::Uniform { min, max } => {
+                if (min..=max).contains(&k) {
+                    1. / (max - min + 1) as f64
+                } else {
+                    0.
+                }
+            }
+            Self::Poisson { mean } => (1..=k).fold((-mean).exp(), |p, i| p * mean / i as f64),
+        }
+    }
+
+    fn distr(&self) -> std::result::Result<FanoutDistr, DirFanoutError> {
+        let invalid = || DirFanoutError(format!("{self:?}"));
+        if !(self.mean().is_finite() && self.mean() > 0.) {
+            return Err(invalid());
+        }
+        match *self {
+            Self::Normal { mean, std_dev } => Normal::new(mean + 0.5, std_dev)
+                .map(FanoutDistr::Normal)
+                .map_err(|_| invalid()),
+            Self::Uniform { min, max } => Uniform::new_inclusive(min, max)
+                .map(FanoutDistr::Uniform)
+                .map_err(|_| invalid()),
+            Self::Poisson { mean } => Poisson::new(mean)
+                .map(FanoutDistr::Poisson)
+                .map_err(|_| invalid()),
+        }
+    }
+}
+
+impl FromStr for DirFanout {
+    type Err = DirFanoutError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let invalid = || DirFanoutError(s.to_string());
+        let (family, params) = s.split_once(':').ok_or_else(invalid)?;
+        let params = params.split(',').map(str::trim).collect::<Vec<_>>();
+
+        let fanout = match (family.trim(), params.as_slice()) {
+            ("normal", [mean]) => {
+                let mean = mean.parse().map_err(|_| invalid())?;
+                Self::Normal {
+                    mean,
+                    std_dev: mean / 3.,
+                }
+            }
+            ("normal", [mean, std_dev]) => Self::Normal {
+                mean: mean.parse().map_err(|_| invalid())?,
+                std_dev: std_dev.parse().map_err(|_| invalid())?,
+            },
+            ("uniform", [min, max]) => Self::Uniform {
+                min: min.parse().map_err(|_| invalid())?,
+                max: max.parse().map_err(|_| invalid())?,
+            },
+            ("poisson", [mean]) => Self::Poisson {
+                mean: mean.parse().map_err(|_| invalid())?,
+            },
+            _ => return Err(invalid()),
+        };
+        fanout.distr().map_err(|_| invalid())?;
+        Ok(fanout)
+    }
+}
+
+impl std::fmt::Display for DirFanout {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            Self::Normal { mean, std_dev } => write!(f, "normal:{mean},{std_dev}"),
+            Self::Uniform { min, max } => write!(f, "uniform:{min},{max}"),
+            Self::Poisson { mean } => write!(f, "poisson:{mean}"),
+        }
+    }
+}
+
+/// Settings overriding the run-wide ones for the directories at one depth of
+/// the tree, the root directory being at depth 0.
+///
+/// Parsed from `DEPTH:KEY=VALUE[;KEY=VALUE]` where the keys are `ftd-ratio`
+/// for the number of files per directory and `dir-fanout` for the
+/// distribution the number of subdirectories is drawn from, e.g.
+/// `0:dir-fanout=uniform:20,40` or `2:ftd-ratio=500;dir-fanout=normal:2`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LevelOverride {
+    pub depth: u32,
+    pub ftd_ratio: Option<NonZeroU64>,
+    pub dir_fanout: Option<DirFanout>,
+}
+
+#[derive(Error, Debug)]
+#[error(
+    "Invalid level override {0:?}: expected DEPTH:KEY=VALUE[;KEY=VALUE] with keys ftd-ratio or \
+     dir-fanout."
+)]
+pub struct LevelOverrideError(String);
+
+impl FromStr for LevelOverride {
+    type Err = LevelOverrideError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let invalid = || LevelOverrideError(s.to_string());
+        let (depth, settings) = s.split_once(':').ok_or_else(invalid)?;
+        let mut level = Self {
+            depth: depth.trim().parse().map_err(|_| invalid())?,
+            ftd_ratio: None,
+            dir_fanout: None,
+        };
+        for setting in settings.split(';') {
+            let (key, value) = setting.split_once('=').ok_or_else(invalid)?;
+            match key.trim() {
+                "ftd-ratio" if level.ftd_ratio.is_none() => {
+                    level.ftd_ratio = Some(value.trim().parse().map_err(|_| invalid())?);
+                }
+                "dir-fanout" if level.dir_fanout.is_none() => {
+                    level.dir_fanout = Some(value.trim().parse().map_err(|_| invalid())?);
+                }
+                _ => return Err(invalid()),
+            }
+        }
+        Ok(level)
+    }
+}
+
+impl std::fmt::Display for LevelOverride {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:", self.depth)?;
+        let settings = self
+            .ftd_ratio
+            .map(|ratio| format!("ftd-ratio={ratio}"))
+            .into_iter()
+            .chain(self.dir_fanout.map(|fanout| format!("dir-fanout={fanout}")));
+        f.write_str(&settings.collect::<Vec<_>>().join(";"))
+    }
+}
+
+/// The distribution file sizes are drawn from, scaled so the mean file size
+/// is the one implied by the number of files and bytes.
+///
+/// Parsed from `normal`, `lognormal[:SIGMA]`, `pareto[:SHAPE]`,
+/// `zipf[:EXPONENT]`, or `uniform`. Heavy-tailed sizes are capped at a
+/// thousand times the mean.
+#[derive(Copy, Clone, PartialEq, Debug, Default)]
+pub enum SizeDistribution {
+    /// A normal distribution truncated to `[0, 2 * mean]`.
+    #[default]
+    Normal,
+    /// A log-normal distribution whose logarithm has a standard deviation of
+    /// `sigma`, 1 by default.
+    LogNormal { sigma: f64 },
+    /// A Pareto distribution with a `shape` above 1, 1.5 by default.
+    Pareto { shape: f64 },
+    /// Multiples of a unit size where the n-th multiple is drawn with a
+    /// probability proportional to `n^-exponent`, 1.2 by default.
+    Zipf { exponent: f64 },
+    /// Every size from zero to twice the mean is equally likely.
+    Uniform,
+}
+
+#[derive(Error, Debug)]
+#[error(
+    "Invalid size distribution {0:?}: expected normal, lognormal[:SIGMA], pareto[:SHAPE], \
+     zipf[:EXPONENT], or uniform with a positive parameter and a shape above 1."
+)]
+pub struct SizeDistributionError(String);
+
+/// The number of multiples of the unit size Zipf-distributed sizes pick from.
+const ZIPF_RANKS: u32 = 1000;
+
+/// The average multiple drawn from a Zipf distribution over [`ZIPF_RANKS`]
+/// multiples with the given `exponent`.
+fn zipf_mean_rank(exponent: f64) -> f64 {
+    let (weights, ranks) = (1..=ZIPF_RANKS)
+        .map(|n| f64::from(n).powf(-exponent))
+        .zip(1..)
+        .fold((0., 0.), |(weights, ranks), (weight, n)| {
+            (weights + weight, ranks + weight * f64::from(n))
+        });
+    ranks / weights
+}
+
+impl SizeDistribution {
+    fn distr(&self, mean: f64) -> std::result::Result<SizeDistr, SizeDistributionError> {
+        let invalid = || SizeDistributionError(format!("{self:?}"));
+        match *self {
+            Self::Normal => Ok(SizeDistr::Normal(truncatable_normal(mean))),
+            Self::LogNormal { sigma } => LogNormal::new(mean.ln() - sigma * sigma / 2., sigma)
+                .map(|distr| SizeDistr::LogNormal { distr, mean })
+                .map_err(|_| invalid()),
+            Self::Pareto { shape } if shape > 1. => Pareto::new(mean * (shape - 1.) / shape, shape)
+                .map(|distr| SizeDistr::Pareto { distr, mean })
+                .map_err(|_| invalid()),
+            Self::Zipf { exponent } if exponent > 0. => Zipf::new(f64::from(ZIPF_RANKS), exponent)
+                .map(|distr| SizeDistr::Zipf {
+                    distr,
+                    unit: mean / zipf_mean_rank(exponent),
+                    mean,
+                })
+                .map_err(|_| invalid()),
+            Self::Uniform => Uniform::new_inclusive(0, (2. * mean) as u64)
+                .map(|distr| SizeDistr::Uniform { distr, mean })
+                .map_err(|_| invalid()),
+            Self::Pareto { .. } | Self::Zipf { .. } => Err(invalid()),
+        }
+    }
+}
+
+impl FromStr for SizeDistribution {
+    type Err = SizeDistributionError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let invalid = || SizeDistributionError(s.to_string());
+        let (family, param) = s
+            .split_once(':')
+            .map_or((s, None), |(family, param)| (family, Some(param.trim())));
+        let parse = |default: f64| {
+            param.map_or(Ok(default), |param| {
+                param
+                    .parse::<f64>()
+                    .ok()
+                    .filter(|param| param.is_finite() && *param > 0.)
+                    .ok_or_else(invalid)
+            })
+        };
+
+        let distribution = match family.trim() {
+            "normal" if param.is_none() => Self::Normal,
+            "lognormal" => Self::LogNormal { sigma: parse(1.)? },
+            "pareto" => Self::Pareto { shape: parse(1.5)? },
+            "zipf" => Self::Zipf {
+                exponent: parse(1.2)?,
+            },
+            "uniform" if param.is_none() => Self::Uniform,
+            _ => return Err(invalid()),
+        };
+        distribution.distr(1.).map_err(|_| invalid())?;
+        Ok(distribution)
+    }
+}
+
+impl std::fmt::Display for SizeDistribution {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            Self::Normal => f.write_str("normal"),
+            Self::LogNormal { sigma } => write!(f, "lognormal:{sigma}"),
+            Self::Pareto { shape } => write!(f, "pareto:{shape}"),
+            Self::Zipf { exponent } => write!(f, "zipf:{exponent}"),
+            Self::Uniform => f.write_str("uniform"),
+        }
+    }
+}
+
+/// The distribution the number of files in each directory is drawn from,
+/// scaled so the mean is the one implied by the number of files and
+/// directories.
+///
+/// Parsed from `normal` or `zipf[:EXPONENT]`.
+#[derive(Copy, Clone, PartialEq, Debug, Default)]
+pub enum FilesPerDirDistribution {
+    /// A normal distribution truncated to `[0, 2 * mean]`.
+    #[default]
+    Normal,
+    /// Multiples of a unit count where the n-th multiple is drawn with a
+    /// probability proportional to `n^-exponent`, 1.2 by default. Most
+    /// directories hold few files while a handful hold very many.
+    Zipf { exponent: f64 },
+}
+
+#[derive(Error, Debug)]
+#[error(
+    "Invalid files per directory distribution {0:?}: expected normal or zipf[:EXPONENT] with a \
+     positive exponent."
+)]
+pub struct FilesPerDirDistributionError(String);
+
+impl FilesPerDirDistribution {
+    fn distr(&self) -> std::result::Result<FilesPerDirDistr, FilesPerDirDistributionError> {
+        match *self {
+            Self::Normal => Ok(FilesPerDirDistr::Normal(truncatable_normal(1.))),
+            Self::Zipf { exponent } if exponent > 0. => Zipf::new(f64::from(ZIPF_RANKS), exponent)
+                .map(|distr| FilesPerDirDistr::Zipf {
+                    distr,
+                    unit: 1. / zipf_mean_rank(exponent),
+                    mean: 1.,
+                })
+                .map_err(|_| FilesPerDirDistributionError(format!("{self:?}"))),
+            Self::Zipf { .. } => Err(FilesPerDirDistributionError(format!("{self:?}"))),
+        }
+    }
+}
+
+impl FromStr for FilesPerDirDistribution {
+    type Err = FilesPerDirDistributionError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let invalid = || FilesPerDirDistributionError(s.to_string());
+        let distribution = match s.split_once(':') {
+            None if s.trim() == "normal" => Self::Normal,
+            None if s.trim() == "zipf" => Self::Zipf { exponent: 1.2 },
+            Some((family, exponent)) if family.trim() == "zipf" => Self::Zipf {
+                exponent: exponent.trim().parse().map_err(|_| invalid())?,
+            },
+            _ => return Err(invalid()),
+        };
+        distribution.distr().map_err(|_| invalid())?;
+        Ok(distribution)
+    }
+}
+
+impl std::fmt::Display for FilesPerDirDistribution {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match *self {
+            Self::Normal => f.write_str("normal"),
+            Self::Zipf { exponent } => write!(f, "zipf:{exponent}"),
+        }
+    }
+}
+
+/// An inclusive range of Unix timestamps, in seconds, parsed from
+/// `START..END`.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub struct TimeRange {
+    pub start: i64,
+    pub end: i64,
+}
+
+#[derive(Error, Debug)]
+#[error("Invalid time range {0:?}: expected START..END in seconds since the Unix epoch.")]
+pub struct TimeRangeError(String);
+
+impl TimeRange {
+    /// The time `hash` picks from the range.
+    #[must_use]
+    pub fn pick(&self, hash: u64) -> i64 {
+        let offset = (self.end.abs_diff(self.start))
+            .checked_add(1)
+            .map_or(hash, |len| hash % len);
+        self.start.wrapping_add_unsigned(offset)
+    }
+}
+
+impl FromStr for TimeRange {
+    type Err = TimeRangeError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let invalid = || TimeRangeError(s.to_string());
+        let (start, end) = s.split_once("..").ok_or_else(invalid)?;
+        let range = Self {
+            start: start.trim().parse().map_err(|_| invalid())?,
+            end: end.trim().parse().map_err(|_| invalid())?,
+        };
+        if range.start > range.end {
+            return Err(invalid());
+        }
+        Ok(range)
+    }
+}
+
+impl std::fmt::Display for TimeRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}..{}", self.start, self.end)
+    }
+}
+
+/// The longest name a path component can have on most file systems, in
+/// bytes.
+pub const NAME_MAX: usize = 255;
+
+/// An inclusive range of file name lengths, in bytes, parsed from
+/// `MIN..MAX`.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub struct NameLengthRange {
+    pub min: usize,
+    pub max: usize,
+}
+
+#[derive(Error, Debug)]
+#[error("Invalid name length range {0:?}: expected MIN..MAX between 1 and {NAME_MAX} bytes.")]
+pub struct NameLengthRangeError(String);
+
+impl NameLengthRange {
+    /// The length `hash` picks from the range.
+    #[must_use]
+    pub fn pick(&self, hash: u64) -> usize {
+        self.min + (hash % (self.max - self.min + 1) as u64) as usize
+    }
+}
+
+impl FromStr for NameLengthRange {
+    type Err = NameLengthRangeError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let invalid = || NameLengthRangeError(s.to_string());
+        let (min, max) = s.split_once("..").ok_or_else(invalid)?;
+        let range = Self {
+            min: min.trim().parse().map_err(|_| invalid())?,
+            max: max.trim().parse().map_err(|_| invalid())?,
+        };
+        if range.min == 0 || range.min > range.max || range.max > NAME_MAX {
+            return Err(invalid());
+        }
+        Ok(range)
+    }
+}
+
+impl std::fmt::Display for NameLengthRange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}..{}", self.min, self.max)
+    }
+}
+
+#[derive(Error, Debug)]
+#[error("Invalid audit format {0:?}: expected csv, sqlite, json, or jsonl.")]
+pub struct AuditFormatError(String);
+
+#[derive(Error, Debug)]
+#[error("Invalid traversal order {0:?}: expected depth-first (dfs) or breadth-first (bfs).")]
+pub struct TraversalOrderError(String);
+
+/// The largest mode permissions can be given: the permission bits along with
+/// the setuid, setgid, and sticky bits.
+pub const MAX_MODE: u32 = 0o7777;
+
+/// How configured permissions are applied to the files created with them.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub enum PermissionMode {
+    /// Files get exactly the configured mode, as if `chmod`ed.
+    #[default]
+    Absolute,
+    /// The process umask is stripped from the configured mode, as it would be
+    /// for files created by a regular application.
+    UmaskRelative,
+}
+
+#[derive(Error, Debug)]
+#[error("Invalid fill pattern {0:?}: expected 0x followed by pairs of hex digits or a string.")]
+pub struct FillPatternError(String);
+
+#[derive(Error, Debug)]
+#[error(
+    "Invalid hole pattern {0:?}: expected DATA:HOLE in whole 4 KiB blocks with a nonempty hole."
+)]
+pub struct HolePatternError(String);
+
+#[derive(Error, Debug)]
+#[error("Invalid link flavor {0:?}: expected hardlink, symlink, or junction.")]
+pub struct LinkFlavorError(String);
+
+#[derive(Error, Debug)]
+#[error("Invalid content mode {0:?}: expected random, text, json, csv, dedup, syslog, or jsonl.")]
+pub struct ContentModeError(String);
+
+#[derive(Error, Debug)]
+#[error("Invalid leftover strategy {0:?}: expected spread, last-file, or largest-file.")]
+pub struct LeftoverStrategyError(String);
+
+#[derive(Error, Debug)]
+#[error("Invalid name style {0:?}: expected numeric, realistic, or unicode.")]
+pub struct NameStyleError(String);
+
+#[derive(Error, Debug)]
+#[error("Invalid permission mode {0:?}: expected absolute or umask-relative.")]
+pub struct PermissionModeError(String);
+
+impl FromStr for PermissionMode {
+    type Err = PermissionModeError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.trim() {
+            "absolute" => Ok(Self::Absolute),
+            "umask-relative" | "umask" => Ok(Self::UmaskRelative),
+            _ => Err(PermissionModeError(s.to_string())),
+        }
+    }
+}
+
+impl std::fmt::Display for PermissionMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Absolute => "absolute",
+            Self::UmaskRelative => "umask-relative",
+        })
+    }
+}
+
+/// Permissions given to the files generated beneath directories matching a
+/// glob instead of the run-wide ones.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PermissionRule {
+    /// Matched against directory paths relative to the root directory, just
+    /// like prune globs.
+    pub glob: String,
     pub permissions: Vec<u32>,
+    /// How often each of the `permissions` is picked relative to the others.
+    /// Every permission is equally likely if empty.
+    pub permission_weights: Vec<u32>,
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+#[derive(Error, Debug)]
+#[error("Invalid permission rule {0:?}: expected GLOB=OCTAL[:WEIGHT][,OCTAL[:WEIGHT]...].")]
+pub struct PermissionRuleError(String);
 
-    #[test]
-    fn minimal_params_succeeds() {
-        let g = Generator::builder()
-            .root_dir(PathBuf::from("abc"))
-            .num_files_with_ratio(NumFilesWithRatio::from_num_files(
-                NonZeroU64::new(1).unwrap(),
-            ))
-            .build();
+impl FromStr for PermissionRule {
+    type Err = PermissionRuleError;
 
-        assert_eq!(g.root_dir, PathBuf::from("abc"));
-        assert_eq!(g.num_files_with_ratio.num_files.get(), 1);
-        assert!(!g.files_exact);
-        assert_eq!(g.num_bytes, 0);
-        assert!(!g.bytes_exact);
-        assert_eq!(g.max_depth, 5);
-        assert_eq!(g.num_files_with_ratio.file_to_dir_ratio.get(), 1);
-        assert_eq!(g.seed, 0);
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let err = || PermissionRuleError(s.to_string());
+        let (glob, permissions) = s.trim().split_once('=').ok_or_else(err)?;
+        let permissions = permissions
+            .split(',')
+            .map(|permission| {
+                let (mode, weight) = permission
+                    .split_once(':')
+                    .map_or((permission, None), |(mode, weight)| (mode, Some(weight)));
+                let mode = u32::from_str_radix(mode.trim(), 8)
+                    .ok()
+                    .filter(|&mode| mode <= MAX_MODE)?;
+                let weight = match weight {
+                    Some(weight) => Some(weight.trim().parse::<u32>().ok()?),
+                    None => None,
+                };
+                Some((mode, weight))
+            })
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(err)?;
+
+        let weighted = permissions.iter().any(|&(_, weight)| weight.is_some());
+        Ok(Self {
+            glob: glob.trim().to_string(),
+            permission_weights: if weighted {
+                permissions
+                    .iter()
+                    .map(|&(_, weight)| weight.unwrap_or(1))
+                    .collect()
+            } else {
+                Vec::new()
+            },
+            permissions: permissions.into_iter().map(|(mode, _)| mode).collect(),
+        })
     }
+}
 
-    #[test]
-    fn ratio_greater_than_num_files_fails() {
-        let r = NumFilesWithRatio::new(NonZeroU64::new(1).unwrap(), NonZeroU64::new(2).unwrap());
+impl std::fmt::Display for PermissionRule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}={}",
+            self.glob,
+            weighted_modes(&self.permissions, &self.permission_weights).join(",")
+        )
+    }
+}
 
-        r.unwrap_err();
+/// Permissions as the `OCTAL[:WEIGHT]` strings they are configured with.
+fn weighted_modes(permissions: &[u32], weights: &[u32]) -> Vec<String> {
+    permissions
+        .iter()
+        .enumerate()
+        .map(|(i, mode)| match weights.get(i) {
+            Some(weight) => format!("{mode:o}:{weight}"),
+            None => format!("{mode:o}"),
+        })
+        .collect()
+}
+
+/// File extensions, each weighted by how often it should be picked, parsed
+/// from `EXT[:WEIGHT][,EXT[:WEIGHT]...]`. Unweighted extensions have a weight
+/// of one.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExtensionWeights(pub Vec<(String, u32)>);
+
+#[derive(Error, Debug)]
+#[error("Invalid extensions {0:?}: expected EXT[:WEIGHT][,EXT[:WEIGHT]...] with a nonzero weight.")]
+pub struct ExtensionWeightsError(String);
+
+impl FromStr for ExtensionWeights {
+    type Err = ExtensionWeightsError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let err = || ExtensionWeightsError(s.to_string());
+        let extensions = s
+            .split(',')
+            .map(|extension| {
+                let (extension, weight) = extension
+                    .split_once(':')
+                    .map_or((extension, None), |(extension, weight)| {
+                        (extension, Some(weight))
+                    });
+                let extension = extension.trim();
+                let extension = extension.strip_prefix('.').unwrap_or(extension);
+                let valid = !extension.is_empty()
+                    && !extension.starts_with('.')
+                    && !extension.ends_with('.')
+                    && extension
+                        .chars()
+                        .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_'));
+                let weight = match weight {
+                    Some(weight) => weight.trim().parse().ok()?,
+                    None => 1,
+                };
+                valid.then(|| (extension.to_string(), weight))
+            })
+            .collect::<Option<Vec<_>>>()
+            .ok_or_else(err)?;
+        if extensions.iter().all(|&(_, weight)| weight == 0) {
+            return Err(err());
+        }
+        Ok(Self(extensions))
     }
 }
 
-impl Generator {
-    pub fn generate(self, output: &mut impl Write) -> Result<(), Error> {
-        let options = validated_options(self)?;
-        print_configuration_info(&options, output)?;
-        print_stats(run_generator(options)?, output);
-        Ok(())
+impl std::fmt::Display for ExtensionWeights {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let extensions = self
+            .0
+            .iter()
+            .map(|(extension, weight)| format!("{extension}:{weight}"))
+            .collect::<Vec<_>>();
+        f.write_str(&extensions.join(","))
+    }
+}
+
+impl FromStr for FillPattern {
+    type Err = FillPatternError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let invalid = || FillPatternError(s.to_string());
+        let bytes = if let Some(hex) = s.strip_prefix("0x") {
+            if hex.len() % 2 != 0 || !hex.is_ascii() {
+                return Err(invalid());
+            }
+            (0..hex.len())
+                .step_by(2)
+                .map(|i| u8::from_str_radix(&hex[i..i + 2], 16))
+                .collect::<std::result::Result<Vec<_>, _>>()
+                .map_err(|_| invalid())?
+        } else {
+            s.as_bytes().to_vec()
+        };
+        Self::new(bytes).ok_or_else(invalid)
+    }
+}
+
+impl std::fmt::Display for FillPattern {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let bytes = self.as_bytes();
+        match std::str::from_utf8(bytes) {
+            Ok(text) if !text.starts_with("0x") => f.write_str(text),
+            _ => {
+                f.write_str("0x")?;
+                bytes.iter().try_for_each(|byte| write!(f, "{byte:02x}"))
+            }
+        }
+    }
+}
+
+impl FromStr for Holes {
+    type Err = HolePatternError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        let size = |s: &str| s.trim().parse::<ByteSize>().ok().map(|size| size.as_u64());
+        s.split_once(':')
+            .and_then(|(data, hole)| Self::pattern(size(data)?, size(hole)?))
+            .ok_or_else(|| HolePatternError(s.to_string()))
+    }
+}
+
+impl std::fmt::Display for Holes {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (data, hole) = self.layout();
+        write!(f, "{data}:{hole}")
+    }
+}
+
+impl FromStr for LinkFlavor {
+    type Err = LinkFlavorError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.trim() {
+            "hardlink" => Ok(Self::Hardlink),
+            "symlink" => Ok(Self::Symlink),
+            "junction" => Ok(Self::Junction),
+            _ => Err(LinkFlavorError(s.to_string())),
+        }
+    }
+}
+
+impl std::fmt::Display for LinkFlavor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Hardlink => "hardlink",
+            Self::Symlink => "symlink",
+            Self::Junction => "junction",
+        })
+    }
+}
+
+impl FromStr for LeftoverStrategy {
+    type Err = LeftoverStrategyError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.trim() {
+            "spread" => Ok(Self::Spread),
+            "last-file" => Ok(Self::LastFile),
+            "largest-file" => Ok(Self::LargestFile),
+            _ => Err(LeftoverStrategyError(s.to_string())),
+        }
+    }
+}
+
+impl std::fmt::Display for LeftoverStrategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Spread => "spread",
+            Self::LastFile => "last-file",
+            Self::LargestFile => "largest-file",
+        })
+    }
+}
+
+impl FromStr for ContentMode {
+    type Err = ContentModeError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.trim() {
+            "random" => Ok(Self::Random),
+            "text" => Ok(Self::Text),
+            "json" => Ok(Self::Json),
+            "csv" => Ok(Self::Csv {
+                columns: DEFAULT_CSV_COLUMNS,
+            }),
+            "dedup" => Ok(Self::Dedup {
+                reuse_threshold: u64::MAX / 2,
+                blocks: DEFAULT_DEDUP_BLOCKS,
+            }),
+            "syslog" => Ok(Self::Syslog),
+            "jsonl" | "ndjson" => Ok(Self::JsonLines),
+            _ => Err(ContentModeError(s.to_string())),
+        }
+    }
+}
+
+impl std::fmt::Display for ContentMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Random => "random",
+            Self::Text => "text",
+            Self::Json => "json",
+            Self::Csv { .. } => "csv",
+            Self::Dedup { .. } => "dedup",
+            Self::Syslog => "syslog",
+            Self::JsonLines => "jsonl",
+        })
+    }
+}
+
+impl FromStr for NameStyle {
+    type Err = NameStyleError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.trim() {
+            "numeric" => Ok(Self::Numeric),
+            "realistic" => Ok(Self::Realistic),
+            "unicode" => Ok(Self::Unicode),
+            _ => Err(NameStyleError(s.to_string())),
+        }
+    }
+}
+
+impl std::fmt::Display for NameStyle {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Numeric => "numeric",
+            Self::Realistic => "realistic",
+            Self::Unicode => "unicode",
+        })
+    }
+}
+
+impl FromStr for AuditFormat {
+    type Err = AuditFormatError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.trim() {
+            "csv" => Ok(Self::Csv),
+            "sqlite" => Ok(Self::Sqlite),
+            "json" => Ok(Self::Json),
+            "jsonl" | "ndjson" => Ok(Self::Jsonl),
+            _ => Err(AuditFormatError(s.to_string())),
+        }
+    }
+}
+
+impl std::fmt::Display for AuditFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Csv => "csv",
+            Self::Sqlite => "sqlite",
+            Self::Json => "json",
+            Self::Jsonl => "jsonl",
+        })
+    }
+}
+
+impl FromStr for TraversalOrder {
+    type Err = TraversalOrderError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.trim() {
+            "depth-first" | "dfs" => Ok(Self::DepthFirst),
+            "breadth-first" | "bfs" => Ok(Self::BreadthFirst),
+            _ => Err(TraversalOrderError(s.to_string())),
+        }
+    }
+}
+
+impl std::fmt::Display for TraversalOrder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::DepthFirst => "depth-first",
+            Self::BreadthFirst => "breadth-first",
+        })
+    }
+}
+
+#[derive(Debug, Clone, Builder)]
+pub struct Generator {
+    #[builder(into)]
+    pub(crate) root_dir: PathBuf,
+    num_files_with_ratio: NumFilesWithRatio,
+    #[builder(default = false)]
+    files_exact: bool,
+    /// The number of directories to generate. The tree is shaped for this
+    /// many directories instead of those implied by the file to directory
+    /// ratio.
+    num_dirs: Option<NonZeroU64>,
+    /// Generate exactly `num_dirs` directories, spreading those the tree
+    /// runs out of room for across the directories generated last.
+    #[builder(default = false)]
+    dirs_exact: bool,
+    #[builder(default = 0)]
+    num_bytes: u64,
+    fill_byte: Option<u8>,
+    /// Repeat this byte sequence as file contents instead of deterministically
+    /// random data. A more general alternative to `fill_byte`.
+    fill_pattern: Option<FillPattern>,
+    /// Repeat the contents of this file as each file's contents, truncating
+    /// the last repetition, so that every file with at least the template's
+    /// size starts with a valid instance of its format. The template is read
+    /// once and shared by all files.
+    #[builder(into)]
+    content_template: Option<PathBuf>,
+    /// Give each file the contents of one of the files in this directory and
+    /// its subdirectories, starting at an offset and wrapping around, both
+    /// picked from the file's seed. The samples are read once and shared by
+    /// all files.
+    #[builder(into)]
+    content_corpus: Option<PathBuf>,
+    #[builder(default = false)]
+    bytes_exact: bool,
+    /// Open content files with `O_DIRECT` where supported so writes bypass the
+    /// page cache.
+    #[builder(default = false)]
+    direct_io: bool,
+    /// Flush written contents to disk every time this many bytes have been
+    /// written by a task.
+    writeback_bytes: Option<NonZeroU64>,
+    /// Append to many open files a block at a time, taking turns, instead of
+    /// writing each file in one go, leaving them heavily fragmented on
+    /// extent-based file systems. File contents are unaffected.
+    #[builder(default = false)]
+    fragment: bool,
+    /// Leave roughly this percentage of every file as holes the file system
+    /// doesn't allocate, seeking over them instead of writing zeros. Every
+    /// 64 KiB of a file end in a hole rounded down to whole 4 KiB blocks.
+    sparse: Option<f64>,
+    /// Lay sparse files out as this exact pattern of data followed by a hole,
+    /// repeated from the start of every file, instead of by percentage.
+    hole_pattern: Option<Holes>,
+    /// Allocate every file's extents with `fallocate` instead of writing its
+    /// contents, so files read back as zeros and huge trees take seconds.
+    /// Elsewhere than on Linux files are only extended.
+    #[builder(default = false)]
+    preallocate: bool,
+    #[builder(default = 5)]
+    max_depth: u32,
+    /// Create exactly the requested number of files in the root directory,
+    /// ignoring the maximum depth and file to directory ratio. Meant for
+    /// stressing directories with tens of millions of entries.
+    #[builder(default = false)]
+    flat: bool,
+    /// The depth at least one chain of directories is guaranteed to reach.
+    /// Can't exceed [`Self::max_depth`].
+    #[builder(default = 0)]
+    min_depth: u32,
+    /// Place every file in a directory at the maximum depth, leaving the
+    /// directories above them holding only other directories.
+    #[builder(default = false)]
+    files_at_leaves: bool,
+    /// How unevenly files and subdirectories are spread among siblings, from
+    /// `0` (the default) for evenly to just under `1` for the first sibling
+    /// getting nearly everything.
+    skew: Option<f64>,
+    /// The distribution the number of files in each directory is drawn from.
+    #[builder(default)]
+    files_per_dir_distribution: FilesPerDirDistribution,
+    #[builder(default = 0)]
+    pub(crate) seed: u64,
+    pub duplicate_percentage: Option<f64>,
+    pub max_duplicates_per_file: Option<NonZeroUsize>,
+    /// Hardlink duplicates to the first file created with their contents
+    /// instead of writing the contents again. Can't be combined with
+    /// [`Self::links_per_inode`].
+    #[builder(default = false)]
+    pub duplicates_as_hardlinks: bool,
+    pub audit_output: Option<PathBuf>,
+    /// The format of the audit trail. Defaults to the one matching the audit
+    /// output's extension.
+    pub audit_format: Option<AuditFormat>,
+    /// The number of rows inserted per statement when writing an SQLite audit
+    /// trail. Defaults to [`DEFAULT_AUDIT_BATCH_SIZE`].
+    pub audit_batch_size: Option<NonZeroUsize>,
+    /// The page size of newly created SQLite audit trails. Defaults to
+    /// [`DEFAULT_AUDIT_PAGE_SIZE`]; SQLite ignores sizes that aren't a power of
+    /// two between 512 and 65536.
+    pub audit_page_size: Option<u32>,
+    /// The memory SQLite may use to cache pages while writing an audit trail.
+    /// Defaults to [`DEFAULT_AUDIT_CACHE_SIZE`].
+    pub audit_cache_size: Option<NonZeroU64>,
+    #[builder(default)]
+    pub permissions: Vec<u32>,
+    /// How often each of the `permissions` is picked relative to the others.
+    /// Every permission is equally likely if empty.
+    #[builder(default)]
+    pub permission_weights: Vec<u32>,
+    /// Permissions to pick from for directories, independently of those of
+    /// files. Each must grant the owner full access so the directory can
+    /// still be filled in.
+    #[builder(default)]
+    pub dir_permissions: Vec<u32>,
+    /// How often each of the `dir_permissions` is picked relative to the
+    /// others. Every permission is equally likely if empty.
+    #[builder(default)]
+    pub dir_permission_weights: Vec<u32>,
+    /// Set the executable bits on this percentage of files, on top of their
+    /// configured permission if any.
+    pub executable_percentage: Option<f64>,
+    /// Whether permissions are applied exactly or relative to the process
+    /// umask.
+    #[builder(default)]
+    pub permission_mode: PermissionMode,
+    /// Use different permissions for the files beneath matching directories.
+    /// The rule matching the deepest directory wins, with earlier rules taking
+    /// precedence at the same depth.
+    #[builder(default)]
+    pub permission_rules: Vec<PermissionRule>,
+    pub fio_output: Option<PathBuf>,
+    /// Write a manifest describing how to regenerate the tree to this path:
+    /// the ftzz version, parameters, plan hash, and a digest of the audit
+    /// trail.
+    pub manifest: Option<PathBuf>,
+    /// Additional target directories into which the identical tree is
+    /// generated, one after the other, for timing comparisons.
+    #[builder(default)]
+    pub compare_with: Vec<PathBuf>,
+    /// Re-walk the tree after generation and fail if it doesn't match the
+    /// generation statistics.
+    #[builder(default = false)]
+    pub validate: bool,
+    /// Additional root directories into which the identical tree is generated
+    /// concurrently.
+    #[builder(default)]
+    pub replicas: Vec<PathBuf>,
+    /// Directories, typically on other filesystems, across which the top-level
+    /// directories of the tree are spread round-robin along with the root.
+    #[builder(default)]
+    pub shards: Vec<PathBuf>,
+    /// Give every replica its own seed derived from the master seed (see
+    /// [`derive_replica_seed`]) instead of generating identical trees. Has no
+    /// effect without replicas.
+    #[builder(default = false)]
+    pub distinct_seeds: bool,
+    /// Glob patterns matched against directory paths relative to the root
+    /// directory. Nothing is generated beneath matching directories, but the
+    /// rest of the tree is unaffected.
+    #[builder(default)]
+    pub prune_globs: Vec<String>,
+    /// Draw the number of subdirectories in each directory from this
+    /// distribution instead of deriving it from the file to directory ratio.
+    pub dir_fanout: Option<DirFanout>,
+    /// Use different file to directory ratios and fan-outs for the directories
+    /// at particular depths, e.g. to make the tree wide at the top and narrow
+    /// below. At most one override per depth.
+    #[builder(default)]
+    pub level_overrides: Vec<LevelOverride>,
+    /// After generation, test how well the achieved file sizes and directory
+    /// fan-out fit the requested distributions and report the p-values.
+    #[builder(default = false)]
+    pub check_distributions: bool,
+    /// The order in which directories are generated. The traversal order is
+    /// part of the seed: changing it produces a different tree.
+    #[builder(default)]
+    pub traversal: TraversalOrder,
+    /// Cap the number of bytes queued for writing but not yet written,
+    /// waiting for outstanding files to be written before queuing more.
+    pub max_in_flight_bytes: Option<NonZeroU64>,
+    /// Split directories containing more files than this into several tasks
+    /// so they are written in parallel. Defaults to
+    /// [`DEFAULT_MAX_FILES_PER_TASK`]. The generated tree is unaffected.
+    pub max_files_per_task: Option<NonZeroUsize>,
+    /// The maximum number of per-task file size buffers kept around for reuse
+    /// in exact bytes mode. Lower values trade allocations for memory.
+    pub byte_counts_pool_capacity: Option<NonZeroUsize>,
+    /// The size of the blocks file contents are staged in before being
+    /// written. Defaults to [`DEFAULT_WRITE_BLOCK_SIZE`] and is rounded up to
+    /// a multiple of 4 KiB. The generated tree is unaffected.
+    pub write_block_size: Option<NonZeroUsize>,
+    /// Name roughly this percentage of files `.wh.<n>` like the whiteouts
+    /// union filesystems use to mark deleted entries.
+    pub whiteout_percentage: Option<f64>,
+    /// Create empty files with plain `open(O_CREAT)` instead of `mknod`, which
+    /// some network and FUSE file systems reject for regular files. Falls
+    /// back automatically when `mknod` fails with `EPERM` or `EOPNOTSUPP`.
+    #[builder(default = false)]
+    pub portable_create: bool,
+    /// How files, and for Unicode names directories too, are named. Only
+    /// numeric names can be combined with case collisions.
+    #[builder(default)]
+    pub name_style: NameStyle,
+    /// Name files after this template instead, such as
+    /// `report_{n:06}_{rand8}.{ext}`. See [`NameTemplate`] for the
+    /// placeholders.
+    pub file_name_template: Option<NameTemplate>,
+    /// Name directories after this template instead. Directory names can't
+    /// contain an extension.
+    pub dir_name_template: Option<NameTemplate>,
+    /// Pad file names with letters to a length picked from this range based
+    /// on their seed. Names already longer are left alone.
+    pub name_length: Option<NameLengthRange>,
+    /// Pad roughly this percentage of file names to exactly [`NAME_MAX`]
+    /// bytes, the longest most file systems allow.
+    pub max_name_percentage: Option<f64>,
+    /// Give every file one of these extensions, picked by weight based on
+    /// its seed, in place of any it would otherwise have. How many files got
+    /// each extension is recorded in the audit trail.
+    pub extensions: Option<ExtensionWeights>,
+    /// Name files `file<n>` and roughly this percentage of them (at most half)
+    /// `File<n - 1>` so they collide with a sibling on case-insensitive file
+    /// systems. Collisions are reported rather than silently merged.
+    pub case_collision_percentage: Option<f64>,
+    /// Create a dangling symlink next to roughly this percentage of files,
+    /// named `<n>.link` and pointing at a nonexistent `<n>.missing`. The
+    /// links are neither counted as files nor recorded in the audit trail.
+    pub broken_symlink_percentage: Option<f64>,
+    /// Create roughly this percentage of files as FIFOs or sockets instead,
+    /// or character and block devices too when running as root. Files with
+    /// duplicates are left alone. Only supported on Linux.
+    pub special_files: Option<f64>,
+    /// Give roughly this percentage of files and directories a POSIX access
+    /// ACL granting one or two extra users and up to one extra group read or
+    /// read-write access. Hardlinked files and special files are left alone.
+    /// The ACLs are recorded in the audit trail. Only supported on Linux.
+    pub acl_percentage: Option<f64>,
+    /// Give every file a modification time picked from this range based on
+    /// its seed, and an access time between that and the end of the range.
+    /// The modification times are recorded in the audit trail.
+    pub mtime_range: Option<TimeRange>,
+    /// Once the tree is complete, stamp every file, directory, and symlink in
+    /// it with this modification and access time, in seconds since the Unix
+    /// epoch, so archives of trees generated from the same seed are
+    /// identical.
+    pub timestamp: Option<i64>,
+    /// Set the read-only, hidden, or system attribute, or a combination of
+    /// them, on roughly this percentage of files and directories. Hardlinked
+    /// and fragmented files are left alone, and files can't be written to by
+    /// later passes. The attributes are recorded in the audit trail. Only
+    /// supported on Windows.
+    pub windows_attribute_percentage: Option<f64>,
+    /// Write one to three named alternate data streams of random contents to
+    /// roughly this percentage of files. Hardlinked and special files are
+    /// left alone. The stream names and sizes are recorded in the audit
+    /// trail. Only supported on Windows.
+    pub ads_percentage: Option<f64>,
+    /// Turn the tree into a hardlink farm: every file is a hardlink to one of
+    /// a small pool of inodes, each getting a share of the links drawn from
+    /// this distribution. The pool holds as many inodes as it takes for the
+    /// mean to cover every file. Inodes keep the default permissions and
+    /// can't be combined with exact byte counts.
+    pub links_per_inode: Option<DirFanout>,
+    /// Once the tree has been generated, append this many bytes to some of
+    /// its files, simulating files that grow after they were first written.
+    pub append_pass: Option<NonZeroU64>,
+    /// The percentage of files the append pass grows. Defaults to
+    /// [`DEFAULT_APPEND_PERCENTAGE`].
+    pub append_percentage: Option<f64>,
+    /// Once the tree has been generated, rewrite roughly this percentage of
+    /// its files with new contents of the same size and push their
+    /// modification times forward, for validating change detection.
+    pub modify_percentage: Option<f64>,
+    /// Write the paths of the files changed by the modify pass here, one per
+    /// line.
+    pub modified_output: Option<PathBuf>,
+    /// Once the tree has been generated, link this many of its directories
+    /// back to themselves or one of their ancestors with a `loop` symlink,
+    /// creating cycles for recursive scanners to trip over. On Windows,
+    /// junctions are created instead if symlinks require a privilege the
+    /// process doesn't hold. The links are recorded in the audit trail as
+    /// either.
+    pub symlink_loops: Option<NonZeroUsize>,
+    /// Link files sharing an inode, and directories linked into loops, with
+    /// this flavor of link instead of hardlinks and symlinks respectively.
+    /// Files can't be linked by junction, nor directories by hardlink.
+    pub link_flavor: Option<LinkFlavor>,
+    /// Once everything has been written, read back roughly this percentage of
+    /// files and compare their hashes against those computed while writing
+    /// them, failing if any no longer match.
+    pub read_back: Option<f64>,
+    /// Where the bytes left over by exact bytes mode once every file has been
+    /// given a size end up.
+    #[builder(default)]
+    pub leftover_strategy: LeftoverStrategy,
+    /// The distribution file sizes are drawn from around the mean file size.
+    #[builder(default)]
+    pub size_distribution: SizeDistribution,
+    /// Start every non-empty file with a line naming its path relative to the
+    /// root directory, its seed, and the run's plan hash, so files are
+    /// self-identifying wherever they end up. The header replaces the start of
+    /// the contents, leaving sizes unchanged.
+    #[builder(default = false)]
+    pub file_headers: bool,
+    /// Start every non-empty file whose extension names a well-known format
+    /// with that format's signature, such as `\x89PNG` for `.png` files, so
+    /// file type sniffers recognize it. The signature precedes the file header
+    /// when both are used.
+    #[builder(default = false)]
+    pub magic_bytes: bool,
+    /// What files are filled with. Text contents compress and index like real
+    /// documents, JSON contents make every file a valid JSON document which
+    /// headers, holes, and later passes over the files would break, CSV
+    /// contents give ETL pipelines tables to load, dedup contents repeat blocks
+    /// at a chosen rate for dedup backends, and log contents fill files with
+    /// timestamped lines for log ingestion.
+    /// Can't be combined with a fill pattern or content template.
+    #[builder(default)]
+    pub content: ContentMode,
+    /// The number of columns in CSV contents, [`DEFAULT_CSV_COLUMNS`] if not
+    /// given.
+    pub csv_columns: Option<NonZeroU8>,
+    /// The percentage of 4 KiB blocks of dedup contents copied from the block
+    /// dictionary rather than unique, 50% if not given.
+    pub dedup_percentage: Option<f64>,
+    /// The number of blocks in the dictionary of dedup contents,
+    /// [`DEFAULT_DEDUP_BLOCKS`] if not given.
+    pub dedup_blocks: Option<NonZeroU32>,
+}
+
+/// The number of files beyond which a directory's files are split across
+/// several tasks.
+pub const DEFAULT_MAX_FILES_PER_TASK: usize = 1 << 14;
+
+/// The percentage of files grown by an append pass unless configured
+/// otherwise.
+pub const DEFAULT_APPEND_PERCENTAGE: f64 = 10.;
+
+/// The number of columns in CSV contents unless configured otherwise.
+pub const DEFAULT_CSV_COLUMNS: NonZeroU8 = NonZeroU8::new(8).unwrap();
+
+/// The number of blocks in the dictionary of dedup contents unless configured
+/// otherwise.
+pub const DEFAULT_DEDUP_BLOCKS: NonZeroU32 = NonZeroU32::new(1024).unwrap();
+
+/// The size of the blocks file contents are staged in before being written.
+pub const DEFAULT_WRITE_BLOCK_SIZE: NonZeroUsize = NonZeroUsize::new(8 * 1024).unwrap();
+
+/// The number of rows inserted per statement when writing an SQLite audit
+/// trail.
+pub const DEFAULT_AUDIT_BATCH_SIZE: NonZeroUsize = NonZeroUsize::new(512).unwrap();
+
+/// The page size of newly created SQLite audit trails.
+pub const DEFAULT_AUDIT_PAGE_SIZE: u32 = 16 * 1024;
+
+/// The memory SQLite may use to cache pages while writing an audit trail.
+pub const DEFAULT_AUDIT_CACHE_SIZE: NonZeroU64 = NonZeroU64::new(64 * 1024 * 1024).unwrap();
+
+/// Derives the seed of replica `index` (the root directory being replica 0)
+/// from the master `seed`.
+///
+/// The derived seed is `seed ^ xxh64(index)`, hashing `index` as a
+/// little-endian `u64` with a hash seed of 0. Passing the derived seed to a
+/// standalone run regenerates that replica exactly.
+#[must_use]
+pub fn derive_replica_seed(seed: u64, index: usize) -> u64 {
+    seed ^ XxHash64::oneshot(0, &(index as u64).to_le_bytes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn minimal_params_succeeds() {
+        let g = Generator::builder()
+            .root_dir(PathBuf::from("abc"))
+            .num_files_with_ratio(NumFilesWithRatio::from_num_files(
+                NonZeroU64::new(1).unwrap(),
+            ))
+            .build();
+
+        assert_eq!(g.root_dir, PathBuf::from("abc"));
+        assert_eq!(g.num_files_with_ratio.num_files.get(), 1);
+        assert!(!g.files_exact);
+        assert_eq!(g.num_bytes, 0);
+        assert!(!g.bytes_exact);
+        assert_eq!(g.max_depth, 5);
+        assert_eq!(g.num_files_with_ratio.file_to_dir_ratio.get(), 1);
+        assert_eq!(g.seed, 0);
+    }
+
+    #[test]
+    fn ratio_greater_than_num_files_fails() {
+        let r = NumFilesWithRatio::new(NonZeroU64::new(1).unwrap(), NonZeroU64::new(2).unwrap());
+
+        r.unwrap_err();
+    }
+
+    #[test]
+    fn entries_are_split_by_ratio() {
+        let r = NumFilesWithRatio::from_num_entries(
+            NonZeroU64::new(1100).unwrap(),
+            Some(NonZeroU64::new(10).unwrap()),
+        )
+        .unwrap();
+        assert_eq!(r.num_files.get(), 1000);
+
+        let r =
+            NumFilesWithRatio::from_num_entries(NonZeroU64::new(1_001_000).unwrap(), None).unwrap();
+        assert_eq!(r.num_files.get(), 1_000_000);
+        assert_eq!(r.file_to_dir_ratio.get(), 1000);
+    }
+
+    #[test]
+    fn dir_fanout_parses() {
+        assert_eq!(
+            "normal:3".parse::<DirFanout>().unwrap(),
+            DirFanout::Normal {
+                mean: 3.,
+                std_dev: 1.
+            }
+        );
+        assert_eq!(
+            "uniform:1,5".parse::<DirFanout>().unwrap(),
+            DirFanout::Uniform { min: 1, max: 5 }
+        );
+        assert_eq!(
+            "poisson:2.5".parse::<DirFanout>().unwrap(),
+            DirFanout::Poisson { mean: 2.5 }
+        );
+    }
+
+    #[test]
+    fn time_range_parses_and_picks_within_bounds() {
+        let range = "-100..100".parse::<TimeRange>().unwrap();
+        assert_eq!(
+            range,
+            TimeRange {
+                start: -100,
+                end: 100
+            }
+        );
+        assert!("100..-100".parse::<TimeRange>().is_err());
+        assert!("100".parse::<TimeRange>().is_err());
+
+        for hash in [0, 1, 200, 201, u64::MAX] {
+            assert!((-100..=100).contains(&range.pick(hash)));
+        }
+        let everything = TimeRange {
+            start: i64::MIN,
+            end: i64::MAX,
+        };
+        assert_eq!(everything.pick(0), i64::MIN);
+        assert_eq!(everything.pick(u64::MAX), i64::MAX);
+    }
+
+    #[test]
+    fn extension_weights_parse() {
+        assert_eq!(
+            "jpg:50, .txt:30,tar.gz"
+                .parse::<ExtensionWeights>()
+                .unwrap(),
+            ExtensionWeights(vec![
+                ("jpg".to_string(), 50),
+                ("txt".to_string(), 30),
+                ("tar.gz".to_string(), 1),
+            ])
+        );
+        for s in ["", "jpg:x", "jpg:0", "a/b", "jpg.", "..", "j pg"] {
+            assert!(s.parse::<ExtensionWeights>().is_err(), "{s}");
+        }
+    }
+
+    #[test]
+    fn name_length_range_parses_and_picks_within_bounds() {
+        let range = "10..255".parse::<NameLengthRange>().unwrap();
+        assert_eq!(range, NameLengthRange { min: 10, max: 255 });
+        for s in ["0..10", "20..10", "10..256", "10"] {
+            assert!(s.parse::<NameLengthRange>().is_err(), "{s}");
+        }
+
+        for hash in [0, 1, 245, 246, u64::MAX] {
+            assert!((10..=255).contains(&range.pick(hash)));
+        }
+        assert_eq!(range.pick(0), 10);
+        assert_eq!(range.pick(245), 255);
+    }
+
+    #[test]
+    fn audit_format_parses() {
+        assert_eq!("jsonl".parse::<AuditFormat>().unwrap(), AuditFormat::Jsonl);
+        assert_eq!(
+            "sqlite".parse::<AuditFormat>().unwrap(),
+            AuditFormat::Sqlite
+        );
+        "xml".parse::<AuditFormat>().unwrap_err();
+    }
+
+    #[test]
+    fn traversal_order_parses() {
+        assert_eq!(
+            "bfs".parse::<TraversalOrder>().unwrap(),
+            TraversalOrder::BreadthFirst
+        );
+        assert_eq!(
+            "depth-first".parse::<TraversalOrder>().unwrap(),
+            TraversalOrder::DepthFirst
+        );
+        "sideways".parse::<TraversalOrder>().unwrap_err();
+    }
+
+    #[test]
+    fn fill_pattern_parses() {
+        assert_eq!(
+            "0xdeadBEEF".parse::<FillPattern>().unwrap().as_bytes(),
+            [0xDE, 0xAD, 0xBE, 0xEF]
+        );
+        assert_eq!("FTZZ".parse::<FillPattern>().unwrap().as_bytes(), b"FTZZ");
+        "".parse::<FillPattern>().unwrap_err();
+        "0x".parse::<FillPattern>().unwrap_err();
+        "0xabc".parse::<FillPattern>().unwrap_err();
+        "0xzz".parse::<FillPattern>().unwrap_err();
+    }
+
+    #[test]
+    fn leftover_strategy_parses() {
+        assert_eq!(
+            "last-file".parse::<LeftoverStrategy>().unwrap(),
+            LeftoverStrategy::LastFile
+        );
+        assert_eq!(
+            "largest-file".parse::<LeftoverStrategy>().unwrap(),
+            LeftoverStrategy::LargestFile
+        );
+        "first-file".parse::<LeftoverStrategy>().unwrap_err();
+    }
+
+    #[test]
+    fn size_distribution_parses() {
+        assert_eq!(
+            "normal".parse::<SizeDistribution>().unwrap(),
+            SizeDistribution::Normal
+        );
+        assert_eq!(
+            "lognormal".parse::<SizeDistribution>().unwrap(),
+            SizeDistribution::LogNormal { sigma: 1. }
+        );
+        assert_eq!(
+            "pareto:2.5".parse::<SizeDistribution>().unwrap(),
+            SizeDistribution::Pareto { shape: 2.5 }
+        );
+        assert_eq!(
+            "zipf:0.8".parse::<SizeDistribution>().unwrap(),
+            SizeDistribution::Zipf { exponent: 0.8 }
+        );
+        assert_eq!(
+            "uniform".parse::<SizeDistribution>().unwrap(),
+            SizeDistribution::Uniform
+        );
+        "pareto:1".parse::<SizeDistribution>().unwrap_err();
+        "lognormal:-1".parse::<SizeDistribution>().unwrap_err();
+        "uniform:3".parse::<SizeDistribution>().unwrap_err();
+        "gamma".parse::<SizeDistribution>().unwrap_err();
+    }
+
+    #[test]
+    fn files_per_dir_distribution_parses() {
+        assert_eq!(
+            "normal".parse::<FilesPerDirDistribution>().unwrap(),
+            FilesPerDirDistribution::Normal
+        );
+        assert_eq!(
+            "zipf".parse::<FilesPerDirDistribution>().unwrap(),
+            FilesPerDirDistribution::Zipf { exponent: 1.2 }
+        );
+        assert_eq!(
+            "zipf:2".parse::<FilesPerDirDistribution>().unwrap(),
+            FilesPerDirDistribution::Zipf { exponent: 2. }
+        );
+        "zipf:0".parse::<FilesPerDirDistribution>().unwrap_err();
+        "normal:1".parse::<FilesPerDirDistribution>().unwrap_err();
+        "pareto".parse::<FilesPerDirDistribution>().unwrap_err();
+    }
+
+    #[test]
+    fn name_style_parses() {
+        assert_eq!("numeric".parse::<NameStyle>().unwrap(), NameStyle::Numeric);
+        assert_eq!(
+            "realistic".parse::<NameStyle>().unwrap(),
+            NameStyle::Realistic
+        );
+        assert_eq!("unicode".parse::<NameStyle>().unwrap(), NameStyle::Unicode);
+        "words".parse::<NameStyle>().unwrap_err();
+    }
+
+    #[test]
+    fn content_mode_parses() {
+        assert_eq!(
+            "random".parse::<ContentMode>().unwrap(),
+            ContentMode::Random
+        );
+        assert_eq!("text".parse::<ContentMode>().unwrap(), ContentMode::Text);
+        assert_eq!("json".parse::<ContentMode>().unwrap(), ContentMode::Json);
+        assert_eq!(
+            "syslog".parse::<ContentMode>().unwrap(),
+            ContentMode::Syslog
+        );
+        assert_eq!(
+            "ndjson".parse::<ContentMode>().unwrap(),
+            ContentMode::JsonLines
+        );
+        "lorem".parse::<ContentMode>().unwrap_err();
+    }
+
+    #[test]
+    fn hole_pattern_parses() {
+        assert_eq!(
+            "1MiB:7MiB".parse::<Holes>().unwrap().layout(),
+            (1 << 20, 7 << 20)
+        );
+        assert_eq!("0 : 4096".parse::<Holes>().unwrap().layout(), (0, 4096));
+        "1MiB".parse::<Holes>().unwrap_err();
+        "1MiB:0".parse::<Holes>().unwrap_err();
+        "1000:4096".parse::<Holes>().unwrap_err();
+    }
+
+    #[test]
+    fn permission_mode_round_trips() {
+        for mode in [PermissionMode::Absolute, PermissionMode::UmaskRelative] {
+            assert_eq!(mode.to_string().parse::<PermissionMode>().unwrap(), mode);
+        }
+        "relative".parse::<PermissionMode>().unwrap_err();
+    }
+
+    #[test]
+    fn parameters_display_as_they_parse() {
+        fn round_trip<T: FromStr + std::fmt::Display>(s: &str)
+        where
+            T::Err: std::fmt::Debug,
+        {
+            let display = s.parse::<T>().unwrap().to_string();
+            assert_eq!(display, s);
+            display.parse::<T>().unwrap();
+        }
+
+        round_trip::<DirFanout>("normal:3,0.5");
+        round_trip::<DirFanout>("uniform:2,8");
+        round_trip::<LevelOverride>("2:ftd-ratio=500;dir-fanout=poisson:2");
+        round_trip::<SizeDistribution>("lognormal:1.5");
+        round_trip::<FilesPerDirDistribution>("zipf:1.2");
+        round_trip::<TimeRange>("-5..1000");
+        round_trip::<NameLengthRange>("4..9");
+        round_trip::<PermissionRule>("**/3.dir=700:2,640:1");
+        round_trip::<ExtensionWeights>("txt:3,tar.gz:1");
+        round_trip::<FillPattern>("abc");
+        round_trip::<FillPattern>("0x00ff");
+        round_trip::<Holes>("4096:8192");
+        round_trip::<LinkFlavor>("symlink");
+        round_trip::<LeftoverStrategy>("largest-file");
+        round_trip::<ContentMode>("csv");
+        round_trip::<NameStyle>("realistic");
+        round_trip::<AuditFormat>("jsonl");
+        round_trip::<TraversalOrder>("breadth-first");
+        round_trip::<NameTemplate>("f_{n:04}_{{x}}_{rand8}.{ext}");
+    }
+
+    #[test]
+    fn permission_rule_parses() {
+        assert_eq!(
+            "**/3.dir=700".parse::<PermissionRule>().unwrap(),
+            PermissionRule {
+                glob: "**/3.dir".to_string(),
+                permissions: vec![0o700],
+                permission_weights: Vec::new(),
+            }
+        );
+        assert_eq!(
+            "3.dir=644:3,755".parse::<PermissionRule>().unwrap(),
+            PermissionRule {
+                glob: "3.dir".to_string(),
+                permissions: vec![0o644, 0o755],
+                permission_weights: vec![3, 1],
+            }
+        );
+        assert_eq!(
+            "bin=4755,2755"
+                .parse::<PermissionRule>()
+                .unwrap()
+                .permissions,
+            vec![0o4755, 0o2755]
+        );
+        for s in ["3.dir", "3.dir=", "3.dir=9", "3.dir=644:x", "3.dir=10644"] {
+            s.parse::<PermissionRule>().unwrap_err();
+        }
+    }
+
+    #[test]
+    fn invalid_dir_fanout_fails() {
+        for s in [
+            "normal",
+            "normal:0",
+            "uniform:5,1",
+            "uniform:0,0",
+            "cauchy:3",
+            "poisson:1,2",
+        ] {
+            s.parse::<DirFanout>().unwrap_err();
+        }
+    }
+
+    #[test]
+    fn level_override_parses() {
+        assert_eq!(
+            "0:dir-fanout=uniform:20,40"
+                .parse::<LevelOverride>()
+                .unwrap(),
+            LevelOverride {
+                depth: 0,
+                ftd_ratio: None,
+                dir_fanout: Some(DirFanout::Uniform { min: 20, max: 40 }),
+            }
+        );
+        assert_eq!(
+            "2:ftd-ratio=500; dir-fanout=poisson:2"
+                .parse::<LevelOverride>()
+                .unwrap(),
+            LevelOverride {
+                depth: 2,
+                ftd_ratio: NonZeroU64::new(500),
+                dir_fanout: Some(DirFanout::Poisson { mean: 2. }),
+            }
+        );
+        for s in [
+            "0",
+            "0:",
+            "x:ftd-ratio=1",
+            "1:ftd-ratio=0",
+            "1:ftd-ratio=1;ftd-ratio=2",
+            "1:dir-fanout=cauchy:3",
+            "1:files=3",
+        ] {
+            s.parse::<LevelOverride>().unwrap_err();
+        }
+    }
+}
+
+impl Generator {
+    /// The parameters as a JSON object keyed like the configuration file, with
+    /// unset options left out.
+    fn parameters(&self) -> serde_json::Map<String, serde_json::Value> {
+        use serde_json::{Value, json};
+
+        let Self {
+            root_dir: _,
+            num_files_with_ratio:
+                NumFilesWithRatio {
+                    num_files,
+                    file_to_dir_ratio,
+                },
+            files_exact,
+            num_dirs,
+            dirs_exact,
+            num_bytes,
+            fill_byte,
+            ref fill_pattern,
+            ref content_template,
+            ref content_corpus,
+            bytes_exact,
+            direct_io,
+            writeback_bytes,
+            fragment,
+            sparse,
+            hole_pattern,
+            preallocate,
+            max_depth,
+            flat,
+            min_depth,
+            files_at_leaves,
+            skew,
+            files_per_dir_distribution,
+            seed,
+            duplicate_percentage,
+            max_duplicates_per_file,
+            duplicates_as_hardlinks,
+            ref audit_output,
+            audit_format,
+            audit_batch_size,
+            audit_page_size,
+            audit_cache_size,
+            ref permissions,
+            ref permission_weights,
+            ref dir_permissions,
+            ref dir_permission_weights,
+            executable_percentage,
+            permission_mode,
+            ref permission_rules,
+            ref fio_output,
+            ref manifest,
+            ref compare_with,
+            validate,
+            ref replicas,
+            ref shards,
+            distinct_seeds,
+            ref prune_globs,
+            dir_fanout,
+            ref level_overrides,
+            check_distributions,
+            traversal,
+            max_in_flight_bytes,
+            max_files_per_task,
+            byte_counts_pool_capacity,
+            write_block_size,
+            whiteout_percentage,
+            portable_create,
+            name_style,
+            ref file_name_template,
+            ref dir_name_template,
+            name_length,
+            max_name_percentage,
+            ref extensions,
+            case_collision_percentage,
+            broken_symlink_percentage,
+            special_files,
+            acl_percentage,
+            mtime_range,
+            timestamp,
+            windows_attribute_percentage,
+            ads_percentage,
+            links_per_inode,
+            append_pass,
+            append_percentage,
+            modify_percentage,
+            ref modified_output,
+            symlink_loops,
+            link_flavor,
+            read_back,
+            leftover_strategy,
+            size_distribution,
+            file_headers,
+            magic_bytes,
+            content,
+            csv_columns,
+            dedup_percentage,
+            dedup_blocks,
+        } = *self;
+        let path = |path: &Option<PathBuf>| {
+            path.as_ref()
+                .map(|path| Value::from(path.display().to_string()))
+        };
+        let paths = |paths: &[PathBuf]| {
+            paths
+                .iter()
+                .map(|path| path.display().to_string())
+                .collect::<Vec<_>>()
+        };
+
+        [
+            ("files", json!(num_files)),
+            ("ftd-ratio", json!(file_to_dir_ratio)),
+            ("files-exact", json!(files_exact)),
+            ("dirs", json!(num_dirs)),
+            ("dirs-exact", json!(dirs_exact)),
+            ("total-bytes", json!(num_bytes)),
+            ("fill-byte", json!(fill_byte)),
+            (
+                "fill-pattern",
+                json!(fill_pattern.as_ref().map(ToString::to_string)),
+            ),
+            ("content-template", json!(path(content_template))),
+            ("content-corpus", json!(path(content_corpus))),
+            ("bytes-exact", json!(bytes_exact)),
+            ("direct-io", json!(direct_io)),
+            ("writeback-bytes", json!(writeback_bytes)),
+            ("fragment", json!(fragment)),
+            ("sparse", json!(sparse)),
+            (
+                "hole-pattern",
+                json!(hole_pattern.as_ref().map(ToString::to_string)),
+            ),
+            ("preallocate", json!(preallocate)),
+            ("max-depth", json!(max_depth)),
+            ("flat", json!(flat)),
+            ("min-depth", json!(min_depth)),
+            ("files-at-leaves", json!(files_at_leaves)),
+            ("skew", json!(skew)),
+            (
+                "files-per-dir-distribution",
+                json!(files_per_dir_distribution.to_string()),
+            ),
+            ("seed", json!(seed)),
+            ("duplicate-percentage", json!(duplicate_percentage)),
+            ("max-duplicates-per-file", json!(max_duplicates_per_file)),
+            ("duplicates-as-hardlinks", json!(duplicates_as_hardlinks)),
+            ("audit-output", json!(path(audit_output))),
+            (
+                "audit-format",
+                json!(audit_format.as_ref().map(ToString::to_string)),
+            ),
+            ("audit-batch-size", json!(audit_batch_size)),
+            ("audit-page-size", json!(audit_page_size)),
+            ("audit-cache-size", json!(audit_cache_size)),
+            (
+                "permissions",
+                json!(weighted_modes(permissions, permission_weights)),
+            ),
+            (
+                "dir-permissions",
+                json!(weighted_modes(dir_permissions, dir_permission_weights)),
+            ),
+            ("executable-percentage", json!(executable_percentage)),
+            ("permission-mode", json!(permission_mode.to_string())),
+            (
+                "permission-rules",
+                json!(
+                    permission_rules
+                        .iter()
+                        .map(ToString::to_string)
+                        .collect::<Vec<_>>()
+                ),
+            ),
+            ("fio-output", json!(path(fio_output))),
+            ("manifest", json!(path(manifest))),
+            ("compare-with", json!(paths(compare_with))),
+            ("validate", json!(validate)),
+            ("replica-dirs", json!(paths(replicas))),
+            ("shards", json!(paths(shards))),
+            ("distinct-seeds", json!(distinct_seeds)),
+            ("prune-globs", json!(prune_globs)),
+            (
+                "dir-fanout",
+                json!(dir_fanout.as_ref().map(ToString::to_string)),
+            ),
+            (
+                "levels",
+                json!(
+                    level_overrides
+                        .iter()
+                        .map(ToString::to_string)
+                        .collect::<Vec<_>>()
+                ),
+            ),
+            ("check-distributions", json!(check_distributions)),
+            ("traversal", json!(traversal.to_string())),
+            ("max-in-flight-bytes", json!(max_in_flight_bytes)),
+            ("max-files-per-task", json!(max_files_per_task)),
+            (
+                "byte-counts-pool-capacity",
+                json!(byte_counts_pool_capacity),
+            ),
+            ("write-block-size", json!(write_block_size)),
+            ("whiteout-percentage", json!(whiteout_percentage)),
+            ("portable-create", json!(portable_create)),
+            ("name-style", json!(name_style.to_string())),
+            (
+                "file-name-template",
+                json!(file_name_template.as_ref().map(ToString::to_string)),
+            ),
+            (
+                "dir-name-template",
+                json!(dir_name_template.as_ref().map(ToString::to_string)),
+            ),
+            (
+                "name-length",
+                json!(name_length.as_ref().map(ToString::to_string)),
+            ),
+            ("max-name-percentage", json!(max_name_percentage)),
+            (
+                "extensions",
+                json!(extensions.as_ref().map(ToString::to_string)),
+            ),
+            (
+                "case-collision-percentage",
+                json!(case_collision_percentage),
+            ),
+            (
+                "broken-symlink-percentage",
+                json!(broken_symlink_percentage),
+            ),
+            ("special-files", json!(special_files)),
+            ("acl-percentage", json!(acl_percentage)),
+            (
+                "mtime-range",
+                json!(mtime_range.as_ref().map(ToString::to_string)),
+            ),
+            ("timestamp", json!(timestamp)),
+            (
+                "windows-attribute-percentage",
+                json!(windows_attribute_percentage),
+            ),
+            ("ads-percentage", json!(ads_percentage)),
+            (
+                "links-per-inode",
+                json!(links_per_inode.as_ref().map(ToString::to_string)),
+            ),
+            ("append-pass", json!(append_pass)),
+            ("append-percentage", json!(append_percentage)),
+            ("modify-percentage", json!(modify_percentage)),
+            ("modified-output", json!(path(modified_output))),
+            ("symlink-loops", json!(symlink_loops)),
+            (
+                "link-flavor",
+                json!(link_flavor.as_ref().map(ToString::to_string)),
+            ),
+            ("read-back", json!(read_back)),
+            ("leftover-strategy", json!(leftover_strategy.to_string())),
+            ("size-distribution", json!(size_distribution.to_string())),
+            ("file-headers", json!(file_headers)),
+            ("magic-bytes", json!(magic_bytes)),
+            ("content", json!(content.to_string())),
+            ("csv-columns", json!(csv_columns)),
+            ("dedup-percentage", json!(dedup_percentage)),
+            ("dedup-blocks", json!(dedup_blocks)),
+        ]
+        .into_iter()
+        .filter(|(_, value)| !value.is_null() && value.as_array().is_none_or(|a| !a.is_empty()))
+        .map(|(key, value)| (key.to_string(), value))
+        .collect()
+    }
+
+    pub fn generate(self, output: &mut impl Write) -> Result<(), Error> {
+        self.generate_with_stats(output).map(|_| ())
+    }
+
+    /// Generates the tree, returning what was created and what was asked for
+    /// unless it was spread across several comparison targets or replicas.
+    pub(crate) fn generate_with_stats(
+        self,
+        output: &mut impl Write,
+    ) -> Result<Option<(GeneratorStats, Targets)>, Error> {
+        let documented = self.audit_output.is_some() || self.manifest.is_some();
+        let parameters = documented.then(|| {
+            [
+                ("root", self.root_dir.display().to_string()),
+                (
+                    "parameters",
+                    serde_json::Value::Object(self.parameters()).to_string(),
+                ),
+                ("seed", self.seed.to_string()),
+            ]
+        });
+        let mut options = validated_options(self)?;
+        if let Some(parameters) = parameters {
+            options.audit_metadata.splice(
+                0..0,
+                [("ftzz version", env!("CARGO_PKG_VERSION").to_string())]
+                    .into_iter()
+                    .chain(parameters)
+                    .chain([("plan", format!("{:016x}", plan_hash(&options)))])
+                    .map(|(key, value)| (key.to_string(), value)),
+            );
+        }
+        // Reading the umask briefly clears it, so get that out of the way
+        // before any files are created.
+        #[cfg(unix)]
+        if !options.permissions.is_empty() || !options.dir_permissions.is_empty() {
+            crate::core::umask();
+        }
+        print_configuration_info(&options, output)?;
+        let distribution_check = options
+            .check_distributions
+            .then(|| DistributionCheck::new(&options));
+        let stats = if !options.compare_with.is_empty() {
+            run_comparison(options, output)?;
+            None
+        } else if !options.replicas.is_empty() {
+            run_replicas(options, output)?;
+            None
+        } else {
+            let targets = Targets::new(&options);
+            let stats = run_generator(options)?;
+            print_stats(stats, targets, output);
+            Some((stats, targets))
+        };
+        if let Some(check) = distribution_check {
+            check.run(output)?;
+        }
+        Ok(stats)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Configuration {
+    root_dir: PathBuf,
+    files: NonZeroU64,
+    bytes: u64,
+    files_exact: bool,
+    dirs_exact: Option<NonZeroU64>,
+    bytes_exact: bool,
+    fill_pattern: Option<FillPattern>,
+    direct_io: bool,
+    writeback_bytes: Option<NonZeroU64>,
+    fragment: bool,
+    sparse: Option<f64>,
+    hole_pattern: Option<Holes>,
+    preallocate: bool,
+    dirs_per_dir: f64,
+    dir_fanout: Option<DirFanout>,
+    level_overrides: Vec<LevelOverride>,
+    bytes_per_file: f64,
+    max_depth: u32,
+    min_depth: u32,
+    files_at_leaves: bool,
+    skew: f64,
+    files_per_dir_distribution: FilesPerDirDistribution,
+    seed: u64,
+    duplicate_percentage: f64,
+    max_duplicates_per_file: NonZeroUsize,
+    duplicates_as_hardlinks: bool,
+    audit_output: Option<PathBuf>,
+    audit_sqlite: SqliteSettings,
+    audit_format: AuditFormat,
+    permissions: PermissionTable,
+    dir_permissions: PermissionTable,
+    permission_rules: Vec<(Pattern, PermissionTable)>,
+    fio_output: Option<PathBuf>,
+    manifest: Option<PathBuf>,
+    compare_with: Vec<PathBuf>,
+    validate: bool,
+    replicas: Vec<PathBuf>,
+    shards: Vec<PathBuf>,
+    replica_seeds: Vec<ReplicaSeed>,
+    audit_metadata: Vec<(String, String)>,
+    prune_globs: Vec<Pattern>,
+    check_distributions: bool,
+    traversal: TraversalOrder,
+    max_in_flight_bytes: Option<NonZeroU64>,
+    max_files_per_task: usize,
+    byte_counts_pool_capacity: Option<NonZeroUsize>,
+    write_block_size: NonZeroUsize,
+    whiteout_percentage: f64,
+    portable_create: bool,
+    name_style: NameStyle,
+    file_name_template: Option<NameTemplate>,
+    dir_name_template: Option<NameTemplate>,
+    name_length: Option<NameLengthRange>,
+    max_name_percentage: Option<f64>,
+    extensions: Option<ExtensionWeights>,
+    case_collision_percentage: Option<f64>,
+    broken_symlink_percentage: Option<f64>,
+    special_files: Option<f64>,
+    acl_percentage: Option<f64>,
+    mtime_range: Option<TimeRange>,
+    timestamp: Option<i64>,
+    windows_attribute_percentage: Option<f64>,
+    ads_percentage: Option<f64>,
+    links_per_inode: Option<DirFanout>,
+    append_pass: Option<NonZeroU64>,
+    append_percentage: f64,
+    modify_percentage: Option<f64>,
+    modified_output: Option<PathBuf>,
+    symlink_loops: Option<NonZeroUsize>,
+    link_flavor: Option<LinkFlavor>,
+    read_back: Option<f64>,
+    leftover_strategy: LeftoverStrategy,
+    size_distribution: SizeDistribution,
+    file_headers: bool,
+    magic_bytes: bool,
+    content: ContentMode,
+    human_info: HumanInfo,
+}
+
+/// Hashes the parameters that shape the generated trees, ignoring where they
+/// are written and how.
+fn plan_hash(
+    Configuration {
+        root_dir: _,
+        files,
+        bytes,
+        files_exact,
+        dirs_exact,
+        bytes_exact,
+        fill_pattern,
+        direct_io: _,
+        writeback_bytes: _,
+        fragment: _,
+        sparse,
+        hole_pattern,
+        preallocate,
+        dirs_per_dir,
+        dir_fanout,
+        level_overrides,
+        bytes_per_file,
+        max_depth,
+        min_depth,
+        files_at_leaves,
+        skew,
+        files_per_dir_distribution,
+        seed,
+        duplicate_percentage,
+        max_duplicates_per_file,
+        duplicates_as_hardlinks,
+        audit_output: _,
+        audit_sqlite: _,
+        audit_format: _,
+        permissions,
+        dir_permissions,
+        permission_rules,
+        fio_output: _,
+        manifest: _,
+        compare_with: _,
+        validate: _,
+        replicas: _,
+        shards: _,
+        replica_seeds,
+        audit_metadata: _,
+        prune_globs,
+        check_distributions: _,
+        traversal,
+        max_in_flight_bytes: _,
+        max_files_per_task: _,
+        byte_counts_pool_capacity: _,
+        write_block_size: _,
+        whiteout_percentage,
+        portable_create: _,
+        name_style,
+        file_name_template,
+        dir_name_template,
+        name_length,
+        max_name_percentage,
+        extensions,
+        case_collision_percentage,
+        broken_symlink_percentage,
+        special_files,
+        acl_percentage,
+        mtime_range,
+        timestamp,
+        windows_attribute_percentage,
+        ads_percentage,
+        links_per_inode,
+        append_pass,
+        append_percentage,
+        modify_percentage,
+        modified_output: _,
+        symlink_loops,
+        link_flavor,
+        read_back: _,
+        leftover_strategy,
+        size_distribution,
+        file_headers,
+        magic_bytes,
+        content,
+        human_info: _,
+    }: &Configuration,
+) -> u64 {
+    let mut hasher = XxHash64::with_seed(0);
+    format!(
+        "{:?}",
+        (
+            (
+                files,
+                bytes,
+                files_exact,
+                dirs_exact,
+                bytes_exact,
+                leftover_strategy,
+                size_distribution
+            ),
+            (
+                fill_pattern,
+                content,
+                file_headers,
+                magic_bytes,
+                sparse,
+                hole_pattern,
+                preallocate
+            ),
+            (
+                dirs_per_dir,
+                dir_fanout,
+                level_overrides,
+                bytes_per_file,
+                max_depth,
+                min_depth,
+                files_at_leaves,
+                skew,
+                files_per_dir_distribution,
+                traversal,
+                seed
+            ),
+            (
+                duplicate_percentage,
+                max_duplicates_per_file,
+                duplicates_as_hardlinks
+            ),
+            (
+                name_style,
+                file_name_template,
+                dir_name_template,
+                name_length,
+                max_name_percentage,
+                extensions
+            ),
+            (
+                whiteout_percentage,
+                case_collision_percentage,
+                broken_symlink_percentage,
+                special_files,
+                acl_percentage,
+                mtime_range,
+                timestamp,
+                windows_attribute_percentage,
+                ads_percentage,
+                links_per_inode
+            ),
+            (
+                append_pass,
+                append_percentage,
+                modify_percentage,
+                symlink_loops,
+                link_flavor
+            ),
+            (permissions, dir_permissions, permission_rules, prune_globs),
+            replica_seeds
+                .iter()
+                .map(|ReplicaSeed { seed, .. }| seed)
+                .collect::<Vec<_>>(),
+        )
+    )
+    .hash(&mut hasher);
+    hasher.finish()
+}
+
+#[derive(Debug, Clone, Copy)]
+struct ReplicaSeed {
+    /// The seed a standalone run would be given.
+    seed: u64,
+    /// The seed the generator is actually run with.
+    generator_seed: u64,
+}
+
+#[derive(Debug, Clone)]
+struct HumanInfo {
+    dirs_per_dir: usize,
+    total_dirs: usize,
+    bytes_per_files: usize,
+}
+
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "trace"))]
+fn validated_options(
+    Generator {
+        root_dir,
+        num_files_with_ratio,
+        files_exact,
+        num_dirs: target_num_dirs,
+        dirs_exact,
+        num_bytes,
+        fill_byte,
+        fill_pattern,
+        content_template,
+        content_corpus,
+        bytes_exact,
+        direct_io,
+        writeback_bytes,
+        fragment,
+        sparse,
+        hole_pattern,
+        preallocate,
+        max_depth,
+        flat,
+        min_depth,
+        files_at_leaves,
+        skew,
+        files_per_dir_distribution,
+        seed,
+        duplicate_percentage,
+        max_duplicates_per_file,
+        duplicates_as_hardlinks,
+        audit_output,
+        audit_format,
+        audit_batch_size,
+        audit_page_size,
+        audit_cache_size,
+        permissions,
+        permission_weights,
+        dir_permissions,
+        dir_permission_weights,
+        executable_percentage,
+        permission_mode,
+        permission_rules,
+        fio_output,
+        manifest,
+        compare_with,
+        validate,
+        replicas,
+        shards,
+        distinct_seeds,
+        prune_globs,
+        dir_fanout,
+        level_overrides,
+        check_distributions,
+        traversal,
+        max_in_flight_bytes,
+        max_files_per_task,
+        byte_counts_pool_capacity,
+        write_block_size,
+        whiteout_percentage,
+        portable_create,
+        name_style,
+        file_name_template,
+        dir_name_template,
+        name_length,
+        max_name_percentage,
+        extensions,
+        case_collision_percentage,
+        broken_symlink_percentage,
+        special_files,
+        acl_percentage,
+        mtime_range,
+        timestamp,
+        windows_attribute_percentage,
+        ads_percentage,
+        links_per_inode,
+        append_pass,
+        append_percentage,
+        modify_percentage,
+        modified_output,
+        symlink_loops,
+        link_flavor,
+        read_back,
+        leftover_strategy,
+        size_distribution,
+        file_headers,
+        magic_bytes,
+        content,
+        csv_columns,
+        dedup_percentage,
+        dedup_blocks,
+    }: Generator,
+) -> Result<Configuration, Error> {
+    for (name, percentage) in [
+        ("sparse percentage", sparse),
+        ("executable percentage", executable_percentage),
+        ("whiteout percentage", whiteout_percentage),
+        ("max name percentage", max_name_percentage),
+        ("case collision percentage", case_collision_percentage),
+        ("broken symlink percentage", broken_symlink_percentage),
+        ("special files percentage", special_files),
+        ("ACL percentage", acl_percentage),
+        ("Windows attribute percentage", windows_attribute_percentage),
+        ("ADS percentage", ads_percentage),
+        ("append percentage", append_percentage),
+        ("modify percentage", modify_percentage),
+        ("read back percentage", read_back),
+        ("dedup percentage", dedup_percentage),
+    ] {
+        validate_percentage(name, percentage)?;
+    }
+    // Duplicates may outnumber the files they duplicate
+    if let Some(percentage) = duplicate_percentage
+        && !(percentage.is_finite() && percentage >= 0.)
+    {
+        return Err(Report::new(Error::InvalidArgs))
+            .attach_printable(format!(
+                "The duplicate percentage ({percentage}) must be a finite number of at least 0."
+            ))
+            .attach(ExitCode::from(sysexits::ExitCode::Usage));
+    }
+    if !shards.is_empty() && (!compare_with.is_empty() || !replicas.is_empty()) {
+        return Err(Report::new(Error::InvalidEnvironment))
+            .attach_printable("Sharded trees cannot be compared or replicated.")
+            .attach(ExitCode::from(sysexits::ExitCode::Usage));
+    }
+    for dir in [&root_dir]
+        .into_iter()
+        .chain(&compare_with)
+        .chain(&replicas)
+        .chain(&shards)
+    {
+        validate_root_dir(dir)?;
+    }
+    // Shards are symlinked into the root, so relative paths would resolve
+    // against the wrong directory
+    let shards = shards
+        .iter()
+        .map(|shard| {
+            std::path::absolute(shard)
+                .attach_printable_lazy(|| format!("Failed to resolve shard {shard:?}"))
+                .change_context(Error::InvalidEnvironment)
+                .attach(ExitCode::from(sysexits::ExitCode::IoErr))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let prune_globs = prune_globs
+        .iter()
+        .map(|glob| {
+            Pattern::new(glob)
+                .attach_printable_lazy(|| format!("Failed to parse prune glob {glob:?}"))
+                .change_context(Error::InvalidPattern)
+                .attach(ExitCode::from(sysexits::ExitCode::Usage))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let (max_depth, files_exact) = if flat {
+        if target_num_dirs.is_some() || min_depth > 0 || files_at_leaves {
+            return Err(Report::new(Error::InvalidEnvironment))
+                .attach_printable("Flat trees cannot have directories.")
+                .attach(ExitCode::from(sysexits::ExitCode::Usage));
+        }
+        (0, true)
+    } else {
+        (max_depth, files_exact)
+    };
+
+    let skew = skew.unwrap_or(0.);
+    if !(0. ..1.).contains(&skew) {
+        return Err(Report::new(Error::InvalidEnvironment))
+            .attach_printable(format!("The skew ({skew}) must be at least 0 and below 1."))
+            .attach(ExitCode::from(sysexits::ExitCode::Usage));
+    }
+    if min_depth > max_depth {
+        return Err(Report::new(Error::InvalidEnvironment))
+            .attach_printable(format!(
+                "The minimum depth ({min_depth}) cannot exceed the maximum depth ({max_depth})."
+            ))
+            .attach(ExitCode::from(sysexits::ExitCode::Usage));
+    }
+
+    let dirs_exact = match (dirs_exact, target_num_dirs) {
+        (false, _) => None,
+        (true, None) => {
+            return Err(Report::new(Error::InvalidEnvironment))
+                .attach_printable("An exact directory count needs a number of directories.")
+                .attach(ExitCode::from(sysexits::ExitCode::Usage));
+        }
+        (true, Some(_)) if max_depth == 0 => {
+            return Err(Report::new(Error::InvalidEnvironment))
+                .attach_printable("A tree with a maximum depth of 0 cannot hold directories.")
+                .attach(ExitCode::from(sysexits::ExitCode::Usage));
+        }
+        (true, Some(_)) if files_at_leaves => {
+            return Err(Report::new(Error::InvalidEnvironment))
+                .attach_printable(
+                    "Trees with files only at their leaves cannot have an exact directory count.",
+                )
+                .attach(ExitCode::from(sysexits::ExitCode::Usage));
+        }
+        (true, Some(_)) if !prune_globs.is_empty() => {
+            return Err(Report::new(Error::InvalidEnvironment))
+                .attach_printable("Pruned trees cannot have an exact directory count.")
+                .attach(ExitCode::from(sysexits::ExitCode::Usage));
+        }
+        (true, Some(dirs)) => Some(dirs),
+    };
+
+    if let Some(fanout) = dir_fanout {
+        fanout
+            .distr()
+            .change_context(Error::InvalidDistribution)
+            .attach(ExitCode::from(sysexits::ExitCode::Usage))?;
+    }
+    for (i, level) in level_overrides.iter().enumerate() {
+        let depth = level.depth;
+        if depth > max_depth {
+            return Err(Report::new(Error::InvalidEnvironment))
+                .attach_printable(format!(
+                    "There are no directories at depth {depth} in a tree with a maximum depth of \
+                     {max_depth}."
+                ))
+                .attach(ExitCode::from(sysexits::ExitCode::Usage));
+        }
+        if level_overrides[..i]
+            .iter()
+            .any(|other| other.depth == depth)
+        {
+            return Err(Report::new(Error::InvalidEnvironment))
+                .attach_printable(format!("Depth {depth} is overridden more than once."))
+                .attach(ExitCode::from(sysexits::ExitCode::Usage));
+        }
+        if let Some(fanout) = level.dir_fanout {
+            fanout
+                .distr()
+                .change_context(Error::InvalidDistribution)
+                .attach(ExitCode::from(sysexits::ExitCode::Usage))?;
+        }
+    }
+    if let Some(links) = links_per_inode {
+        if duplicates_as_hardlinks {
+            return Err(Report::new(Error::InvalidEnvironment))
+                .attach_printable("Hardlink farms cannot also hardlink duplicates.")
+                .attach(ExitCode::from(sysexits::ExitCode::Usage));
+        }
+        if bytes_exact {
+            return Err(Report::new(Error::InvalidEnvironment))
+                .attach_printable("Hardlink farms cannot have an exact byte count.")
+                .attach(ExitCode::from(sysexits::ExitCode::Usage));
+        }
+        links
+            .distr()
+            .change_context(Error::InvalidDistribution)
+            .attach(ExitCode::from(sysexits::ExitCode::Usage))?;
+    }
+    let links_files = links_per_inode.is_some() || duplicates_as_hardlinks;
+    match link_flavor {
+        Some(_) if !links_files && symlink_loops.is_none() => {
+            return Err(Report::new(Error::InvalidEnvironment))
+                .attach_printable("A link flavor needs files or directories to link.")
+                .attach(ExitCode::from(sysexits::ExitCode::Usage));
+        }
+        Some(LinkFlavor::Hardlink) if symlink_loops.is_some() => {
+            return Err(Report::new(Error::InvalidEnvironment))
+                .attach_printable("Directories cannot be hardlinked into loops.")
+                .attach(ExitCode::from(sysexits::ExitCode::Usage));
+        }
+        Some(LinkFlavor::Junction) if links_files => {
+            return Err(Report::new(Error::InvalidEnvironment))
+                .attach_printable("Junctions can only link directories.")
+                .attach(ExitCode::from(sysexits::ExitCode::Usage));
+        }
+        _ => {}
+    }
+    let fill_pattern = match (fill_byte, fill_pattern) {
+        (Some(_), Some(_)) => {
+            return Err(Report::new(Error::InvalidEnvironment))
+                .attach_printable("A fill byte and a fill pattern cannot both be used.")
+                .attach(ExitCode::from(sysexits::ExitCode::Usage));
+        }
+        (fill_byte, fill_pattern) => fill_pattern.or_else(|| fill_byte.map(FillPattern::from)),
+    };
+    if (file_headers || magic_bytes)
+        && duplicate_percentage.is_some_and(|percentage| percentage > 0.)
+    {
+        return Err(Report::new(Error::InvalidEnvironment))
+            .attach_printable("Duplicate files cannot carry their own headers.")
+            .attach(ExitCode::from(sysexits::ExitCode::Usage));
+    }
+    if cfg!(not(target_os = "linux")) && special_files.is_some() {
+        return Err(Report::new(Error::InvalidEnvironment))
+            .attach_printable("Special files can only be created on Linux.")
+            .attach(ExitCode::from(sysexits::ExitCode::Usage));
+    }
+    if cfg!(not(target_os = "linux")) && acl_percentage.is_some() {
+        return Err(Report::new(Error::InvalidEnvironment))
+            .attach_printable("POSIX ACLs can only be applied on Linux.")
+            .attach(ExitCode::from(sysexits::ExitCode::Usage));
+    }
+    if (name_length.is_some() || max_name_percentage.is_some() || extensions.is_some())
+        && case_collision_percentage.is_some()
+    {
+        return Err(Report::new(Error::InvalidEnvironment))
+            .attach_printable("Padded or extended names cannot be made to collide by case.")
+            .attach(ExitCode::from(sysexits::ExitCode::Usage));
+    }
+    if name_style != NameStyle::Numeric && case_collision_percentage.is_some() {
+        return Err(Report::new(Error::InvalidEnvironment))
+            .attach_printable("Only numeric names can be made to collide by case.")
+            .attach(ExitCode::from(sysexits::ExitCode::Usage));
+    }
+    if (file_name_template.is_some() || dir_name_template.is_some())
+        && name_style != NameStyle::Numeric
+    {
+        return Err(Report::new(Error::InvalidEnvironment))
+            .attach_printable("Name templates cannot be combined with a name style.")
+            .attach(ExitCode::from(sysexits::ExitCode::Usage));
+    }
+    if file_name_template.is_some() && case_collision_percentage.is_some() {
+        return Err(Report::new(Error::InvalidEnvironment))
+            .attach_printable("Templated names cannot be made to collide by case.")
+            .attach(ExitCode::from(sysexits::ExitCode::Usage));
+    }
+    if dir_name_template
+        .as_ref()
+        .is_some_and(NameTemplate::has_extension)
+    {
+        return Err(Report::new(Error::InvalidEnvironment))
+            .attach_printable("Directory name templates cannot contain an extension.")
+            .attach(ExitCode::from(sysexits::ExitCode::Usage));
+    }
+    if cfg!(not(windows)) && windows_attribute_percentage.is_some() {
+        return Err(Report::new(Error::InvalidEnvironment))
+            .attach_printable("Windows file attributes can only be set on Windows.")
+            .attach(ExitCode::from(sysexits::ExitCode::Usage));
+    }
+    if cfg!(not(windows)) && ads_percentage.is_some() {
+        return Err(Report::new(Error::InvalidEnvironment))
+            .attach_printable("Alternate data streams can only be written on Windows.")
+            .attach(ExitCode::from(sysexits::ExitCode::Usage));
+    }
+    if windows_attribute_percentage.is_some()
+        && (fragment || append_pass.is_some() || modify_percentage.is_some())
+    {
+        return Err(Report::new(Error::InvalidEnvironment))
+            .attach_printable("Read-only files cannot be written to once generated.")
+            .attach(ExitCode::from(sysexits::ExitCode::Usage));
+    }
+    if sparse.is_some() && hole_pattern.is_some() {
+        return Err(Report::new(Error::InvalidEnvironment))
+            .attach_printable("Holes are laid out by either a percentage or a pattern, not both.")
+            .attach(ExitCode::from(sysexits::ExitCode::Usage));
+    }
+    if (sparse.is_some() || hole_pattern.is_some()) && fragment {
+        return Err(Report::new(Error::InvalidEnvironment))
+            .attach_printable("Sparse files cannot also be fragmented.")
+            .attach(ExitCode::from(sysexits::ExitCode::Usage));
+    }
+    if mtime_range.is_some() && fragment {
+        return Err(Report::new(Error::InvalidEnvironment))
+            .attach_printable("Fragmented files are written too late to be given times.")
+            .attach(ExitCode::from(sysexits::ExitCode::Usage));
+    }
+    if mtime_range.is_some() && timestamp.is_some() {
+        return Err(Report::new(Error::InvalidEnvironment))
+            .attach_printable("Files cannot be given both random and fixed times.")
+            .attach(ExitCode::from(sysexits::ExitCode::Usage));
+    }
+    let fill_pattern = if let Some(template) = content_template {
+        if fill_pattern.is_some() {
+            return Err(Report::new(Error::InvalidEnvironment))
+                .attach_printable("A content template cannot be combined with a fill pattern.")
+                .attach(ExitCode::from(sysexits::ExitCode::Usage));
+        }
+        let contents = fs::read(&template)
+            .attach_printable_lazy(|| format!("Failed to read content template {template:?}"))
+            .change_context(Error::Io)
+            .attach(ExitCode::from(sysexits::ExitCode::NoInput))?;
+        Some(
+            FillPattern::new(contents)
+                .ok_or_else(|| Report::new(Error::InvalidEnvironment))
+                .attach_printable_lazy(|| format!("Content template {template:?} is empty."))
+                .attach(ExitCode::from(sysexits::ExitCode::DataErr))?,
+        )
+    } else {
+        fill_pattern
+    };
+    let fill_pattern = if let Some(corpus) = content_corpus {
+        if fill_pattern.is_some() {
+            return Err(Report::new(Error::InvalidEnvironment))
+                .attach_printable("A content corpus cannot be combined with a fill pattern.")
+                .attach(ExitCode::from(sysexits::ExitCode::Usage));
+        }
+        let samples = read_corpus(&corpus)
+            .attach_printable_lazy(|| format!("Failed to read content corpus {corpus:?}"))
+            .change_context(Error::Io)
+            .attach(ExitCode::from(sysexits::ExitCode::NoInput))?;
+        Some(
+            FillPattern::corpus(samples)
+                .ok_or_else(|| Report::new(Error::InvalidEnvironment))
+                .attach_printable_lazy(|| format!("Content corpus {corpus:?} has no contents."))
+                .attach(ExitCode::from(sysexits::ExitCode::DataErr))?,
+        )
+    } else {
+        fill_pattern
+    };
+    let content = match (content, csv_columns) {
+        (ContentMode::Csv { .. }, Some(columns)) => ContentMode::Csv { columns },
+        (_, Some(_)) => {
+            return Err(Report::new(Error::InvalidEnvironment))
+                .attach_printable("Only CSV contents have columns.")
+                .attach(ExitCode::from(sysexits::ExitCode::Usage));
+        }
+        (content, None) => content,
+    };
+    let content = match (content, dedup_percentage, dedup_blocks) {
+        (
+            ContentMode::Dedup {
+                reuse_threshold,
+                blocks,
+            },
+            percentage,
+            new_blocks,
+        ) => ContentMode::Dedup {
+            reuse_threshold: percentage.map_or(reuse_threshold, |percentage| {
+                (percentage / 100. * u64::MAX as f64) as u64
+            }),
+            blocks: new_blocks.unwrap_or(blocks),
+        },
+        (_, Some(_), _) | (_, _, Some(_)) => {
+            return Err(Report::new(Error::InvalidEnvironment))
+                .attach_printable("Only dedup contents reuse blocks.")
+                .attach(ExitCode::from(sysexits::ExitCode::Usage));
+        }
+        (content, None, None) => content,
+    };
+    if content != ContentMode::Random && fill_pattern.is_some() {
+        return Err(Report::new(Error::InvalidEnvironment))
+            .attach_printable("Generated contents cannot be combined with a fill pattern.")
+            .attach(ExitCode::from(sysexits::ExitCode::Usage));
+    }
+    if matches!(content, ContentMode::Json | ContentMode::Csv { .. })
+        && (file_headers
+            || magic_bytes
+            || sparse.is_some()
+            || hole_pattern.is_some()
+            || append_pass.is_some()
+            || modify_percentage.is_some())
+    {
+        return Err(Report::new(Error::InvalidEnvironment))
+            .attach_printable(
+                "Headers, holes, appends, and modifications would break JSON and CSV files.",
+            )
+            .attach(ExitCode::from(sysexits::ExitCode::Usage));
     }
-}
-
-#[derive(Debug)]
-struct Configuration {
-    root_dir: PathBuf,
-    files: NonZeroU64,
-    bytes: u64,
-    files_exact: bool,
-    bytes_exact: bool,
-    fill_byte: Option<u8>,
-    dirs_per_dir: f64,
-    bytes_per_file: f64,
-    max_depth: u32,
-    seed: u64,
-    duplicate_percentage: f64,
-    max_duplicates_per_file: NonZeroUsize,
-    audit_output: Option<PathBuf>,
-    permissions: Vec<u32>,
-    human_info: HumanInfo,
-}
-
-#[derive(Debug)]
-struct HumanInfo {
-    dirs_per_dir: usize,
-    total_dirs: usize,
-    bytes_per_files: usize,
-}
 
-#[cfg_attr(feature = "tracing", tracing::instrument(level = "trace"))]
-fn validated_options(
-    Generator {
-        root_dir,
-        num_files_with_ratio,
-        files_exact,
-        num_bytes,
-        fill_byte,
-        bytes_exact,
-        max_depth,
-        seed,
-        duplicate_percentage,
-        max_duplicates_per_file,
-        audit_output,
-        permissions,
-    }: Generator,
-) -> Result<Configuration, Error> {
-    create_dir_all(&root_dir)
-        .attach_printable_lazy(|| format!("Failed to create directory {root_dir:?}"))
-        .change_context(Error::InvalidEnvironment)
-        .attach(ExitCode::from(sysexits::ExitCode::IoErr))?;
-    if root_dir
-        .read_dir()
-        .attach_printable_lazy(|| format!("Failed to read directory {root_dir:?}"))
-        .change_context(Error::InvalidEnvironment)
-        .attach(ExitCode::from(sysexits::ExitCode::IoErr))?
-        .count()
-        != 0
+    if preallocate
+        && (fill_pattern.is_some()
+            || content != ContentMode::Random
+            || file_headers
+            || magic_bytes
+            || fragment
+            || sparse.is_some()
+            || hole_pattern.is_some())
     {
         return Err(Report::new(Error::InvalidEnvironment))
-            .attach_printable(format!("The root directory {root_dir:?} must be empty."))
-            .attach(ExitCode::from(sysexits::ExitCode::DataErr));
+            .attach_printable("Preallocated files have no contents to fill, fragment, or punch.")
+            .attach(ExitCode::from(sysexits::ExitCode::Usage));
+    }
+
+    let permission_table = |permissions, permission_weights: &Vec<u32>, executable_percentage| {
+        let permissions = PermissionTable::new(permissions, permission_weights)
+            .ok_or_else(|| Report::new(Error::InvalidPermissionWeights))
+            .attach_printable_lazy(|| {
+                format!("Expected a weight for each permission, got {permission_weights:?}")
+            })
+            .attach(ExitCode::from(sysexits::ExitCode::Usage))?;
+        let permissions = match executable_percentage {
+            Some(percentage) => permissions.with_executable_percentage(percentage),
+            None => permissions,
+        };
+        #[cfg(unix)]
+        let permissions = match permission_mode {
+            PermissionMode::Absolute => permissions,
+            PermissionMode::UmaskRelative => permissions.with_umask(crate::core::umask()),
+        };
+        Ok::<_, Report<Error>>(permissions)
+    };
+    let permissions = permission_table(permissions, &permission_weights, executable_percentage)?;
+    if let Some(mode) = dir_permissions.iter().find(|&&mode| mode & 0o700 != 0o700) {
+        return Err(Report::new(Error::InvalidEnvironment))
+            .attach_printable(format!(
+                "Directory permission {mode:o} doesn't let the directory be filled in: its owner \
+                 needs full access."
+            ))
+            .attach(ExitCode::from(sysexits::ExitCode::Usage));
     }
+    // Directories are executable as is
+    let dir_permissions = permission_table(dir_permissions, &dir_permission_weights, None)?;
+    let permission_rules = permission_rules
+        .into_iter()
+        .map(
+            |PermissionRule {
+                 glob,
+                 permissions,
+                 permission_weights,
+             }| {
+                let pattern = Pattern::new(&glob)
+                    .attach_printable_lazy(|| {
+                        format!("Failed to parse permission rule glob {glob:?}")
+                    })
+                    .change_context(Error::InvalidPattern)
+                    .attach(ExitCode::from(sysexits::ExitCode::Usage))?;
+                Ok((
+                    pattern,
+                    permission_table(permissions, &permission_weights, executable_percentage)?,
+                ))
+            },
+        )
+        .collect::<Result<Vec<_>, Error>>()?;
+    let audit_metadata =
+        if permissions.is_empty() && dir_permissions.is_empty() && permission_rules.is_empty() {
+            Vec::new()
+        } else {
+            vec![("permissions".to_string(), permission_mode.to_string())]
+        };
 
     let num_files = num_files_with_ratio.num_files.get() as f64;
     let bytes_per_file = num_bytes as f64 / num_files;
     let duplicate_percentage = duplicate_percentage.unwrap_or(0.0);
     let max_duplicates_per_file = max_duplicates_per_file.unwrap_or(NonZeroUsize::new(1).unwrap());
+    let max_files_per_task =
+        max_files_per_task.map_or(DEFAULT_MAX_FILES_PER_TASK, NonZeroUsize::get);
+    let write_block_size = write_block_size.unwrap_or(DEFAULT_WRITE_BLOCK_SIZE);
+    let append_percentage = append_percentage.unwrap_or(DEFAULT_APPEND_PERCENTAGE);
+    let whiteout_percentage = whiteout_percentage.unwrap_or(0.0);
+    let audit_sqlite = SqliteSettings {
+        batch_size: audit_batch_size.unwrap_or(DEFAULT_AUDIT_BATCH_SIZE),
+        page_size: audit_page_size.unwrap_or(DEFAULT_AUDIT_PAGE_SIZE),
+        cache_size: audit_cache_size.unwrap_or(DEFAULT_AUDIT_CACHE_SIZE),
+    };
+    let audit_format = audit_format.unwrap_or_else(|| {
+        audit_output
+            .as_deref()
+            .map_or(AuditFormat::Csv, AuditFormat::from_path)
+    });
+
+    let generator_seed = |seed| {
+        if max_depth == 0 {
+            seed
+        } else {
+            let mut hasher = DefaultHasher::new();
+            (num_files_with_ratio, max_depth, seed).hash(&mut hasher);
+            hasher.finish()
+        }
+    };
+    let replica_seeds = if distinct_seeds && !replicas.is_empty() {
+        (0..=replicas.len())
+            .map(|i| {
+                let seed = derive_replica_seed(seed, i);
+                ReplicaSeed {
+                    seed,
+                    generator_seed: generator_seed(seed),
+                }
+            })
+            .collect()
+    } else {
+        Vec::new()
+    };
 
     if max_depth == 0 {
         return Ok(Configuration {
@@ -224,16 +2964,78 @@ fn validated_options(
             files: num_files_with_ratio.num_files,
             bytes: num_bytes,
             files_exact,
+            dirs_exact,
             bytes_exact,
-            fill_byte,
+            fill_pattern,
+            direct_io,
+            writeback_bytes,
+            fragment,
+            sparse,
+            hole_pattern,
+            preallocate,
             dirs_per_dir: 0.,
+            dir_fanout: None,
+            level_overrides: Vec::new(),
             bytes_per_file,
             max_depth: 0,
-            seed,
+            min_depth: 0,
+            files_at_leaves,
+            skew,
+            files_per_dir_distribution,
+            seed: generator_seed(seed),
             duplicate_percentage,
             max_duplicates_per_file,
+            duplicates_as_hardlinks,
             audit_output,
+            audit_sqlite,
+            audit_format,
             permissions,
+            dir_permissions,
+            permission_rules,
+            fio_output,
+            manifest,
+            compare_with,
+            validate,
+            replicas,
+            shards,
+            replica_seeds,
+            audit_metadata: audit_metadata.clone(),
+            prune_globs,
+            check_distributions,
+            traversal,
+            max_in_flight_bytes,
+            max_files_per_task,
+            byte_counts_pool_capacity,
+            write_block_size,
+            whiteout_percentage,
+            portable_create,
+            name_style,
+            file_name_template,
+            dir_name_template,
+            name_length,
+            max_name_percentage,
+            extensions,
+            case_collision_percentage,
+            broken_symlink_percentage,
+            special_files,
+            acl_percentage,
+            mtime_range,
+            timestamp,
+            windows_attribute_percentage,
+            ads_percentage,
+            links_per_inode,
+            append_pass,
+            append_percentage,
+            modify_percentage,
+            modified_output,
+            symlink_loops,
+            link_flavor,
+            read_back,
+            leftover_strategy,
+            size_distribution,
+            file_headers,
+            magic_bytes,
+            content,
             human_info: HumanInfo {
                 dirs_per_dir: 0,
                 total_dirs: 1,
@@ -242,30 +3044,100 @@ fn validated_options(
         });
     }
 
-    let num_dirs = num_files / (num_files_with_ratio.file_to_dir_ratio.get() as f64);
-    // This formula was derived from the following equation:
-    // num_dirs = unknown_num_dirs_per_dir^max_depth
-    let dirs_per_dir = num_dirs.powf(1f64 / f64::from(max_depth));
+    let (num_dirs, dirs_per_dir) = if let Some(fanout) = dir_fanout {
+        let dirs_per_dir = fanout.mean();
+        let num_dirs = target_num_dirs.map_or_else(
+            || dirs_per_dir.powf(f64::from(max_depth)),
+            |dirs| dirs.get() as f64,
+        );
+        (num_dirs, dirs_per_dir)
+    } else {
+        let num_dirs = target_num_dirs.map_or_else(
+            || num_files / (num_files_with_ratio.file_to_dir_ratio.get() as f64),
+            |dirs| dirs.get() as f64,
+        );
+        // This formula was derived from the following equation:
+        // num_dirs = unknown_num_dirs_per_dir^max_depth
+        (num_dirs, num_dirs.powf(1f64 / f64::from(max_depth)))
+    };
 
     Ok(Configuration {
         root_dir,
         files: num_files_with_ratio.num_files,
         bytes: num_bytes,
         files_exact,
+        dirs_exact,
         bytes_exact,
-        fill_byte,
+        fill_pattern,
+        direct_io,
+        writeback_bytes,
+        fragment,
+        sparse,
+        hole_pattern,
+        preallocate,
         bytes_per_file,
         dirs_per_dir,
+        dir_fanout,
+        level_overrides,
         max_depth,
-        seed: {
-            let mut hasher = DefaultHasher::new();
-            (num_files_with_ratio, max_depth, seed).hash(&mut hasher);
-            hasher.finish()
-        },
+        min_depth,
+        files_at_leaves,
+        skew,
+        files_per_dir_distribution,
+        seed: generator_seed(seed),
         duplicate_percentage,
         max_duplicates_per_file,
+        duplicates_as_hardlinks,
         audit_output,
+        audit_sqlite,
+        audit_format,
         permissions,
+        dir_permissions,
+        permission_rules,
+        fio_output,
+        manifest,
+        compare_with,
+        validate,
+        replicas,
+        shards,
+        replica_seeds,
+        audit_metadata,
+        prune_globs,
+        check_distributions,
+        traversal,
+        max_in_flight_bytes,
+        max_files_per_task,
+        byte_counts_pool_capacity,
+        write_block_size,
+        whiteout_percentage,
+        portable_create,
+        name_style,
+        file_name_template,
+        dir_name_template,
+        name_length,
+        max_name_percentage,
+        extensions,
+        case_collision_percentage,
+        broken_symlink_percentage,
+        special_files,
+        acl_percentage,
+        mtime_range,
+        timestamp,
+        windows_attribute_percentage,
+        ads_percentage,
+        links_per_inode,
+        append_pass,
+        append_percentage,
+        modify_percentage,
+        modified_output,
+        symlink_loops,
+        link_flavor,
+        read_back,
+        leftover_strategy,
+        size_distribution,
+        file_headers,
+        magic_bytes,
+        content,
         human_info: HumanInfo {
             dirs_per_dir: dirs_per_dir.round() as usize,
             total_dirs: num_dirs.round() as usize,
@@ -274,6 +3146,61 @@ fn validated_options(
     })
 }
 
+/// Reads every file in `dir` and its subdirectories, in path order so the
+/// samples don't depend on the order they are listed in.
+fn read_corpus(dir: &Path) -> io::Result<Vec<Vec<u8>>> {
+    let mut files = Vec::new();
+    let mut queue = vec![dir.to_path_buf()];
+    while let Some(dir) = queue.pop() {
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            let file_type = entry.file_type()?;
+            if file_type.is_dir() {
+                queue.push(entry.path());
+            } else if file_type.is_file() {
+                files.push(entry.path());
+            }
+        }
+    }
+    files.sort_unstable();
+    files.iter().map(fs::read).collect()
+}
+
+/// Makes sure a percentage is between 0 and 100, as it may not have come
+/// through the CLI's parser.
+fn validate_percentage(name: &str, percentage: Option<f64>) -> Result<(), Error> {
+    match percentage {
+        Some(percentage) if !(0. ..=100.).contains(&percentage) => {
+            Err(Report::new(Error::InvalidArgs))
+                .attach_printable(format!(
+                    "The {name} ({percentage}) must be between 0 and 100."
+                ))
+                .attach(ExitCode::from(sysexits::ExitCode::Usage))
+        }
+        _ => Ok(()),
+    }
+}
+
+fn validate_root_dir(root_dir: &Path) -> Result<(), Error> {
+    create_dir_all(root_dir)
+        .attach_printable_lazy(|| format!("Failed to create directory {root_dir:?}"))
+        .change_context(Error::InvalidEnvironment)
+        .attach(ExitCode::from(sysexits::ExitCode::IoErr))?;
+    if root_dir
+        .read_dir()
+        .attach_printable_lazy(|| format!("Failed to read directory {root_dir:?}"))
+        .change_context(Error::InvalidEnvironment)
+        .attach(ExitCode::from(sysexits::ExitCode::IoErr))?
+        .count()
+        != 0
+    {
+        return Err(Report::new(Error::InvalidEnvironment))
+            .attach_printable(format!("The root directory {root_dir:?} must be empty."))
+            .attach(ExitCode::from(sysexits::ExitCode::DataErr));
+    }
+    Ok(())
+}
+
 #[cfg_attr(
     feature = "tracing",
     tracing::instrument(level = "trace", skip(output))
@@ -284,15 +3211,31 @@ fn print_configuration_info(
         files,
         bytes,
         files_exact,
+        dirs_exact,
         bytes_exact,
-        fill_byte: _,
+        fill_pattern: _,
+        direct_io: _,
+        writeback_bytes: _,
+        fragment: _,
+        sparse: _,
+        hole_pattern: _,
+        preallocate: _,
         dirs_per_dir: _,
+        dir_fanout: _,
+        level_overrides: _,
         bytes_per_file: _,
         max_depth,
+        min_depth: _,
+        files_at_leaves: _,
+        skew: _,
+        files_per_dir_distribution: _,
         seed: _,
         duplicate_percentage,
         max_duplicates_per_file: _,
+        duplicates_as_hardlinks: _,
         audit_output: _,
+        audit_sqlite: _,
+        audit_format: _,
         human_info:
             HumanInfo {
                 dirs_per_dir,
@@ -300,12 +3243,58 @@ fn print_configuration_info(
                 bytes_per_files,
             },
         permissions: _,
+        dir_permissions: _,
+        permission_rules: _,
+        fio_output: _,
+        manifest: _,
+        compare_with: _,
+        validate: _,
+        replicas: _,
+        shards: _,
+        replica_seeds: _,
+        audit_metadata: _,
+        prune_globs: _,
+        check_distributions: _,
+        traversal: _,
+        max_in_flight_bytes: _,
+        max_files_per_task: _,
+        byte_counts_pool_capacity: _,
+        write_block_size: _,
+        whiteout_percentage: _,
+        portable_create: _,
+        name_style: _,
+        file_name_template: _,
+        dir_name_template: _,
+        name_length: _,
+        max_name_percentage: _,
+        extensions: _,
+        case_collision_percentage: _,
+        broken_symlink_percentage: _,
+        special_files: _,
+        acl_percentage: _,
+        mtime_range: _,
+        timestamp: _,
+        windows_attribute_percentage: _,
+        ads_percentage: _,
+        links_per_inode: _,
+        append_pass: _,
+        append_percentage: _,
+        modify_percentage: _,
+        modified_output: _,
+        symlink_loops: _,
+        link_flavor: _,
+        read_back: _,
+        leftover_strategy: _,
+        size_distribution: _,
+        file_headers: _,
+        magic_bytes: _,
+        content: _,
     }: &Configuration,
     output: &mut impl Write,
 ) -> Result<(), Error> {
     writeln!(
         output,
-        "{file_count_type} {} {files_maybe_plural} will be generated in approximately {} \
+        "{file_count_type} {} {files_maybe_plural} will be generated in {dir_count_type} {} \
          {directories_maybe_plural} distributed across a tree of maximum depth {} where each \
          directory contains approximately {} other \
          {dpd_directories_maybe_plural}.{bytes_info}{duplicate_info}",
@@ -314,6 +3303,11 @@ fn print_configuration_info(
         max_depth.separate_with_commas(),
         dirs_per_dir.separate_with_commas(),
         file_count_type = if files_exact { "Exactly" } else { "About" },
+        dir_count_type = if dirs_exact.is_some() {
+            "exactly"
+        } else {
+            "approximately"
+        },
         files_maybe_plural = if files.get() == 1 { "file" } else { "files" },
         directories_maybe_plural = if total_dirs == 1 {
             "directory"
@@ -362,11 +3356,138 @@ fn print_configuration_info(
     .attach(ExitCode::from(sysexits::ExitCode::IoErr))
 }
 
+/// What `--check-distributions` tests the generated tree against.
+#[derive(Debug)]
+struct DistributionCheck {
+    root_dir: PathBuf,
+    max_depth: usize,
+    file_sizes: Option<Normal<f64>>,
+    skipped_file_sizes: Option<&'static str>,
+    dir_fanout: DirFanout,
+}
+
+impl DistributionCheck {
+    fn new(
+        &Configuration {
+            ref root_dir,
+            bytes,
+            bytes_exact,
+            dirs_per_dir,
+            dir_fanout,
+            bytes_per_file,
+            max_depth,
+            size_distribution,
+            ..
+        }: &Configuration,
+    ) -> Self {
+        let skipped_file_sizes = if bytes == 0 {
+            Some("no file contents were requested")
+        } else if bytes_exact {
+            Some("sizes are adjusted to generate an exact number of bytes")
+        } else if size_distribution != SizeDistribution::Normal {
+            Some("only normally distributed file sizes are checked")
+        } else {
+            None
+        };
+
+        Self {
+            root_dir: root_dir.clone(),
+            max_depth: max_depth.try_into().unwrap_or(usize::MAX),
+            file_sizes: skipped_file_sizes
+                .is_none()
+                .then(|| truncatable_normal(bytes_per_file)),
+            skipped_file_sizes,
+            dir_fanout: dir_fanout.unwrap_or(DirFanout::Normal {
+                mean: dirs_per_dir,
+                std_dev: (dirs_per_dir + 0.5) / 3.,
+            }),
+        }
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(level = "trace", skip(output))
+    )]
+    fn run(self, output: &mut impl Write) -> Result<(), Error> {
+        let Self {
+            root_dir,
+            max_depth,
+            file_sizes,
+            skipped_file_sizes,
+            dir_fanout,
+        } = self;
+
+        log!(Level::Info, "Checking generated distributions...");
+        let DistributionReport {
+            file_sizes,
+            dir_fanout,
+        } = check_distributions(&root_dir, max_depth, file_sizes.as_ref(), |k| {
+            dir_fanout.probability(k)
+        })
+        .change_context(Error::DistributionCheck)
+        .attach(ExitCode::from(sysexits::ExitCode::IoErr))?;
+
+        if let Some(reason) = skipped_file_sizes {
+            writeln!(output, "File sizes: not checked since {reason}.")
+        } else {
+            file_sizes.map_or(Ok(()), |test| {
+                print_fit_test("File sizes", "files", test, output)
+            })
+        }
+        .and_then(|()| print_fit_test("Directory fan-out", "directories", dir_fanout, output))
+        .attach_printable("Failed to write to output stream")
+        .change_context(Error::Io)
+        .attach(ExitCode::from(sysexits::ExitCode::IoErr))
+    }
+}
+
+fn print_fit_test(
+    name: &str,
+    samples_name: &str,
+    test: FitTest,
+    output: &mut impl Write,
+) -> std::fmt::Result {
+    match test {
+        FitTest::KolmogorovSmirnov {
+            statistic,
+            samples,
+            p_value,
+        } => writeln!(
+            output,
+            "{name}: Kolmogorov-Smirnov D = {statistic:.4} over {} {samples_name} (p = \
+             {p_value:.3}).",
+            samples.separate_with_commas(),
+        ),
+        FitTest::ChiSquare {
+            statistic,
+            degrees_of_freedom,
+            samples,
+            p_value,
+        } => writeln!(
+            output,
+            "{name}: chi-square = {statistic:.2} with {degrees_of_freedom} degrees of freedom \
+             over {} {samples_name} (p = {p_value:.3}).",
+            samples.separate_with_commas(),
+        ),
+        FitTest::InsufficientData { samples } => writeln!(
+            output,
+            "{name}: not enough {samples_name} to test ({}).",
+            samples.separate_with_commas(),
+        ),
+    }
+}
+
 #[cfg_attr(
     feature = "tracing",
     tracing::instrument(level = "trace", skip(output))
 )]
-fn print_stats(GeneratorStats { files, dirs, bytes }: GeneratorStats, output: &mut impl Write) {
+fn print_stats(stats: GeneratorStats, targets: Targets, output: &mut impl Write) {
+    let GeneratorStats {
+        files,
+        dirs,
+        bytes,
+        duplicates: _,
+    } = stats;
     // Ignore I/O errors since it'd be dumb to fail if we actually succeeded in
     // creating all the files
     let _ = writeln!(
@@ -387,10 +3508,219 @@ fn print_stats(GeneratorStats { files, dirs, bytes }: GeneratorStats, output: &m
             String::new()
         }
     );
+
+    let deltas = targets
+        .deltas(stats)
+        .into_iter()
+        .filter(|delta| delta.name == "files" || delta.requested > 0 || delta.achieved > 0)
+        .map(|delta| {
+            let absolute = delta.absolute();
+            let sign = if absolute < 0 { "-" } else { "+" };
+            let absolute = absolute.unsigned_abs().separate_with_commas();
+            match delta.percentage() {
+                Some(percentage) => format!("{} {sign}{absolute} ({percentage:+.2}%)", delta.name),
+                None => format!("{} {sign}{absolute}", delta.name),
+            }
+        })
+        .collect::<Vec<_>>();
+    let _ = writeln!(output, "Off target by: {}.", deltas.join(", "));
+}
+
+/// What a run was asked to create, to report how close it came.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Targets {
+    files: u64,
+    dirs: u64,
+    bytes: u64,
+    duplicates: u64,
+}
+
+/// How far an achieved count landed from the requested one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Delta {
+    pub name: &'static str,
+    pub requested: u64,
+    pub achieved: u64,
+}
+
+impl Delta {
+    pub(crate) fn absolute(self) -> i128 {
+        i128::from(self.achieved) - i128::from(self.requested)
+    }
+
+    /// The delta relative to the requested count, unless nothing was
+    /// requested.
+    pub(crate) fn percentage(self) -> Option<f64> {
+        (self.requested > 0).then(|| self.absolute() as f64 / self.requested as f64 * 100.)
+    }
+}
+
+impl Targets {
+    fn new(
+        &Configuration {
+            files,
+            bytes,
+            dirs_exact,
+            duplicate_percentage,
+            human_info: HumanInfo { total_dirs, .. },
+            ..
+        }: &Configuration,
+    ) -> Self {
+        Self {
+            files: files.get(),
+            // The root directory isn't counted as generated
+            dirs: dirs_exact.map_or(total_dirs.saturating_sub(1) as u64, NonZeroU64::get),
+            bytes,
+            // Duplicates copy the contents of other files, so none are made
+            // when there are no contents
+            duplicates: if bytes == 0 {
+                0
+            } else {
+                (files.get() as f64 * duplicate_percentage / 100.).round() as u64
+            },
+        }
+    }
+
+    /// Compares each target against what was achieved. Duplicates are
+    /// compared on their own rather than as files.
+    pub(crate) fn deltas(self, stats: GeneratorStats) -> [Delta; 4] {
+        let Self {
+            files,
+            dirs,
+            bytes,
+            duplicates,
+        } = self;
+        [
+            ("files", files, stats.files - stats.duplicates),
+            ("directories", dirs, stats.dirs as u64),
+            ("bytes", bytes, stats.bytes),
+            ("duplicates", duplicates, stats.duplicates),
+        ]
+        .map(|(name, requested, achieved)| Delta {
+            name,
+            requested,
+            achieved,
+        })
+    }
+}
+
+/// Generates the identical tree into the root directory and every comparison
+/// target in turn, reporting how long each target took.
+///
+/// Targets are run sequentially rather than concurrently so they don't compete
+/// for CPU time and the timings remain comparable.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "trace", skip(output))
+)]
+fn run_comparison(mut config: Configuration, output: &mut impl Write) -> Result<(), Error> {
+    let requested = Targets::new(&config);
+    let targets = [config.root_dir.clone()]
+        .into_iter()
+        .chain(mem::take(&mut config.compare_with))
+        .collect::<Vec<_>>();
+
+    let mut timings = Vec::with_capacity(targets.len());
+    for (i, target) in targets.iter().enumerate() {
+        let mut config = config.clone();
+        config.root_dir.clone_from(target);
+        if i > 0 {
+            // Only the first target produces the audit trail and exports
+            config.audit_output = None;
+            config.fio_output = None;
+            config.manifest = None;
+        }
+
+        let start = Instant::now();
+        let stats = run_generator(config)?;
+        timings.push(start.elapsed());
+
+        let _ = write!(output, "{target:?}: ");
+        print_stats(stats, requested, output);
+    }
+
+    let baseline = timings[0].as_secs_f64();
+    for (target, elapsed) in targets.iter().zip(timings) {
+        let _ = writeln!(
+            output,
+            "{target:?} took {elapsed:.2?}{relative}",
+            relative = if baseline > 0. && target != &targets[0] {
+                format!(
+                    " ({:+.1}% vs {:?})",
+                    (elapsed.as_secs_f64() / baseline - 1.) * 100.,
+                    targets[0]
+                )
+            } else {
+                String::new()
+            }
+        );
+    }
+    Ok(())
+}
+
+/// Generates the identical tree into the root directory and every replica
+/// concurrently.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(level = "trace", skip(output))
+)]
+fn run_replicas(mut config: Configuration, output: &mut impl Write) -> Result<(), Error> {
+    let requested = Targets::new(&config);
+    let targets = [config.root_dir.clone()]
+        .into_iter()
+        .chain(mem::take(&mut config.replicas))
+        .collect::<Vec<_>>();
+
+    let replica_seeds = mem::take(&mut config.replica_seeds);
+    config.audit_metadata.extend(
+        targets
+            .iter()
+            .zip(&replica_seeds)
+            .map(|(target, ReplicaSeed { seed, .. })| {
+                (format!("seed {target:?}"), seed.to_string())
+            }),
+    );
+
+    let results = thread::scope(|scope| {
+        targets
+            .iter()
+            .enumerate()
+            .map(|(i, target)| {
+                let mut config = config.clone();
+                config.root_dir.clone_from(target);
+                if let Some(&ReplicaSeed { generator_seed, .. }) = replica_seeds.get(i) {
+                    config.seed = generator_seed;
+                }
+                if i > 0 {
+                    // Only the first replica produces the audit trail and exports
+                    config.audit_output = None;
+                    config.fio_output = None;
+                    config.manifest = None;
+                    config.modified_output = None;
+                }
+                scope.spawn(move || run_generator(config))
+            })
+            .collect::<Vec<_>>()
+            .into_iter()
+            .map(|replica| {
+                replica
+                    .join()
+                    .map_err(|_| Report::new(Error::TaskJoin))
+                    .attach(ExitCode::from(sysexits::ExitCode::Software))
+            })
+            .collect::<Vec<_>>()
+    });
+
+    for (target, result) in targets.iter().zip(results) {
+        let stats = result??;
+        let _ = write!(output, "{target:?}: ");
+        print_stats(stats, requested, output);
+    }
+    Ok(())
 }
 
 #[cfg_attr(feature = "tracing", tracing::instrument(level = "trace"))]
-fn run_generator(config: Configuration) -> Result<GeneratorStats, Error> {
+fn run_generator(mut config: Configuration) -> Result<GeneratorStats, Error> {
     let parallelism = thread::available_parallelism().unwrap_or(NonZeroUsize::new(1).unwrap());
     let mut runtime = tokio::runtime::Builder::new_current_thread();
     #[cfg(all(not(miri), target_os = "linux"))]
@@ -409,54 +3739,255 @@ fn run_generator(config: Configuration) -> Result<GeneratorStats, Error> {
         .attach(ExitCode::from(sysexits::ExitCode::OsErr))?;
 
     log!(Level::Info, "Starting config: {config:?}");
+    let root_dir = config.validate.then(|| config.root_dir.clone());
+    let sharded = !config.shards.is_empty();
     let audit_output = config.audit_output.clone();
-    let audit_trail = audit_output.as_ref().map(|_| Arc::new(AuditTrail::new()));
+    let audit_sqlite = config.audit_sqlite;
+    let audit_format = config.audit_format;
+    let fio_output = config.fio_output.clone();
+    let manifest = config.manifest.clone();
+    let root = config.root_dir.clone();
+    let read_back = config.read_back;
+    let seed = config.seed;
+    let documented = audit_output.is_some() || manifest.is_some();
+    // Files are only kept in memory when something other than the audit output
+    // revisits them, such as reading them back against the trail's hashes
+    let revisited = manifest.is_some()
+        || fio_output.is_some()
+        || read_back.is_some()
+        || config.modify_percentage.is_some()
+        || config.append_pass.is_some();
+    let streaming = audit_output.is_some() && !revisited;
+    let audit_trail = match &audit_output {
+        Some(output) if streaming => Some(
+            AuditTrail::streaming(output, audit_format, audit_sqlite)
+                .attach_printable_lazy(|| format!("Failed to open audit trail {output:?}"))
+                .change_context(Error::Io)
+                .attach(ExitCode::from(sysexits::ExitCode::IoErr))?,
+        ),
+        _ => (documented || revisited).then(AuditTrail::new),
+    }
+    .map(Arc::new);
+    if let Some(trail) = &audit_trail {
+        for (key, value) in mem::take(&mut config.audit_metadata) {
+            trail.add_metadata(key, value);
+        }
+        trail.add_metadata("started".to_string(), timestamp(SystemTime::now()));
+    }
 
+    let run_id = plan_hash(&config);
     let res = runtime.block_on(run_generator_async(
         config,
         parallelism,
         audit_trail.clone(),
+        run_id,
     ));
 
-    if let (Ok(_), Some(output), Some(trail)) = (&res, &audit_output, &audit_trail) {
+    if let (Ok(_), Some(percentage), Some(trail)) = (&res, read_back, &audit_trail) {
+        log!(Level::Info, "Reading back {percentage}% of files...");
+        let (files, mismatches) = read_back_files(
+            trail,
+            (percentage / 100. * u64::MAX as f64) as u64,
+            seed,
+            parallelism,
+        )
+        .change_context(Error::Io)
+        .attach(ExitCode::from(sysexits::ExitCode::IoErr))?;
+        log!(Level::Info, "Read back {files} files");
+        trail.add_metadata(
+            "read back".to_string(),
+            format!("{files} files, {} mismatched", mismatches.len()),
+        );
+        if !mismatches.is_empty() {
+            return Err(Report::new(Error::ReadBackFailed))
+                .attach_printable(format!(
+                    "{} of {files} files read back differ from what was written, starting \
+                     with {:?}",
+                    mismatches.len(),
+                    &mismatches[..mismatches.len().min(10)]
+                ))
+                .attach(ExitCode::from(sysexits::ExitCode::DataErr));
+        }
+    }
+
+    if let (Ok(_), true, Some(trail)) = (&res, documented, &audit_trail) {
+        trail.add_metadata("finished".to_string(), timestamp(SystemTime::now()));
         log!(Level::Info, "Post-processing audit trail...");
         trail.calculate_directory_sizes();
+    }
+
+    if let (Ok(_), Some(output), Some(trail)) = (&res, &audit_output, &audit_trail) {
         log!(Level::Info, "Writing audit trail to {output:?}...");
-        let extension = output.extension().and_then(|s| s.to_str());
-        match extension {
-            Some("db" | "sqlite") => trail.write_sqlite(output).map_err(io::Error::other),
-            _ => trail.write_csv(output),
+        if streaming {
+            trail.finish_stream()
+        } else {
+            trail.write(output, audit_format, audit_sqlite)
         }
         .attach_printable_lazy(|| format!("Failed to write audit trail to {output:?}"))
         .change_context(Error::Io)
         .attach(ExitCode::from(sysexits::ExitCode::IoErr))?;
     }
 
+    if let (Ok(stats), Some(root_dir)) = (&res, root_dir) {
+        log!(Level::Info, "Validating generated tree...");
+        let actual = GeneratorStats {
+            duplicates: stats.duplicates,
+            ..recount(root_dir, parallelism, sharded)
+                .change_context(Error::Io)
+                .attach(ExitCode::from(sysexits::ExitCode::IoErr))?
+        };
+        if actual != *stats {
+            return Err(Report::new(Error::ValidationFailed))
+                .attach_printable(format!("Expected {stats:?}"))
+                .attach_printable(format!("Found {actual:?}"))
+                .attach(ExitCode::from(sysexits::ExitCode::DataErr));
+        }
+    }
+
+    if let (Ok(_), Some(output), Some(trail)) = (&res, &fio_output, &audit_trail) {
+        log!(Level::Info, "Writing fio job file to {output:?}...");
+        trail
+            .write_fio(output)
+            .attach_printable_lazy(|| format!("Failed to write fio job file to {output:?}"))
+            .change_context(Error::Io)
+            .attach(ExitCode::from(sysexits::ExitCode::IoErr))?;
+    }
+
+    if let (Ok(_), Some(output), Some(trail)) = (&res, &manifest, &audit_trail) {
+        log!(Level::Info, "Writing manifest to {output:?}...");
+        trail
+            .write_manifest(output, &root)
+            .attach_printable_lazy(|| format!("Failed to write manifest to {output:?}"))
+            .change_context(Error::Io)
+            .attach(ExitCode::from(sysexits::ExitCode::IoErr))?;
+    }
+
     res
 }
 
 #[cfg_attr(feature = "tracing", tracing::instrument(level = "trace"))]
+/// The kinds of special files that can be created, device nodes requiring
+/// root.
+fn special_types() -> Vec<EntryType> {
+    let mut types = vec![EntryType::Fifo, EntryType::Socket];
+    #[cfg(unix)]
+    if rustix::process::geteuid().is_root() {
+        types.extend([EntryType::CharDevice, EntryType::BlockDevice]);
+    }
+    types
+}
+
 async fn run_generator_async(
     Configuration {
         root_dir,
         files,
         bytes,
         files_exact,
+        dirs_exact,
         bytes_exact,
-        fill_byte,
+        fill_pattern,
+        direct_io,
+        writeback_bytes,
+        fragment,
+        sparse,
+        hole_pattern,
+        preallocate,
         dirs_per_dir,
+        dir_fanout,
+        level_overrides,
         bytes_per_file,
         max_depth,
+        min_depth,
+        files_at_leaves,
+        skew,
+        files_per_dir_distribution,
         seed,
         duplicate_percentage,
         max_duplicates_per_file,
+        duplicates_as_hardlinks,
         audit_output: _,
+        audit_sqlite: _,
+        audit_format: _,
         permissions,
+        dir_permissions,
+        permission_rules,
+        fio_output: _,
+        manifest: _,
+        compare_with: _,
+        validate: _,
+        replicas: _,
+        shards,
+        replica_seeds: _,
+        audit_metadata: _,
+        prune_globs,
+        check_distributions: _,
+        traversal,
+        max_in_flight_bytes,
+        max_files_per_task,
+        byte_counts_pool_capacity,
+        write_block_size,
+        whiteout_percentage,
+        portable_create,
+        name_style,
+        file_name_template,
+        dir_name_template,
+        name_length,
+        max_name_percentage,
+        extensions,
+        case_collision_percentage,
+        broken_symlink_percentage,
+        special_files,
+        acl_percentage,
+        mtime_range,
+        timestamp,
+        windows_attribute_percentage,
+        ads_percentage,
+        links_per_inode,
+        append_pass,
+        append_percentage,
+        modify_percentage,
+        modified_output,
+        symlink_loops,
+        link_flavor,
+        read_back: _,
+        leftover_strategy,
+        size_distribution,
+        file_headers,
+        magic_bytes,
+        content,
         human_info: _,
     }: Configuration,
     parallelism: NonZeroUsize,
     audit_trail: Option<Arc<AuditTrail>>,
+    run_id: u64,
 ) -> Result<GeneratorStats, Error> {
+    let files_per_dir = files_per_dir_distribution
+        .distr()
+        .change_context(Error::InvalidDistribution)
+        .attach(ExitCode::from(sysexits::ExitCode::Usage))?;
+    let mut levels = Vec::new();
+    for LevelOverride {
+        depth,
+        ftd_ratio,
+        dir_fanout,
+    } in level_overrides
+    {
+        let depth = usize::try_from(depth).unwrap_or(usize::MAX);
+        if levels.len() <= depth {
+            levels.resize_with(depth + 1, LevelSettings::default);
+        }
+        levels[depth] = LevelSettings {
+            files_per_dir: ftd_ratio.map(|ratio| ratio.get() as f64),
+            dirs_per_dir: dir_fanout
+                .map(|fanout| fanout.distr().map(|distr| (distr, fanout.mean())))
+                .transpose()
+                .change_context(Error::InvalidDistribution)?,
+        };
+    }
+    let dir_names = DirNames {
+        style: name_style,
+        template: dir_name_template,
+    };
     macro_rules! run {
         ($generator:expr) => {{
             run(
@@ -465,36 +3996,275 @@ async fn run_generator_async(
                 dirs_per_dir,
                 max_depth.try_into().unwrap_or(usize::MAX),
                 parallelism,
+                SchedulerSettings {
+                    traversal,
+                    max_in_flight_bytes,
+                    byte_counts_pool_capacity,
+                    dir_names,
+                    min_depth: min_depth.try_into().unwrap_or(usize::MAX),
+                    files_at_leaves,
+                    skew,
+                    files_per_dir,
+                    levels,
+                },
                 $generator,
             )
             .await
+            .map(
+                |(
+                    stats,
+                    SchedulerStats {
+                        peak_byte_counts_pool_bytes,
+                    },
+                )| {
+                    if peak_byte_counts_pool_bytes > 0 {
+                        log!(
+                            Level::Info,
+                            "Peak byte counts pool memory: {}",
+                            ByteSize(peak_byte_counts_pool_bytes as u64).display().si()
+                        );
+                    }
+                    stats
+                },
+            )
         }};
     }
 
+    let settings = TaskSettings {
+        root_dir: root_dir.clone(),
+        shards,
+        prune_globs,
+        permission_rules,
+        max_files_per_task,
+        write_buffers: WriteBufferPool::new(write_block_size),
+        whiteout_threshold: (whiteout_percentage / 100. * u64::MAX as f64) as u64,
+        portable_create: AtomicBool::new(portable_create),
+        name_style,
+        file_name_template,
+        dir_names: dir_names.clone(),
+        name_length,
+        max_name_threshold: max_name_percentage
+            .map_or(0, |percentage| (percentage / 100. * u64::MAX as f64) as u64),
+        extensions: extensions
+            .map(|ExtensionWeights(weights)| {
+                ExtensionTable::new(weights)
+                    .ok_or_else(|| Report::new(Error::InvalidEnvironment))
+                    .attach_printable("Extensions need a nonzero weight.")
+                    .attach(ExitCode::from(sysexits::ExitCode::Usage))
+            })
+            .transpose()?,
+        content_mime: content.mime(),
+        // Only odd files can collide with their predecessor
+        case_collision_threshold: case_collision_percentage
+            .map(|percentage| ((percentage / 50.).min(1.) * u64::MAX as f64) as u64),
+        case_collisions: AtomicU64::new(0),
+        broken_symlink_threshold: broken_symlink_percentage
+            .map_or(0, |percentage| (percentage / 100. * u64::MAX as f64) as u64),
+        broken_symlinks: AtomicU64::new(0),
+        special_threshold: special_files
+            .map_or(0, |percentage| (percentage / 100. * u64::MAX as f64) as u64),
+        special_types: special_types(),
+        acl_threshold: acl_percentage
+            .map_or(0, |percentage| (percentage / 100. * u64::MAX as f64) as u64),
+        dir_seed: seed,
+        dir_permissions,
+        mtime_range,
+        timestamp,
+        attribute_threshold: windows_attribute_percentage
+            .map_or(0, |percentage| (percentage / 100. * u64::MAX as f64) as u64),
+        stream_threshold: ads_percentage
+            .map_or(0, |percentage| (percentage / 100. * u64::MAX as f64) as u64),
+        hardlinks: links_per_inode
+            .map(|links| {
+                let num_inodes = (files.get() as f64 / links.mean()).ceil() as usize;
+                links
+                    .distr()
+                    .ok()
+                    .and_then(|distr| HardlinkPool::new(&distr, num_inodes.max(1), seed))
+                    .ok_or_else(|| Report::new(Error::InvalidDistribution))
+                    .attach_printable_lazy(|| format!("No inode drew any links from {links:?}"))
+                    .attach(ExitCode::from(sysexits::ExitCode::Usage))
+            })
+            .transpose()?,
+        duplicate_links: duplicates_as_hardlinks.then(DuplicateLinks::default),
+        file_links: link_flavor.unwrap_or_default(),
+    };
+    let settings = Arc::new(settings);
+
     let bytes = NonZeroU64::new(bytes);
+    let num_bytes_distr = bytes
+        .map(|_| size_distribution.distr(bytes_per_file))
+        .transpose()
+        .change_context(Error::InvalidDistribution)
+        .attach(ExitCode::from(sysexits::ExitCode::Usage))?;
+    let holes = hole_pattern.or_else(|| sparse.and_then(Holes::new));
+    if let (Some(holes), Some(_), Some(trail)) = (holes, bytes, &audit_trail) {
+        // Every file repeats the same layout from its start, so the period
+        // describes where all of their holes are
+        let (data, hole) = holes.layout();
+        trail.add_metadata("holes".to_string(), format!("{data}:{hole}"));
+    }
     let dynamic = DynamicGenerator {
-        num_dirs_distr: truncatable_normal(dirs_per_dir),
+        num_dirs_distr: dir_fanout
+            .map_or_else(
+                || Ok(FanoutDistr::Normal(truncatable_normal(dirs_per_dir))),
+                |fanout| fanout.distr(),
+            )
+            .change_context(Error::InvalidDistribution)?,
         seed,
 
-        bytes: bytes.map(|_| GeneratorBytes {
-            num_bytes_distr: truncatable_normal(bytes_per_file),
-            fill_byte,
+        bytes: num_bytes_distr.map(|num_bytes_distr| GeneratorBytes {
+            num_bytes_distr,
+            fill_pattern: fill_pattern.clone(),
+            content,
+            direct_io,
+            writeback_bytes,
+            fragment,
+            holes,
+            preallocate,
+            headers: (file_headers || magic_bytes).then(|| {
+                Arc::new(FileHeaders {
+                    root_dir: settings.root_dir.clone(),
+                    run_id,
+                    identify: file_headers,
+                    magic_bytes,
+                })
+            }),
         }),
         duplicate_percentage,
         max_duplicates_per_file,
-        audit_trail,
+        audit_trail: audit_trail.clone(),
+        settings: settings.clone(),
         permissions,
         pending_duplicates: Vec::new(),
         next_task_index: 0,
     };
 
-    if files_exact || (bytes_exact && bytes.is_some()) {
+    let stats = if files_exact || (bytes_exact && bytes.is_some()) || dirs_exact.is_some() {
         run!(StaticGenerator::new(
             dynamic,
             files_exact.then_some(files),
             bytes_exact.then_some(bytes).flatten(),
+            dirs_exact,
+            leftover_strategy,
+            files_at_leaves,
         ))
     } else {
         run!(dynamic)
+    }?;
+
+    if let Some(percentage) = modify_percentage {
+        log!(Level::Info, "Modifying {percentage}% of files...");
+        let modified = modify_files(
+            &settings.root_dir,
+            (percentage / 100. * u64::MAX as f64) as u64,
+            seed,
+            !settings.shards.is_empty(),
+            audit_trail.as_deref(),
+        )
+        .change_context(Error::Io)
+        .attach(ExitCode::from(sysexits::ExitCode::IoErr))?;
+        if let Some(output) = &modified_output {
+            let list = modified.iter().fold(String::new(), |mut list, path| {
+                list.push_str(&path.to_string_lossy());
+                list.push('\n');
+                list
+            });
+            fs::write(output, list)
+                .attach_printable_lazy(|| format!("Failed to write modified files to {output:?}"))
+                .change_context(Error::Io)
+                .attach(ExitCode::from(sysexits::ExitCode::IoErr))?;
+        }
+    }
+
+    let stats = if let Some(num_bytes) = append_pass {
+        log!(Level::Info, "Appending to {append_percentage}% of files...");
+        let appended = append_to_files(
+            &settings.root_dir,
+            num_bytes.get(),
+            (append_percentage / 100. * u64::MAX as f64) as u64,
+            seed,
+            fill_pattern.as_ref(),
+            !settings.shards.is_empty(),
+            audit_trail.as_deref(),
+        )
+        .change_context(Error::Io)
+        .attach(ExitCode::from(sysexits::ExitCode::IoErr))?;
+        GeneratorStats {
+            bytes: stats.bytes + appended,
+            ..stats
+        }
+    } else {
+        stats
+    };
+
+    if let Some(count) = symlink_loops {
+        log!(Level::Info, "Linking {count} directories into loops...");
+        let links = link_loops(
+            &settings.root_dir,
+            count.get(),
+            seed,
+            !settings.shards.is_empty(),
+            link_flavor == Some(LinkFlavor::Junction),
+        )
+        .change_context(Error::Io)
+        .attach(ExitCode::from(sysexits::ExitCode::IoErr))?;
+        let linked = links.len();
+        let junctions = links
+            .iter()
+            .filter(|&&(_, entry_type)| entry_type == EntryType::Junction)
+            .count();
+        if junctions > 0 && link_flavor != Some(LinkFlavor::Junction) {
+            log!(
+                Level::Warn,
+                "Created {junctions} junctions in place of symlinks, which require a privilege \
+                 this process doesn't hold."
+            );
+        }
+        if linked < count.get() {
+            log!(
+                Level::Warn,
+                "Only {linked} directories could be linked into loops, the tree has no more."
+            );
+        }
+        if let Some(trail) = &audit_trail {
+            for (link, entry_type) in &links {
+                trail.add_special(link, *entry_type, None, timestamp);
+            }
+            trail.add_metadata("symlink loops".to_string(), linked.to_string());
+        }
+    }
+
+    if let Some(timestamp) = timestamp {
+        log!(Level::Info, "Stamping the tree with {timestamp}...");
+        stamp_tree(&settings.root_dir, timestamp, !settings.shards.is_empty())
+            .change_context(Error::Io)
+            .attach(ExitCode::from(sysexits::ExitCode::IoErr))?;
+        if let Some(trail) = &audit_trail {
+            trail.add_metadata("timestamp".to_string(), timestamp.to_string());
+        }
+    }
+
+    let case_collisions = settings.case_collisions.load(Ordering::Relaxed);
+    if case_collisions > 0 {
+        log!(
+            Level::Warn,
+            "{case_collisions} files were not created because the file system considers their \
+             names equal to a sibling's."
+        );
+    }
+    if let (true, Some(trail)) = (case_collision_percentage.is_some(), &audit_trail) {
+        trail.add_metadata("case collisions".to_string(), case_collisions.to_string());
+    }
+    let broken_symlinks = settings.broken_symlinks.load(Ordering::Relaxed);
+    if broken_symlinks > 0 {
+        log!(Level::Info, "Created {broken_symlinks} dangling symlinks.");
+    }
+    if let (true, Some(trail)) = (broken_symlink_percentage.is_some(), &audit_trail) {
+        trail.add_metadata("broken symlinks".to_string(), broken_symlinks.to_string());
+    }
+    if let (Some(extensions), Some(trail)) = (&settings.extensions, &audit_trail) {
+        trail.add_metadata("extensions".to_string(), extensions.summary());
     }
+    Ok(stats)
 }
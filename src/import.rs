@@ -0,0 +1,291 @@
+use std::{
+    fmt::Write,
+    fs,
+    hash::Hasher,
+    path::{Path, PathBuf},
+    process::ExitCode,
+    str::FromStr,
+};
+
+use bon::Builder;
+use error_stack::{Report, Result, ResultExt};
+use thiserror::Error;
+use twox_hash::XxHash64;
+
+use crate::{
+    generator::Error,
+    replay::{ReplayEntry, recreate, relative_path},
+};
+
+/// The format of a listing describing an existing tree.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub enum ListingFormat {
+    /// Lines of `TYPE SIZE MODE PATH` as printed by
+    /// `find . -printf '%y %s %m %P\n'`. Entries other than files and
+    /// directories are skipped.
+    #[default]
+    Find,
+    /// An mtree(5) specification, either with full paths (as written by
+    /// `bsdtar --format=mtree`) or hierarchical (as written by `mtree -c`).
+    Mtree,
+    /// Rows of `path,size` with an optional header. Paths ending in `/` are
+    /// directories.
+    Csv,
+}
+
+#[derive(Error, Debug)]
+#[error("Invalid listing format {0:?}: expected find, mtree, or csv.")]
+pub struct ListingFormatError(String);
+
+impl FromStr for ListingFormat {
+    type Err = ListingFormatError;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s.trim() {
+            "find" => Ok(Self::Find),
+            "mtree" => Ok(Self::Mtree),
+            "csv" => Ok(Self::Csv),
+            _ => Err(ListingFormatError(s.to_string())),
+        }
+    }
+}
+
+/// Generates a tree with exactly the paths and sizes of an external listing,
+/// filling files with synthetic contents.
+///
+/// This reproduces real-world layouts without access to the real data.
+#[derive(Debug, Builder)]
+pub struct ListingImport {
+    listing: PathBuf,
+    #[builder(default)]
+    format: ListingFormat,
+    root_dir: PathBuf,
+    /// Fill files with this byte instead of random data.
+    fill_byte: Option<u8>,
+    /// Seeds the random contents of files along with their paths.
+    #[builder(default = 0)]
+    seed: u64,
+}
+
+impl ListingImport {
+    pub fn run(self, output: &mut impl Write) -> Result<(), Error> {
+        let Self {
+            listing,
+            format,
+            root_dir,
+            fill_byte,
+            seed,
+        } = self;
+        let contents = fs::read(&listing)
+            .attach_printable_lazy(|| format!("Failed to read listing {listing:?}"))
+            .change_context(Error::Io)
+            .attach(ExitCode::from(sysexits::ExitCode::NoInput))?;
+        let contents = String::from_utf8_lossy(&contents);
+
+        let entries = match format {
+            ListingFormat::Find => parse_find(&contents),
+            ListingFormat::Mtree => parse_mtree(&contents),
+            ListingFormat::Csv => parse_csv(&contents),
+        }
+        .map_err(|e| Report::new(Error::InvalidListing).attach_printable(e))
+        .attach_printable_lazy(|| format!("Failed to parse listing {listing:?}"))?
+        .into_iter()
+        .map(|entry| {
+            let path = relative_path(&entry.path)
+                .ok_or_else(|| Report::new(Error::InvalidListing))
+                .attach_printable_lazy(|| format!("{:?} escapes the root directory", entry.path))?;
+            let mut hasher = XxHash64::with_seed(seed);
+            hasher.write(path.as_os_str().as_encoded_bytes());
+            Ok(ReplayEntry {
+                path,
+                seed: hasher.finish(),
+                ..entry
+            })
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+
+        recreate(&root_dir, entries, fill_byte, output)
+    }
+}
+
+fn entry(
+    path: impl Into<PathBuf>,
+    is_dir: bool,
+    size: u64,
+    permissions: Option<u32>,
+) -> ReplayEntry {
+    ReplayEntry {
+        path: path.into(),
+        is_dir,
+        size: if is_dir { 0 } else { size },
+        seed: 0,
+        hash: None,
+        permissions,
+        special: None,
+    }
+}
+
+fn parse_mode(mode: &str) -> std::result::Result<u32, String> {
+    u32::from_str_radix(mode, 8).map_err(|_| format!("Invalid mode {mode:?}"))
+}
+
+fn parse_size(size: &str) -> std::result::Result<u64, String> {
+    size.parse().map_err(|_| format!("Invalid size {size:?}"))
+}
+
+fn parse_find(contents: &str) -> std::result::Result<Vec<ReplayEntry>, String> {
+    let mut entries = Vec::new();
+    for line in contents.lines().filter(|line| !line.is_empty()) {
+        let mut fields = line.splitn(4, ' ');
+        let (Some(kind), Some(size), Some(mode), Some(path)) =
+            (fields.next(), fields.next(), fields.next(), fields.next())
+        else {
+            return Err(format!("Expected `TYPE SIZE MODE PATH`, got {line:?}"));
+        };
+        let is_dir = match kind {
+            "d" => true,
+            "f" => false,
+            _ => continue,
+        };
+        if path.is_empty() {
+            continue;
+        }
+        entries.push(entry(
+            path,
+            is_dir,
+            parse_size(size)?,
+            Some(parse_mode(mode)?),
+        ));
+    }
+    Ok(entries)
+}
+
+/// Decodes the octal `\ooo` and `\\` escapes mtree uses for special
+/// characters in names.
+fn unvis(name: &str) -> String {
+    let mut bytes = Vec::with_capacity(name.len());
+    let mut rest = name.as_bytes();
+    while let Some((&b, tail)) = rest.split_first() {
+        rest = tail;
+        if b != b'\\' {
+            bytes.push(b);
+            continue;
+        }
+        match rest {
+            [a @ b'0'..=b'3', b @ b'0'..=b'7', c @ b'0'..=b'7', tail @ ..] => {
+                bytes.push((a - b'0') << 6 | (b - b'0') << 3 | (c - b'0'));
+                rest = tail;
+            }
+            [b'\\', tail @ ..] => {
+                bytes.push(b'\\');
+                rest = tail;
+            }
+            _ => bytes.push(b'\\'),
+        }
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+fn parse_mtree(contents: &str) -> std::result::Result<Vec<ReplayEntry>, String> {
+    let mut entries = Vec::new();
+    let mut defaults = Vec::<(String, String)>::new();
+    let mut cwd = PathBuf::new();
+
+    let mut logical = String::new();
+    for line in contents.lines() {
+        // Lines ending in a backslash continue on the next one
+        if let Some(line) = line.strip_suffix('\\') {
+            logical.push_str(line);
+            logical.push(' ');
+            continue;
+        }
+        logical.push_str(line);
+        let line = logical.trim();
+
+        let mut words = line.split_whitespace();
+        match words.next() {
+            None => {}
+            Some(word) if word.starts_with('#') => {}
+            Some("/set") => {
+                for (key, value) in words.filter_map(|word| word.split_once('=')) {
+                    defaults.retain(|(k, _)| k != key);
+                    defaults.push((key.to_string(), value.to_string()));
+                }
+            }
+            Some("/unset") => {
+                for key in words {
+                    defaults.retain(|(k, _)| key != "all" && k != key);
+                }
+            }
+            Some("..") => {
+                cwd.pop()
+                    .then_some(())
+                    .ok_or_else(|| "`..` above the root directory".to_string())?;
+            }
+            Some(name) => {
+                let keyword = |key: &str| {
+                    line.split_whitespace()
+                        .skip(1)
+                        .filter_map(|word| word.split_once('='))
+                        .find(|&(k, _)| k == key)
+                        .map(|(_, value)| value)
+                        .or_else(|| {
+                            defaults
+                                .iter()
+                                .find(|(k, _)| k == key)
+                                .map(|(_, value)| value.as_str())
+                        })
+                };
+                let kind = keyword("type").unwrap_or("file");
+                let is_dir = kind == "dir";
+                let size = keyword("size").map(parse_size).transpose()?.unwrap_or(0);
+                let mode = keyword("mode").map(parse_mode).transpose()?;
+
+                let name = unvis(name);
+                // Full paths leave the current directory untouched while
+                // directories in hierarchical specifications are entered.
+                let path = if name.contains('/') {
+                    PathBuf::from(name)
+                } else {
+                    let path = cwd.join(name);
+                    if is_dir {
+                        cwd.clone_from(&path);
+                    }
+                    path
+                };
+                if matches!(kind, "file" | "dir") {
+                    entries.push(entry(path, is_dir, size, mode));
+                }
+            }
+        }
+        logical.clear();
+    }
+    Ok(entries)
+}
+
+fn parse_csv(contents: &str) -> std::result::Result<Vec<ReplayEntry>, String> {
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .from_reader(contents.as_bytes());
+    let mut entries = Vec::new();
+    for (i, record) in reader.records().enumerate() {
+        let record = record.map_err(|e| e.to_string())?;
+        let path = record.get(0).unwrap_or_default();
+        let size = record.get(1).unwrap_or_default().trim();
+        if i == 0 && !size.is_empty() && size.parse::<u64>().is_err() {
+            // Header
+            continue;
+        }
+        let size = if size.is_empty() {
+            0
+        } else {
+            parse_size(size)?
+        };
+        match path.strip_suffix('/') {
+            Some(dir) => entries.push(entry(dir, true, 0, None)),
+            None => entries.push(entry(Path::new(path), false, size, None)),
+        }
+    }
+    Ok(entries)
+}
@@ -1,5 +1,23 @@
+pub use bench::*;
+pub use compat::*;
 pub use generator::*;
+pub use import::*;
+pub use profile::*;
+pub use replay::*;
+pub use seed_search::*;
+pub use serve::*;
+pub use verify::*;
 
+mod bench;
+#[cfg(feature = "capi")]
+pub mod capi;
+mod compat;
 mod core;
 mod generator;
+mod import;
+mod profile;
+mod replay;
+mod seed_search;
+mod serve;
 mod utils;
+mod verify;
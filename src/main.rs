@@ -1,17 +1,25 @@
 use std::{
     borrow::Cow,
+    ffi::OsStr,
     io,
-    io::{stdout, Write},
-    num::NonZeroU64,
-    path::PathBuf,
+    io::{Write, stdout},
+    mem,
+    num::{NonZeroU8, NonZeroU32, NonZeroU64, NonZeroUsize},
+    path::{Component, Path, PathBuf},
     process::{ExitCode, Termination},
 };
 
-use clap::{ArgAction, Args, Parser, ValueHint};
+use clap::{ArgAction, Args, Parser, Subcommand, ValueEnum, ValueHint};
 use clap_num::si_number;
 use clap_verbosity_flag::Verbosity;
 use error_stack::ResultExt;
-use ftzz::{Generator, NumFilesWithRatio, NumFilesWithRatioError};
+use ftzz::{
+    AuditFormat, AuditReplay, AuditVerification, Benchmark, ContentMode, DirFanout,
+    ExtensionWeights, FilesPerDirDistribution, FillPattern, Generator, Holes, LeftoverStrategy,
+    LevelOverride, LinkFlavor, ListingFormat, ListingImport, MAX_MODE, NameLengthRange, NameStyle,
+    NameTemplate, NumFilesWithRatio, NumFilesWithRatioError, PermissionMode, PermissionRule,
+    SeedSearch, Server, SizeDistribution, TimeRange, TraversalOrder, TreeProfiler,
+};
 use io_adapters::WriteExtension;
 
 mod config;
@@ -39,37 +47,259 @@ type DefaultLevel = clap_verbosity_flag::TraceLevel;
 /// the file contents with the `total-bytes` option.
 #[derive(Parser, Debug)]
 #[command(version, author = "Alex Saveau (@SUPERCILEX)")]
-#[command(infer_subcommands = true, infer_long_args = true)]
+#[command(infer_long_args = true)]
 #[command(disable_help_flag = true)]
 #[command(max_term_width = 100)]
+#[command(args_conflicts_with_subcommands = true, subcommand_negates_reqs = true)]
 #[cfg_attr(test, command(help_expected = true))]
 struct Ftzz {
+    #[command(subcommand)]
+    cmd: Option<Cmd>,
+
     #[command(flatten)]
-    options: Generate,
+    options: Option<Generate>,
 
     #[command(flatten)]
     #[command(next_display_order = None)]
     verbose: Verbosity<DefaultLevel>,
 
     /// Path to a TOML configuration file
+    ///
+    /// Each of its `[[layers]]` tables generates a tree of its own into the
+    /// root directory's `N.layer` directory, overriding the rest of the file.
+    /// Command line options apply to every layer. Each layer writes its own
+    /// audit trail and other outputs, named like `audit.N.layer.csv`.
     #[arg(long = "config", value_hint = ValueHint::FilePath, global = true)]
     config_file: Option<PathBuf>,
 
+    /// Check that this build generates the same trees as every supported
+    /// compatibility level
+    ///
+    /// A small canonical tree is generated in a temporary directory and its
+    /// hash compared against the golden values embedded for each level, such
+    /// that upgrades which change deterministic output are caught.
+    #[arg(long = "self-check-compat", exclusive = true)]
+    self_check_compat: bool,
+
     #[arg(short, long, short_alias = '?', global = true)]
     #[arg(action = ArgAction::Help, help = "Print help (use `--help` for more detail)")]
     #[arg(long_help = "Print help (use `-h` for a summary)")]
     help: Option<bool>,
 }
 
+#[derive(Subcommand, Debug)]
+#[allow(clippy::large_enum_variant)]
+enum Cmd {
+    /// Benchmark filesystem metadata throughput over a generated tree
+    ///
+    /// A tree is generated exactly as it would be without this subcommand and
+    /// then put through standardized create, readdir, stat, and unlink phases
+    /// with the throughput of each phase being reported.
+    Bench(Bench),
+    /// Recreate the tree described by an audit trail
+    ///
+    /// Paths, sizes, and permissions are reproduced beneath the new root
+    /// directory. Audit trails only record a hash of each file's contents, so
+    /// contents are regenerated as random data seeded by that hash instead.
+    Replay(Replay),
+    /// Check a tree against the audit trail it was generated with
+    ///
+    /// The tree is walked and every file rehashed to report entries that are
+    /// missing, extra, or corrupted compared to the audit trail, such that
+    /// generated trees can serve as ground truth when testing backup and sync
+    /// tools.
+    Verify(Verify),
+    /// Generate a tree matching an external listing of paths and sizes
+    ///
+    /// Real-world layouts can be reproduced from the output of `find`, an
+    /// mtree specification, or a CSV of paths and sizes without access to the
+    /// real data. Files are filled with synthetic contents.
+    Import(Import),
+    /// Record statistics about an existing tree
+    ///
+    /// The tree is walked to capture its depth, number of files and
+    /// subdirectories per directory, file sizes, extensions, and duplicated
+    /// contents in a TOML profile, such that look-alike trees can be
+    /// synthesized from real ones.
+    Profile(Profile),
+    /// Search for a seed whose generated tree satisfies some constraints
+    ///
+    /// Candidate trees are generated beneath the root directory with
+    /// successive seeds (starting from the given one) and removed again. The
+    /// smallest satisfying seed is printed.
+    FindSeed(FindSeed),
+    /// Serve an HTTP API through which generation jobs are submitted and
+    /// monitored
+    ///
+    /// `POST /jobs` queues a job whose body holds the arguments that would
+    /// otherwise follow the root directory on the command line (the job's
+    /// tree is generated in `ROOT_DIR/job-<id>`) and responds with the job's
+    /// id. `GET /jobs/<id>` reports the job's state along with its stats once
+    /// done, and `GET /jobs/<id>/audit` downloads its audit trail. `GET /jobs`
+    /// lists every job and `POST /shutdown` exits once queued jobs are done.
+    Serve(Serve),
+}
+
 #[derive(Args, Debug)]
 #[command(arg_required_else_help = true)]
-struct Generate {
-    /// The directory in which to generate files
+struct Bench {
+    #[command(flatten)]
+    options: Generate,
+
+    /// Keep the generated tree instead of unlinking it as the final phase
+    #[arg(long = "keep", action = ArgAction::SetTrue)]
+    keep: bool,
+}
+
+#[derive(Args, Debug)]
+#[command(arg_required_else_help = true)]
+struct FindSeed {
+    #[command(flatten)]
+    options: Generate,
+
+    /// The number of seeds to try before giving up [default: 1000]
+    #[arg(long = "attempts", value_name = "SEEDS")]
+    attempts: Option<u64>,
+
+    /// The number of candidate trees to generate at once [default: number of
+    /// CPUs]
+    #[arg(long = "jobs", value_name = "NUM")]
+    jobs: Option<NonZeroUsize>,
+
+    /// Require a directory containing at least this many files
+    #[arg(long = "min-files-in-dir", value_name = "FILES")]
+    #[arg(value_parser = si_number::<u64>)]
+    min_files_in_dir: Option<u64>,
+
+    /// Require a directory containing at least this many subdirectories
+    #[arg(long = "min-dirs-in-dir", value_name = "DIRS")]
+    #[arg(value_parser = si_number::<u64>)]
+    min_dirs_in_dir: Option<u64>,
+}
+
+#[derive(Args, Debug)]
+#[command(arg_required_else_help = true)]
+struct Serve {
+    /// The address to listen on
+    #[arg(long = "listen", value_name = "ADDR", default_value = "127.0.0.1:8080")]
+    listen: String,
+
+    /// The directory in which job trees are generated
+    ///
+    /// The directory will be created if it does not exist.
+    #[arg(value_hint = ValueHint::DirPath)]
+    root_dir: PathBuf,
+}
+
+/// The arguments of a job submitted to the server.
+#[derive(Parser, Debug)]
+#[command(no_binary_name = true, disable_help_flag = true)]
+struct ServeJob {
+    #[command(flatten)]
+    options: Generate,
+}
+
+#[derive(Args, Debug)]
+#[command(arg_required_else_help = true)]
+struct Replay {
+    /// The audit trail (CSV, SQLite, or JSON) describing the tree
+    #[arg(long = "audit", value_hint = ValueHint::FilePath)]
+    audit: PathBuf,
+
+    /// The directory in which to recreate the tree
+    ///
+    /// The directory will be created if it does not exist.
+    #[arg(value_hint = ValueHint::DirPath)]
+    root_dir: PathBuf,
+
+    /// Fill files with this byte instead of random data
+    #[arg(long = "fill-byte")]
+    fill_byte: Option<u8>,
+}
+
+#[derive(Args, Debug)]
+#[command(arg_required_else_help = true)]
+struct Verify {
+    /// The audit trail (CSV, SQLite, or JSON) describing the tree
+    #[arg(value_hint = ValueHint::FilePath)]
+    audit: PathBuf,
+
+    /// The root directory of the tree to check
+    #[arg(value_hint = ValueHint::DirPath)]
+    root_dir: PathBuf,
+
+    /// Only rehash this percentage of the files
+    ///
+    /// The largest files make up half of the sample and the rest is picked by
+    /// the hash of their path, so the same files are sampled every time.
+    /// Every entry is still checked for its presence, type, and size.
+    #[arg(long = "sample", value_name = "PERCENTAGE")]
+    #[arg(value_parser = percentage_parser)]
+    sample: Option<f64>,
+}
+
+#[derive(Args, Debug)]
+#[command(arg_required_else_help = true)]
+struct Profile {
+    /// The root directory of the tree to profile
+    #[arg(value_hint = ValueHint::DirPath)]
+    root_dir: PathBuf,
+
+    /// Write the profile to this path instead of standard output
+    #[arg(short = 'o', long = "output", value_hint = ValueHint::FilePath)]
+    output: Option<PathBuf>,
+}
+
+#[derive(Args, Debug)]
+#[command(arg_required_else_help = true)]
+struct Import {
+    /// The listing describing the tree
+    #[arg(long = "listing", value_hint = ValueHint::FilePath)]
+    listing: PathBuf,
+
+    /// The listing's format [default: find]
+    ///
+    /// `find` reads lines of `TYPE SIZE MODE PATH` as printed by
+    /// `find . -printf '%y %s %m %P\n'`. `mtree` reads an mtree(5)
+    /// specification. `csv` reads rows of `path,size` with an optional header,
+    /// where paths ending in `/` are directories.
+    #[arg(long = "format", value_name = "FORMAT")]
+    format: Option<ListingFormat>,
+
+    /// The directory in which to generate the tree
     ///
     /// The directory will be created if it does not exist.
     #[arg(value_hint = ValueHint::DirPath)]
     root_dir: PathBuf,
 
+    /// Fill files with this byte instead of random data
+    #[arg(long = "fill-byte")]
+    fill_byte: Option<u8>,
+
+    /// Change the seed of the files' random contents [default: 0]
+    #[arg(long = "seed")]
+    seed: Option<u64>,
+}
+
+#[derive(Args, Debug, Clone)]
+#[command(arg_required_else_help = true)]
+struct Generate {
+    /// The directory in which to generate files, optionally followed by more
+    /// directories
+    ///
+    /// The directory will be created if it does not exist. The top-level
+    /// directories of the tree are spread round-robin across it and any
+    /// additional directories, typically one per mount, just like with
+    /// `--shard`. A single additional plain number is still taken to be the
+    /// seed, as it once was, but this is deprecated in favor of `--seed`.
+    /// Other additional directories named by a plain number are rejected;
+    /// write them as `./<number>`.
+    #[arg(value_name = "ROOT_DIR", value_hint = ValueHint::DirPath)]
+    #[arg(required = true, num_args = 1..)]
+    root_dirs: Vec<PathBuf>,
+    #[arg(skip)]
+    root_dir: PathBuf,
+
     /// The number of files to generate
     ///
     /// Note: this value is probabilistically respected, meaning any number of
@@ -78,10 +308,33 @@ struct Generate {
     #[arg(value_parser = num_files_parser)]
     num_files: Option<NonZeroU64>,
 
+    /// The total number of filesystem entries (files and directories) to
+    /// generate
+    ///
+    /// Entries are split into files and directories according to the file to
+    /// directory ratio. Note: like the number of files, this value is
+    /// probabilistically respected.
+    #[arg(long = "entries", conflicts_with = "num_files")]
+    #[arg(value_parser = num_entries_parser)]
+    num_entries: Option<NonZeroU64>,
+
     /// Whether or not to generate exactly N files
     #[arg(long = "files-exact", action = ArgAction::SetTrue)]
     files_exact: bool,
 
+    /// The number of directories to generate
+    ///
+    /// The tree is shaped for N directories instead of those implied by the
+    /// file to directory ratio. Note: this value is probabilistically
+    /// respected unless `--dirs-exact` is given.
+    #[arg(long = "dirs", alias = "num-dirs")]
+    #[arg(value_parser = num_dirs_parser)]
+    num_dirs: Option<NonZeroU64>,
+
+    /// Whether or not to generate exactly N directories
+    #[arg(long = "dirs-exact", action = ArgAction::SetTrue, requires = "num_dirs")]
+    dirs_exact: bool,
+
     /// The total amount of random data to be distributed across the generated
     /// files
     ///
@@ -101,11 +354,104 @@ struct Generate {
     #[arg(requires = "num-bytes")]
     fill_byte: Option<u8>,
 
+    /// Repeat a byte sequence as file contents instead of deterministically
+    /// random data
+    ///
+    /// Given as `0x` followed by hex digits or as a plain string. Files stay
+    /// compressible while containing recognizable markers; a pattern of a
+    /// single byte is equivalent to `--fill-byte`.
+    #[arg(long = "fill-pattern", value_name = "PATTERN")]
+    #[arg(requires = "num-bytes", conflicts_with = "fill_byte")]
+    fill_pattern: Option<FillPattern>,
+
+    /// Repeat the contents of this file as the contents of every file,
+    /// truncating the last repetition to the file's size
+    ///
+    /// Every file at least as large as the template starts with a complete
+    /// copy of it, so a small valid document makes each generated file a
+    /// valid instance of its format. The template is read once up front.
+    #[arg(long = "content-template", value_name = "PATH")]
+    #[arg(requires = "num-bytes", conflicts_with_all = ["fill_byte", "fill_pattern"])]
+    content_template: Option<PathBuf>,
+
+    /// Slice file contents out of the files in this directory instead of
+    /// generating them
+    ///
+    /// Each file takes on the contents of one sample picked from its seed,
+    /// starting at an offset into it and wrapping around, so trees get the
+    /// entropy and structure of real data. Subdirectories are included and
+    /// all samples are read into memory up front.
+    #[arg(long = "content-corpus", value_name = "DIR")]
+    #[arg(value_hint = ValueHint::DirPath)]
+    #[arg(requires = "num-bytes")]
+    #[arg(conflicts_with_all = ["fill_byte", "fill_pattern", "content_template"])]
+    content_corpus: Option<PathBuf>,
+
     /// Whether or not to generate exactly N bytes
     #[arg(long = "bytes-exact", action = ArgAction::SetTrue)]
     #[arg(requires = "num-bytes")]
     bytes_exact: bool,
 
+    /// Write file contents with `O_DIRECT`, bypassing the page cache
+    ///
+    /// Useful for benchmarking the underlying device rather than memory. Falls
+    /// back to buffered writes on file systems that don't support direct I/O.
+    #[arg(long = "direct-io", action = ArgAction::SetTrue)]
+    #[arg(requires = "num-bytes")]
+    direct_io: bool,
+
+    /// Flush written contents to disk every time a task has written this many
+    /// bytes
+    ///
+    /// Keeps the volume of dirty pages bounded so huge generation runs don't
+    /// end in a long writeback stall.
+    #[arg(long = "writeback-bytes", value_name = "BYTES")]
+    #[arg(requires = "num-bytes")]
+    #[arg(value_parser = writeback_bytes_parser)]
+    writeback_bytes: Option<NonZeroU64>,
+
+    /// Interleave appends across many open files instead of writing each file
+    /// in one go
+    ///
+    /// Files are written a 4 KiB block at a time, 64 at once, and each round
+    /// is flushed to disk so the files end up heavily fragmented on
+    /// extent-based file systems. Slow by design; file contents are
+    /// unaffected.
+    #[arg(long = "fragment", action = ArgAction::SetTrue)]
+    #[arg(requires = "num-bytes")]
+    fragment: bool,
+
+    /// Leave this percentage of every file as holes instead of writing them
+    ///
+    /// Every 64 KiB of a file end in a hole rounded down to whole 4 KiB blocks
+    /// that is seeked over rather than written, so files take up far less
+    /// space than their apparent size. Holes read back as zeros.
+    #[arg(long = "sparse", value_name = "PERCENTAGE")]
+    #[arg(requires = "num-bytes", conflicts_with = "fragment")]
+    #[arg(value_parser = percentage_parser)]
+    sparse: Option<f64>,
+
+    /// Lay sparse files out as runs of DATA bytes followed by HOLE bytes of
+    /// hole, repeated from the start of every file
+    ///
+    /// Sizes take binary units, such as `1MiB:7MiB`, and must be whole 4 KiB
+    /// blocks. The layout is recorded in the audit trail's `holes` metadata
+    /// in bytes, so archivers' `SEEK_HOLE` and `SEEK_DATA` handling can be
+    /// checked against it.
+    #[arg(long = "hole-pattern", value_name = "DATA:HOLE")]
+    #[arg(requires = "num-bytes", conflicts_with_all = ["fragment", "sparse"])]
+    hole_pattern: Option<Holes>,
+
+    /// Allocate every file's extents with `fallocate` instead of writing its
+    /// contents
+    ///
+    /// Files read back as zeros, so trees with terabytes of apparent data can
+    /// be generated in seconds. Outside of Linux files are only extended.
+    #[arg(long = "preallocate", action = ArgAction::SetTrue)]
+    #[arg(requires = "num-bytes")]
+    #[arg(conflicts_with_all = ["fill_byte", "fill_pattern", "content_template", "content_corpus", "fragment", "sparse", "hole_pattern"])]
+    preallocate: bool,
+
     /// Whether or not to generate exactly N files and bytes
     #[arg(short = 'e', long = "exact", action = ArgAction::SetTrue)]
     #[arg(conflicts_with_all = & ["files_exact", "bytes_exact"])]
@@ -117,6 +463,48 @@ struct Generate {
     #[arg(help = "The maximum directory tree depth [default: 5]")]
     max_depth: Option<u32>,
 
+    /// The depth at least one chain of directories is guaranteed to reach
+    ///
+    /// The first directory of every level down to this depth always holds at
+    /// least one other directory, so the tree can't bottom out early. Can't
+    /// exceed the maximum depth.
+    #[arg(long = "min-depth")]
+    #[arg(value_parser = si_number::<u32>)]
+    min_depth: Option<u32>,
+
+    /// Create all N files in the root directory
+    ///
+    /// Ignores the maximum depth and file to directory ratio to produce a
+    /// single huge directory, for stressing how file systems cope with tens
+    /// of millions of entries in one place. Implies an exact file count.
+    #[arg(long = "flat", action = ArgAction::SetTrue)]
+    #[arg(conflicts_with_all = ["max_depth", "min_depth", "files_at_leaves", "num_dirs"])]
+    flat: bool,
+
+    /// Place every file in a directory at the maximum depth
+    ///
+    /// Every other directory only holds subdirectories, as in
+    /// content-addressable stores.
+    #[arg(long = "files-at-leaves", action = ArgAction::SetTrue)]
+    files_at_leaves: bool,
+
+    /// How unevenly files and subdirectories are spread among siblings
+    ///
+    /// At 0 every directory is drawn alike. Closer to 1, the first of each
+    /// set of siblings gets ever more of their files and subdirectories,
+    /// producing one hot directory per level. Must be below 1. [default: 0]
+    #[arg(long = "skew", value_name = "SKEW")]
+    #[arg(value_parser = skew_parser)]
+    skew: Option<f64>,
+
+    /// The distribution the number of files in each directory is drawn from
+    ///
+    /// One of `normal` or `zipf[:EXPONENT]`. Both keep the average number of
+    /// files per directory the same, but Zipf-distributed directories are
+    /// mostly small with a few very large ones, as found on real filesystems.
+    #[arg(long = "files-per-dir-distribution", value_name = "DISTRIBUTION")]
+    files_per_dir_distribution: Option<FilesPerDirDistribution>,
+
     /// The number of files to generate per directory (default: files / 1000)
     ///
     /// Note: this value is probabilistically respected, meaning not all
@@ -125,11 +513,55 @@ struct Generate {
     #[arg(value_parser = file_to_dir_ratio_parser)]
     file_to_dir_ratio: Option<NonZeroU64>,
 
+    /// The number of directories to generate per directory (default: inferred
+    /// from the file to directory ratio)
+    ///
+    /// When combined with the file to directory ratio, the maximum depth is
+    /// derived from the two ratios instead of being specified directly.
+    ///
+    /// Note: this value is probabilistically respected, meaning not all
+    /// directories will have N subdirectories.
+    #[arg(long = "dtd-ratio")]
+    #[arg(value_parser = dir_to_dir_ratio_parser)]
+    #[arg(conflicts_with = "dir_fanout")]
+    dir_to_dir_ratio: Option<NonZeroU64>,
+
     /// Write an audit log of all generated files to this path
     #[arg(short = 'a', long = "audit-output", alias = "audit-output")]
     #[arg(value_hint = ValueHint::FilePath)]
     audit_output: Option<PathBuf>,
-    #[arg(help = "Change the PRNG's starting seed [default: 0]")]
+
+    /// The audit log's format [default: based on the audit output's extension]
+    ///
+    /// `csv` writes the metadata as comments followed by a row per entry.
+    /// `sqlite` writes a database (used for `.db` and `.sqlite` files). `json`
+    /// writes an array of records (used for `.json` files) and `jsonl` writes
+    /// one record per line (used for `.jsonl` and `.ndjson` files), the first
+    /// of which holds the metadata.
+    #[arg(long = "audit-format", value_name = "FORMAT")]
+    #[arg(requires = "audit_output")]
+    audit_format: Option<AuditFormat>,
+
+    /// The number of rows inserted per statement when writing an SQLite audit
+    /// trail [default: 512]
+    ///
+    /// Capped at the number of rows SQLite accepts in a single statement.
+    #[arg(long = "audit-batch-size", value_name = "ROWS")]
+    audit_batch_size: Option<NonZeroUsize>,
+
+    /// The page size of newly created SQLite audit trails [default: 16384]
+    #[arg(long = "audit-page-size", value_name = "BYTES")]
+    #[arg(value_parser = audit_page_size_parser)]
+    audit_page_size: Option<u32>,
+
+    /// The memory SQLite may use to cache pages while writing an audit trail
+    /// [default: 64 MiB]
+    #[arg(long = "audit-cache-size", value_name = "BYTES")]
+    #[arg(value_parser = audit_cache_size_parser)]
+    audit_cache_size: Option<NonZeroU64>,
+
+    /// Change the PRNG's starting seed [default: 0]
+    #[arg(long = "seed")]
     seed: Option<u64>,
 
     /// Percentage of additional duplicate files to generate (relative to the
@@ -140,52 +572,1070 @@ struct Generate {
     /// Maximum number of duplicates per file
     #[arg(long = "max-duplicates-per-file", value_name = "MAX")]
     max_duplicates_per_file: Option<std::num::NonZeroUsize>,
+
+    /// Hardlink duplicates to the first copy of their contents instead of
+    /// writing them again
+    ///
+    /// Every copy of a duplicated file, including the original, then shares a
+    /// single inode. Dedup and backup tools treat hardlinks very differently
+    /// from byte-identical copies.
+    #[arg(long = "duplicates-as-hardlinks", action = ArgAction::SetTrue)]
+    #[arg(requires = "duplicate_percentage", conflicts_with = "links_per_inode")]
+    duplicates_as_hardlinks: bool,
+
     /// List of file permission octals to deterministically select from
-    #[arg(long = "permissions", value_name = "OCTAL", value_delimiter = ',')]
+    ///
+    /// Each permission may be followed by a weight (e.g. `644:90,755:10`)
+    /// making it proportionally more likely to be picked. Permissions without
+    /// a weight count once. Four-digit permissions (e.g. `4755`) set the
+    /// setuid, setgid, and sticky bits too.
+    #[arg(
+        long = "permissions",
+        value_name = "OCTAL[:WEIGHT]",
+        value_delimiter = ','
+    )]
     permissions: Option<Vec<String>>,
+
+    /// List of directory permission octals to deterministically select from
+    ///
+    /// Weighted like `--permissions` (e.g. `700:3,755`), but independently of
+    /// the file permissions. Each must grant the owner full access so the
+    /// directory can still be filled in.
+    #[arg(
+        long = "dir-permissions",
+        value_name = "OCTAL[:WEIGHT]",
+        value_delimiter = ','
+    )]
+    dir_permissions: Option<Vec<String>>,
+
+    /// Set the executable bits on this percentage of files
+    ///
+    /// A simpler alternative to listing permissions when only the executable
+    /// bit matters. Applied on top of `--permissions` if given.
+    #[arg(long = "executable-percentage", value_name = "PERCENTAGE")]
+    #[arg(value_parser = percentage_parser)]
+    executable_percentage: Option<f64>,
+
+    /// How permissions are applied to files
+    ///
+    /// `absolute` gives files exactly the configured mode, as `chmod` would.
+    /// `umask-relative` strips the process umask from it, as it would be for
+    /// files created by a regular application. The mode used is recorded in
+    /// the audit trail.
+    #[arg(long = "permission-mode", value_name = "MODE")]
+    permission_mode: Option<PermissionMode>,
+
+    /// Give the files beneath directories matching a glob their own
+    /// permissions
+    ///
+    /// Written as `GLOB=OCTAL[:WEIGHT],...`, e.g. `3.dir=700` or
+    /// `**/shared.dir=664:3,644`. Globs are matched against directory paths
+    /// relative to the root directory, like `--prune-glob`. The rule matching
+    /// the deepest directory wins, with earlier rules taking precedence at the
+    /// same depth. May be specified multiple times.
+    #[arg(long = "permission-rule", value_name = "RULE")]
+    permission_rules: Vec<PermissionRule>,
+
+    /// Write an fio job file describing the generated files to this path
+    ///
+    /// Each non-empty file gets its own job with its exact size such that the
+    /// identical file population can be exercised with fio after creation.
+    #[arg(long = "fio-output")]
+    #[arg(value_hint = ValueHint::FilePath)]
+    fio_output: Option<PathBuf>,
+
+    /// Write a manifest describing how to regenerate the tree to this path
+    ///
+    /// The manifest records the ftzz version, every parameter, a hash of the
+    /// planned tree, and a digest of the generated files, followed by a
+    /// checksum of the manifest itself.
+    #[arg(long = "manifest", value_name = "PATH")]
+    #[arg(value_hint = ValueHint::FilePath)]
+    manifest: Option<PathBuf>,
+
+    /// Also generate the identical tree into this directory and compare timings
+    ///
+    /// Targets (starting with the root directory) are generated one after the
+    /// other such that the workload is guaranteed to be identical, enabling
+    /// direct A/B comparisons of different filesystems or mounts. May be
+    /// specified multiple times.
+    #[arg(long = "compare-with", value_name = "DIR")]
+    #[arg(value_hint = ValueHint::DirPath)]
+    compare_with: Vec<PathBuf>,
+
+    /// Re-walk the tree after generation and verify the actual number of
+    /// files, directories, and bytes matches what was reported
+    ///
+    /// Any discrepancy causes a failing exit code.
+    #[arg(long = "validate", action = ArgAction::SetTrue)]
+    validate: bool,
+
+    /// Also generate the identical tree into this directory, concurrently
+    ///
+    /// May be specified multiple times to seed identical fixtures across
+    /// several mount points in one go.
+    #[arg(long = "replica-dir", value_name = "DIR")]
+    #[arg(value_hint = ValueHint::DirPath)]
+    #[arg(conflicts_with = "compare_with")]
+    replica_dirs: Vec<PathBuf>,
+
+    /// Generate N identical replicas by treating the root directory as a path
+    /// template
+    ///
+    /// Every occurrence of `{n}` in the root directory is replaced with the
+    /// replica number (starting at 0), e.g. `/mnt/disk{n}/tree`.
+    #[arg(long = "replicas", value_name = "N")]
+    #[arg(conflicts_with = "compare_with")]
+    replicas: Option<NonZeroUsize>,
+
+    /// Spread the top-level directories of the tree round-robin across the
+    /// root directory and this directory
+    ///
+    /// May be specified multiple times, typically with one directory per
+    /// mount, to partition a single logical tree across several filesystems.
+    /// Sharded top-level directories are linked into the root so the tree and
+    /// its audit trail remain whole.
+    #[arg(long = "shard", value_name = "DIR")]
+    #[arg(value_hint = ValueHint::DirPath)]
+    #[arg(conflicts_with_all = ["compare_with", "replica_dirs", "replicas"])]
+    shards: Vec<PathBuf>,
+
+    /// Give every replica its own seed derived from the master seed instead of
+    /// generating identical trees
+    ///
+    /// Replica i (the root directory being replica 0) is seeded with
+    /// `seed ^ xxh64(i)`, hashing i as a little-endian 64-bit integer. The
+    /// derived seeds are recorded in the audit trail so any replica can be
+    /// regenerated on its own by passing its seed.
+    #[arg(long = "distinct-seeds", action = ArgAction::SetTrue)]
+    distinct_seeds: bool,
+
+    /// Don't generate anything beneath directories matching this glob
+    ///
+    /// Patterns are matched against directory paths relative to the root
+    /// directory, e.g. `0.dir/*/3.dir` or `**/2.dir`. Matching directories are
+    /// still created but left empty, and the rest of the tree is generated
+    /// exactly as it would have been without the pattern. May be specified
+    /// multiple times.
+    #[arg(long = "prune-glob", value_name = "PATTERN")]
+    #[arg(value_parser = glob_parser)]
+    prune_globs: Vec<String>,
+
+    /// The distribution the number of subdirectories in each directory is
+    /// drawn from
+    ///
+    /// One of `normal:MEAN[,STD_DEV]`, `uniform:MIN,MAX`, or `poisson:MEAN`.
+    /// This controls the shape of the tree independently of how many files
+    /// each directory contains: files are spread across however many
+    /// directories end up being generated.
+    #[arg(long = "dir-fanout", value_name = "DISTRIBUTION")]
+    #[arg(conflicts_with = "file_to_dir_ratio")]
+    dir_fanout: Option<DirFanout>,
+
+    /// Use a different file to directory ratio or fan-out for the directories
+    /// at one depth
+    ///
+    /// Written as `DEPTH:KEY=VALUE[;KEY=VALUE]` with the keys `ftd-ratio` for
+    /// the number of files per directory and `dir-fanout` for the
+    /// distribution the number of subdirectories is drawn from, e.g.
+    /// `0:dir-fanout=uniform:20,40` to make the tree wide at the top. The
+    /// root directory is at depth 0. May be specified multiple times, once
+    /// per depth.
+    #[arg(long = "level", value_name = "OVERRIDE")]
+    level_overrides: Vec<LevelOverride>,
+
+    /// After generation, test whether the achieved file sizes and directory
+    /// fan-out fit the requested distributions
+    ///
+    /// File sizes are tested with a Kolmogorov-Smirnov test and directory
+    /// fan-out with a chi-square test. The resulting p-values are reported,
+    /// but a poor fit does not cause a failing exit code.
+    #[arg(long = "check-distributions", action = ArgAction::SetTrue)]
+    check_distributions: bool,
+
+    /// The order in which directories are generated
+    ///
+    /// `depth-first` (or `dfs`) finishes each subtree before starting its next
+    /// sibling, so completed subtrees become available early. `breadth-first`
+    /// (or `bfs`) finishes each level of the tree before descending, so the
+    /// full width of the tree becomes visible early. The traversal order is
+    /// part of the seed: the same seed produces different trees under each
+    /// order.
+    #[arg(long = "traversal", value_name = "ORDER")]
+    traversal: Option<TraversalOrder>,
+
+    /// The maximum number of bytes queued for writing but not yet written
+    ///
+    /// Generation pauses until outstanding files have been written whenever
+    /// this limit is exceeded, bounding memory pressure from dirty pages when
+    /// generating many large files on slow storage. The limit is checked
+    /// between directories, so it may be exceeded by up to one directory's
+    /// worth of files.
+    #[arg(long = "max-in-flight-bytes", value_name = "BYTES")]
+    #[arg(value_parser = max_in_flight_bytes_parser)]
+    max_in_flight_bytes: Option<NonZeroU64>,
+
+    /// Split directories containing more files than this across several
+    /// tasks
+    ///
+    /// Keeps all worker threads busy when a few directories are vastly larger
+    /// than the rest. The generated tree is unaffected.
+    #[arg(long = "max-files-per-task", value_name = "FILES")]
+    #[arg(value_parser = max_files_per_task_parser)]
+    #[arg(help = "Split directories with more files than this across tasks [default: 16384]")]
+    max_files_per_task: Option<NonZeroUsize>,
+
+    /// The maximum number of file size buffers kept around for reuse when
+    /// generating an exact number of bytes
+    ///
+    /// Each in-flight task holds one buffer with an entry per file. Lower
+    /// values reduce peak memory at the cost of more allocations. Defaults to
+    /// half the task queue's capacity.
+    #[arg(long = "byte-counts-pool-capacity", value_name = "BUFFERS")]
+    byte_counts_pool_capacity: Option<NonZeroUsize>,
+
+    /// The size of the blocks file contents are staged in before being
+    /// written
+    ///
+    /// Every task reuses a single buffer of several blocks across all the files
+    /// it writes. Larger blocks mean fewer syscalls for big files. Rounded up
+    /// to a multiple of 4 KiB. The generated tree is unaffected.
+    #[arg(long = "write-block-size", value_name = "BYTES")]
+    #[arg(value_parser = write_block_size_parser)]
+    write_block_size: Option<NonZeroUsize>,
+
+    /// Name this percentage of files like union filesystem whiteouts
+    ///
+    /// Whiteout files are named `.wh.n` instead of `n`, the way aufs and OCI
+    /// image layers mark deleted entries.
+    #[arg(long = "whiteout-percentage", value_name = "PERCENTAGE")]
+    #[arg(value_parser = percentage_parser)]
+    whiteout_percentage: Option<f64>,
+
+    /// Create empty files with plain `open(O_CREAT)` instead of `mknod`
+    ///
+    /// Some network and FUSE file systems reject `mknod` for regular files.
+    /// This happens automatically once `mknod` fails with `EPERM` or
+    /// `EOPNOTSUPP`, so the option only saves the failed attempt.
+    #[arg(long = "portable-create", action = ArgAction::SetTrue)]
+    portable_create: bool,
+
+    /// How to name files
+    ///
+    /// `numeric` names files by their index in their directory. `realistic`
+    /// names them like a person might, with words, mixed case, and common
+    /// extensions such as `.txt`, `.jpg`, and `.pdf`, picked from each file's
+    /// seed so reruns produce the same names. `unicode` mixes CJK, Cyrillic,
+    /// Arabic, and emoji codepoints into the names of both files and
+    /// directories.
+    #[arg(long = "name-style", value_name = "STYLE")]
+    #[arg(conflicts_with = "case_collision_percentage")]
+    name_style: Option<NameStyle>,
+
+    /// Name files after this template, such as `report_{n:06}_{rand8}.{ext}`
+    ///
+    /// `{n}` is replaced with the file's index in its directory (`{n:0WIDTH}`
+    /// zero-pads it), `{depth}` with its depth, `{randDIGITS}` with up to 16
+    /// hex digits picked from the file's seed, and `{ext}` with an extension
+    /// picked from `--extensions` or a common one otherwise. Templates must
+    /// contain `{n}` so names stay unique. Use `{{` and `}}` for literal
+    /// braces.
+    #[arg(long = "file-name-template", value_name = "TEMPLATE")]
+    #[arg(conflicts_with_all = ["name_style", "case_collision_percentage"])]
+    file_name_template: Option<NameTemplate>,
+
+    /// Name directories after this template, such as `{depth}_{n:03}`
+    ///
+    /// Takes the same placeholders as `--file-name-template` apart from
+    /// `{ext}`. Random digits are picked from the directory's index and depth.
+    #[arg(long = "dir-name-template", value_name = "TEMPLATE")]
+    #[arg(conflicts_with = "name_style")]
+    dir_name_template: Option<NameTemplate>,
+
+    /// Pad file names with letters to a length in bytes picked from this
+    /// range
+    ///
+    /// Each file's length is derived from its seed. The letters go in front of
+    /// the extension, if there is one, and names already longer are left
+    /// alone. Directory names keep their length.
+    #[arg(long = "name-length", value_name = "MIN..MAX")]
+    #[arg(conflicts_with = "case_collision_percentage")]
+    name_length: Option<NameLengthRange>,
+
+    /// Pad this percentage of file names to exactly 255 bytes, the longest
+    /// name most file systems allow
+    ///
+    /// Dangling symlinks aren't created next to files whose names leave no
+    /// room for the `.link` suffix.
+    #[arg(long = "max-name-percentage", value_name = "PERCENTAGE")]
+    #[arg(value_parser = percentage_parser)]
+    #[arg(conflicts_with = "case_collision_percentage")]
+    max_name_percentage: Option<f64>,
+
+    /// Give files extensions drawn from this weighted list, such as
+    /// `jpg:50,txt:30,bin:20`
+    ///
+    /// Each file's extension is picked from its seed and replaces any it would
+    /// otherwise have. Extensions without a weight have a weight of one. How
+    /// many files got each extension is recorded in the audit trail.
+    #[arg(long = "extensions", value_name = "EXT[:WEIGHT],...")]
+    #[arg(conflicts_with = "case_collision_percentage")]
+    extensions: Option<ExtensionWeights>,
+
+    /// Name files so this percentage of them collide with a sibling on
+    /// case-insensitive file systems
+    ///
+    /// Files are named `filen` instead of `n`, and colliding files `Filem`
+    /// where `m` is the preceding file's number, so at most half of all files
+    /// can collide. Files the file system considers to already exist are
+    /// skipped and reported instead of silently merged.
+    #[arg(long = "case-collision-percentage", value_name = "PERCENTAGE")]
+    #[arg(value_parser = percentage_parser)]
+    case_collision_percentage: Option<f64>,
+
+    /// Create a dangling symlink next to this percentage of files
+    ///
+    /// File `n` gets a sibling `n.link` pointing at the nonexistent `n.missing`
+    /// so traversal and archiving tools run into `ENOENT` when following it.
+    /// The links are not counted as files.
+    #[arg(long = "broken-symlink-percentage", value_name = "PERCENTAGE")]
+    #[arg(value_parser = percentage_parser)]
+    broken_symlink_percentage: Option<f64>,
+
+    /// Create this percentage of files as FIFOs and sockets instead, and as
+    /// character and block devices too when running as root
+    ///
+    /// Devices get the numbers of `/dev/null` and `/dev/loop0`. Files with
+    /// duplicates are left alone. The audit trail records each entry's type.
+    /// Only supported on Linux.
+    #[arg(long = "special-files", value_name = "PERCENTAGE")]
+    #[arg(value_parser = percentage_parser)]
+    special_files: Option<f64>,
+
+    /// Give roughly this percentage of files and directories a POSIX access
+    /// ACL granting a few extra users and groups access
+    ///
+    /// Hardlinked files and special files are left alone. The ACLs are
+    /// recorded in the audit trail. Only supported on Linux.
+    #[arg(long = "acl-percentage", value_name = "PERCENTAGE")]
+    #[arg(value_parser = percentage_parser)]
+    acl_percentage: Option<f64>,
+
+    /// Give every file a modification time picked from this range of Unix
+    /// timestamps, in seconds, and an access time between that and the end
+    /// of the range
+    ///
+    /// The times are derived from each file's seed, so they are the same
+    /// across runs. The modification times are recorded in the audit trail.
+    #[arg(long = "mtime-range", value_name = "START..END")]
+    #[arg(conflicts_with = "fragment")]
+    mtime_range: Option<TimeRange>,
+
+    /// Once the tree is complete, stamp every file, directory, and symlink in
+    /// it with this modification and access time, in seconds since the Unix
+    /// epoch
+    ///
+    /// Archives of trees generated with the same seed and timestamp are
+    /// identical.
+    #[arg(long = "timestamp", value_name = "EPOCH")]
+    #[arg(allow_negative_numbers = true, conflicts_with = "mtime_range")]
+    timestamp: Option<i64>,
+
+    /// Set the read-only, hidden, or system attribute, or a combination of
+    /// them, on roughly this percentage of files and directories
+    ///
+    /// Hardlinked files are left alone. The attributes are recorded in the
+    /// audit trail. Only supported on Windows.
+    #[arg(long = "windows-attribute-percentage", value_name = "PERCENTAGE")]
+    #[arg(value_parser = percentage_parser)]
+    #[arg(conflicts_with_all = ["fragment", "append_pass", "modify_percentage"])]
+    windows_attribute_percentage: Option<f64>,
+
+    /// Write one to three named alternate data streams of random contents to
+    /// roughly this percentage of files
+    ///
+    /// Hardlinked files and special files are left alone. The stream names
+    /// and sizes are recorded in the audit trail. Only supported on Windows.
+    #[arg(long = "ads-percentage", value_name = "PERCENTAGE")]
+    #[arg(value_parser = percentage_parser)]
+    ads_percentage: Option<f64>,
+
+    /// Make every file a hardlink to one of a small pool of inodes, with the
+    /// number of links per inode drawn from this distribution
+    ///
+    /// Takes the same forms as `--dir-fanout`. The pool holds as many inodes
+    /// as it takes for the mean to cover every file, so sizes reported per
+    /// directory entry add up to far more than is stored on disk. Inodes keep
+    /// the default permissions.
+    #[arg(long = "links-per-inode", value_name = "DISTRIBUTION")]
+    #[arg(conflicts_with = "bytes_exact")]
+    links_per_inode: Option<DirFanout>,
+
+    /// Once the tree has been generated, reopen some of its files and append
+    /// this many bytes to each
+    ///
+    /// Simulates files that grow after they were first written, like logs
+    /// and databases, for incremental backup testing. The audit trail records
+    /// the files' final sizes and hashes.
+    #[arg(long = "append-pass", value_name = "BYTES")]
+    #[arg(value_parser = append_bytes_parser)]
+    append_pass: Option<NonZeroU64>,
+
+    /// The percentage of files the append pass grows [default: 10]
+    #[arg(long = "append-percentage", value_name = "PERCENTAGE")]
+    #[arg(requires = "append_pass")]
+    #[arg(value_parser = percentage_parser)]
+    append_percentage: Option<f64>,
+
+    /// Once the tree has been generated, rewrite this percentage of its files
+    /// with new contents and bump their modification times
+    ///
+    /// Files keep their size. Produces a known set of changes for validating
+    /// change detection tools; the audit trail records the new hashes.
+    #[arg(long = "modify-percentage", value_name = "PERCENTAGE")]
+    #[arg(value_parser = percentage_parser)]
+    modify_percentage: Option<f64>,
+
+    /// Write the paths of the files changed by `--modify-percentage` to this
+    /// file, one per line
+    #[arg(long = "modified-output", value_name = "PATH")]
+    #[arg(requires = "modify_percentage")]
+    modified_output: Option<PathBuf>,
+
+    /// Once the tree has been generated, link this many of its directories
+    /// back to themselves or one of their ancestors
+    ///
+    /// Each chosen directory gets a `loop` symlink pointing at the directory
+    /// itself or an ancestor within the same top-level directory, so recursive
+    /// scanners that follow symlinks never reach the bottom. The links are not
+    /// counted as files, but are recorded in the audit trail. On Windows,
+    /// junctions are created instead if creating symlinks requires a
+    /// privilege this process doesn't hold.
+    #[arg(long = "symlink-loops", value_name = "COUNT")]
+    symlink_loops: Option<NonZeroUsize>,
+
+    /// Link files and directories with this flavor of link: hardlink, symlink,
+    /// or junction
+    ///
+    /// Applies to the files sharing an inode with `--links-per-inode` or
+    /// `--duplicates-as-hardlinks`, which are hardlinked by default, and to
+    /// the directories linked by `--symlink-loops`, which are symlinked by
+    /// default. Files symlinked to the first of them aren't counted as files
+    /// and are recorded in the audit trail as symlinks. Only files can be hardlinked and only directories
+    /// joined by junctions. On Windows, files are hardlinked and directories
+    /// joined by junctions if symlinks require a privilege this process
+    /// doesn't hold; elsewhere, junctions are created as symlinks.
+    #[arg(long = "link-flavor", value_name = "FLAVOR")]
+    link_flavor: Option<LinkFlavor>,
+
+    /// Once everything has been written, read back this percentage of files
+    /// [default: 100] and compare their hashes against those computed while
+    /// writing them
+    ///
+    /// Detects silent storage corruption immediately: any mismatch fails the
+    /// run with a data error exit code. The audit trail records how many
+    /// files were read back.
+    #[arg(long = "read-back", value_name = "PERCENTAGE")]
+    #[arg(num_args = 0..=1, default_missing_value = "100")]
+    #[arg(value_parser = percentage_parser)]
+    read_back: Option<f64>,
+
+    /// Where exact bytes mode puts the bytes left over once every file has
+    /// been given a size
+    ///
+    /// `spread` divides them evenly over the last files written, distorting
+    /// many sizes slightly. `last-file` adds them all to the very last file
+    /// and `largest-file` to the largest of the last files, leaving every
+    /// other size exactly as drawn. Ignored unless the total bytes are exact.
+    #[arg(long = "leftover-strategy", value_name = "STRATEGY")]
+    leftover_strategy: Option<LeftoverStrategy>,
+
+    /// The distribution file sizes are drawn from around the mean file size
+    ///
+    /// One of `normal`, `lognormal[:SIGMA]`, `pareto[:SHAPE]`,
+    /// `zipf[:EXPONENT]`, or `uniform`. Every distribution is scaled so the
+    /// average file size stays the same, but the heavy-tailed ones generate
+    /// mostly small files and a few very large ones, as found on real
+    /// filesystems. Sizes are capped at a thousand times the average.
+    #[arg(long = "size-distribution", value_name = "DISTRIBUTION")]
+    #[arg(requires = "num_bytes")]
+    size_distribution: Option<SizeDistribution>,
+
+    /// Start every non-empty file with a line naming its relative path, its
+    /// seed, and the run's plan hash
+    ///
+    /// Files become self-identifying wherever they end up, such as object
+    /// stores and backups, without consulting the audit trail. The header
+    /// overwrites the start of the contents so sizes are unchanged, and is
+    /// truncated in files smaller than it.
+    #[arg(long = "file-headers", action = ArgAction::SetTrue)]
+    #[arg(requires = "num-bytes")]
+    #[arg(conflicts_with_all = ["duplicate_percentage", "content_template"])]
+    file_headers: bool,
+
+    /// Start every non-empty file whose extension names a well-known format
+    /// with that format's signature
+    ///
+    /// PNG, JPEG, GIF, PDF, ZIP (including Office documents), gzip, MP3, and
+    /// MP4 files are recognized by file type sniffers and scanners, while the
+    /// rest of their contents stay random. Combine with `--extensions` or
+    /// `--name-style realistic` to give files extensions. The signature
+    /// overwrites the start of the contents, preceding the file header if
+    /// any.
+    #[arg(long = "magic-bytes", action = ArgAction::SetTrue)]
+    #[arg(requires = "num-bytes")]
+    #[arg(conflicts_with_all = ["duplicate_percentage", "content_template"])]
+    magic_bytes: bool,
+
+    /// What to fill files with [default: random]
+    ///
+    /// `random` writes deterministically random bytes. `text` writes words
+    /// strung into English-looking sentences and paragraphs, compressing like
+    /// real documents and giving full-text indexers something to tokenize.
+    /// `json` makes every file a valid JSON array of records padded with
+    /// whitespace to its exact size. `csv` writes a header row followed by
+    /// rows of typed values, see `--csv-columns`. `dedup` writes 4 KiB blocks
+    /// that repeat at a chosen rate, see `--dedup-percentage`. `syslog` and
+    /// `jsonl` write log lines, RFC 5424 syslog messages or JSON Lines
+    /// records, whose timestamps count up from a time in 2024 picked for each
+    /// file. Contents are derived from each file's seed.
+    #[arg(long = "content", value_name = "MODE")]
+    #[arg(requires = "num-bytes")]
+    #[arg(conflicts_with_all = ["fill_byte", "fill_pattern", "content_template", "content_corpus", "preallocate"])]
+    content: Option<ContentMode>,
+
+    /// The number of columns in CSV contents [default: 8]
+    #[arg(long = "csv-columns", value_name = "COLUMNS")]
+    #[arg(requires = "content")]
+    csv_columns: Option<NonZeroU8>,
+
+    /// The percentage of 4 KiB blocks of dedup contents copied from the block
+    /// dictionary [default: 50]
+    ///
+    /// Every other block is unique, so this is roughly the share of the tree
+    /// a block-level dedup backend can save once the dictionary is stored.
+    #[arg(long = "dedup-percentage", value_name = "PERCENTAGE")]
+    #[arg(requires = "content")]
+    #[arg(value_parser = percentage_parser)]
+    dedup_percentage: Option<f64>,
+
+    /// The number of distinct blocks in the dictionary of dedup contents
+    /// [default: 1024]
+    ///
+    /// Dictionary blocks are the same in every run, so trees also dedup
+    /// against each other.
+    #[arg(long = "dedup-blocks", value_name = "BLOCKS")]
+    #[arg(requires = "content")]
+    dedup_blocks: Option<NonZeroU32>,
+
+    /// Fill in unspecified options from a preset tuned for a workload
+    ///
+    /// `overlayfs` stresses container runtimes: 100K files averaging 4 KiB in
+    /// a deep tree, copied across 4 layer directories (`ROOT_DIR/layer-{n}`
+    /// unless the root directory already contains `{n}`) with 5% of files
+    /// named like whiteouts. Explicit options and configuration files take
+    /// precedence.
+    #[arg(long = "preset", value_name = "PRESET")]
+    preset: Option<Preset>,
+}
+
+/// A bundle of defaults tuned for a workload.
+#[derive(Copy, Clone, Debug, Eq, PartialEq, ValueEnum, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+enum Preset {
+    /// Container and overlayfs stress testing
+    Overlayfs,
+}
+
+impl Preset {
+    fn config(self) -> Config {
+        match self {
+            Self::Overlayfs => Config {
+                files: NonZeroU64::new(100_000),
+                total_bytes: Some(100_000 * 4096),
+                max_depth: Some(8),
+                replicas: NonZeroUsize::new(4),
+                whiteout_percentage: Some(5.),
+                ..Config::default()
+            },
+        }
+    }
 }
 
 impl Generate {
     fn merge(&mut self, config: &Config) {
-        if self.num_files.is_none() {
+        if self.num_files.is_none() && self.num_entries.is_none() {
             self.num_files = config.files;
         }
+        if self.num_files.is_none() && self.num_entries.is_none() {
+            self.num_entries = config.entries;
+        }
         if !self.files_exact {
             self.files_exact = config.files_exact.unwrap_or(false);
         }
+        if self.num_dirs.is_none() {
+            self.num_dirs = config.dirs;
+        }
+        if !self.dirs_exact {
+            self.dirs_exact = config.dirs_exact.unwrap_or(false);
+        }
         if self.num_bytes.is_none() {
             self.num_bytes = config.total_bytes;
         }
-        if self.fill_byte.is_none() {
+        if self.fill_byte.is_none()
+            && self.fill_pattern.is_none()
+            && self.content_template.is_none()
+            && self.content_corpus.is_none()
+        {
             self.fill_byte = config.fill_byte;
+            self.fill_pattern.clone_from(&config.fill_pattern);
+            self.content_template.clone_from(&config.content_template);
+            self.content_corpus.clone_from(&config.content_corpus);
         }
         if !self.bytes_exact {
             self.bytes_exact = config.bytes_exact.unwrap_or(false);
         }
+        if !self.direct_io {
+            self.direct_io = config.direct_io.unwrap_or(false);
+        }
+        if self.writeback_bytes.is_none() {
+            self.writeback_bytes = config.writeback_bytes;
+        }
+        if !self.fragment {
+            self.fragment = config.fragment.unwrap_or(false);
+        }
+        if self.sparse.is_none() {
+            self.sparse = config.sparse;
+        }
+        if self.hole_pattern.is_none() {
+            self.hole_pattern = config.hole_pattern;
+        }
+        if !self.preallocate {
+            self.preallocate = config.preallocate.unwrap_or(false);
+        }
         if !self.exact {
             self.exact = config.exact.unwrap_or(false);
         }
         if self.max_depth.is_none() {
             self.max_depth = config.max_depth;
         }
+        if self.min_depth.is_none() {
+            self.min_depth = config.min_depth;
+        }
+        if !self.flat {
+            self.flat = config.flat.unwrap_or(false);
+        }
+        if !self.files_at_leaves {
+            self.files_at_leaves = config.files_at_leaves.unwrap_or(false);
+        }
+        if self.skew.is_none() {
+            self.skew = config.skew;
+        }
+        if self.files_per_dir_distribution.is_none() {
+            self.files_per_dir_distribution = config.files_per_dir_distribution;
+        }
         if self.file_to_dir_ratio.is_none() {
             self.file_to_dir_ratio = config.ftd_ratio;
         }
+        if self.dir_to_dir_ratio.is_none() {
+            self.dir_to_dir_ratio = config.dtd_ratio;
+        }
         if self.seed.is_none() {
             self.seed = config.seed;
         }
         if self.audit_output.is_none() {
             self.audit_output.clone_from(&config.audit_output);
         }
+        if self.audit_format.is_none() {
+            self.audit_format = config.audit_format;
+        }
+        if self.audit_batch_size.is_none() {
+            self.audit_batch_size = config.audit_batch_size;
+        }
+        if self.audit_page_size.is_none() {
+            self.audit_page_size = config.audit_page_size;
+        }
+        if self.audit_cache_size.is_none() {
+            self.audit_cache_size = config.audit_cache_size;
+        }
         if self.duplicate_percentage.is_none() {
             self.duplicate_percentage = config.duplicate_percentage;
         }
         if self.max_duplicates_per_file.is_none() {
             self.max_duplicates_per_file = config.max_duplicates_per_file;
         }
+        if !self.duplicates_as_hardlinks {
+            self.duplicates_as_hardlinks = config.duplicates_as_hardlinks.unwrap_or(false);
+        }
         if self.permissions.is_none() {
             self.permissions.clone_from(&config.permissions);
         }
+        if self.dir_permissions.is_none() {
+            self.dir_permissions.clone_from(&config.dir_permissions);
+        }
+        if self.executable_percentage.is_none() {
+            self.executable_percentage = config.executable_percentage;
+        }
+        if self.permission_mode.is_none() {
+            self.permission_mode = config.permission_mode;
+        }
+        if self.permission_rules.is_empty() {
+            self.permission_rules = config.permission_rules.clone().unwrap_or_default();
+        }
+        if self.fio_output.is_none() {
+            self.fio_output.clone_from(&config.fio_output);
+        }
+        if self.manifest.is_none() {
+            self.manifest.clone_from(&config.manifest);
+        }
+        if !self.validate {
+            self.validate = config.validate.unwrap_or(false);
+        }
+        if self.replica_dirs.is_empty() {
+            self.replica_dirs = config.replica_dirs.clone().unwrap_or_default();
+        }
+        if self.replicas.is_none() {
+            self.replicas = config.replicas;
+        }
+        if self.compare_with.is_empty() {
+            self.compare_with = config.compare_with.clone().unwrap_or_default();
+        }
+        if self.shards.is_empty() {
+            self.shards = config.shards.clone().unwrap_or_default();
+        }
+        if !self.distinct_seeds {
+            self.distinct_seeds = config.distinct_seeds.unwrap_or(false);
+        }
+        if self.prune_globs.is_empty() {
+            self.prune_globs = config.prune_globs.clone().unwrap_or_default();
+        }
+        if self.dir_fanout.is_none() {
+            self.dir_fanout = config.dir_fanout;
+        }
+        if self.level_overrides.is_empty() {
+            self.level_overrides = config.levels.clone().unwrap_or_default();
+        }
+        if !self.check_distributions {
+            self.check_distributions = config.check_distributions.unwrap_or(false);
+        }
+        if self.traversal.is_none() {
+            self.traversal = config.traversal;
+        }
+        if self.max_in_flight_bytes.is_none() {
+            self.max_in_flight_bytes = config.max_in_flight_bytes;
+        }
+        if self.max_files_per_task.is_none() {
+            self.max_files_per_task = config.max_files_per_task;
+        }
+        if self.byte_counts_pool_capacity.is_none() {
+            self.byte_counts_pool_capacity = config.byte_counts_pool_capacity;
+        }
+        if self.write_block_size.is_none() {
+            self.write_block_size = config.write_block_size;
+        }
+        if self.whiteout_percentage.is_none() {
+            self.whiteout_percentage = config.whiteout_percentage;
+        }
+        if !self.portable_create {
+            self.portable_create = config.portable_create.unwrap_or(false);
+        }
+        if self.name_style.is_none() {
+            self.name_style = config.name_style;
+        }
+        if self.file_name_template.is_none() {
+            self.file_name_template = config.file_name_template.clone();
+        }
+        if self.dir_name_template.is_none() {
+            self.dir_name_template = config.dir_name_template.clone();
+        }
+        if self.name_length.is_none() {
+            self.name_length = config.name_length;
+        }
+        if self.max_name_percentage.is_none() {
+            self.max_name_percentage = config.max_name_percentage;
+        }
+        if self.extensions.is_none() {
+            self.extensions = config.extensions.clone();
+        }
+        if self.case_collision_percentage.is_none() {
+            self.case_collision_percentage = config.case_collision_percentage;
+        }
+        if self.broken_symlink_percentage.is_none() {
+            self.broken_symlink_percentage = config.broken_symlink_percentage;
+        }
+        if self.special_files.is_none() {
+            self.special_files = config.special_files;
+        }
+        if self.acl_percentage.is_none() {
+            self.acl_percentage = config.acl_percentage;
+        }
+        if self.mtime_range.is_none() {
+            self.mtime_range = config.mtime_range;
+        }
+        if self.timestamp.is_none() {
+            self.timestamp = config.timestamp;
+        }
+        if self.windows_attribute_percentage.is_none() {
+            self.windows_attribute_percentage = config.windows_attribute_percentage;
+        }
+        if self.ads_percentage.is_none() {
+            self.ads_percentage = config.ads_percentage;
+        }
+        if self.links_per_inode.is_none() {
+            self.links_per_inode = config.links_per_inode;
+        }
+        if self.append_pass.is_none() {
+            self.append_pass = config.append_pass;
+        }
+        if self.append_percentage.is_none() {
+            self.append_percentage = config.append_percentage;
+        }
+        if self.modify_percentage.is_none() {
+            self.modify_percentage = config.modify_percentage;
+        }
+        if self.modified_output.is_none() {
+            self.modified_output.clone_from(&config.modified_output);
+        }
+        if self.symlink_loops.is_none() {
+            self.symlink_loops = config.symlink_loops;
+        }
+        if self.link_flavor.is_none() {
+            self.link_flavor = config.link_flavor;
+        }
+        if self.read_back.is_none() {
+            self.read_back = config.read_back;
+        }
+        if self.leftover_strategy.is_none() {
+            self.leftover_strategy = config.leftover_strategy;
+        }
+        if self.size_distribution.is_none() {
+            self.size_distribution = config.size_distribution;
+        }
+        if !self.file_headers {
+            self.file_headers = config.file_headers.unwrap_or(false);
+        }
+        if !self.magic_bytes {
+            self.magic_bytes = config.magic_bytes.unwrap_or(false);
+        }
+        if self.content.is_none() {
+            self.content = config.content;
+        }
+        if self.csv_columns.is_none() {
+            self.csv_columns = config.csv_columns;
+        }
+        if self.dedup_percentage.is_none() {
+            self.dedup_percentage = config.dedup_percentage;
+        }
+        if self.dedup_blocks.is_none() {
+            self.dedup_blocks = config.dedup_blocks;
+        }
+        if self.preset.is_none() {
+            self.preset = config.preset;
+        }
+    }
+}
+
+impl Generate {
+    /// Splits the positional arguments into the root directory and the
+    /// directories its top-level directories are spread across.
+    fn resolve_root_dirs(&mut self) -> error_stack::Result<(), CliError> {
+        let mut root_dirs = mem::take(&mut self.root_dirs).into_iter();
+        self.root_dir = root_dirs.next().unwrap_or_default();
+        let mut shards = root_dirs.collect::<Vec<_>>();
+        // A lone trailing number is the seed as it used to be given
+        let legacy_seed = match shards.as_slice() {
+            [seed] if self.seed.is_none() => seed.to_str().and_then(|seed| seed.parse().ok()),
+            _ => None,
+        };
+        if let Some(seed) = legacy_seed {
+            log::warn!(
+                "Passing the seed after the root directory is deprecated, use --seed {seed} \
+                 instead."
+            );
+            self.seed = Some(seed);
+            shards.clear();
+        }
+        // Other trailing numbers would silently become directories
+        if let Some(number) = shards
+            .iter()
+            .find(|dir| dir.to_str().is_some_and(|dir| dir.parse::<u64>().is_ok()))
+        {
+            return Err(error_stack::report!(CliError::InvalidArgs)).attach_printable(format!(
+                "Ambiguous root directory {number:?}: pass seeds with --seed, or name the \
+                 directory as ./{}.",
+                number.display()
+            ));
+        }
+        self.shards.splice(0..0, shards);
+        Ok(())
+    }
+
+    /// Keeps a job submitted to the server within the server's root
+    /// directory. Jobs can't name directories of their own or read input
+    /// files, and the output files they ask for must be plain file names, which are written next to
+    /// the job's tree as `job-<id>.<name>`.
+    fn confine_to_job(&mut self, job_dir: &Path) -> Result<(), String> {
+        let mut positionals = self.root_dirs.iter().skip(1);
+        let seed = positionals.next_back();
+        if positionals.next().is_some()
+            || seed.is_some_and(|seed| seed.to_str().and_then(|s| s.parse::<u64>().ok()).is_none())
+        {
+            return Err(
+                "Jobs are generated in their own directory and cannot name others.\n".into(),
+            );
+        }
+        for (option, dirs) in [
+            ("--shard", &self.shards),
+            ("--replica-dir", &self.replica_dirs),
+            ("--compare-with", &self.compare_with),
+        ] {
+            if !dirs.is_empty() {
+                return Err(format!("Jobs cannot use {option}.\n"));
+            }
+        }
+        // Reading inputs from anywhere would expose the server's files
+        for (option, input) in [
+            ("--content-template", &self.content_template),
+            ("--content-corpus", &self.content_corpus),
+        ] {
+            if input.is_some() {
+                return Err(format!("Jobs cannot use {option}.\n"));
+            }
+        }
+
+        let job_name = job_dir.file_name().unwrap_or_default().to_string_lossy();
+        for (option, output) in [
+            ("--audit-output", &mut self.audit_output),
+            ("--fio-output", &mut self.fio_output),
+            ("--manifest", &mut self.manifest),
+            ("--modified-output", &mut self.modified_output),
+        ] {
+            let Some(path) = output else {
+                continue;
+            };
+            let mut components = path.components();
+            let (Some(Component::Normal(name)), None) = (components.next(), components.next())
+            else {
+                return Err(format!(
+                    "{option} must be a plain file name in a job, not {path:?}.\n"
+                ));
+            };
+            *path = job_dir.with_file_name(format!("{job_name}.{}", name.to_string_lossy()));
+        }
+        Ok(())
+    }
+
+    /// Moves the tree into the directory of the `i`th layer within the root
+    /// directory and each of its copies, and gives the layer its own output
+    /// files by inserting `.<i>.layer` before their extension.
+    fn nest_in_layer(&mut self, i: usize) {
+        let name = format!("{i}.layer");
+        self.root_dir.push(&name);
+        for dir in self
+            .shards
+            .iter_mut()
+            .chain(&mut self.replica_dirs)
+            .chain(&mut self.compare_with)
+        {
+            dir.push(&name);
+        }
+        for output in [
+            &mut self.audit_output,
+            &mut self.fio_output,
+            &mut self.manifest,
+            &mut self.modified_output,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            let mut file_name = output.file_stem().unwrap_or_default().to_os_string();
+            file_name.push(format!(".{name}"));
+            if let Some(extension) = output.extension() {
+                file_name.push(".");
+                file_name.push(extension);
+            }
+            output.set_file_name(file_name);
+        }
+    }
+
+    /// Fills in options left unspecified by the command line and
+    /// configuration file from the preset, if any.
+    fn apply_preset(&mut self) {
+        let Some(preset) = self.preset else {
+            return;
+        };
+        self.merge(&preset.config());
+
+        let root_dir = self.root_dir.to_string_lossy();
+        if preset == Preset::Overlayfs
+            && self.replica_dirs.is_empty()
+            && self.replicas.is_some()
+            && !root_dir.contains("{n}")
+        {
+            self.root_dir = self.root_dir.join("layer-{n}");
+        }
+    }
+
+    /// Expands `--replicas N` into concrete replica directories by substituting
+    /// the replica number for `{n}` in the root directory.
+    fn expand_replicas(&mut self) -> error_stack::Result<(), CliError> {
+        let Some(replicas) = self.replicas.take() else {
+            return Ok(());
+        };
+
+        let template = self.root_dir.to_string_lossy().into_owned();
+        if !template.contains("{n}") {
+            return Err(error_stack::report!(CliError::InvalidArgs)).attach_printable(format!(
+                "The root directory {template:?} must contain `{{n}}` to generate replicas."
+            ));
+        }
+
+        self.root_dir = template.replace("{n}", "0").into();
+        self.replica_dirs.extend(
+            (1..replicas.get()).map(|n| PathBuf::from(template.replace("{n}", &n.to_string()))),
+        );
+        Ok(())
+    }
+
+    /// Turns `--entries` into the equivalent number of files.
+    fn resolve_num_entries(&mut self) -> error_stack::Result<(), CliError> {
+        let Some(num_entries) = self.num_entries.take() else {
+            return Ok(());
+        };
+
+        self.num_files = Some(
+            NumFilesWithRatio::from_num_entries(num_entries, self.file_to_dir_ratio)
+                .change_context(CliError::InvalidArgs)?
+                .num_files(),
+        );
+        Ok(())
+    }
+
+    /// Turns `--dtd-ratio` into a directory fan-out, deriving the maximum depth
+    /// if both ratios were specified.
+    fn resolve_dir_to_dir_ratio(&mut self) -> error_stack::Result<(), CliError> {
+        let Some(dtd) = self.dir_to_dir_ratio.take() else {
+            return Ok(());
+        };
+
+        if let (Some(num_files), Some(ftd)) = (self.num_files, self.file_to_dir_ratio) {
+            if self.max_depth.is_some() {
+                return Err(error_stack::report!(CliError::InvalidArgs)).attach_printable(
+                    "The maximum depth cannot be specified alongside both the file to directory \
+                     and directory to directory ratios.",
+                );
+            }
+
+            let num_dirs = num_files.get() as f64 / ftd.get() as f64;
+            let depth = if dtd.get() == 1 {
+                num_dirs
+            } else {
+                num_dirs.ln() / (dtd.get() as f64).ln()
+            };
+            self.max_depth = Some((depth.round() as u32).max(1));
+        }
+        self.dir_fanout = Some(DirFanout::from_ratio(dtd));
+        Ok(())
     }
 }
 
@@ -193,20 +1643,98 @@ impl TryFrom<Generate> for Generator {
     type Error = NumFilesWithRatioError;
     fn try_from(
         Generate {
+            root_dirs: _,
             root_dir,
             num_files,
+            num_entries: _,
             files_exact,
+            num_dirs,
+            dirs_exact,
             num_bytes,
             fill_byte,
+            fill_pattern,
+            content_template,
+            content_corpus,
             bytes_exact,
+            direct_io,
+            writeback_bytes,
+            fragment,
+            sparse,
+            hole_pattern,
+            preallocate,
             exact,
             max_depth,
+            min_depth,
+            flat,
+            files_at_leaves,
+            skew,
+            files_per_dir_distribution,
             file_to_dir_ratio,
+            dir_to_dir_ratio: _,
             seed,
             audit_output,
+            audit_format,
+            audit_batch_size,
+            audit_page_size,
+            audit_cache_size,
             duplicate_percentage,
             max_duplicates_per_file,
+            duplicates_as_hardlinks,
             permissions,
+            dir_permissions,
+            executable_percentage,
+            permission_mode,
+            permission_rules,
+            fio_output,
+            manifest,
+            compare_with,
+            validate,
+            replica_dirs,
+            replicas: _,
+            shards,
+            distinct_seeds,
+            prune_globs,
+            dir_fanout,
+            level_overrides,
+            check_distributions,
+            traversal,
+            max_in_flight_bytes,
+            max_files_per_task,
+            byte_counts_pool_capacity,
+            write_block_size,
+            whiteout_percentage,
+            portable_create,
+            name_style,
+            file_name_template,
+            dir_name_template,
+            name_length,
+            max_name_percentage,
+            extensions,
+            case_collision_percentage,
+            broken_symlink_percentage,
+            special_files,
+            acl_percentage,
+            mtime_range,
+            timestamp,
+            windows_attribute_percentage,
+            ads_percentage,
+            links_per_inode,
+            append_pass,
+            append_percentage,
+            modify_percentage,
+            modified_output,
+            symlink_loops,
+            link_flavor,
+            read_back,
+            leftover_strategy,
+            size_distribution,
+            file_headers,
+            magic_bytes,
+            content,
+            csv_columns,
+            dedup_percentage,
+            dedup_blocks,
+            preset: _,
         }: Generate,
     ) -> Result<Self, Self::Error> {
         let num_files = num_files.ok_or(NumFilesWithRatioError::InvalidRatio {
@@ -223,33 +1751,97 @@ impl TryFrom<Generate> for Generator {
         let builder = Self::builder();
         let builder = builder.root_dir(root_dir);
         let builder = builder.files_exact(files_exact);
+        let builder = builder.maybe_num_dirs(num_dirs);
+        let builder = builder.dirs_exact(dirs_exact);
         let builder = builder.num_bytes(num_bytes);
         let builder = builder.bytes_exact(bytes_exact);
+        let builder = builder.direct_io(direct_io);
+        let builder = builder.maybe_writeback_bytes(writeback_bytes);
+        let builder = builder.fragment(fragment);
+        let builder = builder.maybe_sparse(sparse);
+        let builder = builder.maybe_hole_pattern(hole_pattern);
+        let builder = builder.preallocate(preallocate);
         let builder = builder.max_depth(max_depth);
+        let builder = builder.maybe_min_depth(min_depth);
+        let builder = builder.flat(flat);
+        let builder = builder.files_at_leaves(files_at_leaves);
+        let builder = builder.maybe_skew(skew);
+        let builder = builder.maybe_files_per_dir_distribution(files_per_dir_distribution);
         let builder = builder.seed(seed);
         let builder = builder.maybe_fill_byte(fill_byte);
+        let builder = builder.maybe_fill_pattern(fill_pattern);
+        let builder = builder.maybe_content_template(content_template);
+        let builder = builder.maybe_content_corpus(content_corpus);
         let builder = if let Some(ratio) = file_to_dir_ratio {
             builder.num_files_with_ratio(NumFilesWithRatio::new(num_files, ratio)?)
         } else {
             builder.num_files_with_ratio(NumFilesWithRatio::from_num_files(num_files))
         };
         let builder = builder.maybe_audit_output(audit_output);
+        let builder = builder.maybe_audit_format(audit_format);
+        let builder = builder.maybe_audit_batch_size(audit_batch_size);
+        let builder = builder.maybe_audit_page_size(audit_page_size);
+        let builder = builder.maybe_audit_cache_size(audit_cache_size);
         let builder = builder.maybe_duplicate_percentage(duplicate_percentage);
         let builder = builder.maybe_max_duplicates_per_file(max_duplicates_per_file);
-        let builder = builder.permissions(
-            permissions
-                .unwrap_or_default()
-                .into_iter()
-                .map(|p| {
-                    u32::from_str_radix(&p, 8)
-                        .map_err(|_| Cow::from(format!("Invalid octal permission: {p}")))
-                })
-                .collect::<Result<Vec<_>, _>>()
-                .map_err(|_| NumFilesWithRatioError::InvalidRatio {
-                    num_files: NonZeroU64::new(1).unwrap(),
-                    file_to_dir_ratio: NonZeroU64::new(2).unwrap(),
-                })?, // Hack: NumFilesWithRatioError doesn't have a generic error variant
-        );
+        let builder = builder.duplicates_as_hardlinks(duplicates_as_hardlinks);
+        let builder = builder.maybe_executable_percentage(executable_percentage);
+        let builder = builder.permission_mode(permission_mode.unwrap_or_default());
+        let builder = builder.permission_rules(permission_rules);
+        let builder = builder.maybe_fio_output(fio_output);
+        let builder = builder.maybe_manifest(manifest);
+        let builder = builder.compare_with(compare_with);
+        let builder = builder.validate(validate);
+        let builder = builder.replicas(replica_dirs);
+        let builder = builder.shards(shards);
+        let builder = builder.distinct_seeds(distinct_seeds);
+        let builder = builder.prune_globs(prune_globs);
+        let builder = builder.maybe_dir_fanout(dir_fanout);
+        let builder = builder.level_overrides(level_overrides);
+        let builder = builder.check_distributions(check_distributions);
+        let builder = builder.traversal(traversal.unwrap_or_default());
+        let builder = builder.maybe_max_in_flight_bytes(max_in_flight_bytes);
+        let builder = builder.maybe_max_files_per_task(max_files_per_task);
+        let builder = builder.maybe_byte_counts_pool_capacity(byte_counts_pool_capacity);
+        let builder = builder.maybe_write_block_size(write_block_size);
+        let builder = builder.maybe_whiteout_percentage(whiteout_percentage);
+        let builder = builder.portable_create(portable_create);
+        let builder = builder.maybe_name_style(name_style);
+        let builder = builder.maybe_file_name_template(file_name_template);
+        let builder = builder.maybe_dir_name_template(dir_name_template);
+        let builder = builder.maybe_name_length(name_length);
+        let builder = builder.maybe_max_name_percentage(max_name_percentage);
+        let builder = builder.maybe_extensions(extensions);
+        let builder = builder.maybe_case_collision_percentage(case_collision_percentage);
+        let builder = builder.maybe_broken_symlink_percentage(broken_symlink_percentage);
+        let builder = builder.maybe_special_files(special_files);
+        let builder = builder.maybe_acl_percentage(acl_percentage);
+        let builder = builder.maybe_mtime_range(mtime_range);
+        let builder = builder.maybe_timestamp(timestamp);
+        let builder = builder.maybe_windows_attribute_percentage(windows_attribute_percentage);
+        let builder = builder.maybe_ads_percentage(ads_percentage);
+        let builder = builder.maybe_links_per_inode(links_per_inode);
+        let builder = builder.maybe_append_pass(append_pass);
+        let builder = builder.maybe_append_percentage(append_percentage);
+        let builder = builder.maybe_modify_percentage(modify_percentage);
+        let builder = builder.maybe_modified_output(modified_output);
+        let builder = builder.maybe_symlink_loops(symlink_loops);
+        let builder = builder.maybe_link_flavor(link_flavor);
+        let builder = builder.maybe_read_back(read_back);
+        let builder = builder.maybe_leftover_strategy(leftover_strategy);
+        let builder = builder.maybe_size_distribution(size_distribution);
+        let builder = builder.file_headers(file_headers);
+        let builder = builder.magic_bytes(magic_bytes);
+        let builder = builder.maybe_content(content);
+        let builder = builder.maybe_csv_columns(csv_columns);
+        let builder = builder.maybe_dedup_percentage(dedup_percentage);
+        let builder = builder.maybe_dedup_blocks(dedup_blocks);
+        let (permissions, permission_weights) = weighted_permissions(permissions)?;
+        let builder = builder.permission_weights(permission_weights);
+        let builder = builder.permissions(permissions);
+        let (dir_permissions, dir_permission_weights) = weighted_permissions(dir_permissions)?;
+        let builder = builder.dir_permission_weights(dir_permission_weights);
+        let builder = builder.dir_permissions(dir_permissions);
         Ok(builder.build())
     }
 }
@@ -261,20 +1853,98 @@ mod generate_tests {
     #[test]
     fn params_are_mapped_correctly() {
         let options = Generate {
+            root_dirs: Vec::new(),
             root_dir: PathBuf::from("abc"),
             num_files: Some(NonZeroU64::new(373).unwrap()),
+            num_entries: None,
             num_bytes: Some(637),
             fill_byte: None,
+            fill_pattern: None,
+            content_template: None,
+            content_corpus: None,
             max_depth: Some(43),
+            min_depth: None,
+            flat: false,
+            files_at_leaves: false,
+            skew: None,
+            files_per_dir_distribution: None,
             file_to_dir_ratio: Some(NonZeroU64::new(37).unwrap()),
+            dir_to_dir_ratio: None,
             seed: Some(775),
             files_exact: false,
+            num_dirs: None,
+            dirs_exact: false,
             bytes_exact: false,
+            direct_io: false,
+            writeback_bytes: None,
+            fragment: false,
+            sparse: None,
+            hole_pattern: None,
+            preallocate: false,
             exact: false,
             audit_output: None,
+            audit_format: None,
+            audit_batch_size: None,
+            audit_page_size: None,
+            audit_cache_size: None,
             duplicate_percentage: None,
             max_duplicates_per_file: None,
+            duplicates_as_hardlinks: false,
             permissions: None,
+            dir_permissions: None,
+            executable_percentage: None,
+            permission_mode: None,
+            permission_rules: Vec::new(),
+            fio_output: None,
+            manifest: None,
+            compare_with: Vec::new(),
+            validate: false,
+            replica_dirs: Vec::new(),
+            replicas: None,
+            shards: Vec::new(),
+            distinct_seeds: false,
+            prune_globs: Vec::new(),
+            dir_fanout: None,
+            level_overrides: Vec::new(),
+            check_distributions: false,
+            traversal: None,
+            max_in_flight_bytes: None,
+            max_files_per_task: None,
+            byte_counts_pool_capacity: None,
+            write_block_size: None,
+            whiteout_percentage: None,
+            portable_create: false,
+            name_style: None,
+            file_name_template: None,
+            dir_name_template: None,
+            name_length: None,
+            max_name_percentage: None,
+            extensions: None,
+            case_collision_percentage: None,
+            broken_symlink_percentage: None,
+            special_files: None,
+            acl_percentage: None,
+            mtime_range: None,
+            timestamp: None,
+            windows_attribute_percentage: None,
+            ads_percentage: None,
+            links_per_inode: None,
+            append_pass: None,
+            append_percentage: None,
+            modify_percentage: None,
+            modified_output: None,
+            symlink_loops: None,
+            link_flavor: None,
+            read_back: None,
+            leftover_strategy: None,
+            size_distribution: None,
+            file_headers: false,
+            magic_bytes: false,
+            content: None,
+            csv_columns: None,
+            dedup_percentage: None,
+            dedup_blocks: None,
+            preset: None,
         };
 
         let generator = Generator::try_from(options).unwrap();
@@ -295,7 +1965,10 @@ pub enum CliError {
     Generator,
     #[error("An argument combination was invalid.")]
     InvalidArgs,
-    #[error("The number of files to generate must be specified via --files or configuration.")]
+    #[error(
+        "The number of files to generate must be specified via --files, --entries, or \
+         configuration."
+    )]
     MissingNumFiles,
 }
 
@@ -363,32 +2036,275 @@ fn main() -> ExitCode {
 
 fn ftzz(
     Ftzz {
-        mut options,
+        cmd,
+        options,
         verbose: _,
         help: _,
         config_file,
+        self_check_compat,
     }: Ftzz,
 ) -> error_stack::Result<(), CliError> {
-    if let Some(path) = config_file {
-        let config = Config::from_file(&path).change_context(CliError::InvalidArgs)?;
-        options.merge(&config);
+    if self_check_compat {
+        return ftzz::self_check_compat(&mut stdout().write_adapter())
+            .change_context(CliError::Generator);
     }
 
-    if options.num_files.is_none() {
-        return Err(error_stack::report!(CliError::MissingNumFiles));
-    }
+    let config = config_file
+        .map(|path| Config::from_file(&path).change_context(CliError::InvalidArgs))
+        .transpose()?;
+    let generator = |mut options: Generate, layer: Option<(usize, &Config)>| {
+        options.resolve_root_dirs()?;
+        if let Some((_, layer)) = layer {
+            options.merge(layer);
+        }
+        if let Some(config) = &config {
+            options.merge(config);
+        }
+        if let Some((i, _)) = layer {
+            options.nest_in_layer(i);
+        }
+        options.apply_preset();
+        options.resolve_num_entries()?;
+        if options.num_files.is_none() {
+            return Err(error_stack::report!(CliError::MissingNumFiles));
+        }
+        options.expand_replicas()?;
+        options.resolve_dir_to_dir_ratio()?;
+        Generator::try_from(options).change_context(CliError::InvalidArgs)
+    };
 
     let stdout = stdout();
-    Generator::try_from(options)
-        .change_context(CliError::InvalidArgs)?
-        .generate(&mut stdout.write_adapter())
-        .change_context(CliError::Generator)
+    match cmd {
+        Some(Cmd::Bench(Bench { options, keep })) => Benchmark::builder()
+            .generator(generator(options, None)?)
+            .keep(keep)
+            .build()
+            .run(&mut stdout.write_adapter())
+            .change_context(CliError::Generator),
+        Some(Cmd::FindSeed(FindSeed {
+            options,
+            attempts,
+            jobs,
+            min_files_in_dir,
+            min_dirs_in_dir,
+        })) => SeedSearch::builder()
+            // Candidates are generated with the minimum depth guaranteed
+            .maybe_min_depth(
+                options
+                    .min_depth
+                    .map(|depth| depth.try_into().unwrap_or(usize::MAX)),
+            )
+            .generator(generator(options, None)?)
+            .maybe_attempts(attempts)
+            .maybe_jobs(jobs)
+            .maybe_min_files_in_dir(min_files_in_dir)
+            .maybe_min_dirs_in_dir(min_dirs_in_dir)
+            .build()
+            .run(&mut stdout.write_adapter())
+            .change_context(CliError::Generator),
+        Some(Cmd::Replay(Replay {
+            audit,
+            root_dir,
+            fill_byte,
+        })) => AuditReplay::builder()
+            .audit(audit)
+            .root_dir(root_dir)
+            .maybe_fill_byte(fill_byte)
+            .build()
+            .run(&mut stdout.write_adapter())
+            .change_context(CliError::Generator),
+        Some(Cmd::Verify(Verify {
+            audit,
+            root_dir,
+            sample,
+        })) => AuditVerification::builder()
+            .audit(audit)
+            .root_dir(root_dir)
+            .maybe_sample(sample)
+            .build()
+            .run(&mut stdout.write_adapter())
+            .change_context(CliError::Generator),
+        Some(Cmd::Import(Import {
+            listing,
+            format,
+            root_dir,
+            fill_byte,
+            seed,
+        })) => ListingImport::builder()
+            .listing(listing)
+            .maybe_format(format)
+            .root_dir(root_dir)
+            .maybe_fill_byte(fill_byte)
+            .maybe_seed(seed)
+            .build()
+            .run(&mut stdout.write_adapter())
+            .change_context(CliError::Generator),
+        Some(Cmd::Profile(Profile { root_dir, output })) => TreeProfiler::builder()
+            .root_dir(root_dir)
+            .maybe_profile_output(output)
+            .build()
+            .run(&mut stdout.write_adapter())
+            .change_context(CliError::Generator),
+        Some(Cmd::Serve(Serve { listen, root_dir })) => Server::builder()
+            .listen(listen)
+            .root_dir(root_dir)
+            .build()
+            .run(&mut stdout.write_adapter(), |job_dir, args| {
+                let ServeJob { mut options } = ServeJob::try_parse_from(
+                    [job_dir.as_os_str()]
+                        .into_iter()
+                        .chain(args.split_whitespace().map(OsStr::new)),
+                )
+                .map_err(|e| e.to_string())?;
+                options.confine_to_job(job_dir)?;
+                generator(options, None).map_err(|e| format!("{e:?}\n"))
+            })
+            .change_context(CliError::Generator),
+        None => {
+            let options = options.ok_or(CliError::InvalidArgs)?;
+            let layers = config
+                .as_ref()
+                .and_then(|config| config.layers.as_deref())
+                .unwrap_or_default();
+            if layers.is_empty() {
+                return generator(options, None)?
+                    .generate(&mut stdout.write_adapter())
+                    .change_context(CliError::Generator);
+            }
+
+            let mut output = stdout.write_adapter();
+            for (i, layer) in layers.iter().enumerate() {
+                generator(options.clone(), Some((i, layer)))?
+                    .generate(&mut output)
+                    .change_context(CliError::Generator)
+                    .attach_printable_lazy(|| format!("Failed to generate layer {i}"))?;
+            }
+            Ok(())
+        }
+    }
 }
 
 fn num_files_parser(s: &str) -> Result<NonZeroU64, Cow<'static, str>> {
     NonZeroU64::new(si_number(s)?).ok_or_else(|| "At least one file must be generated.".into())
 }
 
+fn num_dirs_parser(s: &str) -> Result<NonZeroU64, Cow<'static, str>> {
+    NonZeroU64::new(si_number(s)?).ok_or_else(|| "At least one directory must be generated.".into())
+}
+
+fn num_entries_parser(s: &str) -> Result<NonZeroU64, Cow<'static, str>> {
+    NonZeroU64::new(si_number(s)?).ok_or_else(|| "At least one entry must be generated.".into())
+}
+
 fn file_to_dir_ratio_parser(s: &str) -> Result<NonZeroU64, Cow<'static, str>> {
     NonZeroU64::new(si_number(s)?).ok_or_else(|| "Cannot have no files per directory.".into())
 }
+
+fn dir_to_dir_ratio_parser(s: &str) -> Result<NonZeroU64, Cow<'static, str>> {
+    NonZeroU64::new(si_number(s)?).ok_or_else(|| "Cannot have no directories per directory.".into())
+}
+
+fn max_in_flight_bytes_parser(s: &str) -> Result<NonZeroU64, Cow<'static, str>> {
+    NonZeroU64::new(si_number(s)?)
+        .ok_or_else(|| "At least one byte must be allowed in flight.".into())
+}
+
+fn writeback_bytes_parser(s: &str) -> Result<NonZeroU64, Cow<'static, str>> {
+    NonZeroU64::new(si_number(s)?).ok_or_else(|| "Writeback must flush at least one byte.".into())
+}
+
+fn audit_page_size_parser(s: &str) -> Result<u32, Cow<'static, str>> {
+    let size = si_number::<u32>(s)?;
+    if size.is_power_of_two() && (512..=65536).contains(&size) {
+        Ok(size)
+    } else {
+        Err("SQLite pages must be a power of two between 512 and 65536 bytes.".into())
+    }
+}
+
+fn audit_cache_size_parser(s: &str) -> Result<NonZeroU64, Cow<'static, str>> {
+    NonZeroU64::new(si_number(s)?).ok_or_else(|| "The cache must hold at least one byte.".into())
+}
+
+fn write_block_size_parser(s: &str) -> Result<NonZeroUsize, Cow<'static, str>> {
+    NonZeroUsize::new(si_number(s)?).ok_or_else(|| "Blocks must hold at least one byte.".into())
+}
+
+fn append_bytes_parser(s: &str) -> Result<NonZeroU64, Cow<'static, str>> {
+    NonZeroU64::new(si_number(s)?).ok_or_else(|| "Appends must add at least one byte.".into())
+}
+
+fn percentage_parser(s: &str) -> Result<f64, Cow<'static, str>> {
+    let percentage = s.parse::<f64>().map_err(|e| e.to_string())?;
+    if (0. ..=100.).contains(&percentage) {
+        Ok(percentage)
+    } else {
+        Err("Percentages must be between 0 and 100.".into())
+    }
+}
+
+fn skew_parser(s: &str) -> Result<f64, Cow<'static, str>> {
+    let skew = s.parse::<f64>().map_err(|e| e.to_string())?;
+    if (0. ..1.).contains(&skew) {
+        Ok(skew)
+    } else {
+        Err("The skew must be at least 0 and below 1.".into())
+    }
+}
+
+/// Splits `permissions` into their modes and weights, leaving the weights
+/// empty if none were given.
+fn weighted_permissions(
+    permissions: Option<Vec<String>>,
+) -> Result<(Vec<u32>, Vec<u32>), NumFilesWithRatioError> {
+    let permissions = permissions
+        .unwrap_or_default()
+        .iter()
+        .map(|p| permission_parser(p))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|_| NumFilesWithRatioError::InvalidRatio {
+            num_files: NonZeroU64::new(1).unwrap(),
+            file_to_dir_ratio: NonZeroU64::new(2).unwrap(),
+        })?; // Hack: NumFilesWithRatioError doesn't have a generic error variant
+    let weighted = permissions.iter().any(|&(_, weight)| weight.is_some());
+    let weights = if weighted {
+        permissions
+            .iter()
+            .map(|&(_, weight)| weight.unwrap_or(1))
+            .collect()
+    } else {
+        Vec::new()
+    };
+    Ok((
+        permissions.into_iter().map(|(mode, _)| mode).collect(),
+        weights,
+    ))
+}
+
+fn permission_parser(s: &str) -> Result<(u32, Option<u32>), Cow<'static, str>> {
+    let (mode, weight) = s
+        .split_once(':')
+        .map_or((s, None), |(mode, weight)| (mode, Some(weight)));
+    let mode = u32::from_str_radix(mode, 8)
+        .ok()
+        .filter(|&mode| mode <= MAX_MODE)
+        .ok_or_else(|| Cow::from(format!("Invalid octal permission: {mode}")))?;
+    let weight = weight
+        .map(|weight| {
+            weight
+                .parse()
+                .map_err(|_| Cow::from(format!("Invalid permission weight: {weight}")))
+        })
+        .transpose()?;
+    Ok((mode, weight))
+}
+
+fn max_files_per_task_parser(s: &str) -> Result<NonZeroUsize, Cow<'static, str>> {
+    NonZeroUsize::new(si_number(s)?).ok_or_else(|| "Tasks must create at least one file.".into())
+}
+
+fn glob_parser(s: &str) -> Result<String, Cow<'static, str>> {
+    glob::Pattern::new(s)
+        .map(|_| s.to_owned())
+        .map_err(|e| e.to_string().into())
+}
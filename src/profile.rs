@@ -0,0 +1,273 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Write,
+    fs,
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use bon::Builder;
+use bytesize::ByteSize;
+use error_stack::{Result, ResultExt};
+use serde::Serialize;
+use thousands::Separable;
+
+use crate::{core::rehash, generator::Error};
+
+/// Walks an existing tree and records statistics about its shape, file sizes,
+/// extensions, and duplicated contents as a TOML profile.
+///
+/// Symbolic links are counted but not followed.
+#[derive(Debug, Builder)]
+pub struct TreeProfiler {
+    root_dir: PathBuf,
+    /// Write the profile to this path instead of the output stream.
+    profile_output: Option<PathBuf>,
+}
+
+/// Statistics describing an existing tree. Depths are those of directories,
+/// the root directory being at depth 0.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "kebab-case")]
+struct Profile {
+    files: u64,
+    dirs: u64,
+    symlinks: u64,
+    bytes: u64,
+    max_depth: usize,
+    /// The number of files in the directories at each depth.
+    files_per_depth: Vec<u64>,
+    /// The number of subdirectories in the directories at each depth, so the
+    /// root directory is left out like it is from `dirs`.
+    dirs_per_depth: Vec<u64>,
+    /// The number of files directly inside each directory.
+    files_per_dir: Summary,
+    /// The number of subdirectories directly inside each directory.
+    dirs_per_dir: Summary,
+    file_size: Summary,
+    /// The number of files of each size class: empty files first, then files
+    /// of 1 byte, 2-3 bytes, 4-7 bytes, and so on.
+    size_histogram: Vec<u64>,
+    /// The number of files with each extension, the empty one standing for
+    /// files without an extension.
+    extensions: BTreeMap<String, u64>,
+    duplicates: Duplicates,
+}
+
+#[derive(Debug, Default, Serialize)]
+struct Summary {
+    mean: f64,
+    median: u64,
+    p90: u64,
+    p99: u64,
+    max: u64,
+}
+
+impl Summary {
+    #[allow(
+        clippy::cast_precision_loss,
+        clippy::cast_possible_truncation,
+        clippy::cast_sign_loss
+    )]
+    fn new(mut values: Vec<u64>) -> Self {
+        if values.is_empty() {
+            return Self::default();
+        }
+        values.sort_unstable();
+
+        let percentile = |p: f64| values[((values.len() - 1) as f64 * p).round() as usize];
+        Self {
+            mean: values.iter().sum::<u64>() as f64 / values.len() as f64,
+            median: percentile(0.5),
+            p90: percentile(0.9),
+            p99: percentile(0.99),
+            max: values[values.len() - 1],
+        }
+    }
+}
+
+/// Files whose contents are identical to those of another file. Empty files
+/// are never counted.
+#[derive(Debug, Default, Serialize)]
+struct Duplicates {
+    /// The number of files beyond the first with the same contents.
+    files: u64,
+    /// The bytes taken up by those files.
+    bytes: u64,
+    /// The number of distinct contents shared by several files.
+    groups: u64,
+}
+
+/// What a walk of the tree collects before it is summarized.
+#[derive(Default)]
+struct Walk {
+    symlinks: u64,
+    files_per_depth: Vec<u64>,
+    dirs_per_depth: Vec<u64>,
+    files_per_dir: Vec<u64>,
+    dirs_per_dir: Vec<u64>,
+    extensions: BTreeMap<String, u64>,
+    files_by_size: HashMap<u64, Vec<PathBuf>>,
+}
+
+impl TreeProfiler {
+    pub fn run(self, output: &mut impl Write) -> Result<(), Error> {
+        let Self {
+            root_dir,
+            profile_output,
+        } = self;
+
+        let walk = walk(&root_dir)
+            .change_context(Error::Io)
+            .attach(ExitCode::from(sysexits::ExitCode::IoErr))?;
+        let duplicates = duplicates(&walk.files_by_size)
+            .change_context(Error::Io)
+            .attach(ExitCode::from(sysexits::ExitCode::IoErr))?;
+        let profile = summarize(walk, duplicates);
+
+        let toml = toml::to_string(&profile)
+            .attach_printable("Failed to serialize the profile")
+            .change_context(Error::Io)
+            .attach(ExitCode::from(sysexits::ExitCode::Software))?;
+        let Some(path) = profile_output else {
+            return output
+                .write_str(&toml)
+                .attach_printable("Failed to write to output stream")
+                .change_context(Error::Io)
+                .attach(ExitCode::from(sysexits::ExitCode::IoErr));
+        };
+        fs::write(&path, toml)
+            .attach_printable_lazy(|| format!("Failed to write profile {path:?}"))
+            .change_context(Error::Io)
+            .attach(ExitCode::from(sysexits::ExitCode::CantCreat))?;
+
+        writeln!(
+            output,
+            "Profiled {} {files_noun} ({}) across {} {dirs_noun} of maximum depth {}.",
+            profile.files.separate_with_commas(),
+            ByteSize(profile.bytes).display().si(),
+            profile.dirs.separate_with_commas(),
+            profile.max_depth,
+            files_noun = if profile.files == 1 { "file" } else { "files" },
+            dirs_noun = if profile.dirs == 1 {
+                "directory"
+            } else {
+                "directories"
+            },
+        )
+        .attach_printable("Failed to write to output stream")
+        .change_context(Error::Io)
+        .attach(ExitCode::from(sysexits::ExitCode::IoErr))
+    }
+}
+
+fn walk(root_dir: &Path) -> Result<Walk, std::io::Error> {
+    let mut walk = Walk::default();
+    let mut queue = vec![(root_dir.to_path_buf(), 0)];
+    while let Some((dir, depth)) = queue.pop() {
+        let mut files = 0;
+        let mut dirs = 0;
+        for entry in fs::read_dir(&dir)
+            .attach_printable_lazy(|| format!("Failed to read directory {dir:?}"))?
+        {
+            let entry =
+                entry.attach_printable_lazy(|| format!("Failed to read directory {dir:?}"))?;
+            let path = entry.path();
+            let file_type = entry
+                .file_type()
+                .attach_printable_lazy(|| format!("Failed to stat {path:?}"))?;
+            if file_type.is_dir() {
+                dirs += 1;
+                queue.push((path, depth + 1));
+            } else if file_type.is_symlink() {
+                walk.symlinks += 1;
+            } else if file_type.is_file() {
+                files += 1;
+                let size = entry
+                    .metadata()
+                    .attach_printable_lazy(|| format!("Failed to stat {path:?}"))?
+                    .len();
+                let extension = path
+                    .extension()
+                    .map(|extension| extension.to_string_lossy().into_owned())
+                    .unwrap_or_default();
+                *walk.extensions.entry(extension).or_default() += 1;
+                walk.files_by_size.entry(size).or_default().push(path);
+            }
+        }
+
+        if walk.dirs_per_depth.len() <= depth {
+            walk.dirs_per_depth.resize(depth + 1, 0);
+            walk.files_per_depth.resize(depth + 1, 0);
+        }
+        walk.dirs_per_depth[depth] += dirs;
+        walk.files_per_depth[depth] += files;
+        walk.files_per_dir.push(files);
+        walk.dirs_per_dir.push(dirs);
+    }
+    Ok(walk)
+}
+
+/// Hashes the files sharing their size with another file to find those with
+/// identical contents.
+fn duplicates(files_by_size: &HashMap<u64, Vec<PathBuf>>) -> Result<Duplicates, std::io::Error> {
+    let mut duplicates = Duplicates::default();
+    for (&size, files) in files_by_size {
+        if size == 0 || files.len() < 2 {
+            continue;
+        }
+
+        let mut copies = HashMap::<_, u64>::new();
+        for file in files {
+            let (_, hash) =
+                rehash(file).attach_printable_lazy(|| format!("Failed to read {file:?}"))?;
+            *copies.entry(hash).or_default() += 1;
+        }
+        for copies in copies.into_values().filter(|&copies| copies > 1) {
+            duplicates.files += copies - 1;
+            duplicates.bytes += (copies - 1) * size;
+            duplicates.groups += 1;
+        }
+    }
+    Ok(duplicates)
+}
+
+fn summarize(
+    Walk {
+        symlinks,
+        files_per_depth,
+        dirs_per_depth,
+        files_per_dir,
+        dirs_per_dir,
+        extensions,
+        files_by_size,
+    }: Walk,
+    duplicates: Duplicates,
+) -> Profile {
+    let mut size_histogram = Vec::new();
+    let mut sizes = Vec::new();
+    for (&size, files) in &files_by_size {
+        let class = (u64::BITS - size.leading_zeros()) as usize;
+        if size_histogram.len() <= class {
+            size_histogram.resize(class + 1, 0);
+        }
+        size_histogram[class] += files.len() as u64;
+        sizes.extend(std::iter::repeat_n(size, files.len()));
+    }
+
+    Profile {
+        files: sizes.len() as u64,
+        dirs: dirs_per_depth.iter().sum(),
+        symlinks,
+        bytes: sizes.iter().sum(),
+        max_depth: files_per_depth.len() - 1,
+        files_per_depth,
+        dirs_per_depth,
+        files_per_dir: Summary::new(files_per_dir),
+        dirs_per_dir: Summary::new(dirs_per_dir),
+        file_size: Summary::new(sizes),
+        size_histogram,
+        extensions,
+        duplicates,
+    }
+}
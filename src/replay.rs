@@ -0,0 +1,442 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    fs::{self, File},
+    io::{self, Write as IoWrite},
+    num::NonZeroUsize,
+    path::{Component, Path, PathBuf},
+    process::ExitCode,
+    thread,
+};
+
+use bon::Builder;
+use error_stack::{Report, Result, ResultExt};
+use rand::{RngCore, SeedableRng};
+use rand_xoshiro::Xoshiro256PlusPlus;
+use thousands::Separable;
+
+use crate::{
+    core::{audit::EntryType, create_special},
+    generator::{AuditFormat, Error},
+};
+
+/// Recreates the tree described by an audit trail (CSV, SQLite, or JSON)
+/// beneath a new root directory.
+///
+/// Paths, sizes, and permissions are reproduced exactly. Audits only record
+/// a hash of each file's contents, so contents are regenerated as random data
+/// seeded by that hash (or filled with a fixed byte) rather than restored.
+#[derive(Debug, Builder)]
+pub struct AuditReplay {
+    audit: PathBuf,
+    root_dir: PathBuf,
+    /// Fill files with this byte instead of random data.
+    fill_byte: Option<u8>,
+}
+
+/// An entry of a tree to recreate.
+pub(crate) struct ReplayEntry {
+    /// The entry's path, relative to the root directory once it has gone
+    /// through [`relative_path`].
+    pub(crate) path: PathBuf,
+    pub(crate) is_dir: bool,
+    pub(crate) size: u64,
+    /// Seeds the file's random contents.
+    pub(crate) seed: u64,
+    /// The hash of the file's contents recorded by the audit trail, if any.
+    pub(crate) hash: Option<u64>,
+    pub(crate) permissions: Option<u32>,
+    /// The kind of special file the entry is, if it is one.
+    pub(crate) special: Option<EntryType>,
+}
+
+impl ReplayEntry {
+    /// Whether the entry is a link, whose target audit trails don't record.
+    pub(crate) const fn is_link(&self) -> bool {
+        matches!(self.special, Some(EntryType::Symlink | EntryType::Junction))
+    }
+}
+
+impl AuditReplay {
+    pub fn run(self, output: &mut impl Write) -> Result<(), Error> {
+        let Self {
+            audit,
+            root_dir,
+            fill_byte,
+        } = self;
+        let (original_root, mut entries) = read_audit(&audit)
+            .attach_printable_lazy(|| format!("Failed to read audit trail {audit:?}"))?;
+
+        for entry in &mut entries {
+            entry.path = entry
+                .path
+                .strip_prefix(&original_root)
+                .ok()
+                .and_then(relative_path)
+                .ok_or_else(|| Report::new(Error::InvalidAudit))
+                .attach_printable_lazy(|| {
+                    format!("{:?} is outside of the root {original_root:?}", entry.path)
+                })?;
+        }
+        recreate(&root_dir, entries, fill_byte, output)
+    }
+}
+
+/// Normalizes `path` to a path that stays beneath whatever it is joined onto,
+/// or `None` if it would escape it.
+pub(crate) fn relative_path(path: &Path) -> Option<PathBuf> {
+    path.components()
+        .filter(|component| *component != Component::CurDir)
+        .map(|component| match component {
+            Component::Normal(name) => Some(name),
+            _ => None,
+        })
+        .collect()
+}
+
+/// Creates `entries` beneath `root_dir`, along with any parent directories
+/// they imply. Links are skipped since their targets aren't known.
+pub(crate) fn recreate(
+    root_dir: &Path,
+    entries: Vec<ReplayEntry>,
+    fill_byte: Option<u8>,
+    output: &mut impl Write,
+) -> Result<(), Error> {
+    let mut dirs = BTreeMap::new();
+    let mut files = Vec::new();
+    for entry in entries.into_iter().filter(|entry| !entry.is_link()) {
+        for ancestor in entry.path.ancestors().skip(1) {
+            if ancestor.as_os_str().is_empty() || dirs.contains_key(ancestor) {
+                break;
+            }
+            dirs.insert(ancestor.to_path_buf(), None);
+        }
+        if entry.is_dir {
+            if !entry.path.as_os_str().is_empty() {
+                dirs.insert(entry.path, entry.permissions);
+            }
+        } else {
+            files.push(ReplayEntry {
+                path: root_dir.join(&entry.path),
+                ..entry
+            });
+        }
+    }
+
+    // Parents sort before their children
+    create_dir(root_dir)?;
+    for dir in dirs.keys() {
+        create_dir(&root_dir.join(dir))?;
+    }
+
+    let parallelism = thread::available_parallelism().unwrap_or(NonZeroUsize::new(1).unwrap());
+    let chunk_size = files.len().div_ceil(parallelism.get()).max(1);
+    thread::scope(|scope| {
+        let workers = files
+            .chunks(chunk_size)
+            .map(|chunk| {
+                scope.spawn(move || {
+                    let mut buf = vec![0; 64 * 1024];
+                    for file in chunk {
+                        create_file(file, fill_byte, &mut buf).attach_printable_lazy(|| {
+                            format!("Failed to create file {:?}", file.path)
+                        })?;
+                    }
+                    Ok::<_, Report<io::Error>>(())
+                })
+            })
+            .collect::<Vec<_>>();
+
+        for worker in workers {
+            worker
+                .join()
+                .map_err(|_| Report::new(Error::TaskJoin))
+                .attach(ExitCode::from(sysexits::ExitCode::Software))?
+                .change_context(Error::Io)
+                .attach(ExitCode::from(sysexits::ExitCode::IoErr))?;
+        }
+        Ok::<_, Report<Error>>(())
+    })?;
+
+    // Directories are locked down last so read-only ones can still be
+    // populated, children first for the same reason.
+    for (dir, &permissions) in dirs.iter().rev() {
+        let dir = root_dir.join(dir);
+        set_permissions(&dir, permissions)
+            .attach_printable_lazy(|| format!("Failed to set permissions on {dir:?}"))
+            .change_context(Error::Io)
+            .attach(ExitCode::from(sysexits::ExitCode::IoErr))?;
+    }
+
+    writeln!(
+        output,
+        "Created {} {files_noun} and {} {dirs_noun} totaling {}.",
+        files.len().separate_with_commas(),
+        dirs.len().separate_with_commas(),
+        bytesize::ByteSize(files.iter().map(|file| file.size).sum()),
+        files_noun = if files.len() == 1 { "file" } else { "files" },
+        dirs_noun = if dirs.len() == 1 {
+            "directory"
+        } else {
+            "directories"
+        },
+    )
+    .attach_printable("Failed to write to output stream")
+    .change_context(Error::Io)
+    .attach(ExitCode::from(sysexits::ExitCode::IoErr))
+}
+
+fn create_dir(path: &Path) -> Result<(), Error> {
+    fs::create_dir_all(path)
+        .attach_printable_lazy(|| format!("Failed to create directory {path:?}"))
+        .change_context(Error::Io)
+        .attach(ExitCode::from(sysexits::ExitCode::IoErr))
+}
+
+fn create_file(
+    &ReplayEntry {
+        ref path,
+        is_dir: _,
+        size,
+        seed,
+        hash: _,
+        permissions,
+        special,
+    }: &ReplayEntry,
+    fill_byte: Option<u8>,
+    buf: &mut [u8],
+) -> io::Result<()> {
+    if let Some(entry_type) = special {
+        return create_special(path, entry_type, permissions);
+    }
+
+    let mut file = File::create(path)?;
+    let mut random = Xoshiro256PlusPlus::seed_from_u64(seed);
+    if let Some(byte) = fill_byte {
+        buf.fill(byte);
+    }
+
+    let mut remaining = size;
+    while remaining > 0 {
+        let len = usize::try_from(remaining).map_or(buf.len(), |r| r.min(buf.len()));
+        let chunk = &mut buf[..len];
+        if fill_byte.is_none() {
+            random.fill_bytes(chunk);
+        }
+        file.write_all(chunk)?;
+        remaining -= len as u64;
+    }
+    drop(file);
+
+    set_permissions(path, permissions)
+}
+
+fn set_permissions(path: &Path, permissions: Option<u32>) -> io::Result<()> {
+    #[cfg(unix)]
+    if let Some(mode) = permissions {
+        use std::os::unix::fs::PermissionsExt;
+
+        return fs::set_permissions(path, fs::Permissions::from_mode(mode));
+    }
+    let _ = (path, permissions);
+    Ok(())
+}
+
+pub(crate) fn read_audit(audit: &Path) -> Result<(PathBuf, Vec<ReplayEntry>), Error> {
+    let (metadata, entries) = match AuditFormat::from_path(audit) {
+        AuditFormat::Csv => read_csv(audit),
+        AuditFormat::Sqlite => read_sqlite(audit).change_context(Error::InvalidAudit),
+        AuditFormat::Json | AuditFormat::Jsonl => read_json(audit),
+    }?;
+    let root = metadata
+        .into_iter()
+        .find_map(|(key, value)| (key == "root").then(|| PathBuf::from(value)))
+        .ok_or_else(|| Report::new(Error::InvalidAudit))
+        .attach_printable("The audit trail does not record its root directory")?;
+    Ok((root, entries))
+}
+
+type Metadata = Vec<(String, String)>;
+
+fn parse_entry(
+    path: &str,
+    kind: &str,
+    size: u64,
+    hash: Option<&[u8]>,
+    permissions: &str,
+) -> std::result::Result<ReplayEntry, String> {
+    let (is_dir, special) = match kind {
+        "file" => (false, None),
+        "directory" => (true, None),
+        "fifo" => (false, Some(EntryType::Fifo)),
+        "socket" => (false, Some(EntryType::Socket)),
+        "char_device" => (false, Some(EntryType::CharDevice)),
+        "block_device" => (false, Some(EntryType::BlockDevice)),
+        "symlink" => (false, Some(EntryType::Symlink)),
+        "junction" => (false, Some(EntryType::Junction)),
+        _ => return Err(format!("Unknown entry type {kind:?}")),
+    };
+    let hash = match hash {
+        None | Some([]) => None,
+        Some(bytes) => Some(
+            <[u8; 8]>::try_from(bytes)
+                .map(u64::from_be_bytes)
+                .map_err(|_| format!("Invalid hash for {path:?}"))?,
+        ),
+    };
+    let permissions = match permissions {
+        "" | "n/a" => None,
+        mode => Some(
+            u32::from_str_radix(mode, 8)
+                .map_err(|_| format!("Invalid permissions {mode:?} for {path:?}"))?,
+        ),
+    };
+    Ok(ReplayEntry {
+        path: PathBuf::from(path),
+        is_dir,
+        size,
+        seed: hash.unwrap_or(0),
+        hash,
+        permissions,
+        special,
+    })
+}
+
+fn read_csv(audit: &Path) -> Result<(Metadata, Vec<ReplayEntry>), Error> {
+    let contents = fs::read_to_string(audit)
+        .change_context(Error::Io)
+        .attach(ExitCode::from(sysexits::ExitCode::NoInput))?;
+    // Streamed trails add the metadata known only once the run is done to
+    // the end
+    let metadata = contents
+        .lines()
+        .filter_map(|line| line.strip_prefix("# "))
+        .filter_map(|line| line.split_once(": "))
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .collect();
+
+    let mut reader = csv::ReaderBuilder::new()
+        .comment(Some(b'#'))
+        .from_reader(contents.as_bytes());
+    let mut entries = Vec::new();
+    for record in reader.records() {
+        let record = record.change_context(Error::InvalidAudit)?;
+        let field = |i| record.get(i).unwrap_or_default();
+        let size = field(2)
+            .parse()
+            .map_err(|_| Report::new(Error::InvalidAudit))
+            .attach_printable_lazy(|| format!("Invalid size for {:?}", field(0)))?;
+        let hash = match field(3) {
+            "" => None,
+            hex => Some(
+                u64::from_str_radix(hex, 16)
+                    .map_err(|_| Report::new(Error::InvalidAudit))
+                    .attach_printable_lazy(|| format!("Invalid hash for {:?}", field(0)))?
+                    .to_be_bytes(),
+            ),
+        };
+        entries.push(
+            parse_entry(
+                field(0),
+                field(1),
+                size,
+                hash.as_ref().map(|h| &h[..]),
+                field(4),
+            )
+            .map_err(|e| Report::new(Error::InvalidAudit).attach_printable(e))?,
+        );
+    }
+    Ok((metadata, entries))
+}
+
+fn read_json(audit: &Path) -> Result<(Metadata, Vec<ReplayEntry>), Error> {
+    let contents = fs::read_to_string(audit)
+        .change_context(Error::Io)
+        .attach(ExitCode::from(sysexits::ExitCode::NoInput))?;
+    // A JSON array is a single value while JSONL is a stream of them
+    let mut records = Vec::new();
+    for value in serde_json::Deserializer::from_str(&contents).into_iter() {
+        match value.change_context(Error::InvalidAudit)? {
+            serde_json::Value::Array(values) => records.extend(values),
+            value => records.push(value),
+        }
+    }
+
+    let mut metadata = Vec::new();
+    let mut entries = Vec::new();
+    for record in records {
+        let field = |key| record.get(key).and_then(|v| v.as_str()).unwrap_or_default();
+        if field("type") == "metadata" {
+            metadata.extend(
+                record
+                    .as_object()
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|(key, value)| {
+                        value
+                            .as_str()
+                            .filter(|_| key != "type")
+                            .map(|value| (key.clone(), value.to_string()))
+                    }),
+            );
+            continue;
+        }
+
+        let size = record
+            .get("size")
+            .and_then(serde_json::Value::as_u64)
+            .ok_or_else(|| Report::new(Error::InvalidAudit))
+            .attach_printable_lazy(|| format!("Invalid size for {:?}", field("path")))?;
+        let hash = match field("hash") {
+            "" => None,
+            hex => Some(
+                u64::from_str_radix(hex, 16)
+                    .map_err(|_| Report::new(Error::InvalidAudit))
+                    .attach_printable_lazy(|| format!("Invalid hash for {:?}", field("path")))?
+                    .to_be_bytes(),
+            ),
+        };
+        entries.push(
+            parse_entry(
+                field("path"),
+                field("type"),
+                size,
+                hash.as_ref().map(|h| &h[..]),
+                field("permissions"),
+            )
+            .map_err(|e| Report::new(Error::InvalidAudit).attach_printable(e))?,
+        );
+    }
+    Ok((metadata, entries))
+}
+
+fn read_sqlite(audit: &Path) -> rusqlite::Result<(Metadata, Vec<ReplayEntry>)> {
+    let conn =
+        rusqlite::Connection::open_with_flags(audit, rusqlite::OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    let metadata = conn
+        .prepare("SELECT key, value FROM metadata")?
+        .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+        .collect::<rusqlite::Result<_>>()?;
+
+    let mut stmt = conn.prepare("SELECT path, type, size, hash, permissions FROM audit_entries")?;
+    let entries = stmt
+        .query_map([], |row| {
+            let path: String = row.get(0)?;
+            let kind: String = row.get(1)?;
+            let size: i64 = row.get(2)?;
+            let hash: Option<Vec<u8>> = row.get(3)?;
+            let permissions: Option<String> = row.get(4)?;
+            parse_entry(
+                &path,
+                &kind,
+                u64::try_from(size).unwrap_or_default(),
+                hash.as_deref(),
+                permissions.as_deref().unwrap_or_default(),
+            )
+            .map_err(|e| {
+                rusqlite::Error::FromSqlConversionFailure(1, rusqlite::types::Type::Text, e.into())
+            })
+        })?
+        .collect::<rusqlite::Result<_>>()?;
+    Ok((metadata, entries))
+}
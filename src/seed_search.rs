@@ -0,0 +1,153 @@
+use std::{
+    fmt::Write,
+    fs, io,
+    num::NonZeroUsize,
+    path::Path,
+    process::ExitCode,
+    sync::atomic::{AtomicU64, Ordering},
+    thread,
+};
+
+use bon::Builder;
+use error_stack::{Report, Result, ResultExt};
+
+use crate::generator::{Error, Generator};
+
+/// Searches for a seed whose generated tree satisfies a set of constraints,
+/// such as reaching a certain depth or containing a directory with many
+/// files.
+///
+/// Candidate trees are generated beneath the generator's root directory and
+/// removed again. Seeds are tried in parallel starting from the generator's
+/// seed, and the smallest satisfying seed is reported such that the outcome
+/// doesn't depend on the parallelism.
+#[derive(Debug, Builder)]
+pub struct SeedSearch {
+    generator: Generator,
+    /// The number of seeds to try before giving up.
+    #[builder(default = 1000)]
+    attempts: u64,
+    /// The number of candidate trees generated at once.
+    jobs: Option<NonZeroUsize>,
+    /// Require a directory nested at least this deep below the root.
+    min_depth: Option<usize>,
+    /// Require a directory containing at least this many files.
+    min_files_in_dir: Option<u64>,
+    /// Require a directory containing at least this many subdirectories.
+    min_dirs_in_dir: Option<u64>,
+}
+
+#[derive(Debug, Default)]
+struct TreeShape {
+    depth: usize,
+    max_files_in_dir: u64,
+    max_dirs_in_dir: u64,
+}
+
+impl SeedSearch {
+    pub fn run(self, output: &mut impl Write) -> Result<(), Error> {
+        let Self {
+            mut generator,
+            attempts,
+            jobs,
+            min_depth,
+            min_files_in_dir,
+            min_dirs_in_dir,
+        } = self;
+        // Candidates only need their trees
+        generator.audit_output = None;
+        generator.fio_output = None;
+        generator.manifest = None;
+        generator.compare_with.clear();
+        generator.replicas.clear();
+        generator.shards.clear();
+        generator.validate = false;
+        generator.check_distributions = false;
+
+        let scratch = generator.root_dir.clone();
+        let first = generator.seed;
+        let end = first.saturating_add(attempts);
+        let next = AtomicU64::new(first);
+        let found = AtomicU64::new(u64::MAX);
+        let satisfies = |shape: &TreeShape| {
+            min_depth.is_none_or(|min| shape.depth >= min)
+                && min_files_in_dir.is_none_or(|min| shape.max_files_in_dir >= min)
+                && min_dirs_in_dir.is_none_or(|min| shape.max_dirs_in_dir >= min)
+        };
+
+        let jobs = jobs
+            .or_else(|| thread::available_parallelism().ok())
+            .unwrap_or(NonZeroUsize::new(1).unwrap());
+        thread::scope(|scope| {
+            let workers = (0..jobs.get())
+                .map(|_| {
+                    scope.spawn(|| {
+                        loop {
+                            let seed = next.fetch_add(1, Ordering::Relaxed);
+                            if seed >= end || seed > found.load(Ordering::Relaxed) {
+                                return Ok::<_, Report<Error>>(());
+                            }
+
+                            let mut candidate = generator.clone();
+                            candidate.root_dir = scratch.join(format!("seed-{seed}"));
+                            candidate.seed = seed;
+                            let root_dir = candidate.root_dir.clone();
+                            let shape = candidate.generate(&mut String::new()).and_then(|()| {
+                                measure(&root_dir)
+                                    .attach_printable_lazy(|| {
+                                        format!("Failed to measure tree {root_dir:?}")
+                                    })
+                                    .change_context(Error::Io)
+                                    .attach(ExitCode::from(sysexits::ExitCode::IoErr))
+                            });
+                            drop(fs::remove_dir_all(&root_dir));
+
+                            if satisfies(&shape?) {
+                                found.fetch_min(seed, Ordering::Relaxed);
+                            }
+                        }
+                    })
+                })
+                .collect::<Vec<_>>();
+
+            for worker in workers {
+                worker
+                    .join()
+                    .map_err(|_| Report::new(Error::TaskJoin))
+                    .attach(ExitCode::from(sysexits::ExitCode::Software))??;
+            }
+            Ok::<_, Report<Error>>(())
+        })?;
+
+        match found.into_inner() {
+            u64::MAX => Err(Report::new(Error::SeedNotFound))
+                .attach_printable(format!("Tried seeds {first}..{end}"))
+                .attach(ExitCode::from(sysexits::ExitCode::Unavailable)),
+            seed => writeln!(output, "{seed}")
+                .attach_printable("Failed to write to output stream")
+                .change_context(Error::Io)
+                .attach(ExitCode::from(sysexits::ExitCode::IoErr)),
+        }
+    }
+}
+
+fn measure(root_dir: &Path) -> io::Result<TreeShape> {
+    let mut shape = TreeShape::default();
+    let mut pending = vec![(root_dir.to_path_buf(), 0)];
+    while let Some((dir, depth)) = pending.pop() {
+        shape.depth = shape.depth.max(depth);
+        let (mut files, mut dirs) = (0, 0);
+        for entry in fs::read_dir(&dir)? {
+            let entry = entry?;
+            if entry.file_type()?.is_dir() {
+                dirs += 1;
+                pending.push((entry.path(), depth + 1));
+            } else {
+                files += 1;
+            }
+        }
+        shape.max_files_in_dir = shape.max_files_in_dir.max(files);
+        shape.max_dirs_in_dir = shape.max_dirs_in_dir.max(dirs);
+    }
+    Ok(shape)
+}
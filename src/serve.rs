@@ -0,0 +1,334 @@
+use std::{
+    fmt::Write,
+    fs,
+    io::{self, BufRead, BufReader, Read, Write as IoWrite},
+    net::{TcpListener, TcpStream},
+    path::{Path, PathBuf},
+    process::ExitCode,
+    sync::{Arc, Mutex, mpsc},
+    thread,
+    time::{Duration, Instant},
+};
+
+use bon::Builder;
+use error_stack::{Report, Result, ResultExt};
+
+use crate::generator::{Error, Generator};
+
+/// Serves a small HTTP API through which generation jobs are submitted and
+/// monitored, so fleets of test machines can be driven centrally.
+///
+/// - `POST /jobs` queues a job whose parameters are the request body, parsed
+///   like command line arguments. Responds with the job's id.
+/// - `GET /jobs` lists every job and its state.
+/// - `GET /jobs/<id>` reports a job's state and, once it has finished, the
+///   generator's output (including stats) or error.
+/// - `GET /jobs/<id>/audit` downloads the job's audit trail, if it has one.
+/// - `POST /shutdown` stops accepting jobs and exits once queued ones are
+///   done.
+///
+/// Job `<id>` generates its tree in `<root_dir>/job-<id>`. Jobs can't name
+/// directories of their own or read input files such as content templates,
+/// and output files they ask for (such as
+/// `--audit-output audit.csv`) are written next to their tree, as
+/// `<root_dir>/job-<id>.audit.csv`. Jobs run one at a time so they don't
+/// compete for the machine.
+///
+/// Requests are served one at a time too, so clients that take more than ten
+/// seconds to send their request are dropped and bodies larger than 64 KiB
+/// are refused.
+#[derive(Debug, Builder)]
+pub struct Server {
+    /// The address to listen on. Port 0 picks any free port.
+    #[builder(into)]
+    listen: String,
+    root_dir: PathBuf,
+}
+
+#[derive(Debug)]
+enum JobState {
+    Queued,
+    Running,
+    Done(String),
+    Failed(String),
+}
+
+impl JobState {
+    const fn name(&self) -> &'static str {
+        match self {
+            Self::Queued => "queued",
+            Self::Running => "running",
+            Self::Done(_) => "done",
+            Self::Failed(_) => "failed",
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Job {
+    audit_output: Option<PathBuf>,
+    state: JobState,
+}
+
+type Jobs = Arc<Mutex<Vec<Job>>>;
+
+/// The longest a client may take to send its whole request.
+const READ_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The largest request body accepted, in bytes. Job arguments are far
+/// shorter.
+const MAX_BODY_LEN: usize = 64 * 1024;
+
+/// The largest request line and headers accepted, in bytes.
+const MAX_HEAD_LEN: u64 = 16 * 1024;
+
+struct Response {
+    status: &'static str,
+    body: Vec<u8>,
+}
+
+impl Response {
+    fn new(status: &'static str, body: impl Into<Vec<u8>>) -> Self {
+        Self {
+            status,
+            body: body.into(),
+        }
+    }
+}
+
+impl Server {
+    /// Serves until shut down, using `parse` to turn a job's directory and
+    /// arguments into its generator.
+    pub fn run(
+        self,
+        output: &mut impl Write,
+        parse: impl Fn(&Path, &str) -> std::result::Result<Generator, String>,
+    ) -> Result<(), Error> {
+        let Self { listen, root_dir } = self;
+        let listener = TcpListener::bind(&listen)
+            .attach_printable_lazy(|| format!("Failed to listen on {listen:?}"))
+            .change_context(Error::Io)
+            .attach(ExitCode::from(sysexits::ExitCode::Unavailable))?;
+        let addr = listener
+            .local_addr()
+            .change_context(Error::Io)
+            .attach(ExitCode::from(sysexits::ExitCode::OsErr))?;
+        writeln!(output, "Listening on {addr}")
+            .attach_printable("Failed to write to output stream")
+            .change_context(Error::Io)
+            .attach(ExitCode::from(sysexits::ExitCode::IoErr))?;
+
+        let jobs = Jobs::default();
+        let (queue, pending) = mpsc::channel::<(usize, Generator)>();
+        let worker = {
+            let jobs = jobs.clone();
+            thread::spawn(move || {
+                for (id, generator) in pending {
+                    jobs.lock().unwrap()[id].state = JobState::Running;
+                    let mut output = String::new();
+                    let state = match generator.generate(&mut output) {
+                        Ok(()) => JobState::Done(output),
+                        Err(e) => JobState::Failed(format!("{e:?}")),
+                    };
+                    jobs.lock().unwrap()[id].state = state;
+                }
+            })
+        };
+
+        for stream in listener.incoming() {
+            let Ok(mut stream) = stream else {
+                continue;
+            };
+            let (response, shutdown) = match read_request(&mut stream) {
+                Ok((method, path, body)) => {
+                    handle(&method, &path, &body, &root_dir, &jobs, &queue, &parse)
+                }
+                Err(response) => (response, false),
+            };
+            // A client hanging up early shouldn't take the server down with it
+            let _ = write_response(&mut stream, &response);
+            if shutdown {
+                break;
+            }
+        }
+
+        drop(queue);
+        worker
+            .join()
+            .map_err(|_| Report::new(Error::TaskJoin))
+            .attach(ExitCode::from(sysexits::ExitCode::Software))
+    }
+}
+
+fn handle(
+    method: &str,
+    path: &str,
+    body: &str,
+    root_dir: &Path,
+    jobs: &Jobs,
+    queue: &mpsc::Sender<(usize, Generator)>,
+    parse: impl Fn(&Path, &str) -> std::result::Result<Generator, String>,
+) -> (Response, bool) {
+    let segments = path
+        .trim_matches('/')
+        .split('/')
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<_>>();
+    let job = |id: &str| {
+        id.parse::<usize>()
+            .ok()
+            .filter(|&id| id < jobs.lock().unwrap().len())
+    };
+
+    let response = match (method, &segments[..]) {
+        ("POST", ["jobs"]) => {
+            let mut jobs = jobs.lock().unwrap();
+            let id = jobs.len();
+            match parse(&root_dir.join(format!("job-{id}")), body) {
+                Ok(generator) => {
+                    jobs.push(Job {
+                        audit_output: generator.audit_output.clone(),
+                        state: JobState::Queued,
+                    });
+                    drop(jobs);
+                    // The worker only exits once the queue is dropped
+                    queue.send((id, generator)).unwrap();
+                    Response::new("202 Accepted", format!("{id}\n"))
+                }
+                Err(e) => Response::new("400 Bad Request", e),
+            }
+        }
+        ("GET", ["jobs"]) => Response::new(
+            "200 OK",
+            jobs.lock()
+                .unwrap()
+                .iter()
+                .enumerate()
+                .fold(String::new(), |mut list, (id, job)| {
+                    let _ = writeln!(list, "{id}: {}", job.state.name());
+                    list
+                }),
+        ),
+        ("GET", ["jobs", id]) => match job(id) {
+            Some(id) => {
+                let jobs = jobs.lock().unwrap();
+                let state = &jobs[id].state;
+                let mut body = format!("state: {}\n", state.name());
+                if let JobState::Done(output) | JobState::Failed(output) = state {
+                    body.push('\n');
+                    body.push_str(output);
+                }
+                Response::new("200 OK", body)
+            }
+            None => Response::new("404 Not Found", "No such job\n"),
+        },
+        ("GET", ["jobs", id, "audit"]) => {
+            let audit = job(id).and_then(|id| {
+                let jobs = jobs.lock().unwrap();
+                let job = &jobs[id];
+                matches!(job.state, JobState::Done(_))
+                    .then(|| job.audit_output.clone())
+                    .flatten()
+            });
+            match audit.map(fs::read) {
+                Some(Ok(contents)) => Response::new("200 OK", contents),
+                Some(Err(e)) => Response::new("500 Internal Server Error", e.to_string()),
+                None => Response::new("404 Not Found", "No audit trail for this job\n"),
+            }
+        }
+        ("POST", ["shutdown"]) => return (Response::new("200 OK", "Shutting down\n"), true),
+        _ => Response::new("404 Not Found", "Unknown endpoint\n"),
+    };
+    (response, false)
+}
+
+/// Reads from a client until the deadline for its request passes, however
+/// slowly it trickles in.
+struct DeadlineReader<'a> {
+    stream: &'a TcpStream,
+    deadline: Instant,
+}
+
+impl Read for DeadlineReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let remaining = self.deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            return Err(io::ErrorKind::TimedOut.into());
+        }
+        self.stream.set_read_timeout(Some(remaining))?;
+        self.stream.read(buf)
+    }
+}
+
+fn read_request(stream: &mut TcpStream) -> std::result::Result<(String, String, String), Response> {
+    let bad_request = |e: io::Error| match e.kind() {
+        // Timeouts surface as either depending on the platform
+        io::ErrorKind::TimedOut | io::ErrorKind::WouldBlock => Response::new(
+            "408 Request Timeout",
+            format!(
+                "Requests must be sent within {} seconds\n",
+                READ_TIMEOUT.as_secs()
+            ),
+        ),
+        _ => Response::new("400 Bad Request", format!("{e}\n")),
+    };
+
+    let reader = DeadlineReader {
+        stream,
+        deadline: Instant::now() + READ_TIMEOUT,
+    };
+    let mut reader = BufReader::new(reader.take(MAX_HEAD_LEN));
+    let mut line = String::new();
+    reader.read_line(&mut line).map_err(bad_request)?;
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(path)) = (parts.next(), parts.next()) else {
+        return Err(Response::new("400 Bad Request", "Malformed request line\n"));
+    };
+    let (method, path) = (method.to_string(), path.to_string());
+
+    let mut content_length = 0;
+    loop {
+        line.clear();
+        if reader.read_line(&mut line).map_err(bad_request)? == 0 {
+            return Err(Response::new("400 Bad Request", "Truncated headers\n"));
+        }
+        if line.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':')
+            && name.eq_ignore_ascii_case("content-length")
+        {
+            content_length = value
+                .trim()
+                .parse()
+                .map_err(|_| Response::new("400 Bad Request", "Invalid Content-Length\n"))?;
+        }
+    }
+    if content_length > MAX_BODY_LEN {
+        return Err(Response::new(
+            "413 Content Too Large",
+            format!("Bodies are limited to {MAX_BODY_LEN} bytes\n"),
+        ));
+    }
+
+    // The headers may have been read along with part of the body
+    let buffered = reader.buffer().len();
+    reader
+        .get_mut()
+        .set_limit(content_length.saturating_sub(buffered) as u64);
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body).map_err(bad_request)?;
+    let body = String::from_utf8(body)
+        .map_err(|_| Response::new("400 Bad Request", "Body is not valid UTF-8\n"))?;
+    Ok((method, path, body))
+}
+
+fn write_response(stream: &mut TcpStream, Response { status, body }: &Response) -> io::Result<()> {
+    write!(
+        stream,
+        "HTTP/1.1 {status}\r\nContent-Type: text/plain\r\nContent-Length: {}\r\nConnection: \
+         close\r\n\r\n",
+        body.len()
+    )?;
+    stream.write_all(body)
+}
@@ -1,6 +1,7 @@
-use std::{mem::MaybeUninit, ptr, slice};
+use std::{fmt, fmt::Write, mem, mem::MaybeUninit, ptr, slice, str::FromStr};
 
 use itoa::Integer;
+use thiserror::Error;
 
 struct FileNameCache;
 
@@ -70,6 +71,299 @@ pub fn with_dir_name<T>(i: usize, f: impl FnOnce(&str) -> T) -> T {
     })
 }
 
+const ADJECTIVES: [&str; 16] = [
+    "final",
+    "draft",
+    "old",
+    "new",
+    "shared",
+    "personal",
+    "quarterly",
+    "annual",
+    "scanned",
+    "signed",
+    "backup",
+    "edited",
+    "original",
+    "summer",
+    "team",
+    "project",
+];
+const NOUNS: [&str; 16] = [
+    "report", "notes", "invoice", "budget", "photo", "resume", "letter", "slides", "recipe",
+    "contract", "receipt", "agenda", "summary", "song", "map", "schedule",
+];
+const EXTENSIONS: [&str; 16] = [
+    "txt", "jpg", "pdf", "docx", "png", "mp3", "csv", "xlsx", "md", "zip", "json", "log", "pptx",
+    "html", "mp4", "gif",
+];
+
+/// Names file `i` the way a person might, with words and an extension picked
+/// from `seed`. Every name embeds `i` as a run of digits ended by a
+/// non-digit, so names stay unique within a directory even on
+/// case-insensitive file systems.
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "trace", skip(f)))]
+pub fn with_realistic_file_name<T>(i: u64, seed: u64, f: impl FnOnce(&str) -> T) -> T {
+    let pick = |words: &[&'static str; 16], shift: u32| words[(seed >> shift) as usize & 0xF];
+    let capitalize = |word: &str| {
+        let mut chars = word.chars();
+        chars
+            .next()
+            .map(|first| first.to_ascii_uppercase().to_string() + chars.as_str())
+            .unwrap_or_default()
+    };
+    let (adjective, noun, extension) =
+        (pick(&ADJECTIVES, 0), pick(&NOUNS, 4), pick(&EXTENSIONS, 8));
+    f(&match (seed >> 12) % 5 {
+        0 => format!("{} {noun} {i}.{extension}", capitalize(adjective)),
+        1 => format!("{adjective}_{noun}_{i}.{extension}"),
+        2 => format!(
+            "{}{}{i}.{extension}",
+            capitalize(adjective),
+            capitalize(noun)
+        ),
+        3 => format!("IMG_{i:04}.jpg"),
+        _ => format!("{noun}-{adjective} ({i}).{extension}"),
+    })
+}
+
+/// Pads `name` to `len` bytes by inserting lowercase letters picked from
+/// `seed` in front of its extension, if it has one. Names that are already as
+/// long are returned as they are. Since the letters follow the name's
+/// number, padded names stay unique.
+#[must_use]
+pub fn pad_name(name: &str, len: usize, seed: u64) -> String {
+    let Some(missing) = len.checked_sub(name.len()).filter(|&missing| missing > 0) else {
+        return name.to_string();
+    };
+    let at = name.rfind('.').filter(|&at| at > 0).unwrap_or(name.len());
+    let mut state = seed;
+    let letters = (0..missing).map(|_| {
+        state = state
+            .wrapping_mul(0x5851_F42D_4C95_7F2D)
+            .wrapping_add(0x1405_7B7E_F767_814F);
+        char::from(b'a' + ((state >> 59) % 26) as u8)
+    });
+
+    let mut padded = String::with_capacity(len);
+    padded.push_str(&name[..at]);
+    padded.extend(letters);
+    padded.push_str(&name[at..]);
+    padded
+}
+
+/// The ranges of codepoints Unicode names mix in: CJK ideographs, Cyrillic
+/// letters, Arabic letters, and emoji.
+const SCRIPTS: [(u32, u32); 4] = [
+    (0x4E00, 0x9FFF),
+    (0x0410, 0x044F),
+    (0x0627, 0x064A),
+    (0x1F600, 0x1F64F),
+];
+
+/// Appends two to four codepoints picked from [`SCRIPTS`] by `hash` to
+/// `name`.
+fn push_codepoints(name: &mut String, hash: u64) {
+    let mut hash = hash;
+    for _ in 0..2 + hash % 3 {
+        hash = hash.wrapping_mul(0x9E37_79B9_7F4A_7C15).rotate_left(29);
+        let (start, end) = SCRIPTS[(hash >> 62) as usize];
+        // Every codepoint in the ranges is assigned and none are surrogates
+        name.extend(char::from_u32(start + (hash as u32) % (end - start + 1)));
+    }
+}
+
+/// Names file `i` with a few non-Latin codepoints picked from `seed`
+/// followed by `i`. Since `i` is the only run of ASCII digits, names stay
+/// unique within a directory even on case-insensitive file systems.
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "trace", skip(f)))]
+pub fn with_unicode_file_name<T>(i: u64, seed: u64, f: impl FnOnce(&str) -> T) -> T {
+    let mut name = String::new();
+    push_codepoints(&mut name, seed);
+    name.push_str(itoa::Buffer::new().format(i));
+    f(&name)
+}
+
+/// Names directory `i` like [`with_unicode_file_name`], with codepoints
+/// picked from `i` alone and a `.dir` suffix.
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "trace", skip(f)))]
+pub fn with_unicode_dir_name<T>(i: usize, f: impl FnOnce(&str) -> T) -> T {
+    let i = i as u64;
+    with_unicode_file_name(i, i.wrapping_mul(0xD1B5_4A32_D192_ED03), |s| {
+        f(&format!("{s}.dir"))
+    })
+}
+
+/// A part of a [`NameTemplate`].
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+enum TemplatePart {
+    Literal(String),
+    /// The entry's index, zero-padded to this width.
+    Index(usize),
+    /// The entry's depth, the root directory's children being at depth 1.
+    Depth,
+    /// This many random hex digits.
+    Random(usize),
+    Extension,
+}
+
+/// A name with placeholders, parsed from strings like
+/// `report_{n:06}_{rand8}.{ext}`.
+///
+/// The placeholders are `{n}` for the entry's index in its directory
+/// (`{n:0WIDTH}` to zero-pad it), `{depth}` for its depth, `{randDIGITS}` for
+/// up to 16 random hex digits, and `{ext}` for a file's extension. Literal
+/// braces are written `{{` and `}}`. Every template contains `{n}` so names
+/// stay unique within a directory.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct NameTemplate(Vec<TemplatePart>);
+
+#[derive(Error, Debug)]
+#[error(
+    "Invalid name template {0:?}: expected text with an {{n}} placeholder and optionally \
+     {{n:0WIDTH}}, {{depth}}, {{randDIGITS}}, and {{ext}}, without path separators."
+)]
+pub struct NameTemplateError(String);
+
+impl NameTemplate {
+    /// Whether the template places the extension itself.
+    #[must_use]
+    pub fn has_extension(&self) -> bool {
+        self.0.contains(&TemplatePart::Extension)
+    }
+
+    /// Names entry `i` at `depth` after the template, with random digits
+    /// picked from `seed`.
+    fn render(&self, i: u64, depth: usize, seed: u64, extension: &str) -> String {
+        let mut name = String::new();
+        let mut state = seed;
+        for part in &self.0 {
+            match *part {
+                TemplatePart::Literal(ref literal) => name.push_str(literal),
+                TemplatePart::Index(width) => write!(name, "{i:0width$}").unwrap(),
+                TemplatePart::Depth => name.push_str(itoa::Buffer::new().format(depth)),
+                TemplatePart::Random(digits) => {
+                    state = state
+                        .wrapping_add(0x9E37_79B9_7F4A_7C15)
+                        .wrapping_mul(0xBF58_476D_1CE4_E5B9);
+                    let random = (state ^ (state >> 31)) >> (64 - 4 * digits);
+                    write!(name, "{random:0digits$x}").unwrap();
+                }
+                TemplatePart::Extension => name.push_str(extension),
+            }
+        }
+        name
+    }
+}
+
+impl FromStr for NameTemplate {
+    type Err = NameTemplateError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || NameTemplateError(s.to_string());
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        let mut chars = s.chars();
+        while let Some(c) = chars.next() {
+            match c {
+                '{' | '}' if chars.as_str().starts_with(c) => {
+                    chars.next();
+                    literal.push(c);
+                }
+                '{' => {
+                    let (placeholder, rest) = chars.as_str().split_once('}').ok_or_else(invalid)?;
+                    chars = rest.chars();
+                    if !literal.is_empty() {
+                        parts.push(TemplatePart::Literal(mem::take(&mut literal)));
+                    }
+                    let number = |digits: &str, max| {
+                        digits
+                            .parse()
+                            .ok()
+                            .filter(|n| (1..=max).contains(n))
+                            .ok_or_else(invalid)
+                    };
+                    parts.push(match placeholder {
+                        "n" => TemplatePart::Index(0),
+                        "depth" => TemplatePart::Depth,
+                        "ext" => TemplatePart::Extension,
+                        _ => {
+                            if let Some(width) = placeholder.strip_prefix("n:0") {
+                                TemplatePart::Index(number(width, usize::MAX_STR_LEN)?)
+                            } else if let Some(digits) = placeholder.strip_prefix("rand") {
+                                TemplatePart::Random(number(digits, 16)?)
+                            } else {
+                                return Err(invalid());
+                            }
+                        }
+                    });
+                }
+                '}' | '/' | '\\' | '\0' => return Err(invalid()),
+                c => literal.push(c),
+            }
+        }
+        if !literal.is_empty() {
+            parts.push(TemplatePart::Literal(literal));
+        }
+
+        if !parts
+            .iter()
+            .any(|part| matches!(part, TemplatePart::Index(_)))
+        {
+            return Err(invalid());
+        }
+        Ok(Self(parts))
+    }
+}
+
+impl fmt::Display for NameTemplate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for part in &self.0 {
+            match part {
+                TemplatePart::Literal(literal) => {
+                    f.write_str(&literal.replace('{', "{{").replace('}', "}}"))?;
+                }
+                TemplatePart::Index(0) => f.write_str("{n}")?,
+                TemplatePart::Index(width) => write!(f, "{{n:0{width}}}")?,
+                TemplatePart::Depth => f.write_str("{depth}")?,
+                TemplatePart::Random(digits) => write!(f, "{{rand{digits}}}")?,
+                TemplatePart::Extension => f.write_str("{ext}")?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Names file `i` at `depth` after `template`, with random digits picked
+/// from `seed`. `{ext}` is replaced with `extension`, or a common extension
+/// picked from `seed` if there is none.
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "trace", skip(f)))]
+pub fn with_templated_file_name<T>(
+    template: &NameTemplate,
+    i: u64,
+    depth: usize,
+    seed: u64,
+    extension: Option<&str>,
+    f: impl FnOnce(&str) -> T,
+) -> T {
+    let extension = extension.unwrap_or(EXTENSIONS[(seed >> 8) as usize & 0xF]);
+    f(&template.render(i, depth, seed, extension))
+}
+
+/// Names directory `i` at `depth` after `template`, with random digits
+/// picked from `i` and `depth` alone so the scheduler can find the directory
+/// again.
+#[cfg_attr(feature = "tracing", tracing::instrument(level = "trace", skip(f)))]
+pub fn with_templated_dir_name<T>(
+    template: &NameTemplate,
+    i: usize,
+    depth: usize,
+    f: impl FnOnce(&str) -> T,
+) -> T {
+    let seed = (i as u64).wrapping_mul(0xD1B5_4A32_D192_ED03) ^ (depth as u64).rotate_left(32);
+    f(&template.render(i as u64, depth, seed, ""))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -99,4 +393,131 @@ mod tests {
             });
         }
     }
+
+    #[test]
+    fn realistic_names_are_unique_and_stable() {
+        let names = (0..5000)
+            .map(|i: u64| {
+                with_realistic_file_name(
+                    i,
+                    i.wrapping_mul(0x9E37_79B9_7F4A_7C15),
+                    str::to_lowercase,
+                )
+            })
+            .collect::<Vec<_>>();
+        let mut unique = names.clone();
+        unique.sort_unstable();
+        unique.dedup();
+        assert_eq!(unique.len(), names.len());
+        assert!(
+            names
+                .iter()
+                .all(|name| name.contains('.') && !name.ends_with(".dir"))
+        );
+
+        with_realistic_file_name(7, 0, |s| assert_eq!(s, "Final report 7.txt"));
+        with_realistic_file_name(42, 3 << 12, |s| assert_eq!(s, "IMG_0042.jpg"));
+    }
+
+    #[test]
+    fn unicode_names_are_unique_and_mix_scripts() {
+        let names = (0..5000)
+            .map(|i: u64| {
+                with_unicode_file_name(i, i.wrapping_mul(0x9E37_79B9_7F4A_7C15), str::to_lowercase)
+            })
+            .collect::<Vec<_>>();
+        let mut unique = names.clone();
+        unique.sort_unstable();
+        unique.dedup();
+        assert_eq!(unique.len(), names.len());
+        for (start, end) in SCRIPTS {
+            assert!(
+                names
+                    .iter()
+                    .any(|name| { name.chars().any(|c| (start..=end).contains(&u32::from(c))) })
+            );
+        }
+
+        with_unicode_dir_name(3, |s| {
+            assert!(s.ends_with("3.dir"), "{s}");
+            assert!(!s.is_ascii(), "{s}");
+        });
+    }
+
+    #[test]
+    fn names_are_padded_before_their_extension() {
+        assert_eq!(pad_name("12", 2, 0), "12");
+        assert_eq!(pad_name("12", 1, 0), "12");
+
+        let padded = pad_name("12", 255, 7);
+        assert_eq!(padded.len(), 255);
+        assert!(padded.starts_with("12"));
+        assert!(padded[2..].chars().all(|c| c.is_ascii_lowercase()));
+
+        let padded = pad_name("Final report 12.pdf", 40, 7);
+        assert_eq!(padded.len(), 40);
+        assert!(padded.starts_with("Final report 12"));
+        assert!(padded.ends_with(".pdf"));
+        assert_eq!(padded, pad_name("Final report 12.pdf", 40, 7));
+    }
+
+    #[test]
+    fn name_templates_parse() {
+        let template = "report_{n:06}_{rand8}.{ext}"
+            .parse::<NameTemplate>()
+            .unwrap();
+        assert!(template.has_extension());
+        with_templated_file_name(&template, 42, 3, 7, Some("csv"), |s| {
+            assert!(s.starts_with("report_000042_"), "{s}");
+            assert!(s.ends_with(".csv"), "{s}");
+            assert_eq!(s.len(), "report_000042_12345678.csv".len());
+            assert!(s[14..22].chars().all(|c| c.is_ascii_hexdigit()), "{s}");
+        });
+
+        let template = "{{d{depth}}}-{n}".parse::<NameTemplate>().unwrap();
+        assert!(!template.has_extension());
+        with_templated_dir_name(&template, 5, 2, |s| assert_eq!(s, "{d2}-5"));
+
+        for invalid in [
+            "",
+            "report",
+            "{rand8}",
+            "{n",
+            "{n}}",
+            "{size}{n}",
+            "{n:6}",
+            "{n:00}",
+            "{rand17}{n}",
+            "{rand0}{n}",
+            "a/{n}",
+        ] {
+            invalid.parse::<NameTemplate>().unwrap_err();
+        }
+    }
+
+    #[test]
+    fn templated_names_are_unique_and_stable() {
+        let template = "{rand4}_{n}.{ext}".parse::<NameTemplate>().unwrap();
+        let names = (0..5000)
+            .map(|i: u64| {
+                with_templated_file_name(
+                    &template,
+                    i,
+                    1,
+                    i.wrapping_mul(0x9E37_79B9_7F4A_7C15),
+                    None,
+                    str::to_string,
+                )
+            })
+            .collect::<Vec<_>>();
+        let mut unique = names.clone();
+        unique.sort_unstable();
+        unique.dedup();
+        assert_eq!(unique.len(), names.len());
+
+        with_templated_dir_name(&template, 3, 1, |s| {
+            with_templated_dir_name(&template, 3, 1, |t| assert_eq!(s, t));
+            with_templated_dir_name(&template, 3, 2, |t| assert_ne!(s, t));
+        });
+    }
 }
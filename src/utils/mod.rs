@@ -1,4 +1,4 @@
-pub use fast_path::FastPathBuf;
+pub use fast_path::{FastPathBuf, PopGuard};
 pub use file_names::*;
 
 mod fast_path;
@@ -0,0 +1,287 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    fs,
+    num::NonZeroUsize,
+    path::{Path, PathBuf},
+    process::ExitCode,
+    thread,
+};
+
+use bon::Builder;
+use error_stack::{Report, Result, ResultExt};
+use thousands::Separable;
+use twox_hash::XxHash64;
+
+use crate::{
+    core::rehash,
+    generator::Error,
+    replay::{ReplayEntry, read_audit, relative_path},
+};
+
+/// Checks the tree beneath a root directory against the audit trail (CSV,
+/// SQLite, or JSON) it was generated with.
+///
+/// Every entry is compared against the trail: entries the trail records but
+/// the tree lacks are missing, entries the tree has but the trail doesn't
+/// record are extra, and files whose type, size, or hash changed are
+/// corrupted.
+///
+/// Hashing every file of a huge tree takes as long as reading it back, so only
+/// a sample of the files can be rehashed instead. Every entry is still checked
+/// for its presence, type, and size.
+#[derive(Debug, Builder)]
+pub struct AuditVerification {
+    audit: PathBuf,
+    root_dir: PathBuf,
+    /// Rehash only this percentage of the files. The largest files make up
+    /// half of the sample and the rest is picked by the hash of their path,
+    /// so the same files are sampled every time.
+    sample: Option<f64>,
+}
+
+/// A difference between the tree and its audit trail.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Problem {
+    Missing,
+    Extra,
+    Corrupted(&'static str),
+}
+
+impl AuditVerification {
+    pub fn run(self, output: &mut impl Write) -> Result<(), Error> {
+        let Self {
+            audit,
+            root_dir,
+            sample,
+        } = self;
+        let (original_root, entries) = read_audit(&audit)
+            .attach_printable_lazy(|| format!("Failed to read audit trail {audit:?}"))?;
+
+        let mut expected = BTreeMap::new();
+        for entry in entries {
+            let path = entry
+                .path
+                .strip_prefix(&original_root)
+                .ok()
+                .and_then(relative_path)
+                .ok_or_else(|| Report::new(Error::InvalidAudit))
+                .attach_printable_lazy(|| {
+                    format!("{:?} is outside of the root {original_root:?}", entry.path)
+                })?;
+            // Links aren't followed, so they can't be checked
+            if !path.as_os_str().is_empty() && !entry.is_link() {
+                expected.insert(path, entry);
+            }
+        }
+
+        let actual = walk(&root_dir)
+            .change_context(Error::Io)
+            .attach(ExitCode::from(sysexits::ExitCode::IoErr))?;
+
+        let mut problems = Vec::new();
+        let mut to_hash = Vec::new();
+        for (path, (is_dir, size)) in &actual {
+            let Some(entry) = expected.get(path) else {
+                problems.push((path.clone(), Problem::Extra));
+                continue;
+            };
+            if entry.is_dir != *is_dir {
+                problems.push((
+                    path.clone(),
+                    Problem::Corrupted(if entry.is_dir {
+                        "expected a directory"
+                    } else {
+                        "expected a file"
+                    }),
+                ));
+            } else if !is_dir && entry.size != *size {
+                problems.push((path.clone(), Problem::Corrupted("size differs")));
+            } else if !is_dir && let Some(hash) = entry.hash {
+                to_hash.push((path, hash, *size));
+            }
+        }
+        problems.extend(
+            expected
+                .keys()
+                .filter(|path| !actual.contains_key(*path))
+                .map(|path| (path.clone(), Problem::Missing)),
+        );
+        let hashable = to_hash.len();
+        let to_hash = match sample {
+            Some(percentage) => sample_files(to_hash, percentage),
+            None => to_hash
+                .into_iter()
+                .map(|(path, hash, _)| (path, hash))
+                .collect(),
+        };
+        problems.extend(
+            hash_mismatches(&root_dir, &to_hash)?
+                .into_iter()
+                .map(|path| (path, Problem::Corrupted("contents differ"))),
+        );
+        problems.sort_unstable();
+
+        let sampled = sample.map(|_| (to_hash.len(), hashable));
+        report(&expected, &problems, sampled, output)
+            .attach_printable("Failed to write to output stream")
+            .change_context(Error::Io)
+            .attach(ExitCode::from(sysexits::ExitCode::IoErr))?;
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(Report::new(Error::VerificationFailed))
+                .attach_printable(format!(
+                    "{} entries of {root_dir:?} differ from {audit:?}",
+                    problems.len()
+                ))
+                .attach(ExitCode::from(sysexits::ExitCode::DataErr))
+        }
+    }
+}
+
+/// Lists every entry beneath `root_dir` by its path relative to the root,
+/// along with whether it is a directory and its size.
+///
+/// Symbolic links directly inside the root are followed so sharded top-level
+/// directories are included. Other symbolic links, and dangling ones, aren't
+/// recorded by audit trails and are skipped.
+fn walk(root_dir: &Path) -> Result<BTreeMap<PathBuf, (bool, u64)>, std::io::Error> {
+    let mut entries = BTreeMap::new();
+    let mut queue = vec![root_dir.to_path_buf()];
+    while let Some(dir) = queue.pop() {
+        let in_root = dir == root_dir;
+        let stat: fn(&Path) -> std::io::Result<fs::Metadata> = if in_root {
+            |path| fs::metadata(path)
+        } else {
+            |path| fs::symlink_metadata(path)
+        };
+        for entry in fs::read_dir(&dir)
+            .attach_printable_lazy(|| format!("Failed to read directory {dir:?}"))?
+        {
+            let path = entry
+                .attach_printable_lazy(|| format!("Failed to read directory {dir:?}"))?
+                .path();
+            let metadata = match stat(&path) {
+                Err(e) if in_root && e.kind() == std::io::ErrorKind::NotFound => continue,
+                r => r.attach_printable_lazy(|| format!("Failed to stat {path:?}"))?,
+            };
+            if metadata.is_symlink() {
+                continue;
+            }
+            let relative = path.strip_prefix(root_dir).unwrap_or(&path).to_path_buf();
+            entries.insert(relative, (metadata.is_dir(), metadata.len()));
+            if metadata.is_dir() {
+                queue.push(path);
+            }
+        }
+    }
+    Ok(entries)
+}
+
+/// Picks `percentage` of `files` (along with their hash and size) to rehash:
+/// the largest ones first, up to half of the sample, then the rest ordered by
+/// the hash of their path.
+fn sample_files(mut files: Vec<(&PathBuf, u64, u64)>, percentage: f64) -> Vec<(&PathBuf, u64)> {
+    let count = (files.len() as f64 * percentage / 100.).ceil() as usize;
+    files.sort_unstable_by(|(a, _, a_size), (b, _, b_size)| b_size.cmp(a_size).then(a.cmp(b)));
+    let mut rest = files.split_off(count.div_ceil(2).min(files.len()));
+    rest.sort_by_cached_key(|(path, ..)| XxHash64::oneshot(0, path.as_os_str().as_encoded_bytes()));
+    files
+        .into_iter()
+        .chain(rest)
+        .take(count)
+        .map(|(path, hash, _)| (path, hash))
+        .collect()
+}
+
+/// Rehashes `files`, spread across threads, returning those whose contents no
+/// longer match their recorded hash.
+fn hash_mismatches(root_dir: &Path, files: &[(&PathBuf, u64)]) -> Result<Vec<PathBuf>, Error> {
+    let parallelism = thread::available_parallelism().unwrap_or(NonZeroUsize::new(1).unwrap());
+    let chunk_size = files.len().div_ceil(parallelism.get()).max(1);
+    thread::scope(|scope| {
+        let workers = files
+            .chunks(chunk_size)
+            .map(|chunk| {
+                scope.spawn(move || {
+                    let mut mismatches = Vec::new();
+                    for &(path, hash) in chunk {
+                        let full_path = root_dir.join(path);
+                        let (_, actual) = rehash(&full_path)
+                            .attach_printable_lazy(|| format!("Failed to read {full_path:?}"))?;
+                        if actual != Some(hash) {
+                            mismatches.push(path.clone());
+                        }
+                    }
+                    Ok::<_, Report<std::io::Error>>(mismatches)
+                })
+            })
+            .collect::<Vec<_>>();
+
+        let mut mismatches = Vec::new();
+        for worker in workers {
+            mismatches.extend(
+                worker
+                    .join()
+                    .map_err(|_| Report::new(Error::TaskJoin))
+                    .attach(ExitCode::from(sysexits::ExitCode::Software))?
+                    .change_context(Error::Io)
+                    .attach(ExitCode::from(sysexits::ExitCode::IoErr))?,
+            );
+        }
+        Ok(mismatches)
+    })
+}
+
+fn report(
+    expected: &BTreeMap<PathBuf, ReplayEntry>,
+    problems: &[(PathBuf, Problem)],
+    sampled: Option<(usize, usize)>,
+    output: &mut impl Write,
+) -> std::fmt::Result {
+    for (path, problem) in problems {
+        match problem {
+            Problem::Missing => writeln!(output, "Missing: {}", path.display())?,
+            Problem::Extra => writeln!(output, "Extra: {}", path.display())?,
+            Problem::Corrupted(reason) => {
+                writeln!(output, "Corrupted: {} ({reason})", path.display())?;
+            }
+        }
+    }
+
+    let count = |kind: fn(&Problem) -> bool| {
+        problems
+            .iter()
+            .filter(|(_, problem)| kind(problem))
+            .count()
+            .separate_with_commas()
+    };
+    let dirs = expected.values().filter(|entry| entry.is_dir).count();
+    let files = expected.len() - dirs;
+    writeln!(
+        output,
+        "Verified {} {files_noun} and {} {dirs_noun}: {} missing, {} extra, {} corrupted.",
+        files.separate_with_commas(),
+        dirs.separate_with_commas(),
+        count(|problem| *problem == Problem::Missing),
+        count(|problem| *problem == Problem::Extra),
+        count(|problem| matches!(problem, Problem::Corrupted(_))),
+        files_noun = if files == 1 { "file" } else { "files" },
+        dirs_noun = if dirs == 1 {
+            "directory"
+        } else {
+            "directories"
+        },
+    )?;
+    if let Some((hashed, hashable)) = sampled {
+        writeln!(
+            output,
+            "Rehashed a sample of {} of {} files.",
+            hashed.separate_with_commas(),
+            hashable.separate_with_commas()
+        )?;
+    }
+    Ok(())
+}
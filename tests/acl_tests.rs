@@ -0,0 +1,79 @@
+#![cfg(target_os = "linux")]
+
+use std::{fs, path::Path, process::Command};
+
+use tempfile::TempDir;
+
+#[test]
+fn test_acls_are_applied_and_audited() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path().join("tree");
+    let audit = temp.path().join("audit.csv");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg(&root)
+        .args(["-n", "500", "--acl-percentage", "30", "--validate"])
+        .arg("--audit-output")
+        .arg(&audit)
+        .output()
+        .unwrap();
+    if String::from_utf8_lossy(&output.stderr).contains("Operation not supported") {
+        // The file system has no ACL support
+        return;
+    }
+    assert!(output.status.success(), "{output:?}");
+
+    let mut reader = csv::ReaderBuilder::new()
+        .comment(Some(b'#'))
+        .from_path(&audit)
+        .unwrap();
+    let mut acls = 0;
+    for record in reader.records() {
+        let record = record.unwrap();
+        let acl = &record[7];
+        let path = Path::new(&record[0]);
+        let mut xattr = [0; 256];
+        let xattr = rustix::fs::getxattr(path, "system.posix_acl_access", &mut xattr);
+        if acl.is_empty() {
+            assert!(xattr.is_err(), "{path:?} has an unaudited ACL");
+            continue;
+        }
+
+        acls += 1;
+        assert!(xattr.is_ok(), "{path:?} has no ACL");
+        assert!(acl.starts_with("user::"), "{acl}");
+        assert!(acl.contains(",user:"), "{acl}");
+        assert!(acl.contains(",mask::"), "{acl}");
+        assert!(acl.contains(",other::"), "{acl}");
+        assert!(matches!(&record[1], "file" | "directory"), "{record:?}");
+    }
+    assert!(acls > 0);
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg("verify")
+        .arg(&audit)
+        .arg(&root)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+
+    let audit_again = temp.path().join("audit_again.csv");
+    fs::remove_dir_all(&root).unwrap();
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg(&root)
+        .args(["-n", "500", "--acl-percentage", "30"])
+        .arg("--audit-output")
+        .arg(&audit_again)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+    let entries = |path: &Path| {
+        fs::read_to_string(path)
+            .unwrap()
+            .lines()
+            .filter(|line| !line.starts_with('#'))
+            .map(String::from)
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(entries(&audit), entries(&audit_again));
+}
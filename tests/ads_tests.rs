@@ -0,0 +1,68 @@
+use std::process::Command;
+
+use tempfile::TempDir;
+
+#[cfg(windows)]
+#[test]
+fn test_alternate_data_streams_are_written_and_audited() {
+    use std::fs;
+
+    let temp = TempDir::new().unwrap();
+    let root = temp.path().join("tree");
+    let audit = temp.path().join("audit.csv");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg(&root)
+        .args(["-n", "500", "--ads-percentage", "30"])
+        .arg("--audit-output")
+        .arg(&audit)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+
+    let mut reader = csv::ReaderBuilder::new()
+        .comment(Some(b'#'))
+        .from_path(&audit)
+        .unwrap();
+    let mut streams = 0;
+    for record in reader.records() {
+        let record = record.unwrap();
+        if record[10].is_empty() {
+            continue;
+        }
+
+        assert_eq!(&record[1], "file", "{record:?}");
+        for stream in record[10].split(';') {
+            let (name, size) = stream.split_once(':').unwrap();
+            let contents = fs::read(format!("{}:{name}", &record[0])).unwrap();
+            assert_eq!(contents.len().to_string(), size, "{record:?}");
+            streams += 1;
+        }
+    }
+    assert!(streams > 0);
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg("verify")
+        .arg(&audit)
+        .arg(&root)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+}
+
+#[cfg(not(windows))]
+#[test]
+fn test_alternate_data_streams_are_rejected_elsewhere() {
+    let temp = TempDir::new().unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg(temp.path())
+        .args(["-n", "10", "--ads-percentage", "30"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success(), "{output:?}");
+    assert!(
+        String::from_utf8_lossy(&output.stderr).contains("only be written on Windows"),
+        "{output:?}"
+    );
+}
@@ -0,0 +1,58 @@
+use std::{collections::HashMap, fs, path::Path, process::Command};
+
+use tempfile::TempDir;
+
+fn generate(root: &Path, audit: &Path, extra_args: &[&str]) -> HashMap<String, u64> {
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg(root)
+        .args(["-n", "500", "-b", "1M", "--validate", "--audit-output"])
+        .arg(audit)
+        .args(extra_args)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+
+    fs::read_to_string(audit)
+        .unwrap()
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .skip(1)
+        .filter_map(|line| {
+            let fields = line.split(',').collect::<Vec<_>>();
+            (fields[1] == "file").then(|| {
+                let path = Path::new(fields[0]).strip_prefix(root).unwrap();
+                (path.display().to_string(), fields[2].parse().unwrap())
+            })
+        })
+        .collect()
+}
+
+#[test]
+fn test_append_pass_grows_audited_files() {
+    let temp = TempDir::new().unwrap();
+    let plain = temp.path().join("plain");
+    let grown = temp.path().join("grown");
+
+    let before = generate(&plain, &temp.path().join("plain.csv"), &[]);
+    let after = generate(
+        &grown,
+        &temp.path().join("grown.csv"),
+        &["--append-pass", "1K", "--append-percentage", "50"],
+    );
+
+    assert_eq!(before.len(), after.len());
+    let mut appended = 0;
+    for (path, &size) in &after {
+        assert_eq!(fs::metadata(grown.join(path)).unwrap().len(), size);
+        match size - before[path] {
+            0 => {}
+            1000 => appended += 1,
+            delta => panic!("{path} grew by {delta} bytes"),
+        }
+    }
+    assert!(
+        (after.len() * 3 / 10..after.len() * 7 / 10).contains(&appended),
+        "{appended} of {} files grew",
+        after.len()
+    );
+}
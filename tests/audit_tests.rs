@@ -24,7 +24,10 @@ fn test_audit_trail_csv() {
 
     // Read and verify CSV content
     let content = fs::read_to_string(&audit_file).unwrap();
-    let lines: Vec<&str> = content.lines().collect();
+    let lines: Vec<&str> = content
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .collect();
 
     // Should have header + entries
     assert!(lines.len() > 1);
@@ -32,7 +35,7 @@ fn test_audit_trail_csv() {
     // Verify header
     assert_eq!(
         lines[0],
-        "path,type,size,hash,permissions,owner,is_duplicate"
+        "path,type,size,hash,permissions,owner,is_duplicate,acl,mtime,attributes,streams,extension,mime"
     );
 
     // Verify at least some entries exist
@@ -115,3 +118,342 @@ fn test_audit_trail_sqlite() {
         .unwrap();
     assert!(has_dir);
 }
+
+#[test]
+fn test_audit_trail_paths_and_directory_sizes() {
+    let temp = TempDir::new().unwrap();
+    let root_dir = temp.path().join("output");
+    let audit_file = temp.path().join("audit.csv");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg(&root_dir)
+        .arg("-n")
+        .arg("200")
+        .arg("-b")
+        .arg("10000")
+        .arg("--exact")
+        .arg("--audit-output")
+        .arg(&audit_file)
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+
+    let content = fs::read_to_string(&audit_file).unwrap();
+    let entries = content
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .skip(1)
+        .map(|line| {
+            let parts: Vec<&str> = line.split(',').collect();
+            (parts[0], parts[1], parts[2].parse::<u64>().unwrap())
+        })
+        .collect::<Vec<_>>();
+
+    for &(path, kind, _) in &entries {
+        assert!(
+            fs::symlink_metadata(path).is_ok(),
+            "{path} ({kind}) missing"
+        );
+    }
+    for &(dir, _, size) in entries.iter().filter(|(_, kind, _)| *kind == "directory") {
+        let prefix = format!("{dir}{}", std::path::MAIN_SEPARATOR);
+        let expected = entries
+            .iter()
+            .filter(|(path, kind, _)| *kind == "file" && path.starts_with(&prefix))
+            .map(|(_, _, size)| size)
+            .sum::<u64>();
+        assert_eq!(size, expected, "{dir}");
+    }
+}
+
+#[cfg(unix)]
+#[test]
+fn test_audit_trail_records_applied_permissions() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let temp = TempDir::new().unwrap();
+    let root_dir = temp.path().join("output");
+    let audit_file = temp.path().join("audit.csv");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg(&root_dir)
+        .arg("-n")
+        .arg("50")
+        .arg("--audit-output")
+        .arg(&audit_file)
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+
+    let content = fs::read_to_string(&audit_file).unwrap();
+    for line in content
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .skip(1)
+    {
+        let parts: Vec<&str> = line.split(',').collect();
+        let mode = fs::metadata(parts[0]).unwrap().permissions().mode() & 0o7777;
+        assert_eq!(parts[4], format!("{mode:o}"), "{line}");
+    }
+}
+
+#[test]
+fn test_audit_trail_sqlite_batches_and_schema() {
+    let temp = TempDir::new().unwrap();
+    let root_dir = temp.path().join("output");
+    let audit_file = temp.path().join("audit.db");
+
+    // A trail written with the original, unversioned schema is replaced
+    let conn = rusqlite::Connection::open(&audit_file).unwrap();
+    conn.execute_batch(
+        "CREATE TABLE audit_entries (
+            path TEXT NOT NULL,
+            type TEXT NOT NULL,
+            size INTEGER NOT NULL,
+            hash TEXT,
+            permissions TEXT,
+            owner TEXT,
+            is_duplicate BOOLEAN NOT NULL DEFAULT 0
+        );
+        INSERT INTO audit_entries VALUES ('stale', 'file', 0, 'ff', NULL, NULL, 0);",
+    )
+    .unwrap();
+    drop(conn);
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg(&root_dir)
+        .arg("-n")
+        .arg("100")
+        .arg("--files-exact")
+        .arg("-b")
+        .arg("10000")
+        .arg("--audit-batch-size")
+        .arg("7")
+        .arg("--audit-output")
+        .arg(&audit_file)
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+
+    let conn = rusqlite::Connection::open(&audit_file).unwrap();
+    let version: u32 = conn
+        .query_row("SELECT version FROM schema_version", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(version, 7);
+
+    let files: u32 = conn
+        .query_row(
+            "SELECT count(*) FROM audit_entries WHERE type = 'file'",
+            [],
+            |row| row.get(0),
+        )
+        .unwrap();
+    assert_eq!(files, 100);
+
+    let stale: bool = conn
+        .query_row(
+            "SELECT EXISTS(SELECT 1 FROM audit_entries WHERE path = 'stale')",
+            [],
+            |row| row.get(0),
+        )
+        .unwrap();
+    assert!(!stale);
+
+    let bad_hashes: u32 = conn
+        .query_row(
+            "SELECT count(*) FROM audit_entries
+             WHERE hash IS NOT NULL AND (typeof(hash) != 'blob' OR length(hash) != 8)",
+            [],
+            |row| row.get(0),
+        )
+        .unwrap();
+    assert_eq!(bad_hashes, 0);
+
+    let indexed: bool = conn
+        .query_row(
+            "SELECT EXISTS(SELECT 1 FROM sqlite_master
+             WHERE type = 'index' AND tbl_name = 'audit_entries')",
+            [],
+            |row| row.get(0),
+        )
+        .unwrap();
+    assert!(indexed);
+}
+
+#[test]
+fn test_audit_trail_sqlite_pragmas() {
+    let temp = TempDir::new().unwrap();
+    let root_dir = temp.path().join("output");
+    let audit_file = temp.path().join("audit.db");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg(&root_dir)
+        .arg("-n")
+        .arg("10")
+        .arg("--audit-page-size")
+        .arg("32768")
+        .arg("--audit-cache-size")
+        .arg("1M")
+        .arg("--audit-output")
+        .arg(&audit_file)
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+
+    let conn = rusqlite::Connection::open(&audit_file).unwrap();
+    let page_size: u32 = conn
+        .query_row("PRAGMA page_size", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(page_size, 32 * 1024);
+    let journal_mode: String = conn
+        .query_row("PRAGMA journal_mode", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(journal_mode, "wal");
+}
+
+#[test]
+fn test_audit_trail_describes_run() {
+    let temp = TempDir::new().unwrap();
+    let root_dir = temp.path().join("output");
+    let audit_file = temp.path().join("audit.csv");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg(&root_dir)
+        .arg("-n")
+        .arg("10")
+        .arg("--seed")
+        .arg("42")
+        .arg("--audit-output")
+        .arg(&audit_file)
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+
+    let content = fs::read_to_string(&audit_file).unwrap();
+    // Metadata only known once the run is done trails the entries
+    let metadata = content
+        .lines()
+        .filter_map(|line| line.strip_prefix("# "))
+        .filter_map(|line| line.split_once(": "))
+        .collect::<Vec<_>>();
+    let keys = metadata.iter().map(|&(key, _)| key).collect::<Vec<_>>();
+    assert_eq!(
+        keys,
+        [
+            "ftzz version",
+            "root",
+            "parameters",
+            "seed",
+            "plan",
+            "started",
+            "finished"
+        ]
+    );
+    assert_eq!(metadata[0].1, env!("CARGO_PKG_VERSION"));
+    assert_eq!(metadata[1].1, root_dir.display().to_string());
+    let parameters = serde_json::from_str::<serde_json::Value>(metadata[2].1).unwrap();
+    assert_eq!(parameters["files"], 10);
+    assert_eq!(parameters["seed"], 42);
+    assert_eq!(parameters["name-style"], "numeric");
+    assert!(parameters.get("dir-fanout").is_none(), "{parameters}");
+    assert_eq!(metadata[3].1, "42");
+}
+
+#[test]
+fn test_audit_trail_json() {
+    let temp = TempDir::new().unwrap();
+    let root_dir = temp.path().join("output");
+    let audit_file = temp.path().join("audit.json");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg(&root_dir)
+        .args(["-n", "10", "-b", "100", "--audit-output"])
+        .arg(&audit_file)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+
+    let records: Vec<serde_json::Value> =
+        serde_json::from_str(&fs::read_to_string(&audit_file).unwrap()).unwrap();
+    assert_eq!(records[0]["type"], "metadata");
+    assert_eq!(records[0]["root"], root_dir.to_str().unwrap());
+
+    let files = records
+        .iter()
+        .filter(|record| record["type"] == "file")
+        .collect::<Vec<_>>();
+    assert!(!files.is_empty());
+    for file in files {
+        let path = file["path"].as_str().unwrap();
+        assert_eq!(
+            file["size"].as_u64().unwrap(),
+            fs::metadata(path).unwrap().len()
+        );
+        assert!(file["is_duplicate"].is_boolean());
+    }
+    assert!(records.iter().any(|record| record["type"] == "directory"));
+}
+
+#[test]
+fn test_audit_trail_jsonl_from_flag() {
+    let temp = TempDir::new().unwrap();
+    let root_dir = temp.path().join("output");
+    let audit_file = temp.path().join("audit.log");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg(&root_dir)
+        .args(["-n", "10", "--audit-format", "jsonl", "--audit-output"])
+        .arg(&audit_file)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+
+    let content = fs::read_to_string(&audit_file).unwrap();
+    let records = content
+        .lines()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(records[0]["type"], "metadata");
+    assert!(
+        records
+            .iter()
+            .filter(|record| record["type"] != "metadata")
+            .all(|record| record["path"].is_string())
+    );
+    assert!(records.iter().any(|record| record["type"] == "file"));
+}
+
+#[test]
+fn test_audit_trail_records_extension_and_mime() {
+    let temp = TempDir::new().unwrap();
+
+    for (content, mime) in [("random", "image/png"), ("json", "application/json")] {
+        let root_dir = temp.path().join(content);
+        let audit_file = temp.path().join(format!("{content}.db"));
+        let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+            .arg(&root_dir)
+            .args(["-n", "20", "-b", "1000", "--extensions", "png"])
+            .args(["--content", content, "--audit-output"])
+            .arg(&audit_file)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{output:?}");
+
+        let conn = rusqlite::Connection::open(&audit_file).unwrap();
+        let mismatched: u32 = conn
+            .query_row(
+                "SELECT count(*) FROM audit_entries
+                 WHERE type = 'file' AND (extension IS NOT 'png' OR mime IS NOT ?1)
+                    OR type = 'directory' AND (extension IS NOT NULL OR mime IS NOT NULL)",
+                [mime],
+                |row| row.get(0),
+            )
+            .unwrap();
+        assert_eq!(mismatched, 0);
+    }
+}
@@ -0,0 +1,71 @@
+use std::process::Command;
+
+use tempfile::TempDir;
+
+#[test]
+fn test_bench_reports_all_phases() {
+    let temp = TempDir::new().unwrap();
+    let root_dir = temp.path().join("output");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg("bench")
+        .arg(&root_dir)
+        .arg("-n")
+        .arg("100")
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    for phase in ["create", "readdir", "stat", "unlink"] {
+        assert!(
+            stdout.lines().any(|line| line.starts_with(phase)),
+            "Missing {phase} phase in {stdout}"
+        );
+    }
+
+    // The unlink phase should leave the root empty
+    assert_eq!(root_dir.read_dir().unwrap().count(), 0);
+}
+
+#[test]
+fn test_bench_keep_skips_unlink() {
+    let temp = TempDir::new().unwrap();
+    let root_dir = temp.path().join("output");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg("bench")
+        .arg(&root_dir)
+        .arg("-n")
+        .arg("100")
+        .arg("--keep")
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(!stdout.lines().any(|line| line.starts_with("unlink")));
+    assert_ne!(root_dir.read_dir().unwrap().count(), 0);
+}
+
+#[test]
+fn test_root_dirs_prefixing_subcommands_still_generate() {
+    let temp = TempDir::new().unwrap();
+
+    for root_dir in ["b", "s", "v"] {
+        let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+            .current_dir(temp.path())
+            .arg(root_dir)
+            .arg("-n")
+            .arg("10")
+            .output()
+            .unwrap();
+
+        assert!(output.status.success(), "{output:?}");
+        assert!(
+            String::from_utf8_lossy(&output.stdout).contains("About 10 files"),
+            "{output:?}"
+        );
+        assert!(temp.path().join(root_dir).is_dir());
+    }
+}
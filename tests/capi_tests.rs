@@ -0,0 +1,64 @@
+#![cfg(feature = "capi")]
+
+use std::ffi::{CStr, CString, c_char};
+
+use ftzz::capi::{ftzz_free_string, ftzz_generate};
+use tempfile::tempdir;
+
+fn call(config: &str) -> (i32, String) {
+    let config = CString::new(config).unwrap();
+    let mut out: *mut c_char = std::ptr::null_mut();
+    let status = unsafe { ftzz_generate(config.as_ptr(), &mut out) };
+    let json = unsafe { CStr::from_ptr(out) }.to_str().unwrap().to_string();
+    unsafe { ftzz_free_string(out) };
+    (status, json)
+}
+
+#[test]
+fn generates_and_reports_stats() {
+    let dir = tempdir().unwrap();
+    let root = dir.path().join("tree");
+    let (status, json) = call(&format!(
+        r#"{{"root-dir": {root:?}, "files": 100, "files-exact": true, "total-bytes": 1000, "bytes-exact": true, "seed": 7}}"#
+    ));
+
+    assert_eq!(status, 0, "{json}");
+    assert!(json.contains(r#""files":100"#), "{json}");
+    assert!(json.contains(r#""bytes":1000"#), "{json}");
+    assert!(root.exists());
+}
+
+#[test]
+fn reports_deltas_from_request() {
+    let dir = tempdir().unwrap();
+    let root = dir.path().join("tree");
+    let (status, json) = call(&format!(
+        r#"{{"root-dir": {root:?}, "files": 100, "files-exact": true, "seed": 7}}"#
+    ));
+
+    assert_eq!(status, 0, "{json}");
+    assert!(
+        json.contains(r#""files":{"absolute":0,"achieved":100,"percentage":0.0,"requested":100}"#),
+        "{json}"
+    );
+    assert!(json.contains(r#""directories":{"#), "{json}");
+}
+
+#[test]
+fn reports_invalid_configuration() {
+    let (status, json) = call(r#"{"files": 100}"#);
+
+    assert_eq!(status, 1);
+    assert!(
+        json.starts_with(r#"{"error":"Invalid configuration"#),
+        "{json}"
+    );
+}
+
+#[test]
+fn rejects_null_pointers() {
+    assert_eq!(
+        unsafe { ftzz_generate(std::ptr::null(), std::ptr::null_mut()) },
+        -1
+    );
+}
@@ -0,0 +1,61 @@
+use std::{fs, path::Path, process::Command};
+
+use tempfile::TempDir;
+
+fn list_files(root: &Path) -> Vec<String> {
+    let mut files = Vec::new();
+    let mut queue = vec![root.to_path_buf()];
+    while let Some(dir) = queue.pop() {
+        for entry in fs::read_dir(&dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                queue.push(path);
+            } else {
+                files.push(path.strip_prefix(root).unwrap().display().to_string());
+            }
+        }
+    }
+    files.sort();
+    files
+}
+
+#[test]
+fn test_case_collisions_pair_siblings() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path().join("tree");
+    let audit = temp.path().join("audit.csv");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg(&root)
+        .args(["-n", "1000", "--case-collision-percentage", "20"])
+        .arg("--audit-output")
+        .arg(&audit)
+        .output()
+        .unwrap();
+
+    assert!(output.status.success(), "{output:?}");
+    let files = list_files(&root);
+    let twins = files
+        .iter()
+        .filter(|path| path.rsplit('/').next().unwrap().starts_with("File"))
+        .collect::<Vec<_>>();
+    assert!(!twins.is_empty());
+    for twin in twins {
+        let (dir, name) = twin.rsplit_once('/').unwrap_or(("", twin));
+        let sibling = Path::new(dir).join(name.to_lowercase());
+        assert!(
+            files.contains(&sibling.display().to_string()),
+            "{twin} has no sibling"
+        );
+    }
+    assert!(files.iter().all(|path| {
+        path.rsplit('/')
+            .next()
+            .unwrap()
+            .to_lowercase()
+            .starts_with("file")
+    }));
+
+    let audit = fs::read_to_string(audit).unwrap();
+    assert!(audit.lines().any(|line| line == "# case collisions: 0"));
+}
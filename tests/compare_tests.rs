@@ -0,0 +1,73 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use tempfile::TempDir;
+
+fn list_tree(root: &Path) -> Vec<(PathBuf, Vec<u8>)> {
+    let mut entries = Vec::new();
+    let mut queue = vec![root.to_path_buf()];
+    while let Some(dir) = queue.pop() {
+        for entry in fs::read_dir(&dir).unwrap() {
+            let path = entry.unwrap().path();
+            let relative = path.strip_prefix(root).unwrap().to_path_buf();
+            if path.is_dir() {
+                entries.push((relative, Vec::new()));
+                queue.push(path);
+            } else {
+                entries.push((relative, fs::read(&path).unwrap()));
+            }
+        }
+    }
+    entries.sort();
+    entries
+}
+
+#[test]
+fn test_compare_generates_identical_trees() {
+    let temp = TempDir::new().unwrap();
+    let a = temp.path().join("a");
+    let b = temp.path().join("b");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg(&a)
+        .arg("-n")
+        .arg("100")
+        .arg("-b")
+        .arg("10000")
+        .arg("--compare-with")
+        .arg(&b)
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains(&format!("{b:?} took")));
+    assert!(stdout.contains("% vs"));
+
+    let tree = list_tree(&a);
+    assert!(!tree.is_empty());
+    assert_eq!(tree, list_tree(&b));
+}
+
+#[test]
+fn test_compare_target_must_be_empty() {
+    let temp = TempDir::new().unwrap();
+    let a = temp.path().join("a");
+    let b = temp.path().join("b");
+    fs::create_dir(&b).unwrap();
+    fs::write(b.join("file"), "").unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg(&a)
+        .arg("-n")
+        .arg("10")
+        .arg("--compare-with")
+        .arg(&b)
+        .output()
+        .unwrap();
+
+    assert!(!output.status.success());
+}
@@ -0,0 +1,15 @@
+use std::process::Command;
+
+#[test]
+fn test_self_check_compat() {
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg("--self-check-compat")
+        .output()
+        .unwrap();
+
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "compat level 4: ok\n"
+    );
+}
@@ -75,3 +75,169 @@ fn test_missing_config_fails() {
 
     assert!(!output.status.success());
 }
+
+#[test]
+fn test_out_of_range_percentages_fail() {
+    let temp = TempDir::new().unwrap();
+    let config_path = temp.path().join("config.toml");
+    let root_dir = temp.path().join("output");
+
+    for setting in [
+        "sparse = 150",
+        "whiteout-percentage = -5.0",
+        "dedup-percentage = nan",
+        "duplicate-percentage = -1.0",
+    ] {
+        fs::write(
+            &config_path,
+            format!("files = 100\ntotal-bytes = 1000000\n{setting}\n"),
+        )
+        .unwrap();
+
+        let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+            .arg("--config")
+            .arg(&config_path)
+            .arg(&root_dir)
+            .output()
+            .unwrap();
+
+        assert!(!output.status.success(), "{setting}: {output:?}");
+        assert!(
+            String::from_utf8_lossy(&output.stderr).contains("percentage"),
+            "{setting}: {output:?}"
+        );
+        assert!(!root_dir.exists());
+    }
+}
+
+#[test]
+fn test_layers_are_generated_side_by_side() {
+    let temp = TempDir::new().unwrap();
+    let config_path = temp.path().join("config.toml");
+    let root_dir = temp.path().join("output");
+
+    fs::write(
+        &config_path,
+        r#"
+max-depth = 2
+
+[[layers]]
+files = 500
+files-exact = true
+total-bytes = 500
+
+[[layers]]
+files = 3
+files-exact = true
+total-bytes = 3000000
+max-depth = 0
+"#,
+    )
+    .unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg("--config")
+        .arg(&config_path)
+        .arg(&root_dir)
+        .output()
+        .unwrap();
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("Exactly 500 files"));
+    assert!(stdout.contains("maximum depth 2"));
+    assert!(stdout.contains("Exactly 3 files"));
+
+    let mut layers = fs::read_dir(&root_dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name())
+        .collect::<Vec<_>>();
+    layers.sort();
+    assert_eq!(layers, ["0.layer", "1.layer"]);
+    assert_eq!(fs::read_dir(root_dir.join("1.layer")).unwrap().count(), 3);
+}
+
+#[test]
+fn test_nested_layers_fail() {
+    let temp = TempDir::new().unwrap();
+    let config_path = temp.path().join("config.toml");
+    let root_dir = temp.path().join("output");
+
+    fs::write(
+        &config_path,
+        r"
+[[layers]]
+files = 10
+
+[[layers.layers]]
+files = 10
+",
+    )
+    .unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg("--config")
+        .arg(&config_path)
+        .arg(&root_dir)
+        .output()
+        .unwrap();
+
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_layers_write_their_own_audit_trails() {
+    let temp = TempDir::new().unwrap();
+    let config_path = temp.path().join("config.toml");
+    let root_dir = temp.path().join("output");
+    let audit = temp.path().join("audit.csv");
+
+    fs::write(
+        &config_path,
+        r#"
+[[layers]]
+files = 50
+files-exact = true
+
+[[layers]]
+files = 7
+files-exact = true
+max-depth = 0
+"#,
+    )
+    .unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg("--config")
+        .arg(&config_path)
+        .arg(&root_dir)
+        .arg("--audit-output")
+        .arg(&audit)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(!audit.exists());
+
+    for (layer, files) in [(0, 50), (1, 7)] {
+        let trail =
+            fs::read_to_string(temp.path().join(format!("audit.{layer}.layer.csv"))).unwrap();
+        let rows = trail.lines().filter(|line| line.contains(",file,")).count();
+        assert_eq!(rows, files, "{trail}");
+
+        let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+            .arg("verify")
+            .arg(temp.path().join(format!("audit.{layer}.layer.csv")))
+            .arg(root_dir.join(format!("{layer}.layer")))
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{output:?}");
+    }
+}
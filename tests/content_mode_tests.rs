@@ -0,0 +1,267 @@
+use std::{fs, path::Path, process::Command};
+
+use tempfile::TempDir;
+
+fn generate(root: &Path, content: &str, extra: &[&str]) {
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg(root)
+        .args(["-n", "200", "-b", "4M", "--content", content, "--validate"])
+        .args(extra)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+}
+
+fn files(root: &Path) -> Vec<(String, Vec<u8>)> {
+    let mut files = Vec::new();
+    let mut queue = vec![root.to_path_buf()];
+    while let Some(dir) = queue.pop() {
+        for entry in fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                queue.push(path);
+            } else {
+                let relative = path.strip_prefix(root).unwrap().display().to_string();
+                files.push((relative, fs::read(&path).unwrap()));
+            }
+        }
+    }
+    files.sort();
+    files
+}
+
+#[test]
+fn test_text_contents_are_sentences() {
+    let temp = TempDir::new().unwrap();
+    generate(temp.path(), "text", &[]);
+
+    let files = files(temp.path());
+    assert!(!files.is_empty());
+    let mut sentences = 0;
+    for (path, contents) in &files {
+        assert!(
+            contents
+                .iter()
+                .all(|&b| b.is_ascii_alphabetic() || b" ,.\n".contains(&b)),
+            "{path}"
+        );
+        sentences += contents.windows(2).filter(|w| w == b". ").count();
+    }
+    assert!(sentences > 0);
+}
+
+#[test]
+fn test_text_contents_ignore_how_files_are_written() {
+    let temp = TempDir::new().unwrap();
+    let a = temp.path().join("a");
+    let b = temp.path().join("b");
+    generate(&a, "text", &[]);
+    generate(&b, "text", &["--fragment", "--write-block-size", "64K"]);
+
+    assert_eq!(files(&a), files(&b));
+}
+
+#[test]
+fn test_json_contents_are_valid_documents() {
+    let temp = TempDir::new().unwrap();
+    generate(temp.path(), "json", &["--fragment"]);
+
+    let files = files(temp.path());
+    assert!(!files.is_empty());
+    for (path, contents) in &files {
+        if !contents.is_empty() {
+            serde_json::from_slice::<serde_json::Value>(contents)
+                .unwrap_or_else(|e| panic!("{path}: {e}"));
+        }
+    }
+}
+
+#[test]
+fn test_json_contents_reject_headers() {
+    let temp = TempDir::new().unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg(temp.path())
+        .args([
+            "-n",
+            "10",
+            "-b",
+            "1M",
+            "--content",
+            "json",
+            "--file-headers",
+        ])
+        .output()
+        .unwrap();
+    assert!(!output.status.success(), "{output:?}");
+}
+
+#[test]
+fn test_log_timestamps_count_up() {
+    let temp = TempDir::new().unwrap();
+    let syslog = temp.path().join("syslog");
+    let jsonl = temp.path().join("jsonl");
+    generate(&syslog, "syslog", &[]);
+    generate(&jsonl, "jsonl", &["--fragment"]);
+
+    let syslog = files(&syslog);
+    let jsonl = files(&jsonl);
+    assert!(!syslog.is_empty() && !jsonl.is_empty());
+    for (path, contents) in &syslog {
+        let mut previous = String::new();
+        for line in str::from_utf8(contents).unwrap().lines() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let mut fields = line.split(' ');
+            assert!(fields.next().unwrap().ends_with(">1"), "{path}: {line}");
+            let time = fields.next().unwrap().to_string();
+            assert!(
+                time.starts_with("202") && time >= previous,
+                "{path}: {line}"
+            );
+            previous = time;
+        }
+    }
+    for (path, contents) in &jsonl {
+        let mut previous = String::new();
+        for line in str::from_utf8(contents).unwrap().lines() {
+            if line.trim().is_empty() {
+                continue;
+            }
+            let record = serde_json::from_str::<serde_json::Value>(line)
+                .unwrap_or_else(|e| panic!("{path}: {e}"));
+            let time = record["timestamp"].as_str().unwrap().to_string();
+            assert!(time >= previous, "{path}: {line}");
+            previous = time;
+        }
+    }
+}
+
+#[test]
+fn test_csv_contents_are_tables() {
+    let temp = TempDir::new().unwrap();
+    generate(temp.path(), "csv", &["--csv-columns", "5"]);
+
+    let files = files(temp.path());
+    assert!(!files.is_empty());
+    let mut rows = 0;
+    for (path, contents) in &files {
+        let mut reader = csv::Reader::from_reader(&contents[..]);
+        if contents.len() > 100 {
+            assert_eq!(
+                reader.headers().unwrap(),
+                vec!["id", "name", "amount", "date", "active"],
+                "{path}"
+            );
+        }
+        for record in reader.records() {
+            assert_eq!(record.unwrap().len(), 5, "{path}");
+            rows += 1;
+        }
+    }
+    assert!(rows > 0);
+}
+
+#[test]
+fn test_csv_columns_require_csv_contents() {
+    let temp = TempDir::new().unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg(temp.path())
+        .args([
+            "-n",
+            "10",
+            "-b",
+            "1M",
+            "--content",
+            "text",
+            "--csv-columns",
+            "3",
+        ])
+        .output()
+        .unwrap();
+    assert!(!output.status.success(), "{output:?}");
+}
+
+#[test]
+fn test_dedup_contents_repeat_blocks() {
+    let temp = TempDir::new().unwrap();
+    let blocks = |percentage: &str| {
+        let root = temp.path().join(percentage);
+        generate(
+            &root,
+            "dedup",
+            &["--dedup-percentage", percentage, "--dedup-blocks", "16"],
+        );
+
+        let mut blocks = Vec::new();
+        for (_, contents) in files(&root) {
+            blocks.extend(contents.chunks_exact(4096).map(<[u8]>::to_vec));
+        }
+        let total = blocks.len();
+        blocks.sort_unstable();
+        blocks.dedup();
+        (total, blocks.len())
+    };
+
+    let (total, distinct) = blocks("90");
+    assert!(total > 300, "{total}");
+    assert!(distinct < total / 5, "{distinct} of {total}");
+    let (total, distinct) = blocks("0");
+    assert_eq!(distinct, total);
+}
+
+#[test]
+fn test_corpus_contents_are_sliced_from_samples() {
+    let temp = TempDir::new().unwrap();
+    let corpus = temp.path().join("corpus");
+    fs::create_dir_all(corpus.join("nested")).unwrap();
+    let samples = [b"abcdefghijklm".repeat(100), b"0123456789".repeat(150)];
+    fs::write(corpus.join("letters"), &samples[0]).unwrap();
+    fs::write(corpus.join("nested/digits"), &samples[1]).unwrap();
+
+    let root = temp.path().join("root");
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg(&root)
+        .args(["-n", "100", "-b", "1M", "--validate", "--content-corpus"])
+        .arg(&corpus)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+
+    let mut used = [false; 2];
+    for (path, contents) in files(&root) {
+        let Some(&first) = contents.first() else {
+            continue;
+        };
+        let index = usize::from(first.is_ascii_digit());
+        let sample = &samples[index];
+        let start = sample.iter().position(|&b| b == first).unwrap();
+        assert!(
+            contents
+                .iter()
+                .zip(sample.iter().cycle().skip(start))
+                .all(|(a, b)| a == b),
+            "{path}"
+        );
+        used[index] = true;
+    }
+    assert_eq!(used, [true, true]);
+}
+
+#[test]
+fn test_empty_corpus_is_rejected() {
+    let temp = TempDir::new().unwrap();
+    let corpus = temp.path().join("corpus");
+    fs::create_dir(&corpus).unwrap();
+    fs::write(corpus.join("empty"), b"").unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg(temp.path().join("root"))
+        .args(["-n", "10", "-b", "1M", "--content-corpus"])
+        .arg(&corpus)
+        .output()
+        .unwrap();
+    assert!(!output.status.success(), "{output:?}");
+}
@@ -0,0 +1,56 @@
+use std::{fs, path::Path, process::Command};
+
+use tempfile::TempDir;
+
+fn generate(root: &Path, template: &Path) -> std::process::Output {
+    Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg(root)
+        .args(["-n", "200", "-b", "1M", "--content-template"])
+        .arg(template)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn test_content_template_repeats_in_every_file() {
+    let temp = TempDir::new().unwrap();
+    let template = temp.path().join("template.pdf");
+    let contents = b"%PDF-1.4\n1 0 obj << >> endobj\ntrailer << >>\n%%EOF\n";
+    fs::write(&template, contents).unwrap();
+    let root = temp.path().join("root");
+
+    let output = generate(&root, &template);
+    assert!(output.status.success(), "{output:?}");
+
+    let mut queue = vec![root];
+    let mut files = 0;
+    while let Some(dir) = queue.pop() {
+        for entry in fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                queue.push(path);
+                continue;
+            }
+            let bytes = fs::read(&path).unwrap();
+            for (i, chunk) in bytes.chunks(contents.len()).enumerate() {
+                assert_eq!(chunk, &contents[..chunk.len()], "{path:?} at {i}");
+            }
+            files += 1;
+        }
+    }
+    assert!(files > 0);
+}
+
+#[test]
+fn test_empty_content_template_is_rejected() {
+    let temp = TempDir::new().unwrap();
+    let template = temp.path().join("empty");
+    fs::write(&template, b"").unwrap();
+
+    let output = generate(&temp.path().join("root"), &template);
+    assert!(!output.status.success());
+    assert!(
+        String::from_utf8_lossy(&output.stderr).contains("is empty"),
+        "{output:?}"
+    );
+}
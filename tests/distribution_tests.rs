@@ -0,0 +1,145 @@
+use std::process::Command;
+
+use tempfile::TempDir;
+
+#[test]
+fn test_check_distributions_reports_p_values() {
+    let temp = TempDir::new().unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg(temp.path().join("root"))
+        .arg("-n")
+        .arg("10000")
+        .arg("-b")
+        .arg("1M")
+        .arg("--dir-fanout")
+        .arg("poisson:4")
+        .arg("--check-distributions")
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(
+        stdout.contains("File sizes: Kolmogorov-Smirnov"),
+        "{stdout}"
+    );
+    assert!(stdout.contains("Directory fan-out: chi-square"), "{stdout}");
+}
+
+#[test]
+fn test_check_distributions_skips_exact_sizes() {
+    let temp = TempDir::new().unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg(temp.path().join("root"))
+        .arg("-n")
+        .arg("1000")
+        .arg("-b")
+        .arg("1M")
+        .arg("--exact")
+        .arg("--check-distributions")
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("File sizes: not checked"), "{stdout}");
+}
+
+fn file_sizes(root: &std::path::Path) -> Vec<u64> {
+    let mut sizes = Vec::new();
+    let mut queue = vec![root.to_path_buf()];
+    while let Some(dir) = queue.pop() {
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let entry = entry.unwrap();
+            if entry.file_type().unwrap().is_dir() {
+                queue.push(entry.path());
+            } else {
+                sizes.push(entry.metadata().unwrap().len());
+            }
+        }
+    }
+    sizes.sort_unstable();
+    sizes
+}
+
+#[test]
+fn test_heavy_tailed_size_distributions_keep_the_mean() {
+    for distribution in ["lognormal", "pareto", "zipf"] {
+        let temp = TempDir::new().unwrap();
+        let root = temp.path().join("root");
+
+        let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+            .arg(&root)
+            .args(["-n", "5000", "-b", "50M", "--files-exact"])
+            .args(["--size-distribution", distribution])
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{output:?}");
+
+        let sizes = file_sizes(&root);
+        assert_eq!(sizes.len(), 5000);
+        let mean = sizes.iter().sum::<u64>() / 5000;
+        let median = sizes[sizes.len() / 2];
+        assert!((5_000..20_000).contains(&mean), "{distribution}: {mean}");
+        assert!(median < mean, "{distribution}: {median} {mean}");
+        assert!(sizes[sizes.len() - 1] > 10 * mean, "{distribution}");
+    }
+}
+
+#[test]
+fn test_check_distributions_skips_other_size_distributions() {
+    let temp = TempDir::new().unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg(temp.path().join("root"))
+        .args(["-n", "1000", "-b", "1M"])
+        .args(["--size-distribution", "uniform", "--check-distributions"])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success(), "{output:?}");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("File sizes: not checked"), "{stdout}");
+}
+
+fn files_per_dir(root: &std::path::Path) -> Vec<u64> {
+    let mut counts = Vec::new();
+    let mut queue = vec![root.to_path_buf()];
+    while let Some(dir) = queue.pop() {
+        let mut files = 0;
+        for entry in std::fs::read_dir(dir).unwrap() {
+            let entry = entry.unwrap();
+            if entry.file_type().unwrap().is_dir() {
+                queue.push(entry.path());
+            } else {
+                files += 1;
+            }
+        }
+        counts.push(files);
+    }
+    counts.sort_unstable();
+    counts
+}
+
+#[test]
+fn test_zipf_files_per_dir_are_heavy_tailed() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path().join("root");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg(&root)
+        .args(["-n", "20000", "--files-exact"])
+        .args(["--files-per-dir-distribution", "zipf"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+
+    let counts = files_per_dir(&root);
+    assert_eq!(counts.iter().sum::<u64>(), 20000);
+    let mean = 20000 / counts.len() as u64;
+    let median = counts[counts.len() / 2];
+    assert!(median < mean, "{median} {mean}");
+    assert!(counts[counts.len() - 1] > 10 * mean, "{mean}");
+}
@@ -0,0 +1,64 @@
+use std::process::Command;
+
+use tempfile::TempDir;
+
+#[cfg(windows)]
+#[test]
+fn test_windows_attributes_are_set_and_audited() {
+    use std::{fs, os::windows::fs::MetadataExt, path::Path};
+
+    let temp = TempDir::new().unwrap();
+    let root = temp.path().join("tree");
+    let audit = temp.path().join("audit.csv");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg(&root)
+        .args(["-n", "500", "--windows-attribute-percentage", "30"])
+        .args(["--timestamp", "1000000000"])
+        .arg("--audit-output")
+        .arg(&audit)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+
+    let mut reader = csv::ReaderBuilder::new()
+        .comment(Some(b'#'))
+        .from_path(&audit)
+        .unwrap();
+    let mut attributed = 0;
+    for record in reader.records() {
+        let record = record.unwrap();
+        let letters = &record[9];
+        let attributes = fs::symlink_metadata(Path::new(&record[0]))
+            .unwrap()
+            .file_attributes();
+        for (attribute, letter) in [(0x1, 'R'), (0x2, 'H'), (0x4, 'S')] {
+            assert_eq!(
+                attributes & attribute != 0,
+                letters.contains(letter),
+                "{record:?}"
+            );
+        }
+        if !letters.is_empty() {
+            attributed += 1;
+        }
+    }
+    assert!(attributed > 0);
+}
+
+#[cfg(not(windows))]
+#[test]
+fn test_windows_attributes_are_rejected_elsewhere() {
+    let temp = TempDir::new().unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg(temp.path())
+        .args(["-n", "10", "--windows-attribute-percentage", "30"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success(), "{output:?}");
+    assert!(
+        String::from_utf8_lossy(&output.stderr).contains("only be set on Windows"),
+        "{output:?}"
+    );
+}
@@ -0,0 +1,129 @@
+use std::{fs, path::Path, process::Command};
+
+use tempfile::TempDir;
+
+fn generate(root: &Path, audit: &Path) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg(root)
+        .args(["-n", "300", "-b", "3M", "--file-headers", "--validate"])
+        .arg("--audit-output")
+        .arg(audit)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+
+    let audit = fs::read_to_string(audit).unwrap();
+    audit
+        .lines()
+        .find_map(|line| line.strip_prefix("# plan: "))
+        .unwrap()
+        .to_string()
+}
+
+#[test]
+fn test_files_start_with_their_header() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path().join("root");
+    let plan = generate(&root, &temp.path().join("audit.csv"));
+
+    let mut queue = vec![root.clone()];
+    let mut headers = 0;
+    while let Some(dir) = queue.pop() {
+        for entry in fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                queue.push(path);
+                continue;
+            }
+
+            let contents = fs::read(&path).unwrap();
+            let relative = path.strip_prefix(&root).unwrap().display().to_string();
+            let prefix = format!("ftzz run={plan} seed=");
+            let suffix = format!(" path={relative}\n");
+            let header_len = prefix.len() + 16 + suffix.len();
+            if contents.len() < header_len {
+                assert!(
+                    prefix
+                        .as_bytes()
+                        .starts_with(&contents[..contents.len().min(prefix.len())])
+                );
+                continue;
+            }
+
+            assert!(contents.starts_with(prefix.as_bytes()), "{path:?}");
+            assert_eq!(
+                &contents[prefix.len() + 16..header_len],
+                suffix.as_bytes(),
+                "{path:?}"
+            );
+            headers += 1;
+        }
+    }
+    assert!(headers > 0);
+}
+
+#[test]
+fn test_file_headers_are_deterministic() {
+    let temp = TempDir::new().unwrap();
+    let a = temp.path().join("a");
+    let b = temp.path().join("b");
+    generate(&a, &temp.path().join("a.csv"));
+    generate(&b, &temp.path().join("b.csv"));
+
+    let mut queue = vec![a.clone()];
+    while let Some(dir) = queue.pop() {
+        for entry in fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                queue.push(path);
+            } else {
+                let twin = b.join(path.strip_prefix(&a).unwrap());
+                assert_eq!(fs::read(&path).unwrap(), fs::read(twin).unwrap());
+            }
+        }
+    }
+}
+
+#[test]
+fn test_magic_bytes_match_extensions() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path().join("root");
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg(&root)
+        .args(["-n", "300", "-b", "3M", "--magic-bytes", "--validate"])
+        .args(["--extensions", "png,jpg,pdf,zip,txt"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+
+    let signatures: [(&str, &[u8]); 4] = [
+        ("png", b"\x89PNG\r\n\x1a\n"),
+        ("jpg", b"\xff\xd8\xff"),
+        ("pdf", b"%PDF-"),
+        ("zip", b"PK\x03\x04"),
+    ];
+    let mut queue = vec![root];
+    let mut signed = 0;
+    while let Some(dir) = queue.pop() {
+        for entry in fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                queue.push(path);
+                continue;
+            }
+
+            let contents = fs::read(&path).unwrap();
+            let extension = path.extension().unwrap().to_str().unwrap();
+            let Some(&(_, signature)) = signatures.iter().find(|(e, _)| *e == extension) else {
+                assert_eq!(extension, "txt");
+                continue;
+            };
+            let len = contents.len().min(signature.len());
+            assert_eq!(&contents[..len], &signature[..len], "{path:?}");
+            if len == signature.len() {
+                signed += 1;
+            }
+        }
+    }
+    assert!(signed > 0);
+}
@@ -0,0 +1,44 @@
+use std::{fs, process::Command};
+
+use tempfile::TempDir;
+
+#[test]
+fn test_fio_job_file() {
+    let temp = TempDir::new().unwrap();
+    let root_dir = temp.path().join("output");
+    let fio_file = temp.path().join("jobs.fio");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg(&root_dir)
+        .arg("-n")
+        .arg("10")
+        .arg("-b")
+        .arg("10000")
+        .arg("--fio-output")
+        .arg(&fio_file)
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+
+    let content = fs::read_to_string(&fio_file).unwrap();
+    assert!(content.contains("[global]"));
+
+    // Every job must point at a generated file of the advertised size
+    let mut jobs = 0;
+    let mut lines = content.lines();
+    while let Some(line) = lines.next() {
+        let Some(filename) = line.strip_prefix("filename=") else {
+            continue;
+        };
+        let size: u64 = lines
+            .next()
+            .and_then(|l| l.strip_prefix("size="))
+            .unwrap()
+            .parse()
+            .unwrap();
+        assert_eq!(fs::metadata(filename).unwrap().len(), size);
+        jobs += 1;
+    }
+    assert!(jobs > 0);
+}
@@ -4,21 +4,22 @@ use std::{
     cmp::{max, min},
     collections::VecDeque,
     fmt::Write,
-    fs::{DirEntry, File, create_dir},
+    fs::{self, DirEntry, File, create_dir},
     hash::{DefaultHasher, Hasher},
     io,
     io::{BufReader, Read, stdout},
-    num::NonZeroU64,
-    num::NonZeroUsize,
+    num::{NonZeroU64, NonZeroUsize},
     path::Path,
 };
 
-use ftzz::{Generator, NumFilesWithRatio};
+use ftzz::{
+    DirFanout, Error, FillPattern, Generator, LeftoverStrategy, LevelOverride, NumFilesWithRatio,
+    TraversalOrder,
+};
 use io_adapters::WriteExtension;
 use more_asserts::assert_le;
 use rand::Rng;
 use rstest::rstest;
-use toml::value::Array;
 
 use crate::inspect::InspectableTempDir;
 
@@ -152,7 +153,6 @@ fn simple_create_files(#[case] num_files: u64) {
     assert_snapshot!(&golden);
 }
 
-
 #[rstest]
 #[case(1_000)]
 #[cfg_attr(not(miri), case(10_000))]
@@ -162,7 +162,6 @@ fn simple_duplicate_files(
     #[values(0.0, 5.0)] duplicate_percentage: f64,
     //#[values(NonZeroUsize::new(1).unwrap(),NonZeroUsize::new(2).unwrap())] max_duplicates_per_file: NonZeroUsize
 ) {
-
     let dir = InspectableTempDir::new();
     let mut golden = String::new();
 
@@ -192,10 +191,9 @@ fn simple_permissions(
     #[values(
         vec![600,700],
         vec![666,777],
-    )] perm: Vec<u32>
-    //#[values(NonZeroUsize::new(1).unwrap(),NonZeroUsize::new(2).unwrap())] max_duplicates_per_file: NonZeroUsize
+    )]
+    perm: Vec<u32>, //#[values(NonZeroUsize::new(1).unwrap(),NonZeroUsize::new(2).unwrap())] max_duplicates_per_file: NonZeroUsize
 ) {
-
     let dir = InspectableTempDir::new();
     let mut golden = String::new();
 
@@ -212,7 +210,14 @@ fn simple_permissions(
     print_and_hash_dir(&dir.path, &mut golden);
 
     //set_snapshot_suffix!("{}_{}", num_files, perm.join("-"));
-    set_snapshot_suffix!("{}_{}", num_files, perm.iter().map(|p| p.to_string()).collect::<Vec<_>>().join("-"));
+    set_snapshot_suffix!(
+        "{}_{}",
+        num_files,
+        perm.iter()
+            .map(|p| p.to_string())
+            .collect::<Vec<_>>()
+            .join("-")
+    );
     assert_snapshot!(&golden);
 }
 
@@ -226,7 +231,7 @@ fn duplicate_create_files(
     #[values(0, 1, 10)] max_depth: u32,
     #[values(1, 100, 1_000)] ftd_ratio: u64,
     #[values(false, true)] files_exact: bool,
- //   #[values(0.0, 5.0)] duplicate_percentage: f64,
+    //   #[values(0.0, 5.0)] duplicate_percentage: f64,
 ) {
     #[cfg(miri)]
     if num_files > 100 || bytes.0 > 10_000 {
@@ -249,7 +254,7 @@ fn duplicate_create_files(
         .files_exact(files_exact)
         .bytes_exact(bytes.1)
         .max_depth(max_depth)
-   //     .duplicate_percentage(duplicate_percentage)
+        //     .duplicate_percentage(duplicate_percentage)
         .build()
         .generate(&mut golden)
         .unwrap();
@@ -273,15 +278,12 @@ fn duplicate_create_files(
         if bytes.1 { "_exact" } else { "" },
         num_files,
         max_depth,
-        ftd_ratio
-     //   if duplicate_percentage > 0.0 { format!("_dup_{}", duplicate_percentage) } else { String::new() }
-        //duplicate_percentage
+        ftd_ratio //   if duplicate_percentage > 0.0 { format!("_dup_{}", duplicate_percentage) } else { String::new() }
+                  //duplicate_percentage
     );
     assert_snapshot!(&golden);
 }
 
-
-
 #[rstest]
 fn advanced_create_files(
     #[values(1, 1_000, 10_000)] num_files: u64,
@@ -419,6 +421,47 @@ fn fill_byte_is_respected(#[case] fill_byte: u8) {
     assert_snapshot!(&golden);
 }
 
+#[rstest]
+#[case(false)]
+#[case(true)]
+#[cfg_attr(miri, ignore)] // Miri is way too slow unfortunately
+fn fill_pattern_is_respected(#[case] fragment: bool) {
+    let dir = InspectableTempDir::new();
+    let pattern = b"FTZZ-marker\n";
+
+    Generator::builder()
+        .root_dir(dir.path.clone())
+        .num_files_with_ratio(NumFilesWithRatio::from_num_files(
+            NonZeroU64::new(100).unwrap(),
+        ))
+        .num_bytes(1_000_000)
+        .fill_pattern(FillPattern::new(*pattern).unwrap())
+        .fragment(fragment)
+        .build()
+        .generate(&mut String::new())
+        .unwrap();
+
+    let mut queue = VecDeque::from([dir.path.clone()]);
+    while let Some(path) = queue.pop_front() {
+        for entry in path.read_dir().unwrap() {
+            let entry = entry.unwrap();
+            if entry.file_type().unwrap().is_dir() {
+                queue.push_back(entry.path());
+            } else {
+                let contents = fs::read(entry.path()).unwrap();
+                assert!(
+                    contents
+                        .iter()
+                        .zip(pattern.iter().cycle())
+                        .all(|(a, b)| a == b),
+                    "{:?}",
+                    entry.path()
+                );
+            }
+        }
+    }
+}
+
 #[test]
 //#[ignore]
 #[cfg_attr(miri, ignore)] // Miri is way too slow unfortunately
@@ -463,6 +506,480 @@ fn fuzz_test() {
     }
 }
 
+#[rstest]
+#[case(false, false)]
+#[case(true, false)]
+#[case(true, true)]
+#[cfg_attr(miri, ignore)] // Miri is way too slow unfortunately
+fn validate_accepts_generated_tree(#[case] files_exact: bool, #[case] bytes_exact: bool) {
+    let dir = InspectableTempDir::new();
+
+    Generator::builder()
+        .root_dir(dir.path.clone())
+        .num_files_with_ratio(NumFilesWithRatio::from_num_files(
+            NonZeroU64::new(1_000).unwrap(),
+        ))
+        .num_bytes(100_000)
+        .files_exact(files_exact)
+        .bytes_exact(bytes_exact)
+        .duplicate_percentage(10.)
+        .max_duplicates_per_file(NonZeroUsize::new(3).unwrap())
+        .validate(true)
+        .build()
+        .generate(&mut String::new())
+        .unwrap();
+}
+
+#[test]
+#[cfg_attr(miri, ignore)] // Miri is way too slow unfortunately
+fn fixed_dir_fanout_builds_complete_tree() {
+    let dir = InspectableTempDir::new();
+
+    let mut output = String::new();
+    Generator::builder()
+        .root_dir(dir.path.clone())
+        .num_files_with_ratio(NumFilesWithRatio::from_num_files(
+            NonZeroU64::new(1_000).unwrap(),
+        ))
+        .max_depth(3)
+        .dir_fanout(DirFanout::Uniform { min: 3, max: 3 })
+        .validate(true)
+        .build()
+        .generate(&mut output)
+        .unwrap();
+
+    // 3 + 9 + 27
+    assert!(output.contains("across 39 directories"), "{output}");
+}
+
+#[test]
+fn breadth_first_builds_complete_tree() {
+    let dir = InspectableTempDir::new();
+
+    let mut output = String::new();
+    Generator::builder()
+        .root_dir(dir.path.clone())
+        .num_files_with_ratio(NumFilesWithRatio::from_num_files(
+            NonZeroU64::new(1_000).unwrap(),
+        ))
+        .max_depth(3)
+        .dir_fanout(DirFanout::Uniform { min: 3, max: 3 })
+        .traversal(TraversalOrder::BreadthFirst)
+        .validate(true)
+        .build()
+        .generate(&mut output)
+        .unwrap();
+
+    assert!(output.contains("across 39 directories"), "{output}");
+}
+
+#[test]
+fn in_flight_bytes_limit_preserves_tree() {
+    let generate = |max_in_flight_bytes| {
+        let dir = InspectableTempDir::new();
+        Generator::builder()
+            .root_dir(dir.path.clone())
+            .num_files_with_ratio(NumFilesWithRatio::from_num_files(
+                NonZeroU64::new(1_000).unwrap(),
+            ))
+            .num_bytes(1_000_000)
+            .bytes_exact(true)
+            .maybe_max_in_flight_bytes(max_in_flight_bytes)
+            .validate(true)
+            .build()
+            .generate(&mut String::new())
+            .unwrap();
+
+        let mut output = String::new();
+        print_and_hash_dir(&dir.path, &mut output);
+        output
+    };
+
+    assert_eq!(generate(None), generate(NonZeroU64::new(4_096)));
+}
+
+#[test]
+fn chunked_tasks_preserve_tree() {
+    let generate = |bytes_exact, max_files_per_task| {
+        let dir = InspectableTempDir::new();
+        Generator::builder()
+            .root_dir(dir.path.clone())
+            .num_files_with_ratio(
+                NumFilesWithRatio::new(
+                    NonZeroU64::new(2_000).unwrap(),
+                    NonZeroU64::new(500).unwrap(),
+                )
+                .unwrap(),
+            )
+            .num_bytes(100_000)
+            .bytes_exact(bytes_exact)
+            .maybe_max_files_per_task(max_files_per_task)
+            .validate(true)
+            .build()
+            .generate(&mut String::new())
+            .unwrap();
+
+        let mut output = String::new();
+        print_and_hash_dir(&dir.path, &mut output);
+        output
+    };
+
+    for bytes_exact in [false, true] {
+        assert_eq!(
+            generate(bytes_exact, None),
+            generate(bytes_exact, NonZeroUsize::new(7))
+        );
+    }
+}
+
+#[test]
+fn bounded_byte_counts_pool_preserves_tree() {
+    let generate = |byte_counts_pool_capacity| {
+        let dir = InspectableTempDir::new();
+        Generator::builder()
+            .root_dir(dir.path.clone())
+            .num_files_with_ratio(NumFilesWithRatio::from_num_files(
+                NonZeroU64::new(1_000).unwrap(),
+            ))
+            .num_bytes(100_000)
+            .bytes_exact(true)
+            .maybe_byte_counts_pool_capacity(byte_counts_pool_capacity)
+            .validate(true)
+            .build()
+            .generate(&mut String::new())
+            .unwrap();
+
+        let mut output = String::new();
+        print_and_hash_dir(&dir.path, &mut output);
+        output
+    };
+
+    assert_eq!(generate(None), generate(NonZeroUsize::new(1)));
+}
+
+#[test]
+fn breadth_first_exact_files() {
+    let dir = InspectableTempDir::new();
+
+    let mut output = String::new();
+    Generator::builder()
+        .root_dir(dir.path.clone())
+        .num_files_with_ratio(NumFilesWithRatio::from_num_files(
+            NonZeroU64::new(1_337).unwrap(),
+        ))
+        .files_exact(true)
+        .traversal(TraversalOrder::BreadthFirst)
+        .validate(true)
+        .build()
+        .generate(&mut output)
+        .unwrap();
+
+    assert!(output.contains("1,337 files"), "{output}");
+}
+
+#[rstest]
+#[case(LeftoverStrategy::Spread)]
+#[case(LeftoverStrategy::LastFile)]
+#[case(LeftoverStrategy::LargestFile)]
+#[cfg_attr(miri, ignore)] // Miri is way too slow unfortunately
+fn leftover_strategy_keeps_bytes_exact(#[case] leftover_strategy: LeftoverStrategy) {
+    let dir = InspectableTempDir::new();
+
+    Generator::builder()
+        .root_dir(dir.path.clone())
+        .num_files_with_ratio(NumFilesWithRatio::from_num_files(
+            NonZeroU64::new(1_000).unwrap(),
+        ))
+        .num_bytes(100_000)
+        .files_exact(true)
+        .bytes_exact(true)
+        .leftover_strategy(leftover_strategy)
+        .validate(true)
+        .build()
+        .generate(&mut String::new())
+        .unwrap();
+}
+
+#[test]
+#[cfg_attr(miri, ignore)] // Miri is way too slow unfortunately
+fn exact_files_remainder_is_spread_across_tree() {
+    let dir = InspectableTempDir::new();
+
+    let mut output = String::new();
+    Generator::builder()
+        .root_dir(dir.path.clone())
+        .num_files_with_ratio(
+            NumFilesWithRatio::new(
+                NonZeroU64::new(1_000).unwrap(),
+                NonZeroU64::new(10).unwrap(),
+            )
+            .unwrap(),
+        )
+        .max_depth(1)
+        .files_exact(true)
+        .build()
+        .generate(&mut output)
+        .unwrap();
+
+    assert_eq!(count_num_files(&dir.path), 1_000);
+    let root_files = dir
+        .path
+        .read_dir()
+        .unwrap()
+        .filter(|entry| entry.as_ref().unwrap().file_type().unwrap().is_file())
+        .count();
+    assert_le!(root_files, 50, "{output}");
+}
+
+#[rstest]
+#[case(1_000, 137, false)]
+#[case(10, 500, false)]
+#[case(5_000, 77, true)]
+#[cfg_attr(miri, ignore)] // Miri is way too slow unfortunately
+fn exact_dirs_are_generated(
+    #[case] num_files: u64,
+    #[case] num_dirs: u64,
+    #[case] files_exact: bool,
+) {
+    let dir = InspectableTempDir::new();
+
+    Generator::builder()
+        .root_dir(dir.path.clone())
+        .num_files_with_ratio(NumFilesWithRatio::from_num_files(
+            NonZeroU64::new(num_files).unwrap(),
+        ))
+        .num_dirs(NonZeroU64::new(num_dirs).unwrap())
+        .dirs_exact(true)
+        .files_exact(files_exact)
+        .max_depth(3)
+        .build()
+        .generate(&mut String::new())
+        .unwrap();
+
+    assert_eq!(count_num_dirs(&dir.path), num_dirs);
+    if files_exact {
+        assert_eq!(count_num_files(&dir.path), num_files);
+    }
+}
+
+#[rstest]
+#[cfg_attr(miri, ignore)] // Miri is way too slow unfortunately
+fn min_depth_is_reached(
+    #[values(TraversalOrder::DepthFirst, TraversalOrder::BreadthFirst)] traversal: TraversalOrder,
+    #[values(1, 2, 3, 4, 5)] seed: u64,
+) {
+    let dir = InspectableTempDir::new();
+
+    Generator::builder()
+        .root_dir(dir.path.clone())
+        .num_files_with_ratio(
+            NumFilesWithRatio::new(
+                NonZeroU64::new(1_000).unwrap(),
+                NonZeroU64::new(1_000).unwrap(),
+            )
+            .unwrap(),
+        )
+        .max_depth(12)
+        .min_depth(12)
+        .traversal(traversal)
+        .seed(seed)
+        .build()
+        .generate(&mut String::new())
+        .unwrap();
+
+    assert_eq!(find_max_depth(&dir.path), 12);
+    let root_files = dir
+        .path
+        .read_dir()
+        .unwrap()
+        .filter(|entry| entry.as_ref().unwrap().file_type().unwrap().is_file())
+        .count();
+    assert_le!(root_files, 250);
+}
+
+#[test]
+fn min_depth_past_max_depth_is_rejected() {
+    let dir = InspectableTempDir::new();
+
+    let result = Generator::builder()
+        .root_dir(dir.path.clone())
+        .num_files_with_ratio(NumFilesWithRatio::from_num_files(
+            NonZeroU64::new(10).unwrap(),
+        ))
+        .max_depth(3)
+        .min_depth(4)
+        .build()
+        .generate(&mut String::new());
+
+    assert!(matches!(
+        result.unwrap_err().current_context(),
+        Error::InvalidEnvironment
+    ));
+}
+
+#[rstest]
+#[cfg_attr(miri, ignore)] // Miri is way too slow unfortunately
+fn files_at_leaves_are_at_max_depth(
+    #[values(TraversalOrder::DepthFirst, TraversalOrder::BreadthFirst)] traversal: TraversalOrder,
+    #[values(false, true)] files_exact: bool,
+) {
+    let dir = InspectableTempDir::new();
+
+    Generator::builder()
+        .root_dir(dir.path.clone())
+        .num_files_with_ratio(NumFilesWithRatio::from_num_files(
+            NonZeroU64::new(5_000).unwrap(),
+        ))
+        .num_bytes(100_000)
+        .max_depth(3)
+        .files_at_leaves(true)
+        .files_exact(files_exact)
+        .traversal(traversal)
+        .build()
+        .generate(&mut String::new())
+        .unwrap();
+
+    let mut queue = VecDeque::from([(dir.path.clone(), 0)]);
+    while let Some((path, depth)) = queue.pop_front() {
+        for entry in path.read_dir().unwrap() {
+            let entry = entry.unwrap();
+            if entry.file_type().unwrap().is_dir() {
+                queue.push_back((entry.path(), depth + 1));
+            } else {
+                assert_eq!(depth, 3, "{:?}", entry.path());
+            }
+        }
+    }
+    if files_exact {
+        assert_eq!(count_num_files(&dir.path), 5_000);
+    }
+}
+
+#[rstest]
+#[cfg_attr(miri, ignore)] // Miri is way too slow unfortunately
+fn level_overrides_shape_the_tree(
+    #[values(TraversalOrder::DepthFirst, TraversalOrder::BreadthFirst)] traversal: TraversalOrder,
+) {
+    let dir = InspectableTempDir::new();
+
+    Generator::builder()
+        .root_dir(dir.path.clone())
+        .num_files_with_ratio(NumFilesWithRatio::from_num_files(
+            NonZeroU64::new(1_000).unwrap(),
+        ))
+        .max_depth(2)
+        .level_overrides(vec![
+            "0:dir-fanout=uniform:7,7".parse().unwrap(),
+            "1:dir-fanout=uniform:2,2".parse().unwrap(),
+        ])
+        .traversal(traversal)
+        .build()
+        .generate(&mut String::new())
+        .unwrap();
+
+    let root_dirs = dir
+        .path
+        .read_dir()
+        .unwrap()
+        .filter(|entry| entry.as_ref().unwrap().file_type().unwrap().is_dir())
+        .count();
+    assert_eq!(root_dirs, 7);
+    assert_eq!(count_num_dirs(&dir.path), 7 + 7 * 2);
+}
+
+#[test]
+fn level_override_past_max_depth_is_rejected() {
+    let dir = InspectableTempDir::new();
+
+    let result = Generator::builder()
+        .root_dir(dir.path.clone())
+        .num_files_with_ratio(NumFilesWithRatio::from_num_files(
+            NonZeroU64::new(10).unwrap(),
+        ))
+        .max_depth(2)
+        .level_overrides(vec![LevelOverride {
+            depth: 3,
+            ftd_ratio: NonZeroU64::new(5),
+            dir_fanout: None,
+        }])
+        .build()
+        .generate(&mut String::new());
+
+    assert!(matches!(
+        result.unwrap_err().current_context(),
+        Error::InvalidEnvironment
+    ));
+}
+
+#[test]
+#[cfg_attr(miri, ignore)] // Miri is way too slow unfortunately
+fn flat_puts_every_file_in_the_root() {
+    let dir = InspectableTempDir::new();
+
+    Generator::builder()
+        .root_dir(dir.path.clone())
+        .num_files_with_ratio(NumFilesWithRatio::from_num_files(
+            NonZeroU64::new(50_000).unwrap(),
+        ))
+        .max_depth(5)
+        .flat(true)
+        .build()
+        .generate(&mut String::new())
+        .unwrap();
+
+    assert_eq!(dir.path.read_dir().unwrap().count(), 50_000);
+    assert_eq!(count_num_dirs(&dir.path), 0);
+}
+
+#[test]
+#[cfg_attr(miri, ignore)] // Miri is way too slow unfortunately
+fn skew_concentrates_files_in_first_sibling() {
+    let dir = InspectableTempDir::new();
+
+    Generator::builder()
+        .root_dir(dir.path.clone())
+        .num_files_with_ratio(NumFilesWithRatio::from_num_files(
+            NonZeroU64::new(10_000).unwrap(),
+        ))
+        .max_depth(2)
+        .skew(0.5)
+        .build()
+        .generate(&mut String::new())
+        .unwrap();
+
+    let mut subtrees = dir
+        .path
+        .read_dir()
+        .unwrap()
+        .map(|entry| entry.unwrap())
+        .filter(|entry| entry.file_type().unwrap().is_dir())
+        .map(|entry| (entry.file_name(), count_num_files(&entry.path())))
+        .collect::<Vec<_>>();
+    subtrees.sort_by_key(|&(_, files)| files);
+    let (hottest, files) = subtrees.pop().unwrap();
+    assert_eq!(hottest, "0.dir");
+    assert_le!(5 * subtrees.last().unwrap().1, 4 * files);
+}
+
+#[test]
+fn skew_of_one_is_rejected() {
+    let dir = InspectableTempDir::new();
+
+    let result = Generator::builder()
+        .root_dir(dir.path.clone())
+        .num_files_with_ratio(NumFilesWithRatio::from_num_files(
+            NonZeroU64::new(10).unwrap(),
+        ))
+        .skew(1.)
+        .build()
+        .generate(&mut String::new());
+
+    assert!(matches!(
+        result.unwrap_err().current_context(),
+        Error::InvalidEnvironment
+    ));
+}
+
 /// Recursively hashes the file and directory names in dir
 fn print_and_hash_dir(dir: &Path, output: &mut impl Write) {
     writeln!(output).unwrap();
@@ -537,6 +1054,21 @@ fn count_num_files(dir: &Path) -> u64 {
     num_files
 }
 
+fn count_num_dirs(dir: &Path) -> u64 {
+    let mut num_dirs = 0;
+    let mut queue = VecDeque::from([dir.to_path_buf()]);
+    while let Some(path) = queue.pop_front() {
+        for entry in path.read_dir().unwrap() {
+            let entry = entry.unwrap();
+            if entry.file_type().unwrap().is_dir() {
+                num_dirs += 1;
+                queue.push_back(entry.path());
+            }
+        }
+    }
+    num_dirs
+}
+
 fn count_num_bytes(dir: &Path) -> u64 {
     let mut num_bytes = 0;
     let mut queue = VecDeque::from([dir.to_path_buf()]);
@@ -552,3 +1084,123 @@ fn count_num_bytes(dir: &Path) -> u64 {
     }
     num_bytes
 }
+
+#[test]
+fn direct_io_preserves_tree() {
+    let generate = |direct_io| {
+        let dir = InspectableTempDir::new();
+        Generator::builder()
+            .root_dir(dir.path.clone())
+            .num_files_with_ratio(NumFilesWithRatio::from_num_files(
+                NonZeroU64::new(200).unwrap(),
+            ))
+            .num_bytes(2_000_000)
+            .direct_io(direct_io)
+            .validate(true)
+            .build()
+            .generate(&mut String::new())
+            .unwrap();
+
+        let mut output = String::new();
+        print_and_hash_dir(&dir.path, &mut output);
+        output
+    };
+
+    assert_eq!(generate(false), generate(true));
+}
+
+#[test]
+fn writeback_preserves_tree() {
+    let generate = |writeback_bytes| {
+        let dir = InspectableTempDir::new();
+        Generator::builder()
+            .root_dir(dir.path.clone())
+            .num_files_with_ratio(NumFilesWithRatio::from_num_files(
+                NonZeroU64::new(200).unwrap(),
+            ))
+            .num_bytes(2_000_000)
+            .maybe_writeback_bytes(writeback_bytes)
+            .validate(true)
+            .build()
+            .generate(&mut String::new())
+            .unwrap();
+
+        let mut output = String::new();
+        print_and_hash_dir(&dir.path, &mut output);
+        output
+    };
+
+    assert_eq!(generate(None), generate(NonZeroU64::new(100_000)));
+}
+
+#[rstest]
+#[case(false)]
+#[case(true)]
+fn fragment_preserves_tree(#[case] bytes_exact: bool) {
+    let generate = |fragment| {
+        let dir = InspectableTempDir::new();
+        Generator::builder()
+            .root_dir(dir.path.clone())
+            .num_files_with_ratio(NumFilesWithRatio::from_num_files(
+                NonZeroU64::new(200).unwrap(),
+            ))
+            .num_bytes(2_000_000)
+            .bytes_exact(bytes_exact)
+            .fragment(fragment)
+            .validate(true)
+            .build()
+            .generate(&mut String::new())
+            .unwrap();
+
+        let mut output = String::new();
+        print_and_hash_dir(&dir.path, &mut output);
+        output
+    };
+
+    assert_eq!(generate(false), generate(true));
+}
+
+#[test]
+fn write_block_size_preserves_tree() {
+    let generate = |write_block_size| {
+        let dir = InspectableTempDir::new();
+        Generator::builder()
+            .root_dir(dir.path.clone())
+            .num_files_with_ratio(NumFilesWithRatio::from_num_files(
+                NonZeroU64::new(200).unwrap(),
+            ))
+            .num_bytes(20_000_000)
+            .maybe_write_block_size(write_block_size)
+            .validate(true)
+            .build()
+            .generate(&mut String::new())
+            .unwrap();
+
+        let mut output = String::new();
+        print_and_hash_dir(&dir.path, &mut output);
+        output
+    };
+
+    assert_eq!(generate(None), generate(NonZeroUsize::new(1)));
+    assert_eq!(generate(None), generate(NonZeroUsize::new(1 << 20)));
+}
+
+#[test]
+fn mismatched_permission_weights_are_rejected() {
+    let dir = InspectableTempDir::new();
+
+    let result = Generator::builder()
+        .root_dir(dir.path.clone())
+        .num_files_with_ratio(NumFilesWithRatio::from_num_files(
+            NonZeroU64::new(10).unwrap(),
+        ))
+        .permissions(vec![0o644, 0o755])
+        .permission_weights(vec![1])
+        .build()
+        .generate(&mut String::new());
+
+    assert!(matches!(
+        result.unwrap_err().current_context(),
+        Error::InvalidPermissionWeights
+    ));
+}
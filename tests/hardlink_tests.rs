@@ -0,0 +1,211 @@
+#![cfg(unix)]
+
+use std::{
+    collections::{BTreeSet, HashMap, HashSet},
+    fs,
+    os::unix::fs::MetadataExt,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use tempfile::TempDir;
+
+fn file_metadata(root: &Path) -> Vec<fs::Metadata> {
+    let mut files = Vec::new();
+    let mut queue = vec![root.to_path_buf()];
+    while let Some(dir) = queue.pop() {
+        for entry in fs::read_dir(&dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                queue.push(path);
+            } else {
+                files.push(fs::metadata(path).unwrap());
+            }
+        }
+    }
+    files
+}
+
+#[test]
+fn test_files_share_a_small_pool_of_inodes() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path().join("tree");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg(&root)
+        .args(["-n", "1000", "--files-exact", "-b", "100K"])
+        .args(["--links-per-inode", "uniform:1,99", "--validate"])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success(), "{output:?}");
+    let files = file_metadata(&root);
+    assert_eq!(files.len(), 1000);
+    let inodes = files.iter().map(MetadataExt::ino).collect::<HashSet<_>>();
+    assert!(inodes.len() <= 20, "{} inodes", inodes.len());
+    for file in &files {
+        let links = files.iter().filter(|f| f.ino() == file.ino()).count();
+        assert_eq!(file.nlink(), links as u64);
+    }
+}
+
+#[test]
+fn test_links_per_inode_rejects_exact_bytes() {
+    let temp = TempDir::new().unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg(temp.path())
+        .args(["-n", "100", "-b", "1K", "--bytes-exact"])
+        .args(["--links-per-inode", "poisson:10"])
+        .output()
+        .unwrap();
+
+    assert!(!output.status.success());
+}
+
+/// Groups the non-empty files beneath `root` that share a key, keeping only
+/// groups with more than one file.
+fn groups<K: std::hash::Hash + Eq>(
+    root: &Path,
+    key: impl Fn(&Path) -> K,
+) -> BTreeSet<BTreeSet<PathBuf>> {
+    let mut groups = HashMap::<K, BTreeSet<PathBuf>>::new();
+    let mut queue = vec![root.to_path_buf()];
+    while let Some(dir) = queue.pop() {
+        for entry in fs::read_dir(&dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                queue.push(path);
+            } else if fs::metadata(&path).unwrap().len() > 0 {
+                groups
+                    .entry(key(&path))
+                    .or_default()
+                    .insert(path.strip_prefix(root).unwrap().to_path_buf());
+            }
+        }
+    }
+    groups
+        .into_values()
+        .filter(|group| group.len() > 1)
+        .collect()
+}
+
+#[test]
+fn test_duplicates_as_hardlinks_share_an_inode() {
+    let temp = TempDir::new().unwrap();
+    let generate = |root: &Path, hardlinks: bool| {
+        let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+            .arg(root)
+            .args(["-n", "500", "-b", "1M", "--duplicate-percentage", "20"])
+            .args(["--max-duplicates-per-file", "3", "--validate"])
+            .args(hardlinks.then_some("--duplicates-as-hardlinks"))
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{output:?}");
+    };
+    let copies = temp.path().join("copies");
+    let links = temp.path().join("links");
+    generate(&copies, false);
+    generate(&links, true);
+
+    let copied = groups(&copies, |path| fs::read(path).unwrap());
+    assert!(!copied.is_empty());
+    assert!(groups(&copies, |path| fs::metadata(path).unwrap().ino()).is_empty());
+    assert_eq!(
+        groups(&links, |path| fs::metadata(path).unwrap().ino()),
+        copied
+    );
+}
+
+#[test]
+fn test_duplicates_as_hardlinks_rejects_hardlink_farms() {
+    let temp = TempDir::new().unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg(temp.path())
+        .args(["-n", "100", "--duplicate-percentage", "10"])
+        .args([
+            "--duplicates-as-hardlinks",
+            "--links-per-inode",
+            "poisson:10",
+        ])
+        .output()
+        .unwrap();
+
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_duplicates_can_be_symlinked() {
+    let temp = TempDir::new().unwrap();
+    let copies = temp.path().join("copies");
+    let links = temp.path().join("links");
+    let audit = temp.path().join("audit.csv");
+    for root in [&copies, &links] {
+        let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+            .arg(root)
+            .args(["-n", "500", "-b", "1M", "--duplicate-percentage", "20"])
+            .args(["--max-duplicates-per-file", "3", "--validate"])
+            .args(
+                (root == &links)
+                    .then_some(["--duplicates-as-hardlinks", "--link-flavor", "symlink"])
+                    .into_iter()
+                    .flatten(),
+            )
+            .arg("--audit-output")
+            .arg(&audit)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{output:?}");
+    }
+
+    // Duplicates point at the first copy, within the tree
+    let copied = groups(&copies, |path| fs::read(path).unwrap());
+    assert!(!copied.is_empty());
+    assert_eq!(groups(&links, |path| fs::read(path).unwrap()), copied);
+    assert_eq!(
+        groups(&links, |path| fs::canonicalize(path).unwrap()),
+        copied
+    );
+
+    let trail = fs::read_to_string(&audit).unwrap();
+    let symlinks = trail
+        .lines()
+        .filter(|line| line.contains(",symlink,"))
+        .map(|line| PathBuf::from(line.split(',').next().unwrap()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        symlinks.len(),
+        copied.iter().map(|group| group.len() - 1).sum::<usize>()
+    );
+    for link in &symlinks {
+        assert!(fs::read_link(link).unwrap().is_relative(), "{link:?}");
+    }
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg("verify")
+        .arg(&audit)
+        .arg(&links)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+}
+
+#[test]
+fn test_link_flavors_must_fit_what_they_link() {
+    let temp = TempDir::new().unwrap();
+
+    for flavor in [
+        ["--link-flavor", "junction", "--duplicates-as-hardlinks"],
+        ["--link-flavor", "hardlink", "--symlink-loops=2"],
+        ["--link-flavor", "symlink", "--files-exact"],
+    ] {
+        let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+            .arg(temp.path().join("tree"))
+            .args(["-n", "100", "--duplicate-percentage", "10"])
+            .args(flavor)
+            .output()
+            .unwrap();
+        assert!(!output.status.success(), "{flavor:?}");
+    }
+}
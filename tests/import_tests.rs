@@ -0,0 +1,117 @@
+use std::{fs, path::Path, process::Command};
+
+use tempfile::TempDir;
+
+fn import(listing: &str, format: &str) -> (TempDir, bool) {
+    let temp = TempDir::new().unwrap();
+    let listing_file = temp.path().join("listing");
+    fs::write(&listing_file, listing).unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg("import")
+        .arg("--listing")
+        .arg(&listing_file)
+        .arg("--format")
+        .arg(format)
+        .arg(temp.path().join("output"))
+        .output()
+        .unwrap();
+    (temp, output.status.success())
+}
+
+fn size(root: &Path, path: &str) -> u64 {
+    fs::metadata(root.join("output").join(path)).unwrap().len()
+}
+
+#[cfg(unix)]
+fn mode(root: &Path, path: &str) -> u32 {
+    use std::os::unix::fs::PermissionsExt;
+
+    fs::metadata(root.join("output").join(path))
+        .unwrap()
+        .permissions()
+        .mode()
+        & 0o7777
+}
+
+#[test]
+fn test_import_find() {
+    let (temp, success) = import(
+        "d 4096 755 \nd 4096 750 a\nf 10 644 a/b c\nl 4 777 link\nf 3 600 top\n",
+        "find",
+    );
+    assert!(success);
+
+    let root = temp.path();
+    assert_eq!(size(root, "a/b c"), 10);
+    assert_eq!(size(root, "top"), 3);
+    assert!(!root.join("output/link").exists());
+    #[cfg(unix)]
+    {
+        assert_eq!(mode(root, "a"), 0o750);
+        assert_eq!(mode(root, "top"), 0o600);
+    }
+}
+
+#[test]
+fn test_import_mtree_hierarchical() {
+    let (temp, success) = import(
+        r"#mtree
+/set type=file mode=0644
+. type=dir
+    sub type=dir mode=0700
+        x size=5
+        y\040z size=7 \
+            mode=0600
+        l type=link link=x
+    ..
+    top size=2
+..
+",
+        "mtree",
+    );
+    assert!(success);
+
+    let root = temp.path();
+    assert_eq!(size(root, "sub/x"), 5);
+    assert_eq!(size(root, "sub/y z"), 7);
+    assert_eq!(size(root, "top"), 2);
+    assert!(!root.join("output/sub/l").exists());
+    #[cfg(unix)]
+    {
+        assert_eq!(mode(root, "sub"), 0o700);
+        assert_eq!(mode(root, "sub/x"), 0o644);
+        assert_eq!(mode(root, "sub/y z"), 0o600);
+    }
+}
+
+#[test]
+fn test_import_mtree_full_paths() {
+    let (temp, success) = import(
+        "#mtree\n. type=dir\n./a type=dir\n./a/b type=file size=3\n./c/d type=file size=4\n",
+        "mtree",
+    );
+    assert!(success);
+
+    let root = temp.path();
+    assert_eq!(size(root, "a/b"), 3);
+    assert_eq!(size(root, "c/d"), 4);
+}
+
+#[test]
+fn test_import_csv() {
+    let (temp, success) = import("path,size\nempty/\ndir/f,12\nother/g,3\n", "csv");
+    assert!(success);
+
+    let root = temp.path();
+    assert!(root.join("output/empty").is_dir());
+    assert_eq!(size(root, "dir/f"), 12);
+    assert_eq!(size(root, "other/g"), 3);
+}
+
+#[test]
+fn test_import_rejects_escaping_paths() {
+    let (temp, success) = import("../evil,1\n", "csv");
+    assert!(!success);
+    assert!(!temp.path().join("evil").exists());
+}
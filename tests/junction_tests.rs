@@ -0,0 +1,78 @@
+#![cfg(windows)]
+
+use std::{fs, path::Path, process::Command};
+
+use tempfile::TempDir;
+
+#[test]
+fn test_symlink_loops_fall_back_to_junctions() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path().join("tree");
+    let audit = temp.path().join("audit.csv");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg(&root)
+        .args(["-n", "1000", "--symlink-loops", "5"])
+        .arg("--audit-output")
+        .arg(&audit)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+
+    let mut reader = csv::ReaderBuilder::new()
+        .comment(Some(b'#'))
+        .from_path(&audit)
+        .unwrap();
+    let mut links = 0;
+    for record in reader.records() {
+        let record = record.unwrap();
+        if !matches!(&record[1], "symlink" | "junction") {
+            continue;
+        }
+
+        let link = Path::new(&record[0]);
+        assert!(fs::symlink_metadata(link).unwrap().is_symlink(), "{link:?}");
+        let dir = link.parent().unwrap().canonicalize().unwrap();
+        assert!(
+            dir.starts_with(link.canonicalize().unwrap()),
+            "{link:?} doesn't loop"
+        );
+        links += 1;
+    }
+    assert_eq!(links, 5);
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg("verify")
+        .arg(&audit)
+        .arg(&root)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+}
+
+#[test]
+fn test_symlink_loops_can_be_junctions() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path().join("tree");
+    let audit = temp.path().join("audit.csv");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg(&root)
+        .args([
+            "-n",
+            "1000",
+            "--symlink-loops",
+            "5",
+            "--link-flavor",
+            "junction",
+        ])
+        .arg("--audit-output")
+        .arg(&audit)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+
+    let trail = fs::read_to_string(&audit).unwrap();
+    assert_eq!(trail.matches(",junction,").count(), 5, "{trail}");
+    assert!(!trail.contains(",symlink,"), "{trail}");
+}
@@ -0,0 +1,81 @@
+use std::{collections::HashMap, fs, path::Path, process::Command};
+
+use tempfile::TempDir;
+use twox_hash::XxHash64;
+
+fn generate(root_dir: &Path, manifest: &Path, seed: &str, args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg(root_dir)
+        .args(["--seed", seed])
+        .arg("-n")
+        .arg("100")
+        .arg("-b")
+        .arg("10000")
+        .arg("--manifest")
+        .arg(manifest)
+        .args(args)
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    fs::read_to_string(manifest).unwrap()
+}
+
+fn fields(manifest: &str) -> HashMap<&str, &str> {
+    manifest
+        .lines()
+        .filter_map(|line| line.split_once(": "))
+        .collect()
+}
+
+#[test]
+fn test_manifest_is_location_independent() {
+    let temp = TempDir::new().unwrap();
+    let a = generate(&temp.path().join("a"), &temp.path().join("a.txt"), "7", &[]);
+    let b = generate(&temp.path().join("b"), &temp.path().join("b.txt"), "7", &[]);
+    let c = generate(&temp.path().join("c"), &temp.path().join("c.txt"), "8", &[]);
+    let (a, b, c) = (fields(&a), fields(&b), fields(&c));
+
+    assert_eq!(a["ftzz version"], env!("CARGO_PKG_VERSION"));
+    assert_eq!(a["seed"], "7");
+    assert_eq!(a["plan"], b["plan"]);
+    assert_eq!(a["audit digest"], b["audit digest"]);
+    assert_ne!(a["plan"], c["plan"]);
+    assert_ne!(a["audit digest"], c["audit digest"]);
+}
+
+#[test]
+fn test_manifest_checksum() {
+    let temp = TempDir::new().unwrap();
+    let manifest = generate(
+        &temp.path().join("output"),
+        &temp.path().join("manifest.txt"),
+        "0",
+        &[],
+    );
+
+    let (body, checksum) = manifest.rsplit_once("checksum: ").unwrap();
+    assert_eq!(
+        checksum.trim_end(),
+        format!("{:016x}", XxHash64::oneshot(0, body.as_bytes()))
+    );
+}
+
+#[test]
+fn test_manifest_plan_covers_traversal_order() {
+    let temp = TempDir::new().unwrap();
+    let dfs = generate(
+        &temp.path().join("dfs"),
+        &temp.path().join("dfs.txt"),
+        "7",
+        &["--traversal", "dfs"],
+    );
+    let bfs = generate(
+        &temp.path().join("bfs"),
+        &temp.path().join("bfs.txt"),
+        "7",
+        &["--traversal", "bfs"],
+    );
+
+    assert_ne!(fields(&dfs)["plan"], fields(&bfs)["plan"]);
+}
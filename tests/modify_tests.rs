@@ -0,0 +1,83 @@
+use std::{fs, path::Path, process::Command};
+
+use tempfile::TempDir;
+
+fn list_files(root: &Path) -> Vec<String> {
+    let mut files = Vec::new();
+    let mut queue = vec![root.to_path_buf()];
+    while let Some(dir) = queue.pop() {
+        for entry in fs::read_dir(&dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                queue.push(path);
+            } else {
+                files.push(path.strip_prefix(root).unwrap().display().to_string());
+            }
+        }
+    }
+    files.sort();
+    files
+}
+
+fn generate(root: &Path, extra_args: &[&str]) {
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg(root)
+        .args(["-n", "500", "-b", "1M", "--validate"])
+        .args(extra_args)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+}
+
+#[test]
+fn test_modify_pass_reports_changed_files() {
+    let temp = TempDir::new().unwrap();
+    let plain = temp.path().join("plain");
+    let modified = temp.path().join("modified");
+    let list = temp.path().join("modified.txt");
+
+    generate(&plain, &[]);
+    generate(
+        &modified,
+        &[
+            "--modify-percentage",
+            "30",
+            "--modified-output",
+            list.to_str().unwrap(),
+        ],
+    );
+
+    let files = list_files(&plain);
+    assert_eq!(files, list_files(&modified));
+    let changed = fs::read_to_string(&list)
+        .unwrap()
+        .lines()
+        .map(|path| {
+            Path::new(path)
+                .strip_prefix(&modified)
+                .unwrap()
+                .display()
+                .to_string()
+        })
+        .collect::<Vec<_>>();
+    assert!(!changed.is_empty());
+
+    for file in files {
+        let (before, after) = (plain.join(&file), modified.join(&file));
+        let (before_contents, after_contents) =
+            (fs::read(&before).unwrap(), fs::read(&after).unwrap());
+        assert_eq!(before_contents.len(), after_contents.len());
+        if changed.contains(&file) {
+            assert!(
+                before_contents.is_empty() || before_contents != after_contents,
+                "{file} is unchanged"
+            );
+            assert!(
+                fs::metadata(&after).unwrap().modified().unwrap()
+                    > fs::metadata(&before).unwrap().modified().unwrap()
+            );
+        } else {
+            assert_eq!(before_contents, after_contents, "{file} changed");
+        }
+    }
+}
@@ -0,0 +1,245 @@
+use std::{fs, path::Path, process::Command};
+
+use tempfile::TempDir;
+
+fn file_names(root: &Path) -> Vec<String> {
+    let mut names = Vec::new();
+    let mut queue = vec![root.to_path_buf()];
+    while let Some(dir) = queue.pop() {
+        for entry in fs::read_dir(&dir).unwrap() {
+            let entry = entry.unwrap();
+            if entry.file_type().unwrap().is_dir() {
+                queue.push(entry.path());
+            } else {
+                names.push(
+                    entry
+                        .path()
+                        .strip_prefix(root)
+                        .unwrap()
+                        .display()
+                        .to_string(),
+                );
+            }
+        }
+    }
+    names.sort();
+    names
+}
+
+#[test]
+fn test_realistic_names_are_deterministic() {
+    let temp = TempDir::new().unwrap();
+    let generate = |root: &Path| {
+        let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+            .arg(root)
+            .args(["-n", "500", "--name-style", "realistic", "--validate"])
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{output:?}");
+        file_names(root)
+    };
+
+    let names = generate(&temp.path().join("a"));
+    assert!(!names.is_empty());
+    for name in &names {
+        let name = name.rsplit(['/', '\\']).next().unwrap();
+        assert!(name.parse::<u64>().is_err(), "{name}");
+        let (_, extension) = name.rsplit_once('.').unwrap();
+        assert!(
+            extension.chars().all(|c| c.is_ascii_alphanumeric()),
+            "{name}"
+        );
+    }
+    assert!(
+        names
+            .iter()
+            .any(|name| name.chars().any(|c| c.is_ascii_uppercase()))
+    );
+    assert_eq!(names, generate(&temp.path().join("b")));
+}
+
+#[test]
+fn test_realistic_names_reject_case_collisions() {
+    let temp = TempDir::new().unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg(temp.path())
+        .args(["-n", "10", "--name-style", "realistic"])
+        .args(["--case-collision-percentage", "10"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success(), "{output:?}");
+}
+
+#[test]
+fn test_unicode_names_cover_files_and_dirs() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path().join("tree");
+    let audit = temp.path().join("audit.csv");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg(&root)
+        .args(["-n", "500", "--name-style", "unicode", "--validate"])
+        .arg("--audit-output")
+        .arg(&audit)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+
+    let names = file_names(&root);
+    assert!(!names.is_empty());
+    assert!(names.iter().all(|name| !name.is_ascii()), "{names:?}");
+    assert!(
+        names
+            .iter()
+            .any(|name| name.contains(".dir/") || name.contains(".dir\\"))
+    );
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg("verify")
+        .arg(&audit)
+        .arg(&root)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+}
+
+#[test]
+fn test_name_lengths_are_padded() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path().join("tree");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg(&root)
+        .args(["-n", "500", "--name-length", "20..40"])
+        .args(["--max-name-percentage", "20", "--validate"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+
+    let lengths = file_names(&root)
+        .iter()
+        .map(|name| Path::new(name).file_name().unwrap().len())
+        .collect::<Vec<_>>();
+    assert!(!lengths.is_empty());
+    assert!(
+        lengths
+            .iter()
+            .all(|len| (20..=40).contains(len) || *len == 255),
+        "{lengths:?}"
+    );
+    let max = lengths.iter().filter(|&&len| len == 255).count();
+    assert!(max > 0 && max < lengths.len(), "{lengths:?}");
+}
+
+#[test]
+fn test_extensions_follow_their_weights() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path().join("tree");
+    let audit = temp.path().join("audit.csv");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg(&root)
+        .args(["-n", "2000", "--extensions", "jpg:50,txt:30,bin:20,tmp:0"])
+        .args(["--name-style", "realistic"])
+        .arg("--audit-output")
+        .arg(&audit)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+
+    let names = file_names(&root);
+    let count = |extension: &str| {
+        names
+            .iter()
+            .filter(|name| name.ends_with(&format!(".{extension}")))
+            .count()
+    };
+    let (jpg, txt, bin) = (count("jpg"), count("txt"), count("bin"));
+    assert_eq!(jpg + txt + bin, names.len());
+    assert!(jpg > txt && txt > bin && bin > 0, "{jpg} {txt} {bin}");
+    assert!(
+        names.iter().all(|name| {
+            let name = Path::new(name).file_name().unwrap().to_str().unwrap();
+            name.matches('.').count() == 1
+        }),
+        "{names:?}"
+    );
+
+    let trail = fs::read_to_string(&audit).unwrap();
+    assert!(
+        trail
+            .lines()
+            .any(|line| line == format!("# extensions: jpg={jpg},txt={txt},bin={bin},tmp=0")),
+        "{trail}"
+    );
+}
+
+#[test]
+fn test_name_templates_shape_files_and_dirs() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path().join("tree");
+    let audit = temp.path().join("audit.csv");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg(&root)
+        .args(["-n", "500", "--extensions", "csv,pdf", "--validate"])
+        .args(["--file-name-template", "report_{n:06}_{rand8}.{ext}"])
+        .args(["--dir-name-template", "d{depth}_{n:03}"])
+        .arg("--audit-output")
+        .arg(&audit)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+
+    let names = file_names(&root);
+    assert!(!names.is_empty());
+    for path in &names {
+        let mut components = Path::new(path).components().collect::<Vec<_>>();
+        let name = components.pop().unwrap().as_os_str().to_str().unwrap();
+        let (stem, extension) = name.rsplit_once('.').unwrap();
+        assert!(["csv", "pdf"].contains(&extension), "{path}");
+        let [report, index, random] = stem.split('_').collect::<Vec<_>>()[..] else {
+            panic!("{path}");
+        };
+        assert_eq!(report, "report");
+        assert!(index.len() == 6 && index.parse::<u64>().is_ok(), "{path}");
+        assert!(
+            random.len() == 8 && random.chars().all(|c| c.is_ascii_hexdigit()),
+            "{path}"
+        );
+
+        for (depth, dir) in components.iter().enumerate() {
+            let dir = dir.as_os_str().to_str().unwrap();
+            assert!(dir.starts_with(&format!("d{}_", depth + 1)), "{path}");
+        }
+    }
+    assert!(names.iter().any(|name| name.contains("d1_000")));
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg("verify")
+        .arg(&audit)
+        .arg(&root)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+}
+
+#[test]
+fn test_invalid_name_templates_are_rejected() {
+    for args in [
+        &["--file-name-template", "report.txt"][..],
+        &["--dir-name-template", "{n}.{ext}"],
+        &["--file-name-template", "{n}", "--name-style", "realistic"],
+    ] {
+        let temp = TempDir::new().unwrap();
+
+        let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+            .arg(temp.path())
+            .args(["-n", "10"])
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(!output.status.success(), "{args:?}: {output:?}");
+    }
+}
@@ -99,3 +99,390 @@ fn test_cli_permissions() {
         }
     }
 }
+
+#[test]
+fn test_permissions_masked_by_umask() {
+    for bytes in ["0", "100000"] {
+        let temp = TempDir::new().unwrap();
+        let root_dir = temp.path().join("output");
+
+        let output = Command::new("sh")
+            .arg("-c")
+            .arg(r#"umask 077 && exec "$0" "$@""#)
+            .arg(env!("CARGO_BIN_EXE_ftzz"))
+            .arg("-n")
+            .arg("50")
+            .arg("--files-exact")
+            .arg("-b")
+            .arg(bytes)
+            .arg("--permissions")
+            .arg("666,777")
+            .arg(&root_dir)
+            .output()
+            .unwrap();
+
+        assert!(output.status.success());
+
+        let mut dirs = vec![root_dir];
+        let mut files = 0;
+        while let Some(dir) = dirs.pop() {
+            for entry in fs::read_dir(dir).unwrap() {
+                let entry = entry.unwrap();
+                let metadata = entry.metadata().unwrap();
+                if metadata.is_dir() {
+                    dirs.push(entry.path());
+                    continue;
+                }
+
+                let mode = metadata.permissions().mode() & 0o777;
+                assert!(
+                    mode == 0o666 || mode == 0o777,
+                    "File {:?} has unexpected mode {:o}",
+                    entry.path(),
+                    mode
+                );
+                files += 1;
+            }
+        }
+        assert_eq!(files, 50);
+    }
+}
+
+#[test]
+fn test_weighted_permissions() {
+    let temp = TempDir::new().unwrap();
+    let root_dir = temp.path().join("output");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg("-n")
+        .arg("2000")
+        .arg("--files-exact")
+        .arg("--permissions")
+        .arg("644:90,755:10")
+        .arg(&root_dir)
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+
+    let mut dirs = vec![root_dir];
+    let (mut regular, mut executable) = (0, 0);
+    while let Some(dir) = dirs.pop() {
+        for entry in fs::read_dir(dir).unwrap() {
+            let entry = entry.unwrap();
+            let metadata = entry.metadata().unwrap();
+            if metadata.is_dir() {
+                dirs.push(entry.path());
+                continue;
+            }
+
+            match metadata.permissions().mode() & 0o777 {
+                0o644 => regular += 1,
+                0o755 => executable += 1,
+                mode => panic!("File {:?} has unexpected mode {:o}", entry.path(), mode),
+            }
+        }
+    }
+    assert_eq!(regular + executable, 2000);
+    assert!(
+        (100..300).contains(&executable),
+        "{executable} of 2000 files are executable"
+    );
+}
+
+#[test]
+fn test_executable_percentage() {
+    let temp = TempDir::new().unwrap();
+    let root_dir = temp.path().join("output");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg("-n")
+        .arg("2000")
+        .arg("--files-exact")
+        .arg("--permissions")
+        .arg("600")
+        .arg("--executable-percentage")
+        .arg("25")
+        .arg(&root_dir)
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+
+    let mut dirs = vec![root_dir];
+    let (mut regular, mut executable) = (0, 0);
+    while let Some(dir) = dirs.pop() {
+        for entry in fs::read_dir(dir).unwrap() {
+            let entry = entry.unwrap();
+            let metadata = entry.metadata().unwrap();
+            if metadata.is_dir() {
+                dirs.push(entry.path());
+                continue;
+            }
+
+            match metadata.permissions().mode() & 0o777 {
+                0o600 => regular += 1,
+                0o711 => executable += 1,
+                mode => panic!("File {:?} has unexpected mode {:o}", entry.path(), mode),
+            }
+        }
+    }
+    assert_eq!(regular + executable, 2000);
+    assert!(
+        (400..600).contains(&executable),
+        "{executable} of 2000 files are executable"
+    );
+}
+
+#[test]
+fn test_configured_executable_percentage_out_of_range_fails() {
+    let temp = TempDir::new().unwrap();
+    let config_path = temp.path().join("config.toml");
+
+    for percentage in ["150.0", "-5.0"] {
+        fs::write(
+            &config_path,
+            format!("files = 10\npermissions = [\"600\"]\nexecutable-percentage = {percentage}\n"),
+        )
+        .unwrap();
+
+        let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+            .arg("--config")
+            .arg(&config_path)
+            .arg(temp.path().join("output"))
+            .output()
+            .unwrap();
+
+        assert!(!output.status.success(), "{percentage}: {output:?}");
+        assert!(
+            String::from_utf8_lossy(&output.stderr).contains("between 0 and 100"),
+            "{output:?}"
+        );
+        assert!(!temp.path().join("output").exists());
+    }
+}
+
+#[test]
+fn test_umask_relative_permissions() {
+    let temp = TempDir::new().unwrap();
+    let root_dir = temp.path().join("output");
+    let audit = temp.path().join("audit.csv");
+
+    let output = Command::new("sh")
+        .arg("-c")
+        .arg(r#"umask 027 && exec "$0" "$@""#)
+        .arg(env!("CARGO_BIN_EXE_ftzz"))
+        .arg("-n")
+        .arg("20")
+        .arg("--permissions")
+        .arg("666,777")
+        .arg("--permission-mode")
+        .arg("umask-relative")
+        .arg("--audit-output")
+        .arg(&audit)
+        .arg(&root_dir)
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+
+    let mut dirs = vec![root_dir];
+    while let Some(dir) = dirs.pop() {
+        for entry in fs::read_dir(dir).unwrap() {
+            let entry = entry.unwrap();
+            let metadata = entry.metadata().unwrap();
+            if metadata.is_dir() {
+                dirs.push(entry.path());
+                continue;
+            }
+
+            let mode = metadata.permissions().mode() & 0o777;
+            assert!(
+                mode == 0o640 || mode == 0o750,
+                "File {:?} has unexpected mode {:o}",
+                entry.path(),
+                mode
+            );
+        }
+    }
+
+    let audit = fs::read_to_string(audit).unwrap();
+    assert!(
+        audit
+            .lines()
+            .any(|line| line == "# permissions: umask-relative"),
+        "{audit}"
+    );
+    assert!(audit.lines().all(|line| {
+        !line.contains(",file,") || line.contains(",640,") || line.contains(",750,")
+    }));
+}
+
+#[test]
+fn test_permission_rules() {
+    let temp = TempDir::new().unwrap();
+    let config_path = temp.path().join("config.toml");
+    let root_dir = temp.path().join("output");
+
+    fs::write(
+        &config_path,
+        r#"
+files = 1000
+permissions = ["644"]
+permission-rules = ["1.dir=700", "1.dir/0.dir=755"]
+"#,
+    )
+    .unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg("--config")
+        .arg(&config_path)
+        .arg(&root_dir)
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+
+    let mut dirs = vec![root_dir.clone()];
+    let mut seen = [0; 3];
+    while let Some(dir) = dirs.pop() {
+        for entry in fs::read_dir(dir).unwrap() {
+            let entry = entry.unwrap();
+            let metadata = entry.metadata().unwrap();
+            if metadata.is_dir() {
+                dirs.push(entry.path());
+                continue;
+            }
+
+            let path = entry.path();
+            let relative = path.strip_prefix(&root_dir).unwrap();
+            let (expected, seen) = if relative.starts_with("1.dir/0.dir") {
+                (0o755, &mut seen[2])
+            } else if relative.starts_with("1.dir") {
+                (0o700, &mut seen[1])
+            } else {
+                (0o644, &mut seen[0])
+            };
+            assert_eq!(
+                metadata.permissions().mode() & 0o777,
+                expected,
+                "{relative:?}"
+            );
+            *seen += 1;
+        }
+    }
+    assert!(seen.iter().all(|&n| n > 0), "{seen:?}");
+}
+
+#[test]
+fn test_dir_permissions() {
+    let temp = TempDir::new().unwrap();
+    let root_dir = temp.path().join("output");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg("-n")
+        .arg("2000")
+        .arg("--permissions")
+        .arg("600")
+        .arg("--dir-permissions")
+        .arg("700:3,755")
+        .arg(&root_dir)
+        .output()
+        .unwrap();
+
+    assert!(output.status.success(), "{output:?}");
+
+    let mut dirs = vec![root_dir];
+    let (mut private, mut public) = (0, 0);
+    while let Some(dir) = dirs.pop() {
+        for entry in fs::read_dir(dir).unwrap() {
+            let entry = entry.unwrap();
+            let metadata = entry.metadata().unwrap();
+            let mode = metadata.permissions().mode() & 0o777;
+            if !metadata.is_dir() {
+                assert_eq!(mode, 0o600, "File {:?} has unexpected mode", entry.path());
+                continue;
+            }
+
+            dirs.push(entry.path());
+            match mode {
+                0o700 => private += 1,
+                0o755 => public += 1,
+                mode => panic!("Dir {:?} has unexpected mode {:o}", entry.path(), mode),
+            }
+        }
+    }
+    assert!(public > 0);
+    assert!(
+        private > 2 * public,
+        "{private} private and {public} public dirs"
+    );
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg("-n")
+        .arg("10")
+        .arg("--dir-permissions")
+        .arg("555")
+        .arg(temp.path().join("locked"))
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_special_permission_bits() {
+    let temp = TempDir::new().unwrap();
+    let root_dir = temp.path().join("output");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg("-n")
+        .arg("500")
+        .arg("-b")
+        .arg("1M")
+        .arg("--permissions")
+        .arg("4755,2755,644")
+        .arg("--dir-permissions")
+        .arg("1777")
+        .arg(&root_dir)
+        .output()
+        .unwrap();
+
+    assert!(output.status.success(), "{output:?}");
+
+    let mut dirs = vec![root_dir];
+    let mut setuid = 0;
+    let mut setgid = 0;
+    while let Some(dir) = dirs.pop() {
+        for entry in fs::read_dir(dir).unwrap() {
+            let entry = entry.unwrap();
+            let metadata = entry.metadata().unwrap();
+            let mode = metadata.permissions().mode() & 0o7777;
+            if metadata.is_dir() {
+                assert_eq!(mode, 0o1777, "Dir {:?} isn't sticky", entry.path());
+                dirs.push(entry.path());
+                continue;
+            }
+
+            match mode {
+                0o4755 => setuid += 1,
+                0o2755 => setgid += 1,
+                0o644 => {}
+                mode => panic!("File {:?} has unexpected mode {:o}", entry.path(), mode),
+            }
+        }
+    }
+    assert!(
+        setuid > 0 && setgid > 0,
+        "{setuid} setuid and {setgid} setgid files"
+    );
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg("-n")
+        .arg("10")
+        .arg("--permissions")
+        .arg("10644")
+        .arg(temp.path().join("invalid"))
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+}
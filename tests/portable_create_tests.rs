@@ -0,0 +1,52 @@
+use std::{
+    fs,
+    os::unix::fs::PermissionsExt,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use tempfile::TempDir;
+
+fn list_tree(root: &Path) -> Vec<(PathBuf, u64, u32)> {
+    let mut entries = Vec::new();
+    let mut queue = vec![root.to_path_buf()];
+    while let Some(dir) = queue.pop() {
+        for entry in fs::read_dir(&dir).unwrap() {
+            let path = entry.unwrap().path();
+            let metadata = fs::symlink_metadata(&path).unwrap();
+            if metadata.is_dir() {
+                queue.push(path.clone());
+            }
+            entries.push((
+                path.strip_prefix(root).unwrap().to_path_buf(),
+                if metadata.is_dir() { 0 } else { metadata.len() },
+                metadata.permissions().mode() & 0o7777,
+            ));
+        }
+    }
+    entries.sort();
+    entries
+}
+
+#[test]
+fn test_portable_create_matches_default() {
+    let temp = TempDir::new().unwrap();
+    let generate = |root: &Path, args: &[&str]| {
+        let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+            .arg(root)
+            .args(["-n", "500", "--permissions", "600,640,700", "--seed", "7"])
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "{output:?}");
+    };
+
+    let default = temp.path().join("default");
+    let portable = temp.path().join("portable");
+    generate(&default, &[]);
+    generate(&portable, &["--portable-create"]);
+
+    let tree = list_tree(&default);
+    assert!(tree.iter().any(|&(_, _, mode)| mode == 0o640));
+    assert_eq!(tree, list_tree(&portable));
+}
@@ -0,0 +1,68 @@
+use std::{fs, path::Path, process::Command};
+
+use tempfile::TempDir;
+
+fn files(root: &Path) -> Vec<fs::Metadata> {
+    let mut files = Vec::new();
+    let mut queue = vec![root.to_path_buf()];
+    while let Some(dir) = queue.pop() {
+        for entry in fs::read_dir(&dir).unwrap() {
+            let path = entry.unwrap().path();
+            let metadata = fs::symlink_metadata(&path).unwrap();
+            if metadata.is_dir() {
+                queue.push(path);
+            } else {
+                assert!(fs::read(&path).unwrap().iter().all(|&b| b == 0));
+                files.push(metadata);
+            }
+        }
+    }
+    files
+}
+
+#[test]
+fn test_preallocated_files_read_as_zeros() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path().join("tree");
+    let audit = temp.path().join("audit.csv");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg(&root)
+        .args(["-n", "20", "-b", "20M", "--preallocate", "--validate"])
+        .arg("--audit-output")
+        .arg(&audit)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+
+    let files = files(&root);
+    let apparent = files.iter().map(fs::Metadata::len).sum::<u64>();
+    assert!(apparent > 1_000_000, "{apparent}");
+    #[cfg(target_os = "linux")]
+    {
+        use std::os::unix::fs::MetadataExt;
+
+        let allocated = files.iter().map(|m| m.blocks() * 512).sum::<u64>();
+        assert!(allocated >= apparent, "{allocated} of {apparent}");
+    }
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg("verify")
+        .arg(&audit)
+        .arg(&root)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+}
+
+#[test]
+fn test_preallocate_rejects_contents() {
+    let temp = TempDir::new().unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg(temp.path().join("tree"))
+        .args(["-n", "20", "-b", "1M", "--preallocate", "--fill-byte", "7"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+}
@@ -0,0 +1,98 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use tempfile::TempDir;
+
+fn list_files(root: &Path) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    let mut queue = vec![root.to_path_buf()];
+    while let Some(dir) = queue.pop() {
+        for entry in fs::read_dir(&dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                queue.push(path);
+            } else {
+                files.push(path.strip_prefix(root).unwrap().to_path_buf());
+            }
+        }
+    }
+    files.sort();
+    files
+}
+
+fn is_whiteout(path: &Path) -> bool {
+    path.file_name()
+        .unwrap()
+        .to_str()
+        .unwrap()
+        .starts_with(".wh.")
+}
+
+#[test]
+fn test_whiteout_percentage() {
+    let temp = TempDir::new().unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg(temp.path())
+        .args(["-n", "2000", "--files-exact", "--whiteout-percentage", "25"])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success(), "{output:?}");
+    let files = list_files(temp.path());
+    assert_eq!(files.len(), 2000);
+    let whiteouts = files.iter().filter(|path| is_whiteout(path)).count();
+    assert!((350..650).contains(&whiteouts), "{whiteouts} whiteouts");
+}
+
+#[test]
+fn test_overlayfs_preset() {
+    let temp = TempDir::new().unwrap();
+
+    // Explicit options take precedence over the preset
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg(temp.path())
+        .args(["--preset", "overlayfs", "-n", "500", "-b", "100000"])
+        .output()
+        .unwrap();
+
+    assert!(output.status.success(), "{output:?}");
+    let layers = (0..4)
+        .map(|n| list_files(&temp.path().join(format!("layer-{n}"))))
+        .collect::<Vec<_>>();
+    assert!(!layers[0].is_empty());
+    assert!(layers.iter().all(|layer| *layer == layers[0]));
+    assert!(layers[0].iter().any(|path| is_whiteout(path)));
+    assert!(!temp.path().join("layer-4").exists());
+}
+
+#[test]
+fn test_config_overrides_preset() {
+    let temp = TempDir::new().unwrap();
+    let config_path = temp.path().join("config.toml");
+    fs::write(
+        &config_path,
+        r#"
+preset = "overlayfs"
+files = 200
+max-depth = 2
+"#,
+    )
+    .unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg("--config")
+        .arg(&config_path)
+        .arg(temp.path().join("tree-{n}"))
+        .output()
+        .unwrap();
+
+    assert!(output.status.success(), "{output:?}");
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("About 200 files"), "{stdout}");
+    assert!(stdout.contains("maximum depth 2"), "{stdout}");
+    assert!((0..4).all(|n| temp.path().join(format!("tree-{n}")).is_dir()));
+}
@@ -0,0 +1,131 @@
+use std::{fs, path::Path, process::Command};
+
+use tempfile::TempDir;
+
+fn count_entries(root: &Path) -> (i64, i64, i64) {
+    let (mut files, mut dirs, mut max_depth) = (0, 0, 0);
+    let mut queue = vec![(root.to_path_buf(), 0)];
+    while let Some((dir, depth)) = queue.pop() {
+        max_depth = max_depth.max(depth);
+        for entry in fs::read_dir(&dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                dirs += 1;
+                queue.push((path, depth + 1));
+            } else {
+                files += 1;
+            }
+        }
+    }
+    (files, dirs, max_depth)
+}
+
+#[test]
+fn test_profile_matches_the_tree() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path().join("tree");
+    let profile = temp.path().join("profile.toml");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg(&root)
+        .args(["-n", "500", "-b", "1M", "--extensions", "txt:3,rs"])
+        .args(["--duplicate-percentage", "20"])
+        .args(["--max-duplicates-per-file", "3"])
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg("profile")
+        .arg(&root)
+        .arg("-o")
+        .arg(&profile)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+    assert!(
+        String::from_utf8_lossy(&output.stdout).starts_with("Profiled "),
+        "{output:?}"
+    );
+
+    let profile = fs::read_to_string(profile)
+        .unwrap()
+        .parse::<toml::Table>()
+        .unwrap();
+    let (files, dirs, max_depth) = count_entries(&root);
+    assert_eq!(profile["files"].as_integer(), Some(files));
+    assert_eq!(profile["dirs"].as_integer(), Some(dirs));
+    assert_eq!(profile["max-depth"].as_integer(), Some(max_depth));
+
+    let files_per_depth = profile["files-per-depth"].as_array().unwrap();
+    assert_eq!(files_per_depth.len() as i64, max_depth + 1);
+    assert_eq!(
+        files_per_depth
+            .iter()
+            .map(|files| files.as_integer().unwrap())
+            .sum::<i64>(),
+        files
+    );
+
+    let dirs_per_depth = profile["dirs-per-depth"].as_array().unwrap();
+    assert_eq!(dirs_per_depth.len() as i64, max_depth + 1);
+    assert_eq!(
+        dirs_per_depth
+            .iter()
+            .map(|dirs| dirs.as_integer().unwrap())
+            .sum::<i64>(),
+        dirs
+    );
+    assert_eq!(dirs_per_depth.last().unwrap().as_integer(), Some(0));
+
+    let extensions = profile["extensions"].as_table().unwrap();
+    assert_eq!(
+        extensions.keys().collect::<Vec<_>>(),
+        ["rs", "txt"],
+        "{extensions:?}"
+    );
+    assert!(profile["duplicates"]["files"].as_integer().unwrap() > 0);
+}
+
+#[test]
+fn test_profile_prints_to_stdout_without_output() {
+    let temp = TempDir::new().unwrap();
+    fs::write(temp.path().join("a.txt"), "hello").unwrap();
+    fs::write(temp.path().join("b.txt"), "hello").unwrap();
+    fs::create_dir(temp.path().join("dir")).unwrap();
+    fs::write(temp.path().join("dir/c"), "").unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg("profile")
+        .arg(temp.path())
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+
+    let profile = String::from_utf8(output.stdout)
+        .unwrap()
+        .parse::<toml::Table>()
+        .unwrap();
+    assert_eq!(profile["files"].as_integer(), Some(3));
+    assert_eq!(profile["dirs"].as_integer(), Some(1));
+    assert_eq!(
+        profile["dirs-per-depth"].as_array().unwrap(),
+        &[1.into(), 0.into()]
+    );
+    assert_eq!(profile["bytes"].as_integer(), Some(10));
+    assert_eq!(profile["extensions"][""].as_integer(), Some(1));
+    assert_eq!(profile["duplicates"]["files"].as_integer(), Some(1));
+    assert_eq!(profile["duplicates"]["bytes"].as_integer(), Some(5));
+}
+
+#[test]
+fn test_profile_of_missing_dir_fails() {
+    let temp = TempDir::new().unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg("profile")
+        .arg(temp.path().join("missing"))
+        .output()
+        .unwrap();
+    assert!(!output.status.success(), "{output:?}");
+}
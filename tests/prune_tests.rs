@@ -0,0 +1,89 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use tempfile::TempDir;
+
+fn list_tree(root: &Path) -> Vec<(PathBuf, Vec<u8>)> {
+    let mut entries = Vec::new();
+    let mut queue = vec![root.to_path_buf()];
+    while let Some(dir) = queue.pop() {
+        for entry in fs::read_dir(&dir).unwrap() {
+            let path = entry.unwrap().path();
+            let relative = path.strip_prefix(root).unwrap().to_path_buf();
+            if path.is_dir() {
+                entries.push((relative, Vec::new()));
+                queue.push(path);
+            } else {
+                entries.push((relative, fs::read(&path).unwrap()));
+            }
+        }
+    }
+    entries.sort();
+    entries
+}
+
+fn generate(root: &Path, args: &[&str]) {
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg(root)
+        .arg("-n")
+        .arg("1000")
+        .arg("-b")
+        .arg("10000")
+        .args(args)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+}
+
+#[test]
+fn test_prune_glob_carves_holes() {
+    let temp = TempDir::new().unwrap();
+    let plain = temp.path().join("plain");
+    let pruned = temp.path().join("pruned");
+
+    generate(&plain, &[]);
+    generate(
+        &pruned,
+        &["--prune-glob", "0.dir", "--prune-glob", "*/1.dir"],
+    );
+
+    let is_pruned = |path: &Path| {
+        let mut components = path.components().map(|c| c.as_os_str().to_str().unwrap());
+        let first = components.next();
+        let second = components.next();
+        (first == Some("0.dir") && second.is_some())
+            || (second == Some("1.dir") && components.next().is_some())
+    };
+    let expected = list_tree(&plain)
+        .into_iter()
+        .filter(|(path, _)| !is_pruned(path))
+        .collect::<Vec<_>>();
+
+    assert!(pruned.join("0.dir").is_dir());
+    assert_eq!(fs::read_dir(pruned.join("0.dir")).unwrap().count(), 0);
+    assert!(expected.len() < list_tree(&plain).len());
+    assert_eq!(expected, list_tree(&pruned));
+}
+
+#[test]
+fn test_invalid_prune_glob_is_rejected() {
+    let temp = TempDir::new().unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg(temp.path().join("root"))
+        .arg("-n")
+        .arg("100")
+        .arg("--prune-glob")
+        .arg("[")
+        .output()
+        .unwrap();
+
+    assert!(!output.status.success());
+}
@@ -0,0 +1,107 @@
+use std::{fs, process::Command};
+
+use tempfile::TempDir;
+
+#[test]
+fn test_both_ratios_derive_depth() {
+    let temp = TempDir::new().unwrap();
+    let config_path = temp.path().join("config.toml");
+
+    fs::write(
+        &config_path,
+        r#"
+files = 1000
+ftd-ratio = 10
+dtd-ratio = 10
+"#,
+    )
+    .unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg("--config")
+        .arg(&config_path)
+        .arg(temp.path().join("output"))
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("maximum depth 2"));
+    assert!(stdout.contains("approximately 10 other directories"));
+}
+
+#[test]
+fn test_dtd_ratio_alone_keeps_depth() {
+    let temp = TempDir::new().unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg(temp.path().join("output"))
+        .arg("-n")
+        .arg("1000")
+        .arg("--dtd-ratio")
+        .arg("3")
+        .arg("-d")
+        .arg("3")
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("maximum depth 3"));
+    assert!(stdout.contains("approximately 3 other directories"));
+}
+
+#[test]
+fn test_both_ratios_and_depth_are_rejected() {
+    let temp = TempDir::new().unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg(temp.path().join("output"))
+        .arg("-n")
+        .arg("1000")
+        .arg("--ftd-ratio")
+        .arg("10")
+        .arg("--dtd-ratio")
+        .arg("10")
+        .arg("-d")
+        .arg("3")
+        .output()
+        .unwrap();
+
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_entries_are_split_by_ratio() {
+    let temp = TempDir::new().unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg(temp.path().join("output"))
+        .arg("--entries")
+        .arg("1100")
+        .arg("--ftd-ratio")
+        .arg("10")
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("About 1,000 files"));
+    assert!(stdout.contains("approximately 100 directories"));
+}
+
+#[test]
+fn test_entries_conflict_with_files() {
+    let temp = TempDir::new().unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg(temp.path().join("output"))
+        .arg("--entries")
+        .arg("1100")
+        .arg("-n")
+        .arg("1000")
+        .output()
+        .unwrap();
+
+    assert!(!output.status.success());
+}
@@ -0,0 +1,56 @@
+use std::{fs, path::Path, process::Command};
+
+use tempfile::TempDir;
+
+fn read_back(root: &Path, audit: &Path, extra_args: &[&str]) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg(root)
+        .args(["-n", "500", "-b", "1M", "--audit-output"])
+        .arg(audit)
+        .args(extra_args)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+
+    fs::read_to_string(audit)
+        .unwrap()
+        .lines()
+        .find_map(|line| line.strip_prefix("# read back: "))
+        .unwrap()
+        .to_string()
+}
+
+#[test]
+fn test_read_back_checks_every_file() {
+    let temp = TempDir::new().unwrap();
+    let audit = temp.path().join("audit.csv");
+
+    let summary = read_back(
+        &temp.path().join("tree"),
+        &audit,
+        &["--fragment", "--append-pass", "1K", "--read-back"],
+    );
+
+    let files = fs::read_to_string(&audit)
+        .unwrap()
+        .lines()
+        .filter(|line| line.contains(",file,"))
+        .count();
+    assert_eq!(summary, format!("{files} files, 0 mismatched"));
+}
+
+#[test]
+fn test_read_back_samples_files() {
+    let temp = TempDir::new().unwrap();
+
+    let summary = read_back(
+        &temp.path().join("tree"),
+        &temp.path().join("audit.csv"),
+        &["--read-back=20"],
+    );
+
+    let (files, _) = summary.split_once(' ').unwrap();
+    let files = files.parse::<u64>().unwrap();
+    assert!((10..100).contains(&files), "{summary}");
+    assert!(summary.ends_with(", 0 mismatched"));
+}
@@ -0,0 +1,125 @@
+use std::{fs, path::Path, process::Command};
+
+use tempfile::TempDir;
+
+fn listing(root: &Path) -> Vec<(String, bool, u64, u32)> {
+    fn walk(root: &Path, dir: &Path, out: &mut Vec<(String, bool, u64, u32)>) {
+        for entry in fs::read_dir(dir).unwrap() {
+            let entry = entry.unwrap();
+            let metadata = entry.metadata().unwrap();
+            #[cfg(unix)]
+            let mode = std::os::unix::fs::PermissionsExt::mode(&metadata.permissions()) & 0o7777;
+            #[cfg(not(unix))]
+            let mode = 0;
+            let path = entry.path();
+            out.push((
+                path.strip_prefix(root).unwrap().display().to_string(),
+                metadata.is_dir(),
+                if metadata.is_dir() { 0 } else { metadata.len() },
+                mode,
+            ));
+            if metadata.is_dir() {
+                walk(root, &path, out);
+            }
+        }
+    }
+
+    let mut out = Vec::new();
+    walk(root, root, &mut out);
+    out.sort();
+    out
+}
+
+fn replay_matches(audit_name: &str) {
+    let temp = TempDir::new().unwrap();
+    let original = temp.path().join("original");
+    let replayed = temp.path().join("replayed");
+    let audit = temp.path().join(audit_name);
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg(&original)
+        .arg("-n")
+        .arg("200")
+        .arg("-b")
+        .arg("100000")
+        .arg("--permissions")
+        .arg("600")
+        .arg("--permissions")
+        .arg("644")
+        .arg("--audit-output")
+        .arg(&audit)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg("replay")
+        .arg("--audit")
+        .arg(&audit)
+        .arg(&replayed)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+
+    assert_eq!(listing(&original), listing(&replayed));
+}
+
+#[test]
+fn test_replay_from_csv() {
+    replay_matches("audit.csv");
+}
+
+#[test]
+fn test_replay_from_sqlite() {
+    replay_matches("audit.db");
+}
+
+#[test]
+fn test_replay_from_jsonl() {
+    replay_matches("audit.jsonl");
+}
+
+#[test]
+fn test_replay_fill_byte() {
+    let temp = TempDir::new().unwrap();
+    let original = temp.path().join("original");
+    let replayed = temp.path().join("replayed");
+    let audit = temp.path().join("audit.csv");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg(&original)
+        .arg("-n")
+        .arg("10")
+        .arg("-b")
+        .arg("10000")
+        .arg("--audit-output")
+        .arg(&audit)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg("replay")
+        .arg("--audit")
+        .arg(&audit)
+        .arg("--fill-byte")
+        .arg("7")
+        .arg(&replayed)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+
+    let mut files = 0;
+    for (path, is_dir, ..) in listing(&replayed) {
+        if !is_dir {
+            files += 1;
+            assert!(
+                fs::read(replayed.join(path))
+                    .unwrap()
+                    .iter()
+                    .all(|&b| b == 7)
+            );
+        }
+    }
+    assert!(files > 0);
+}
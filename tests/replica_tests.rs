@@ -0,0 +1,133 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use tempfile::TempDir;
+
+fn list_tree(root: &Path) -> Vec<(PathBuf, Vec<u8>)> {
+    let mut entries = Vec::new();
+    let mut queue = vec![root.to_path_buf()];
+    while let Some(dir) = queue.pop() {
+        for entry in fs::read_dir(&dir).unwrap() {
+            let path = entry.unwrap().path();
+            let relative = path.strip_prefix(root).unwrap().to_path_buf();
+            if path.is_dir() {
+                entries.push((relative, Vec::new()));
+                queue.push(path);
+            } else {
+                entries.push((relative, fs::read(&path).unwrap()));
+            }
+        }
+    }
+    entries.sort();
+    entries
+}
+
+#[test]
+fn test_replica_dirs_are_identical() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path().join("root");
+    let replica = temp.path().join("replica");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg(&root)
+        .arg("-n")
+        .arg("100")
+        .arg("-b")
+        .arg("10000")
+        .arg("--replica-dir")
+        .arg(&replica)
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    let tree = list_tree(&root);
+    assert!(!tree.is_empty());
+    assert_eq!(tree, list_tree(&replica));
+}
+
+#[test]
+fn test_replicas_expand_template() {
+    let temp = TempDir::new().unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg(temp.path().join("replica{n}"))
+        .arg("-n")
+        .arg("100")
+        .arg("--replicas")
+        .arg("3")
+        .output()
+        .unwrap();
+
+    assert!(output.status.success());
+    let tree = list_tree(&temp.path().join("replica0"));
+    assert!(!tree.is_empty());
+    for n in 1..3 {
+        assert_eq!(tree, list_tree(&temp.path().join(format!("replica{n}"))));
+    }
+}
+
+#[test]
+fn test_replicas_require_template() {
+    let temp = TempDir::new().unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg(temp.path().join("root"))
+        .arg("-n")
+        .arg("100")
+        .arg("--replicas")
+        .arg("3")
+        .output()
+        .unwrap();
+
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_distinct_seeds_regenerate_standalone() {
+    let temp = TempDir::new().unwrap();
+    let audit = temp.path().join("audit.csv");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg(temp.path().join("replica{n}"))
+        .arg("-n")
+        .arg("100")
+        .arg("-b")
+        .arg("10000")
+        .arg("--replicas")
+        .arg("2")
+        .arg("--distinct-seeds")
+        .arg("--audit-output")
+        .arg(&audit)
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let replica0 = list_tree(&temp.path().join("replica0"));
+    let replica1 = list_tree(&temp.path().join("replica1"));
+    assert_ne!(replica0, replica1);
+
+    let audit = fs::read_to_string(&audit).unwrap();
+    let seeds = audit
+        .lines()
+        .filter_map(|line| line.strip_prefix("# seed "))
+        .map(|line| line.rsplit_once(": ").unwrap().1.to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(seeds.len(), 2);
+
+    let standalone = temp.path().join("standalone");
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg(&standalone)
+        .arg("-n")
+        .arg("100")
+        .arg("-b")
+        .arg("10000")
+        .arg("--seed")
+        .arg(&seeds[1])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(replica1, list_tree(&standalone));
+}
@@ -0,0 +1,73 @@
+use std::{fs, path::Path, process::Command};
+
+use tempfile::TempDir;
+
+fn max_files_in_dir(dir: &Path) -> usize {
+    let (mut files, mut max) = (0, 0);
+    for entry in fs::read_dir(dir).unwrap() {
+        let entry = entry.unwrap();
+        if entry.file_type().unwrap().is_dir() {
+            max = max.max(max_files_in_dir(&entry.path()));
+        } else {
+            files += 1;
+        }
+    }
+    max.max(files)
+}
+
+#[test]
+fn test_find_seed() {
+    let temp = TempDir::new().unwrap();
+    let scratch = temp.path().join("scratch");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg("find-seed")
+        .arg(&scratch)
+        .arg("-n")
+        .arg("2000")
+        .arg("--min-files-in-dir")
+        .arg("60")
+        .arg("--jobs")
+        .arg("3")
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+    let seed = String::from_utf8(output.stdout).unwrap();
+    let seed = seed.trim().parse::<u64>().unwrap();
+
+    // Candidates are cleaned up
+    assert_eq!(fs::read_dir(&scratch).unwrap().count(), 0);
+
+    // The seed is the first one satisfying the constraints
+    for candidate in 0..=seed {
+        let root_dir = temp.path().join(candidate.to_string());
+        let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+            .arg(&root_dir)
+            .arg("--seed")
+            .arg(candidate.to_string())
+            .arg("-n")
+            .arg("2000")
+            .output()
+            .unwrap();
+        assert!(output.status.success());
+        assert_eq!(max_files_in_dir(&root_dir) >= 60, candidate == seed);
+    }
+}
+
+#[test]
+fn test_find_seed_gives_up() {
+    let temp = TempDir::new().unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg("find-seed")
+        .arg(temp.path())
+        .arg("-n")
+        .arg("100")
+        .arg("--min-depth")
+        .arg("100")
+        .arg("--attempts")
+        .arg("5")
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+}
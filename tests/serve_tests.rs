@@ -0,0 +1,164 @@
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::TcpStream,
+    path::Path,
+    process::{Child, Command, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+
+use tempfile::tempdir;
+
+fn serve(root_dir: &Path) -> (Child, String) {
+    let mut server = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .args(["serve", "--listen", "127.0.0.1:0"])
+        .arg(root_dir)
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdout = BufReader::new(server.stdout.take().unwrap());
+    let mut line = String::new();
+    stdout.read_line(&mut line).unwrap();
+    let addr = line
+        .trim()
+        .strip_prefix("Listening on ")
+        .unwrap()
+        .to_string();
+    (server, addr)
+}
+
+fn request(addr: &str, method: &str, path: &str, body: &str) -> String {
+    let mut stream = TcpStream::connect(addr).unwrap();
+    write!(
+        stream,
+        "{method} {path} HTTP/1.1\r\nContent-Length: {}\r\n\r\n{body}",
+        body.len()
+    )
+    .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    response
+}
+
+#[test]
+fn jobs_are_generated_and_reported() {
+    let dir = tempdir().unwrap();
+    let (mut server, addr) = serve(&dir.path().join("jobs"));
+
+    let response = request(
+        &addr,
+        "POST",
+        "/jobs",
+        "-n 100 --audit-output audit.csv --seed 42",
+    );
+    assert!(response.starts_with("HTTP/1.1 202"), "{response}");
+    assert!(response.ends_with("\r\n\r\n0\n"), "{response}");
+
+    let response = request(&addr, "POST", "/jobs", "--bogus");
+    assert!(response.starts_with("HTTP/1.1 400"), "{response}");
+
+    let mut status = String::new();
+    for _ in 0..100 {
+        status = request(&addr, "GET", "/jobs/0", "");
+        if !status.contains("state: queued") && !status.contains("state: running") {
+            break;
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+    assert!(status.contains("state: done"), "{status}");
+    assert!(status.contains("Created "), "{status}");
+    assert!(dir.path().join("jobs/job-0").is_dir());
+    assert!(dir.path().join("jobs/job-0.audit.csv").is_file());
+
+    let response = request(&addr, "GET", "/jobs/0/audit", "");
+    assert!(response.starts_with("HTTP/1.1 200"), "{response}");
+    assert!(response.contains("# seed: 42"), "{response}");
+
+    let response = request(&addr, "GET", "/jobs/1", "");
+    assert!(response.starts_with("HTTP/1.1 404"), "{response}");
+
+    request(&addr, "POST", "/shutdown", "");
+    assert!(server.wait().unwrap().success());
+}
+
+#[test]
+fn jobs_cannot_escape_their_directory() {
+    let dir = tempdir().unwrap();
+    let outside = dir.path().join("outside");
+    let (mut server, addr) = serve(&dir.path().join("jobs"));
+
+    for body in [
+        format!("-n 10 {}", outside.display()),
+        format!("-n 10 {} 42", outside.display()),
+        format!(
+            "-n 10 --audit-output {}",
+            outside.join("audit.csv").display()
+        ),
+        "-n 10 --manifest ../manifest.json".to_string(),
+        format!("-n 10 --fio-output {}", outside.display()),
+        format!("-n 10 --replica-dir {}", outside.display()),
+        format!("-n 10 --shard {}", outside.display()),
+        format!("-n 10 --compare-with {}", outside.display()),
+        "-n 10 -b 1M --content-template /etc/passwd".to_string(),
+        "-n 10 -b 1M --content-corpus /".to_string(),
+    ] {
+        let response = request(&addr, "POST", "/jobs", &body);
+        assert!(response.starts_with("HTTP/1.1 400"), "{body}: {response}");
+    }
+    let response = request(&addr, "GET", "/jobs", "");
+    assert!(response.ends_with("\r\n\r\n"), "{response}");
+
+    request(&addr, "POST", "/shutdown", "");
+    assert!(server.wait().unwrap().success());
+    assert!(!outside.exists());
+}
+
+#[test]
+fn oversized_bodies_are_refused() {
+    let dir = tempdir().unwrap();
+    let (mut server, addr) = serve(dir.path());
+
+    let mut stream = TcpStream::connect(&addr).unwrap();
+    write!(
+        stream,
+        "POST /jobs HTTP/1.1\r\nContent-Length: 18446744073709551615\r\n\r\n-n 10"
+    )
+    .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    assert!(response.starts_with("HTTP/1.1 413"), "{response}");
+
+    let response = request(&addr, "GET", "/jobs", "");
+    assert!(response.ends_with("\r\n\r\n"), "{response}");
+
+    request(&addr, "POST", "/shutdown", "");
+    assert!(server.wait().unwrap().success());
+}
+
+#[test]
+fn trickling_clients_time_out() {
+    let dir = tempdir().unwrap();
+    let (mut server, addr) = serve(dir.path());
+
+    let start = Instant::now();
+    let mut stream = TcpStream::connect(&addr).unwrap();
+    let mut writer = stream.try_clone().unwrap();
+    // Each byte arrives well within the timeout, but the request never ends
+    let trickle = thread::spawn(move || {
+        for byte in b"POST /jobs HTTP/1.1\r\nX-Slow: ".iter().cycle() {
+            if writer.write_all(&[*byte]).is_err() {
+                break;
+            }
+            thread::sleep(Duration::from_millis(500));
+        }
+    });
+    let mut response = String::new();
+    let _ = stream.read_to_string(&mut response);
+    assert!(response.starts_with("HTTP/1.1 408"), "{response}");
+    assert!(start.elapsed() < Duration::from_secs(15));
+    drop(stream);
+    trickle.join().unwrap();
+
+    request(&addr, "POST", "/shutdown", "");
+    assert!(server.wait().unwrap().success());
+}
@@ -0,0 +1,156 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use tempfile::TempDir;
+
+fn list_tree(root: &Path) -> Vec<(PathBuf, Vec<u8>)> {
+    let mut entries = Vec::new();
+    let mut queue = vec![root.to_path_buf()];
+    while let Some(dir) = queue.pop() {
+        for entry in fs::read_dir(&dir).unwrap() {
+            let path = entry.unwrap().path();
+            let relative = path.strip_prefix(root).unwrap().to_path_buf();
+            if path.is_dir() {
+                entries.push((relative, Vec::new()));
+                queue.push(path);
+            } else {
+                entries.push((relative, fs::read(&path).unwrap()));
+            }
+        }
+    }
+    entries.sort();
+    entries
+}
+
+#[test]
+fn test_sharded_tree_matches_unsharded_tree() {
+    let temp = TempDir::new().unwrap();
+    let plain = temp.path().join("plain");
+    let root = temp.path().join("root");
+    let shard = temp.path().join("shard");
+    let audit = temp.path().join("audit.csv");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg(&plain)
+        .arg("-n")
+        .arg("1000")
+        .arg("-b")
+        .arg("10000")
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg(&root)
+        .arg("-n")
+        .arg("1000")
+        .arg("-b")
+        .arg("10000")
+        .arg("--shard")
+        .arg(&shard)
+        .arg("--validate")
+        .arg("--audit-output")
+        .arg(&audit)
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    assert_eq!(list_tree(&plain), list_tree(&root));
+
+    let audit = fs::read_to_string(&audit).unwrap();
+    let sharded = fs::read_dir(&shard)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name())
+        .collect::<Vec<_>>();
+    assert!(!sharded.is_empty());
+    for name in sharded {
+        let link = root.join(&name);
+        assert!(link.symlink_metadata().unwrap().is_symlink());
+        // The audit trail records the logical path through the root
+        assert!(audit.contains(link.to_str().unwrap()));
+    }
+}
+
+#[test]
+fn test_extra_root_dirs_shard_the_tree() {
+    let temp = TempDir::new().unwrap();
+    let plain = temp.path().join("plain");
+    let roots = ["a", "b", "c"].map(|name| temp.path().join(name));
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg(&plain)
+        .args(["-n", "1000", "-d", "2", "--seed", "7"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .args(&roots)
+        .args(["-n", "1000", "-d", "2", "--seed", "7"])
+        .output()
+        .unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    assert_eq!(list_tree(&plain), list_tree(&roots[0]));
+    for (i, root) in roots.iter().enumerate().skip(1) {
+        let sharded = fs::read_dir(root)
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+            .collect::<Vec<_>>();
+        assert!(!sharded.is_empty());
+        for name in sharded {
+            let index = name.strip_suffix(".dir").unwrap().parse::<usize>().unwrap();
+            assert_eq!(index % roots.len(), i);
+        }
+    }
+}
+
+#[test]
+fn test_trailing_numbers_are_seeds_or_ambiguous() {
+    let temp = TempDir::new().unwrap();
+    let generate = |args: &[&str]| {
+        Command::new(env!("CARGO_BIN_EXE_ftzz"))
+            .current_dir(temp.path())
+            .args(args)
+            .args(["-n", "100", "-b", "10000"])
+            .output()
+            .unwrap()
+    };
+
+    let output = generate(&["legacy", "7"]);
+    assert!(output.status.success(), "{output:?}");
+    assert!(
+        String::from_utf8_lossy(&output.stderr).contains("--seed 7"),
+        "{output:?}"
+    );
+    assert!(!temp.path().join("7").exists());
+    let output = generate(&["flag", "--seed", "7"]);
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(
+        list_tree(&temp.path().join("legacy")),
+        list_tree(&temp.path().join("flag"))
+    );
+
+    let output = generate(&["out", "7", "8"]);
+    assert!(!output.status.success());
+    assert!(
+        String::from_utf8_lossy(&output.stderr).contains("--seed"),
+        "{output:?}"
+    );
+    assert!(!temp.path().join("out").exists());
+
+    let output = generate(&["out", "./7"]);
+    assert!(output.status.success(), "{output:?}");
+    assert!(temp.path().join("7").is_dir());
+}
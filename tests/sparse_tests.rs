@@ -0,0 +1,104 @@
+#![cfg(unix)]
+
+use std::{fs, os::unix::fs::MetadataExt, path::Path, process::Command};
+
+use tempfile::TempDir;
+
+fn usage(root: &Path) -> (u64, u64) {
+    let mut apparent = 0;
+    let mut allocated = 0;
+    let mut queue = vec![root.to_path_buf()];
+    while let Some(dir) = queue.pop() {
+        for entry in fs::read_dir(&dir).unwrap() {
+            let path = entry.unwrap().path();
+            let metadata = fs::symlink_metadata(&path).unwrap();
+            if metadata.is_dir() {
+                queue.push(path);
+            } else {
+                apparent += metadata.len();
+                allocated += metadata.blocks() * 512;
+            }
+        }
+    }
+    (apparent, allocated)
+}
+
+#[test]
+fn test_sparse_files_have_holes() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path().join("tree");
+    let audit = temp.path().join("audit.csv");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg(&root)
+        .args(["-n", "20", "-b", "20M", "--sparse", "75", "--validate"])
+        .arg("--audit-output")
+        .arg(&audit)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+
+    let (apparent, allocated) = usage(&root);
+    assert!(apparent > 1_000_000, "{apparent}");
+    assert!(allocated < apparent / 2, "{allocated} of {apparent}");
+
+    // The recorded hashes cover the zeros read back from the holes
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg("verify")
+        .arg(&audit)
+        .arg(&root)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+}
+
+#[test]
+fn test_sparse_rejects_fragment() {
+    let temp = TempDir::new().unwrap();
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg(temp.path().join("tree"))
+        .args(["-n", "20", "-b", "1M", "--sparse", "50", "--fragment"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+}
+
+#[test]
+fn test_hole_pattern_is_laid_out_and_audited() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path().join("tree");
+    let audit = temp.path().join("audit.csv");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg(&root)
+        .args(["-n", "10", "-b", "10M", "--hole-pattern", "4KiB:28KiB"])
+        .arg("--audit-output")
+        .arg(&audit)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+
+    let trail = fs::read_to_string(&audit).unwrap();
+    assert!(trail.contains("# holes: 4096:28672\n"), "{trail}");
+
+    let (apparent, allocated) = usage(&root);
+    assert!(allocated < apparent / 4, "{allocated} of {apparent}");
+    for line in trail.lines().filter(|line| line.contains(",file,")) {
+        let contents = fs::read(line.split(',').next().unwrap()).unwrap();
+        for (i, chunk) in contents.chunks(4096).enumerate() {
+            assert_eq!(
+                i % 8 != 0,
+                chunk.iter().all(|&b| b == 0),
+                "block {i} of {line}"
+            );
+        }
+    }
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg(temp.path().join("other"))
+        .args(["-n", "10", "-b", "1M", "--hole-pattern", "4KiB:1000"])
+        .output()
+        .unwrap();
+    assert!(!output.status.success());
+}
@@ -0,0 +1,91 @@
+#![cfg(target_os = "linux")]
+
+use std::{fs, os::unix::fs::FileTypeExt, path::Path, process::Command};
+
+use tempfile::TempDir;
+
+fn special_files(root: &Path) -> Vec<(String, &'static str)> {
+    let mut specials = Vec::new();
+    let mut queue = vec![root.to_path_buf()];
+    while let Some(dir) = queue.pop() {
+        for entry in fs::read_dir(&dir).unwrap() {
+            let entry = entry.unwrap();
+            let path = entry.path();
+            let file_type = entry.file_type().unwrap();
+            let kind = if file_type.is_dir() {
+                queue.push(path);
+                continue;
+            } else if file_type.is_fifo() {
+                "fifo"
+            } else if file_type.is_socket() {
+                "socket"
+            } else if file_type.is_char_device() {
+                "char_device"
+            } else if file_type.is_block_device() {
+                "block_device"
+            } else {
+                continue;
+            };
+            specials.push((path.strip_prefix(root).unwrap().display().to_string(), kind));
+        }
+    }
+    specials.sort();
+    specials
+}
+
+#[test]
+fn test_special_files_are_audited_by_type() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path().join("tree");
+    let audit = temp.path().join("audit.csv");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg(&root)
+        .args([
+            "-n",
+            "500",
+            "-b",
+            "1M",
+            "--special-files",
+            "20",
+            "--validate",
+        ])
+        .arg("--audit-output")
+        .arg(&audit)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+
+    let specials = special_files(&root);
+    assert!(specials.iter().any(|(_, kind)| *kind == "fifo"));
+    assert!(specials.iter().any(|(_, kind)| *kind == "socket"));
+
+    let trail = fs::read_to_string(&audit).unwrap();
+    for (path, kind) in &specials {
+        let path = root.join(path);
+        let line = format!("{},{kind},0,", path.display());
+        assert!(
+            trail.lines().any(|l| l.starts_with(&line)),
+            "{line} not in audit"
+        );
+    }
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg("verify")
+        .arg(&audit)
+        .arg(&root)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+
+    let replayed = temp.path().join("replayed");
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg("replay")
+        .arg("--audit")
+        .arg(&audit)
+        .arg(&replayed)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+    assert_eq!(special_files(&replayed), specials);
+}
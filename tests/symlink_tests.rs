@@ -0,0 +1,134 @@
+#![cfg(unix)]
+
+use std::{fs, path::Path, process::Command};
+
+use tempfile::TempDir;
+
+fn list_links(root: &Path) -> Vec<(String, String)> {
+    let mut links = Vec::new();
+    let mut queue = vec![root.to_path_buf()];
+    while let Some(dir) = queue.pop() {
+        for entry in fs::read_dir(&dir).unwrap() {
+            let entry = entry.unwrap();
+            let path = entry.path();
+            let file_type = entry.file_type().unwrap();
+            if file_type.is_dir() {
+                queue.push(path);
+            } else if file_type.is_symlink() {
+                let target = fs::read_link(&path).unwrap();
+                links.push((
+                    path.strip_prefix(root).unwrap().display().to_string(),
+                    target.display().to_string(),
+                ));
+            }
+        }
+    }
+    links.sort();
+    links
+}
+
+#[test]
+fn test_broken_symlinks_dangle() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path().join("tree");
+    let audit = temp.path().join("audit.csv");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg(&root)
+        .args([
+            "-n",
+            "1000",
+            "--broken-symlink-percentage",
+            "10",
+            "--validate",
+        ])
+        .arg("--audit-output")
+        .arg(&audit)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+
+    let links = list_links(&root);
+    assert!(!links.is_empty());
+    for (link, target) in &links {
+        let name = link.rsplit('/').next().unwrap();
+        let file = name.strip_suffix(".link").unwrap();
+        assert_eq!(*target, format!("{file}.missing"));
+
+        let link = root.join(link);
+        assert!(link.with_file_name(file).is_file(), "{link:?} has no file");
+        assert_eq!(
+            fs::metadata(&link).unwrap_err().kind(),
+            std::io::ErrorKind::NotFound
+        );
+    }
+
+    let trail = fs::read_to_string(&audit).unwrap();
+    assert!(
+        trail
+            .lines()
+            .any(|line| line == format!("# broken symlinks: {}", links.len()))
+    );
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg("verify")
+        .arg(&audit)
+        .arg(&root)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+}
+
+#[test]
+fn test_symlink_loops_point_at_ancestors() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path().join("tree");
+    let audit = temp.path().join("audit.csv");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg(&root)
+        .args(["-n", "1000", "--symlink-loops", "5", "--validate"])
+        .arg("--audit-output")
+        .arg(&audit)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+
+    let links = list_links(&root);
+    assert_eq!(links.len(), 5, "{links:?}");
+    for (link, _) in &links {
+        let link = root.join(link);
+        assert_eq!(link.file_name().unwrap(), "loop");
+        let dir = link.parent().unwrap().canonicalize().unwrap();
+        let target = link.canonicalize().unwrap();
+        assert!(dir.starts_with(&target), "{link:?} doesn't loop");
+        assert_ne!(target, root.canonicalize().unwrap());
+    }
+
+    let trail = fs::read_to_string(&audit).unwrap();
+    assert!(trail.lines().any(|line| line == "# symlink loops: 5"));
+    let audited = trail
+        .lines()
+        .filter(|line| line.contains("/loop,symlink,"))
+        .count();
+    assert_eq!(audited, 5, "{trail}");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg("verify")
+        .arg(&audit)
+        .arg(&root)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+
+    let replayed = temp.path().join("replayed");
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg("replay")
+        .arg("--audit")
+        .arg(&audit)
+        .arg(&replayed)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+    assert!(list_links(&replayed).is_empty());
+}
@@ -0,0 +1,137 @@
+#![cfg(unix)]
+
+use std::{
+    fs,
+    os::unix::fs::MetadataExt,
+    path::{Path, PathBuf},
+    process::Command,
+};
+
+use tempfile::TempDir;
+
+#[test]
+fn test_mtime_range_stamps_files() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path().join("tree");
+    let audit = temp.path().join("audit.csv");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg(&root)
+        .args([
+            "-n",
+            "500",
+            "-b",
+            "1M",
+            "--mtime-range",
+            "1000000000..1100000000",
+            "--validate",
+        ])
+        .arg("--audit-output")
+        .arg(&audit)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+
+    let mut reader = csv::ReaderBuilder::new()
+        .comment(Some(b'#'))
+        .from_path(&audit)
+        .unwrap();
+    let mut mtimes = Vec::new();
+    for record in reader.records() {
+        let record = record.unwrap();
+        if &record[1] != "file" {
+            continue;
+        }
+        let mtime = record[8].parse::<i64>().unwrap();
+        let metadata = fs::symlink_metadata(&record[0]).unwrap();
+        assert_eq!(metadata.mtime(), mtime, "{record:?}");
+        assert!((1_000_000_000..=1_100_000_000).contains(&mtime));
+        assert!((mtime..=1_100_000_000).contains(&metadata.atime()));
+        mtimes.push(mtime);
+    }
+    let files = mtimes.len();
+    mtimes.sort_unstable();
+    mtimes.dedup();
+    assert!(mtimes.len() > files * 9 / 10, "{mtimes:?}");
+
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg("verify")
+        .arg(&audit)
+        .arg(&root)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+}
+
+fn list_entries(root: &Path) -> Vec<(PathBuf, u64, i64)> {
+    let mut entries = Vec::new();
+    let mut queue = vec![root.to_path_buf()];
+    while let Some(dir) = queue.pop() {
+        for entry in fs::read_dir(&dir).unwrap() {
+            let path = entry.unwrap().path();
+            let metadata = fs::symlink_metadata(&path).unwrap();
+            if metadata.is_dir() {
+                queue.push(path.clone());
+            }
+            let size = if metadata.is_file() {
+                metadata.len()
+            } else {
+                0
+            };
+            entries.push((
+                path.strip_prefix(root).unwrap().to_path_buf(),
+                size,
+                metadata.mtime(),
+            ));
+        }
+    }
+    entries.sort();
+    entries
+}
+
+#[test]
+fn test_timestamp_stamps_every_entry() {
+    let temp = TempDir::new().unwrap();
+    let audit = temp.path().join("audit.csv");
+
+    let generate = |root: &Path, audit: Option<&Path>| {
+        let mut command = Command::new(env!("CARGO_BIN_EXE_ftzz"));
+        command.arg(root).args([
+            "-n",
+            "500",
+            "-b",
+            "1M",
+            "--broken-symlink-percentage",
+            "10",
+            "--symlink-loops",
+            "3",
+            "--timestamp",
+            "1234567890",
+        ]);
+        if let Some(audit) = audit {
+            command.arg("--audit-output").arg(audit);
+        }
+        let output = command.output().unwrap();
+        assert!(output.status.success(), "{output:?}");
+    };
+    let root = temp.path().join("tree");
+    let again = temp.path().join("again");
+    generate(&root, Some(&audit));
+    generate(&again, None);
+
+    let entries = list_entries(&root);
+    assert!(entries.iter().all(|&(_, _, mtime)| mtime == 1_234_567_890));
+    assert_eq!(fs::metadata(&root).unwrap().mtime(), 1_234_567_890);
+    assert_eq!(list_entries(&again), entries);
+
+    let mut reader = csv::ReaderBuilder::new()
+        .comment(Some(b'#'))
+        .from_path(&audit)
+        .unwrap();
+    for record in reader.records() {
+        let record = record.unwrap();
+        assert_eq!(&record[8], "1234567890", "{record:?}");
+    }
+    let trail = fs::read_to_string(&audit).unwrap();
+    assert!(trail.lines().any(|line| line == "# timestamp: 1234567890"));
+}
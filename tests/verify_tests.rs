@@ -0,0 +1,156 @@
+use std::{fs, path::Path, process::Command};
+
+use tempfile::TempDir;
+
+fn generate(root: &Path, audit: &Path) {
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg(root)
+        .args(["-n", "200", "-b", "100000", "--audit-output"])
+        .arg(audit)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+}
+
+fn verify(root: &Path, audit: &Path) -> (bool, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+        .arg("verify")
+        .arg(audit)
+        .arg(root)
+        .output()
+        .unwrap();
+    (
+        output.status.success(),
+        String::from_utf8(output.stdout).unwrap(),
+    )
+}
+
+fn first_nonempty_file(dir: &Path) -> std::path::PathBuf {
+    let mut queue = vec![dir.to_path_buf()];
+    while let Some(dir) = queue.pop() {
+        for entry in fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                queue.push(path);
+            } else if fs::metadata(&path).unwrap().len() > 0 {
+                return path;
+            }
+        }
+    }
+    panic!("No non-empty files");
+}
+
+fn verify_detects_changes(audit_name: &str) {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path().join("root");
+    let audit = temp.path().join(audit_name);
+    generate(&root, &audit);
+
+    let (success, stdout) = verify(&root, &audit);
+    assert!(success, "{stdout}");
+    assert!(
+        stdout.contains("0 missing, 0 extra, 0 corrupted"),
+        "{stdout}"
+    );
+
+    let corrupted = first_nonempty_file(&root);
+    let mut contents = fs::read(&corrupted).unwrap();
+    contents[0] ^= 0xFF;
+    fs::write(&corrupted, contents).unwrap();
+    fs::write(root.join("extra"), b"").unwrap();
+    let missing = root.join("0");
+    if missing.is_dir() {
+        fs::remove_dir_all(&missing).unwrap();
+    } else {
+        fs::remove_file(&missing).unwrap();
+    }
+
+    let (success, stdout) = verify(&root, &audit);
+    assert!(!success);
+    let relative = corrupted.strip_prefix(&root).unwrap().display().to_string();
+    if !relative.starts_with("0/") && relative != "0" {
+        assert!(
+            stdout.contains(&format!("Corrupted: {relative} (contents differ)")),
+            "{stdout}"
+        );
+    }
+    assert!(stdout.contains("Extra: extra\n"), "{stdout}");
+    assert!(stdout.contains("Missing: 0\n"), "{stdout}");
+}
+
+#[test]
+fn test_verify_csv() {
+    verify_detects_changes("audit.csv");
+}
+
+#[test]
+fn test_verify_sqlite() {
+    verify_detects_changes("audit.db");
+}
+
+fn largest_file(dir: &Path) -> std::path::PathBuf {
+    let mut largest = (0, None);
+    let mut queue = vec![dir.to_path_buf()];
+    while let Some(dir) = queue.pop() {
+        for entry in fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.is_dir() {
+                queue.push(path);
+            } else {
+                let len = fs::metadata(&path).unwrap().len();
+                if len > largest.0 {
+                    largest = (len, Some(path));
+                }
+            }
+        }
+    }
+    largest.1.unwrap()
+}
+
+#[test]
+fn test_verify_sample_always_checks_the_largest_files() {
+    let temp = TempDir::new().unwrap();
+    let root = temp.path().join("root");
+    let audit = temp.path().join("audit.csv");
+    generate(&root, &audit);
+
+    let sample = |percentage: &str| {
+        let output = Command::new(env!("CARGO_BIN_EXE_ftzz"))
+            .arg("verify")
+            .args(["--sample", percentage])
+            .arg(&audit)
+            .arg(&root)
+            .output()
+            .unwrap();
+        (
+            output.status.success(),
+            String::from_utf8(output.stdout).unwrap(),
+        )
+    };
+
+    let (success, stdout) = sample("5");
+    assert!(success, "{stdout}");
+    let counts = stdout
+        .split_once("Rehashed a sample of ")
+        .unwrap()
+        .1
+        .trim_end_matches(" files.\n")
+        .split(" of ")
+        .map(|count| count.parse::<usize>().unwrap())
+        .collect::<Vec<_>>();
+    assert!(counts[0] > 0 && counts[0] * 10 < counts[1], "{stdout}");
+
+    let corrupted = largest_file(&root);
+    let mut contents = fs::read(&corrupted).unwrap();
+    contents[0] ^= 0xFF;
+    fs::write(&corrupted, contents).unwrap();
+
+    let (success, stdout) = sample("1");
+    assert!(!success);
+    let relative = corrupted.strip_prefix(&root).unwrap().display().to_string();
+    assert!(
+        stdout.contains(&format!("Corrupted: {relative} (contents differ)")),
+        "{stdout}"
+    );
+    assert_eq!(sample("1").1, stdout);
+}